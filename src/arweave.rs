@@ -0,0 +1,102 @@
+//! Receipt fetches from Arweave
+//!
+//! `ar://<txid>` URLs are read through the `arweave_gateway`, which serves a
+//! transaction's data at `{gateway}/{txid}`. A freshly posted transaction can
+//! 404 until the gateway has seen it, so not-found responses are retried a
+//! bounded number of times before giving up.
+
+use anyhow::Result;
+use reqwest::{Client, StatusCode};
+use std::time::Duration;
+use tracing::{debug, info};
+use crate::config::Config;
+use crate::error::VerifierError;
+
+/// Pause between not-found retries, multiplied by the attempt number
+const NOT_FOUND_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Transaction ID of an `ar://` URL: 43 characters of unpadded base64url
+pub fn parse_txid(url: &str) -> Result<&str> {
+    let txid = url.strip_prefix("ar://").unwrap_or(url).trim_end_matches('/');
+    let is_base64url = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if txid.len() != 43 || !txid.chars().all(is_base64url) {
+        return Err(VerifierError::Config(format!("Invalid Arweave URL (expected ar://<txid>): {}", url)).into());
+    }
+    Ok(txid)
+}
+
+/// Download a transaction's data, refusing anything over `config.max_receipt_size`
+pub async fn fetch_arweave_receipt(url: &str, client: &Client, config: &Config) -> Result<Vec<u8>> {
+    let txid = parse_txid(url)?;
+    let gateway_url = format!("{}/{}", config.arweave_gateway.trim_end_matches('/'), txid);
+
+    info!("Fetching receipt from Arweave: {}", gateway_url);
+    fetch_with_retries(client, &gateway_url, config, NOT_FOUND_RETRY_DELAY).await
+}
+
+async fn fetch_with_retries(client: &Client, gateway_url: &str, config: &Config, delay: Duration) -> Result<Vec<u8>> {
+    let mut retries = 0;
+    loop {
+        // Gateways are plain HTTP, so the fetch has the HTTP timeout
+        let response = client.get(gateway_url).timeout(config.fetch_timeout(gateway_url)).send().await?;
+        let status = response.status();
+
+        if status == StatusCode::NOT_FOUND && retries < config.arweave_not_found_retries {
+            retries += 1;
+            debug!("Arweave gateway has not seen {} yet; retry {}/{}", gateway_url, retries, config.arweave_not_found_retries);
+            tokio::time::sleep(delay * retries).await;
+            continue;
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(VerifierError::HttpStatus { service: "Arweave gateway", status: status.as_u16(), body }.into());
+        }
+
+        return crate::read_limited(response, config.max_receipt_size).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TXID: &str = "bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt_U";
+
+    #[test]
+    fn test_parse_txid() {
+        assert_eq!(parse_txid(&format!("ar://{}", TXID)).unwrap(), TXID);
+        assert!(parse_txid("ar://").is_err());
+        assert!(parse_txid("ar://not-a-txid").is_err());
+        assert!(parse_txid(&format!("ar://{}/../admin", TXID)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_retried_until_the_gateway_catches_up() {
+        let mut server = mockito::Server::new_async().await;
+        let pending = server.mock("GET", format!("/{}", TXID).as_str())
+            .with_status(404)
+            .expect(2)
+            .create_async()
+            .await;
+        let available = server.mock("GET", format!("/{}", TXID).as_str())
+            .with_body(b"receipt bytes")
+            .create_async()
+            .await;
+
+        let config = Config { arweave_gateway: server.url(), arweave_not_found_retries: 3, ..Config::default() };
+        let gateway_url = format!("{}/{}", server.url(), TXID);
+        let bytes = fetch_with_retries(&Client::new(), &gateway_url, &config, Duration::from_millis(1)).await.unwrap();
+        assert_eq!(bytes, b"receipt bytes");
+        pending.assert_async().await;
+        available.assert_async().await;
+
+        // A transaction that never shows up fails once the retries run out
+        available.remove_async().await;
+        let config = Config { arweave_not_found_retries: 1, ..config };
+        let err = fetch_with_retries(&Client::new(), &gateway_url, &config, Duration::from_millis(1)).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::HttpStatus { status: 404, .. })
+        ));
+    }
+}
@@ -0,0 +1,196 @@
+//! Append-only audit log of signed receipts
+//!
+//! Every receipt the verifier signs and tries to submit is appended to the
+//! log as one JSON line: the full `VerifiedReceipt`, when it was logged and
+//! how its submission went. The log is a record of what the verifier signed
+//! that does not depend on on-chain state.
+//!
+//! Lines are synced to disk as they are written. The file is reopened when
+//! it is moved or deleted, so external rotation (e.g. logrotate) starts a
+//! fresh file instead of writing into the rotated one.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+use crate::types::VerifiedReceipt;
+
+/// How submitting a logged receipt went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubmissionResult {
+    Submitted,
+    Failed(String),
+    /// Signed in a dry run, so never submitted
+    DryRun,
+}
+
+/// One line of the log
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    #[serde(flatten)]
+    receipt: &'a VerifiedReceipt,
+    /// Unix timestamp the line was written at
+    logged_at: u64,
+    /// `submitted`, `failed` or `dry_run`
+    submission: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    submission_error: Option<&'a str>,
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    /// Open the log at `path` for appending, creating it if needed
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let file = open_append(&path)?;
+        Ok(Self { path, file: Mutex::new(file) })
+    }
+
+    /// Append a receipt and its submission result. Write failures are
+    /// logged rather than returned, since the submission already happened.
+    pub fn record(&self, receipt: &VerifiedReceipt, result: &SubmissionResult) {
+        let (submission, submission_error) = match result {
+            SubmissionResult::Submitted => ("submitted", None),
+            SubmissionResult::Failed(error) => ("failed", Some(error.as_str())),
+            SubmissionResult::DryRun => ("dry_run", None),
+        };
+        let entry = AuditEntry {
+            receipt,
+            logged_at: chrono::Utc::now().timestamp() as u64,
+            submission,
+            submission_error,
+        };
+
+        if let Err(e) = self.append(&entry) {
+            warn!("Failed to write receipt {} to audit log {}: {}", receipt.receipt_hash, self.path.display(), e);
+        }
+    }
+
+    fn append(&self, entry: &AuditEntry<'_>) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap();
+        if self.rotated(&file) {
+            *file = open_append(&self.path)?;
+        }
+        file.write_all(&line)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Whether the open file is no longer the one at `path`
+    fn rotated(&self, file: &File) -> bool {
+        let Ok(current) = std::fs::metadata(&self.path) else {
+            return true;
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            file.metadata().map_or(true, |open| (open.dev(), open.ino()) != (current.dev(), current.ino()))
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = (file, current);
+            false
+        }
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("Failed to open audit log {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProofKind;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lcore-audit-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn receipt(input_index: u64) -> VerifiedReceipt {
+        VerifiedReceipt {
+            device_id: "device-1".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: format!("0x{:064x}", input_index),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Succinct,
+            journal_summary: Some(serde_json::json!([42, true])),
+            epoch_index: 1,
+            input_index,
+            signature: "0x1234".to_string(),
+            timestamp: Some(1_700_000_000),
+            verifier_address: Some("0x0000000000000000000000000000000000000001".to_string()),
+            bls_signature: None,
+            bls_public_key: None,
+        }
+    }
+
+    fn read_lines(path: &Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_lines_round_trip_to_receipts() {
+        let path = temp_path("round-trip");
+        let log = AuditLog::open(&path).unwrap();
+        log.record(&receipt(1), &SubmissionResult::Submitted);
+        log.record(&receipt(2), &SubmissionResult::Failed("InputBox returned HTTP 503".to_string()));
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 2);
+        for (line, input_index) in lines.iter().zip([1, 2]) {
+            let logged: VerifiedReceipt = serde_json::from_value(line.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&logged).unwrap(), serde_json::to_value(receipt(input_index)).unwrap());
+            assert!(line["logged_at"].as_u64().unwrap() > 0);
+        }
+        assert_eq!(lines[0]["submission"], "submitted");
+        assert!(lines[0].get("submission_error").is_none());
+        assert_eq!(lines[1]["submission"], "failed");
+        assert_eq!(lines[1]["submission_error"], "InputBox returned HTTP 503");
+
+        // Reopening appends rather than truncating
+        AuditLog::open(&path).unwrap().record(&receipt(3), &SubmissionResult::DryRun);
+        assert_eq!(read_lines(&path).len(), 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rotated_file_is_reopened() {
+        let path = temp_path("rotation");
+        let rotated = path.with_extension("jsonl.1");
+        let log = AuditLog::open(&path).unwrap();
+        log.record(&receipt(1), &SubmissionResult::Submitted);
+
+        std::fs::rename(&path, &rotated).unwrap();
+        log.record(&receipt(2), &SubmissionResult::Submitted);
+
+        assert_eq!(read_lines(&rotated).len(), 1);
+        let fresh = read_lines(&path);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0]["input_index"], 2);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! Bloom filter used as a fast "definitely not seen" check in front of the
+//! processed-receipt store
+
+use sha3::{Digest, Keccak256};
+
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Size a filter for `capacity` items at the given false-positive rate
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        let num_bits = (-(capacity * rate.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / capacity) * ln2).round().max(1.0) as u32;
+
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, item: &str) {
+        for bit in self.bit_indexes(item) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Forget every inserted item
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+
+    /// `false` means the item was definitely never inserted
+    pub fn might_contain(&self, item: &str) -> bool {
+        self.bit_indexes(item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Double hashing over a single Keccak256 digest
+    fn bit_indexes(&self, item: &str) -> impl Iterator<Item = u64> {
+        let hash = Keccak256::digest(item.as_bytes());
+        let h1 = u64::from_le_bytes(hash[..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(hash[8..16].try_into().unwrap()) | 1;
+        let num_bits = self.num_bits;
+
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = BloomFilter::new(1_000, 0.01);
+        let items: Vec<String> = (0..5_000).map(|i| format!("0x{:064x}", i)).collect();
+
+        // Deliberately overfill past capacity; membership must still hold
+        for item in &items {
+            filter.insert(item);
+        }
+        for item in &items {
+            assert!(filter.might_contain(item));
+        }
+    }
+
+    #[test]
+    fn test_false_positive_rate_is_bounded() {
+        let mut filter = BloomFilter::new(10_000, 0.01);
+        for i in 0..10_000 {
+            filter.insert(&format!("seen-{}", i));
+        }
+
+        let false_positives = (0..10_000)
+            .filter(|i| filter.might_contain(&format!("unseen-{}", i)))
+            .count();
+
+        // Allow generous slack over the 1% target
+        assert!(false_positives < 300, "false positives: {}", false_positives);
+    }
+}
@@ -0,0 +1,164 @@
+//! Optional BLS12-381 receipt signatures for off-chain aggregation
+//!
+//! Uses the "minimal public key" variant: 48-byte G1 public keys and 96-byte
+//! G2 signatures. Signatures from many verifiers over the same receipt hash
+//! aggregate into one signature checked against the aggregate public key.
+
+use anyhow::Result;
+use blst::min_pk::{AggregatePublicKey, AggregateSignature, PublicKey, SecretKey, Signature};
+use blst::BLST_ERROR;
+use crate::error::VerifierError;
+
+/// Domain separation tag of the IETF BLS signature suite (proof-of-possession scheme)
+const DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+pub struct BlsSigner {
+    secret_key: SecretKey,
+    public_key: String,
+}
+
+impl BlsSigner {
+    /// Create a signer from a 32-byte hex secret key
+    pub fn new(secret_key_hex: &str) -> Result<Self> {
+        let bytes = hex::decode(secret_key_hex.trim_start_matches("0x"))
+            .map_err(|e| VerifierError::signing_fatal(format!("Invalid BLS key hex: {}", e)))?;
+        let secret_key = SecretKey::from_bytes(&bytes)
+            .map_err(|e| VerifierError::signing_fatal(format!("Invalid BLS key: {:?}", e)))?;
+        let public_key = format!("0x{}", hex::encode(secret_key.sk_to_pk().compress()));
+
+        Ok(Self { secret_key, public_key })
+    }
+
+    /// Compressed public key as 0x-prefixed hex
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// Sign a 32-byte message, returning the compressed signature as 0x-prefixed hex
+    pub fn sign(&self, message: &[u8; 32]) -> String {
+        let signature = self.secret_key.sign(message, DST, &[]);
+        format!("0x{}", hex::encode(signature.compress()))
+    }
+}
+
+/// Aggregate signatures over the same message into one
+#[allow(dead_code)]
+pub fn aggregate_signatures(signatures: &[String]) -> Result<String> {
+    let signatures = signatures.iter()
+        .map(|sig| decode_signature(sig))
+        .collect::<Result<Vec<_>>>()?;
+    let refs: Vec<&Signature> = signatures.iter().collect();
+
+    let aggregate = AggregateSignature::aggregate(&refs, true)
+        .map_err(|e| VerifierError::ProofVerification(format!("Failed to aggregate BLS signatures: {:?}", e)))?;
+
+    Ok(format!("0x{}", hex::encode(aggregate.to_signature().compress())))
+}
+
+/// Aggregate public keys into the key an aggregate signature verifies against
+#[allow(dead_code)]
+pub fn aggregate_public_keys(public_keys: &[String]) -> Result<String> {
+    let public_keys = public_keys.iter()
+        .map(|pk| decode_public_key(pk))
+        .collect::<Result<Vec<_>>>()?;
+    let refs: Vec<&PublicKey> = public_keys.iter().collect();
+
+    let aggregate = AggregatePublicKey::aggregate(&refs, true)
+        .map_err(|e| VerifierError::ProofVerification(format!("Failed to aggregate BLS public keys: {:?}", e)))?;
+
+    Ok(format!("0x{}", hex::encode(aggregate.to_public_key().compress())))
+}
+
+/// Check a (possibly aggregate) signature against a (possibly aggregate) public key
+#[allow(dead_code)]
+pub fn verify(signature: &str, public_key: &str, message: &[u8; 32]) -> Result<bool> {
+    let signature = decode_signature(signature)?;
+    let public_key = decode_public_key(public_key)?;
+
+    Ok(signature.verify(true, message, DST, &[], &public_key, true) == BLST_ERROR::BLST_SUCCESS)
+}
+
+fn decode_signature(signature: &str) -> Result<Signature> {
+    let bytes = hex::decode(signature.trim_start_matches("0x"))?;
+    Signature::uncompress(&bytes)
+        .map_err(|e| VerifierError::ProofVerification(format!("Invalid BLS signature: {:?}", e)).into())
+}
+
+fn decode_public_key(public_key: &str) -> Result<PublicKey> {
+    let bytes = hex::decode(public_key.trim_start_matches("0x"))?;
+    PublicKey::uncompress(&bytes)
+        .map_err(|e| VerifierError::ProofVerification(format!("Invalid BLS public key: {:?}", e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt_signer::{compute_receipt_hash, ReceiptSigner};
+    use crate::types::{ProofKind, VerifiedReceipt};
+
+    fn receipt() -> VerifiedReceipt {
+        VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0xabc".to_string(),
+            image_id: "0ximage".to_string(),
+            journal_hash: "0xjournal".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: None,
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receipt_carries_bls_signature() {
+        let signer = ReceiptSigner::new(&"11".repeat(32)).unwrap()
+            .with_bls(&"22".repeat(32)).unwrap();
+        let signed = signer.sign_receipt(receipt()).await.unwrap();
+
+        let signature = signed.bls_signature.as_deref().unwrap();
+        let public_key = signed.bls_public_key.as_deref().unwrap();
+        assert!(verify(signature, public_key, &compute_receipt_hash(&signed)).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_signatures_aggregate() {
+        let a = ReceiptSigner::new(&"11".repeat(32)).unwrap().with_bls(&"22".repeat(32)).unwrap();
+        let b = ReceiptSigner::new(&"33".repeat(32)).unwrap().with_bls(&"44".repeat(32)).unwrap();
+
+        let signed_a = a.sign_receipt(receipt()).await.unwrap();
+        let signed_b = b.sign_receipt(receipt()).await.unwrap();
+        // The BLS message excludes per-verifier fields, so both sign the same hash
+        let message = compute_receipt_hash(&signed_a);
+        assert_eq!(message, compute_receipt_hash(&signed_b));
+
+        let signature = aggregate_signatures(&[
+            signed_a.bls_signature.clone().unwrap(),
+            signed_b.bls_signature.clone().unwrap(),
+        ]).unwrap();
+        let public_key = aggregate_public_keys(&[
+            signed_a.bls_public_key.clone().unwrap(),
+            signed_b.bls_public_key.clone().unwrap(),
+        ]).unwrap();
+
+        assert!(verify(&signature, &public_key, &message).unwrap());
+
+        // The aggregate does not verify against either key alone
+        assert!(!verify(&signature, signed_a.bls_public_key.as_deref().unwrap(), &message).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_key_is_fatal() {
+        let err = BlsSigner::new("zz").err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::Signing { kind: crate::error::SignerErrorKind::Fatal, .. })
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+//! Checking IPFS content against its CID
+//!
+//! Gateways are not trusted to return the content a CID names, so fetched
+//! bytes are hashed again and compared with the CID's multihash. Supported
+//! are sha2-256 CIDs in two forms:
+//!
+//! - CIDv1 `raw` (`bafkrei...`): the digest is the SHA-256 of the content.
+//! - `dag-pb` (CIDv0 `Qm...`, or CIDv1 `bafybei...`): the digest is of the
+//!   UnixFS node wrapping the content. Content that fit into a single chunk
+//!   when added is rebuilt into that node and checked. Larger content is
+//!   split across a DAG of blocks that the bytes alone cannot be checked
+//!   against, so it is reported as unverifiable.
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use crate::error::VerifierError;
+
+/// Multicodec of raw binary content
+const RAW: u64 = 0x55;
+/// Multicodec of MerkleDAG protobuf nodes
+const DAG_PB: u64 = 0x70;
+/// Multihash code of sha2-256
+const SHA2_256: u64 = 0x12;
+
+/// Largest content `ipfs add` stores as a single block (the default chunk size)
+const MAX_SINGLE_BLOCK: usize = 256 * 1024;
+
+/// A parsed sha2-256 CID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cid {
+    codec: u64,
+    digest: [u8; 32],
+}
+
+impl Cid {
+    pub fn parse(cid: &str) -> Result<Self> {
+        let invalid = |reason: &str| -> anyhow::Error {
+            VerifierError::ProofVerification(format!("Invalid IPFS CID {}: {}", cid, reason)).into()
+        };
+
+        // CIDv0 is a bare base58btc sha2-256 multihash of a dag-pb node
+        if cid.len() == 46 && cid.starts_with("Qm") {
+            let multihash = bs58::decode(cid).into_vec().map_err(|e| invalid(&e.to_string()))?;
+            let digest = sha2_256_digest(&mut multihash.as_slice()).map_err(|e| invalid(&e))?;
+            return Ok(Self { codec: DAG_PB, digest });
+        }
+
+        let bytes = match cid.split_at_checked(1) {
+            Some(("b", encoded)) => base32_decode(encoded).ok_or_else(|| invalid("malformed base32"))?,
+            Some(("z", encoded)) => bs58::decode(encoded).into_vec().map_err(|e| invalid(&e.to_string()))?,
+            _ => return Err(invalid("unsupported multibase (expected base32 or base58btc)")),
+        };
+        let mut bytes = bytes.as_slice();
+        if read_varint(&mut bytes) != Some(1) {
+            return Err(invalid("unsupported CID version"));
+        }
+        let codec = read_varint(&mut bytes).ok_or_else(|| invalid("truncated codec"))?;
+        if codec != RAW && codec != DAG_PB {
+            return Err(invalid(&format!("unsupported codec 0x{:x}", codec)));
+        }
+        let digest = sha2_256_digest(&mut bytes).map_err(|e| invalid(&e))?;
+
+        Ok(Self { codec, digest })
+    }
+
+    /// Whether `content` is what this CID names, or `None` if that cannot
+    /// be told from the content alone
+    pub fn matches(&self, content: &[u8]) -> Option<bool> {
+        let block = match self.codec {
+            RAW => content.to_vec(),
+            _ if content.len() <= MAX_SINGLE_BLOCK => unixfs_file_node(content),
+            _ => return None,
+        };
+        let digest: [u8; 32] = Sha256::digest(&block).into();
+        Some(digest == self.digest)
+    }
+}
+
+/// Check that `content` is what `ipfs_path` (a CID, possibly followed by a
+/// path inside it) names. Content that cannot be checked is refused unless
+/// `allow_unverifiable` is set.
+pub fn verify_content(ipfs_path: &str, content: &[u8], allow_unverifiable: bool) -> Result<()> {
+    let (cid, path) = ipfs_path.split_once('/').unwrap_or((ipfs_path, ""));
+    let parsed = Cid::parse(cid)?;
+
+    let matches = if path.trim_matches('/').is_empty() { parsed.matches(content) } else { None };
+    match matches {
+        Some(true) => Ok(()),
+        Some(false) => Err(VerifierError::ProofVerification(
+            format!("Content returned for ipfs://{} does not match its CID", ipfs_path)
+        ).into()),
+        None if allow_unverifiable => Ok(()),
+        None => Err(VerifierError::ProofVerification(format!(
+            "ipfs://{} names a multi-block DAG or a path inside one, which cannot be checked \
+             against the fetched bytes; publish receipts as CIDv1 raw blocks",
+            ipfs_path
+        )).into()),
+    }
+}
+
+/// The dag-pb node `ipfs add` stores for a file that fits into one block:
+/// `PBNode { Data: UnixFS { Type: File, Data: content, filesize } }`
+fn unixfs_file_node(content: &[u8]) -> Vec<u8> {
+    let mut unixfs = vec![0x08, 0x02];
+    if !content.is_empty() {
+        unixfs.push(0x12);
+        write_varint(&mut unixfs, content.len() as u64);
+        unixfs.extend_from_slice(content);
+    }
+    unixfs.push(0x18);
+    write_varint(&mut unixfs, content.len() as u64);
+
+    let mut node = vec![0x0a];
+    write_varint(&mut node, unixfs.len() as u64);
+    node.extend_from_slice(&unixfs);
+    node
+}
+
+/// Read a sha2-256 multihash that must make up the rest of `bytes`
+fn sha2_256_digest(bytes: &mut &[u8]) -> Result<[u8; 32], String> {
+    let code = read_varint(bytes).ok_or("truncated multihash")?;
+    if code != SHA2_256 {
+        return Err(format!("unsupported hash function 0x{:x} (only sha2-256)", code));
+    }
+    if read_varint(bytes) != Some(32) {
+        return Err("sha2-256 digest must be 32 bytes".to_string());
+    }
+    (*bytes).try_into().map_err(|_| "digest length mismatch".to_string())
+}
+
+/// Read an unsigned LEB128 varint from the front of `bytes`
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Decode unpadded lowercase RFC 4648 base32
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in encoded.bytes() {
+        let value = match c {
+            b'a'..=b'z' => c - b'a',
+            b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | u32::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CIDs `ipfs add` gives these files with default settings
+    const HELLO_V0: &str = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+    const EMPTY_V0: &str = "QmbFMke1KXqnYyBBWxB74N4c5SBnJMVAiMNRcGu6x1AwQH";
+
+    /// CIDv1 raw of `content`, base32-encoded
+    fn raw_cid(content: &[u8]) -> String {
+        let mut bytes = vec![0x01, 0x55, 0x12, 0x20];
+        bytes.extend_from_slice(&Sha256::digest(content));
+        let mut encoded = String::from("b");
+        for chunk in bytes.chunks(5) {
+            let mut padded = [0u8; 5];
+            padded[..chunk.len()].copy_from_slice(chunk);
+            let value = padded.iter().fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+            for i in 0..(chunk.len() * 8).div_ceil(5) {
+                encoded.push(b"abcdefghijklmnopqrstuvwxyz234567"[((value >> (35 - 5 * i)) & 31) as usize] as char);
+            }
+        }
+        encoded
+    }
+
+    #[test]
+    fn test_matching_content() {
+        verify_content(HELLO_V0, b"hello world\n", false).unwrap();
+        verify_content(EMPTY_V0, b"", false).unwrap();
+
+        let receipt = vec![0x5a; 1000];
+        let cid = raw_cid(&receipt);
+        assert!(cid.starts_with("bafkrei"), "{}", cid);
+        verify_content(&cid, &receipt, false).unwrap();
+    }
+
+    #[test]
+    fn test_mismatching_content() {
+        for (cid, content) in [
+            (HELLO_V0.to_string(), &b"hello world"[..]),
+            (EMPTY_V0.to_string(), &b"\0"[..]),
+            (raw_cid(b"original receipt"), &b"forged receipt"[..]),
+        ] {
+            let err = verify_content(&cid, content, false).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ProofVerification(_))),
+                "{}: {}", cid, err
+            );
+        }
+    }
+
+    #[test]
+    fn test_unverifiable_and_invalid_cids() {
+        // Too large for one block, or a path inside a directory
+        let large = vec![0u8; MAX_SINGLE_BLOCK + 1];
+        assert!(verify_content(HELLO_V0, &large, false).is_err());
+        verify_content(HELLO_V0, &large, true).unwrap();
+        assert!(verify_content(&format!("{}/receipt.bin", HELLO_V0), b"hello world\n", false).is_err());
+
+        assert!(Cid::parse("QmNotAValidCidAtAll").is_err());
+        assert!(Cid::parse("fexample").is_err());
+        assert!(Cid::parse(&raw_cid(b"x").replace('a', "1")).is_err());
+    }
+}
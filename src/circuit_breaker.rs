@@ -0,0 +1,156 @@
+//! Circuit breaking for a dependency that may be down for minutes
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Whether calls are let through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through; failures are counted
+    Closed,
+
+    /// Calls fail fast until the cooldown has elapsed
+    Open,
+
+    /// The cooldown has elapsed; the next call is let through as a probe
+    HalfOpen,
+}
+
+#[derive(Default)]
+struct Inner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+/// Opens after `failure_threshold` consecutive failures, fails calls fast for
+/// `cooldown`, then lets a single probe through: its success closes the
+/// circuit, its failure opens it for another cooldown. A threshold of 0
+/// never opens.
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// State of the circuit at `now`
+    pub fn state(&self, now: Instant) -> BreakerState {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if now < opened_at + self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Let a call through, or return how long until the next probe may go
+    pub fn try_acquire(&self, now: Instant) -> Result<(), Duration> {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(opened_at) = inner.opened_at else {
+            return Ok(());
+        };
+
+        let reopens_at = opened_at + self.cooldown;
+        if now < reopens_at {
+            return Err(reopens_at - now);
+        }
+        // Half-open: only one probe at a time
+        if inner.probe_in_flight {
+            return Err(Duration::ZERO);
+        }
+        inner.probe_in_flight = true;
+        Ok(())
+    }
+
+    /// Record a call that succeeded, returning whether this closed the circuit
+    pub fn record_success(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let was_open = inner.opened_at.is_some();
+        *inner = Inner::default();
+        was_open
+    }
+
+    /// Record a call that failed at `now`, returning whether this opened the circuit
+    pub fn record_failure(&self, now: Instant) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.probe_in_flight {
+            // The probe failed: wait out another cooldown
+            inner.probe_in_flight = false;
+            inner.opened_at = Some(now);
+            return true;
+        }
+
+        inner.consecutive_failures += 1;
+        let trips = self.failure_threshold > 0
+            && inner.opened_at.is_none()
+            && inner.consecutive_failures >= self.failure_threshold;
+        if trips {
+            inner.opened_at = Some(now);
+        }
+        trips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_transitions() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        // Closed: failures below the threshold let calls through
+        for _ in 0..2 {
+            assert!(breaker.try_acquire(start).is_ok());
+            assert!(!breaker.record_failure(start));
+        }
+        assert_eq!(breaker.state(start), BreakerState::Closed);
+
+        // The third consecutive failure opens it
+        assert!(breaker.record_failure(start));
+        assert_eq!(breaker.state(start), BreakerState::Open);
+        let later = start + Duration::from_secs(45);
+        assert_eq!(breaker.try_acquire(later), Err(Duration::from_secs(15)));
+
+        // After the cooldown a single probe goes through
+        let probe_at = start + Duration::from_secs(60);
+        assert_eq!(breaker.state(probe_at), BreakerState::HalfOpen);
+        assert!(breaker.try_acquire(probe_at).is_ok());
+        assert_eq!(breaker.try_acquire(probe_at), Err(Duration::ZERO));
+
+        // A failed probe opens it for another cooldown
+        assert!(breaker.record_failure(probe_at));
+        assert_eq!(breaker.state(probe_at + Duration::from_secs(59)), BreakerState::Open);
+
+        // A successful probe closes it and resets the count
+        let probe_at = probe_at + Duration::from_secs(60);
+        assert!(breaker.try_acquire(probe_at).is_ok());
+        assert!(breaker.record_success());
+        assert_eq!(breaker.state(probe_at), BreakerState::Closed);
+        assert!(!breaker.record_failure(probe_at));
+        assert_eq!(breaker.state(probe_at), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_zero_threshold_never_opens() {
+        let breaker = CircuitBreaker::new(0, Duration::from_secs(60));
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(!breaker.record_failure(now));
+        }
+        assert!(breaker.try_acquire(now).is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+//! Compressed receipt bodies
+//!
+//! Receipts may be served gzip- or zstd-compressed, announced by a
+//! `Content-Encoding` header or a `.gz`/`.zst` URL suffix. Bodies are
+//! decompressed before decoding, and `max_receipt_size` bounds the
+//! decompressed size as well as the bytes read off the wire, so a small
+//! compressed body cannot expand without limit.
+
+use anyhow::Result;
+use std::io::Read;
+use crate::error::VerifierError;
+
+/// How a fetched receipt body is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// From a `Content-Encoding` header value; `identity` and unknown codings give `None`
+    pub fn from_content_encoding(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "zstd" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// From the file suffix of a receipt URL, ignoring any query or fragment
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        if path.ends_with(".gz") {
+            Some(Self::Gzip)
+        } else if path.ends_with(".zst") {
+            Some(Self::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// From a response's `Content-Encoding` header, falling back to the URL suffix
+    pub fn from_response(response: &reqwest::Response, url: &str) -> Option<Self> {
+        response.headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::from_content_encoding)
+            .or_else(|| Self::from_url(url))
+    }
+
+    /// Decompress `bytes`, refusing output larger than `max` bytes
+    pub fn decompress(self, bytes: &[u8], max: usize) -> Result<Vec<u8>> {
+        let decoder: Box<dyn Read + '_> = match self {
+            Self::Gzip => Box::new(flate2::read::GzDecoder::new(bytes)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(bytes)?),
+        };
+
+        // Read one byte past the limit to tell "exactly max" from "more"
+        let mut decompressed = Vec::new();
+        decoder.take(max as u64 + 1).read_to_end(&mut decompressed).map_err(|e| {
+            VerifierError::ProofVerification(format!("Invalid {:?} receipt body: {}", self, e))
+        })?;
+        if decompressed.len() > max {
+            return Err(VerifierError::ReceiptTooLarge { size: decompressed.len(), max }.into());
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Decompress `bytes` if `compression` is set, otherwise return them as they are
+pub fn decompress(bytes: Vec<u8>, compression: Option<Compression>, max: usize) -> Result<Vec<u8>> {
+    match compression {
+        Some(compression) => compression.decompress(&bytes, max),
+        None => Ok(bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_detection() {
+        assert_eq!(Compression::from_content_encoding("gzip"), Some(Compression::Gzip));
+        assert_eq!(Compression::from_content_encoding(" ZSTD "), Some(Compression::Zstd));
+        assert_eq!(Compression::from_content_encoding("identity"), None);
+        assert_eq!(Compression::from_url("https://host/receipt.bin.gz"), Some(Compression::Gzip));
+        assert_eq!(Compression::from_url("https://host/receipt.zst?sig=abc"), Some(Compression::Zstd));
+        assert_eq!(Compression::from_url("https://host/receipt.bin"), None);
+    }
+
+    #[test]
+    fn test_decompressed_size_is_bounded() {
+        let body = vec![0u8; 4096];
+        assert_eq!(Compression::Gzip.decompress(&gzip(&body), 4096).unwrap(), body);
+        let zstd = zstd::encode_all(&body[..], 0).unwrap();
+        assert_eq!(Compression::Zstd.decompress(&zstd, 4096).unwrap(), body);
+
+        // A body well under the limit on the wire that expands past it
+        let compressed = gzip(&vec![0u8; 1 << 20]);
+        assert!(compressed.len() < 4096);
+        let err = Compression::Gzip.decompress(&compressed, 4096).unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ReceiptTooLarge { max: 4096, .. })), "{}", err);
+
+        assert!(Compression::Gzip.decompress(b"not gzip", 4096).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! EIP-712 typed structured data hashing
+//!
+//! Shared by receipt signing and sequencer submission. Only the pieces the
+//! verifier's own structs need are implemented: static ABI words, `string`
+//! and `bytes` members (hashed), and the standard four-field domain.
+
+use anyhow::Result;
+use sha3::{Digest, Keccak256};
+use crate::config::Config;
+use crate::error::VerifierError;
+
+/// Type string of the domain every verifier struct is signed under
+pub const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+/// Signing domain, equivalent to Solidity's `EIP712(name, version)` on
+/// `verifying_contract` deployed to `chain_id`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip712Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: [u8; 20],
+}
+
+impl Eip712Domain {
+    pub fn new(name: &str, version: &str, chain_id: u64, verifying_contract: &str) -> Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            chain_id,
+            verifying_contract: parse_address(verifying_contract)?,
+        })
+    }
+
+    /// The receipt signing domain configured by the `eip712_*` keys
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let verifying_contract = config.eip712_verifying_contract.as_deref()
+            .ok_or_else(|| VerifierError::Config("eip712_verifying_contract is required".to_string()))?;
+        Self::new(&config.eip712_name, &config.eip712_version, config.eip712_chain_id, verifying_contract)
+    }
+
+    /// `hashStruct(domain)`
+    pub fn separator(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(Keccak256::digest(DOMAIN_TYPE));
+        hasher.update(Keccak256::digest(&self.name));
+        hasher.update(Keccak256::digest(&self.version));
+        hasher.update(word(&self.chain_id.to_be_bytes()));
+        hasher.update(word(&self.verifying_contract));
+        hasher.finalize().into()
+    }
+
+    /// Digest to sign for a struct hash: `keccak256(0x1901 || separator || struct_hash)`
+    pub fn signing_hash(&self, struct_hash: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update([0x19, 0x01]);
+        hasher.update(self.separator());
+        hasher.update(struct_hash);
+        hasher.finalize().into()
+    }
+}
+
+/// Left-pad a big-endian value to a 32-byte ABI word
+pub fn word(bytes: &[u8]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[32 - bytes.len()..].copy_from_slice(bytes);
+    word
+}
+
+/// Parse a 0x-prefixed 20-byte address
+pub fn parse_address(address: &str) -> Result<[u8; 20]> {
+    hex::decode(address.trim_start_matches("0x")).ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| VerifierError::Config(format!("Invalid address: {}", address)).into())
+}
+
+/// Parse a hex `bytes32` value, with or without a 0x prefix
+pub fn parse_bytes32(value: &str) -> Result<[u8; 32]> {
+    hex::decode(value.trim_start_matches("0x")).ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| VerifierError::signing_fatal(format!("Expected 32 hex-encoded bytes, got {:?}", value)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `Mail` example from the EIP-712 specification
+    #[test]
+    fn test_specification_example() {
+        let domain = Eip712Domain::new("Ether Mail", "1", 1, "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC").unwrap();
+        assert_eq!(
+            hex::encode(domain.separator()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+
+        let person = |name: &str, wallet: &str| -> [u8; 32] {
+            let mut hasher = Keccak256::new();
+            hasher.update(Keccak256::digest("Person(string name,address wallet)"));
+            hasher.update(Keccak256::digest(name));
+            hasher.update(word(&parse_address(wallet).unwrap()));
+            hasher.finalize().into()
+        };
+        let mut mail = Keccak256::new();
+        mail.update(Keccak256::digest(
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+        ));
+        mail.update(person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"));
+        mail.update(person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"));
+        mail.update(Keccak256::digest("Hello, Bob!"));
+        let struct_hash: [u8; 32] = mail.finalize().into();
+
+        assert_eq!(
+            hex::encode(domain.signing_hash(&struct_hash)),
+            "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Device-signed receipt envelopes
+//!
+//! A device may wrap its receipt to prove provenance:
+//!
+//! ```text
+//! "LCEV" | version (1 byte) | signature (65 bytes: r || s || v) | receipt bytes
+//! ```
+//!
+//! The signature is a recoverable secp256k1 signature over the Keccak256 of
+//! the receipt bytes, made with the device's registered key. Bytes that do not
+//! start with the magic are treated as a bare receipt.
+
+use anyhow::Result;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+use crate::error::VerifierError;
+use crate::receipt_signer::derive_address;
+
+/// Leading bytes identifying an envelope
+pub const ENVELOPE_MAGIC: &[u8; 4] = b"LCEV";
+
+/// Only envelope version understood so far
+const ENVELOPE_VERSION: u8 = 1;
+
+const SIGNATURE_LEN: usize = 65;
+const HEADER_LEN: usize = ENVELOPE_MAGIC.len() + 1 + SIGNATURE_LEN;
+
+/// A parsed envelope borrowing from the fetched bytes
+pub struct Envelope<'a> {
+    signature: &'a [u8],
+    pub payload: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    /// Parse an envelope, returning `None` for bytes without the magic
+    pub fn parse(bytes: &'a [u8]) -> Result<Option<Self>> {
+        if !bytes.starts_with(ENVELOPE_MAGIC) {
+            return Ok(None);
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(VerifierError::ProofVerification("Truncated receipt envelope".to_string()).into());
+        }
+
+        let version = bytes[ENVELOPE_MAGIC.len()];
+        if version != ENVELOPE_VERSION {
+            return Err(VerifierError::ProofVerification(
+                format!("Unsupported receipt envelope version {}", version)
+            ).into());
+        }
+
+        Ok(Some(Self {
+            signature: &bytes[ENVELOPE_MAGIC.len() + 1..HEADER_LEN],
+            payload: &bytes[HEADER_LEN..],
+        }))
+    }
+
+    /// Address of the key that signed the payload
+    pub fn signer(&self) -> Result<String> {
+        let invalid = |e: &dyn std::fmt::Display| -> anyhow::Error {
+            VerifierError::ProofVerification(format!("Invalid envelope signature: {}", e)).into()
+        };
+
+        let signature = Signature::from_slice(&self.signature[..64]).map_err(|e| invalid(&e))?;
+        // Accept both raw (0/1) and Ethereum-style (27/28) recovery ids
+        let v = self.signature[64];
+        let recovery_id = RecoveryId::from_byte(if v >= 27 { v - 27 } else { v })
+            .ok_or_else(|| invalid(&format!("bad recovery id {}", v)))?;
+
+        let digest = Keccak256::digest(self.payload);
+        let key = VerifyingKey::recover_from_prehash(&digest, &signature, recovery_id)
+            .map_err(|e| invalid(&e))?;
+
+        Ok(derive_address(&key))
+    }
+}
+
+/// Wrap a receipt in an envelope signed by `key`
+#[cfg(test)]
+pub fn seal(key: &k256::ecdsa::SigningKey, payload: &[u8]) -> Vec<u8> {
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&Keccak256::digest(payload)).unwrap();
+
+    let mut bytes = ENVELOPE_MAGIC.to_vec();
+    bytes.push(ENVELOPE_VERSION);
+    bytes.extend_from_slice(&signature.to_bytes());
+    bytes.push(recovery_id.to_byte() + 27);
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+
+    #[test]
+    fn test_round_trip_recovers_signer() {
+        let key = SigningKey::from_slice(&[0x22; 32]).unwrap();
+        let bytes = seal(&key, b"receipt");
+
+        let envelope = Envelope::parse(&bytes).unwrap().unwrap();
+        assert_eq!(envelope.payload, b"receipt");
+        assert_eq!(envelope.signer().unwrap(), derive_address(key.verifying_key()));
+    }
+
+    #[test]
+    fn test_bare_and_malformed_bytes() {
+        assert!(Envelope::parse(b"\x00\x00\x00\x00receipt").unwrap().is_none());
+        assert!(Envelope::parse(b"LCEV\x01short").is_err());
+
+        let mut bytes = seal(&SigningKey::from_slice(&[0x22; 32]).unwrap(), b"receipt");
+        bytes[4] = 9;
+        assert!(Envelope::parse(&bytes).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! Per-epoch bookkeeping for the verifier's epoch summary attestations
+
+use anyhow::Result;
+use sha3::{Digest, Keccak256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use crate::types::EpochSummary;
+
+/// A submitted receipt as it enters an epoch batch.
+///
+/// Ordering is by epoch, then input, then device (receipt hash breaks any
+/// remaining tie), so a batch sorts the same regardless of processing order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BatchEntry {
+    pub epoch_index: u64,
+    pub input_index: u64,
+    pub device_id: String,
+    /// Hex receipt hash
+    pub receipt_hash: String,
+}
+
+/// An epoch still waiting for its summary
+#[derive(Default)]
+struct OpenEpoch {
+    entries: Vec<BatchEntry>,
+    /// Highest input of the epoch read so far, submitted or not
+    last_input: u64,
+}
+
+/// Receipts submitted so far, grouped by epoch, and the epochs already
+/// summarized
+pub struct EpochTracker {
+    epochs: Mutex<BTreeMap<u64, OpenEpoch>>,
+    summarized: Mutex<BTreeSet<u64>>,
+}
+
+impl EpochTracker {
+    /// A tracker that never reopens the `summarized` epochs
+    pub fn new(summarized: BTreeSet<u64>) -> Self {
+        Self {
+            epochs: Mutex::new(BTreeMap::new()),
+            summarized: Mutex::new(summarized),
+        }
+    }
+
+    /// Note that a request for `input_index` of `epoch_index` was read, so
+    /// the epoch stays open until the watermark passes it
+    pub fn observe(&self, epoch_index: u64, input_index: u64) {
+        if self.summarized.lock().unwrap().contains(&epoch_index) {
+            return;
+        }
+        let mut epochs = self.epochs.lock().unwrap();
+        let epoch = epochs.entry(epoch_index).or_default();
+        epoch.last_input = epoch.last_input.max(input_index);
+    }
+
+    /// Record a submitted receipt under its epoch
+    pub fn record(&self, entry: BatchEntry) {
+        if self.summarized.lock().unwrap().contains(&entry.epoch_index) {
+            return;
+        }
+        let mut epochs = self.epochs.lock().unwrap();
+        let epoch = epochs.entry(entry.epoch_index).or_default();
+        epoch.last_input = epoch.last_input.max(entry.input_index);
+        epoch.entries.push(entry);
+    }
+
+    /// Remove and return every epoch the chain has moved past (older than
+    /// `chain_epoch`) whose inputs are all before `watermark`, so none of
+    /// them can still be retried. Epochs without submitted receipts are
+    /// dropped.
+    pub fn take_finalized(&self, chain_epoch: u64, watermark: u64) -> Vec<(u64, Vec<BatchEntry>)> {
+        let mut epochs = self.epochs.lock().unwrap();
+        let closed: Vec<u64> = epochs.range(..chain_epoch)
+            .filter(|(_, epoch)| epoch.last_input < watermark)
+            .map(|(epoch_index, _)| *epoch_index)
+            .collect();
+        closed.into_iter()
+            .filter_map(|epoch_index| epochs.remove(&epoch_index).map(|epoch| (epoch_index, epoch.entries)))
+            .filter(|(_, entries)| !entries.is_empty())
+            .collect()
+    }
+
+    /// Put an epoch back after a failed summary submission
+    pub fn restore(&self, epoch_index: u64, entries: Vec<BatchEntry>) {
+        let mut epochs = self.epochs.lock().unwrap();
+        let epoch = epochs.entry(epoch_index).or_default();
+        epoch.last_input = entries.iter().map(|entry| entry.input_index).fold(epoch.last_input, u64::max);
+        epoch.entries.extend(entries);
+    }
+
+    /// Close `epoch_index` for good once its summary is submitted
+    pub fn mark_summarized(&self, epoch_index: u64) {
+        self.summarized.lock().unwrap().insert(epoch_index);
+        self.epochs.lock().unwrap().remove(&epoch_index);
+    }
+}
+
+/// Build the (unsigned) summary of an epoch's verified receipts.
+///
+/// Entries are sorted first so the root depends only on the set of receipts.
+pub fn summarize(epoch_index: u64, entries: &[BatchEntry]) -> Result<EpochSummary> {
+    let mut entries = entries.to_vec();
+    entries.sort();
+
+    let leaves = entries.iter()
+        .map(|entry| {
+            let hash = &entry.receipt_hash;
+            let bytes = hex::decode(hash.trim_start_matches("0x"))?;
+            <[u8; 32]>::try_from(bytes.as_slice())
+                .map_err(|_| anyhow::anyhow!("Receipt hash is not 32 bytes: {}", hash))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(EpochSummary {
+        epoch_index,
+        receipt_count: leaves.len() as u64,
+        merkle_root: format!("0x{}", hex::encode(merkle_root(&leaves))),
+        signature: String::new(),
+        verifier_address: None,
+    })
+}
+
+/// Prefix of a hashed leaf, `keccak256(0x00 || leaf)`
+const LEAF_PREFIX: u8 = 0x00;
+
+/// Prefix of a hashed pair, `keccak256(0x01 || left || right)`
+const NODE_PREFIX: u8 = 0x01;
+
+/// Keccak256 binary Merkle root, with leaves and inner nodes hashed under
+/// different prefixes (as in RFC 6962) so no inner node can pass for a leaf.
+/// An odd node is carried up unchanged and an empty tree has the zero root.
+pub fn merkle_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return [0u8; 32];
+    }
+
+    let mut level: Vec<[u8; 32]> = leaves.iter()
+        .map(|leaf| Keccak256::new().chain_update([LEAF_PREFIX]).chain_update(leaf).finalize().into())
+        .collect();
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => Keccak256::new()
+                    .chain_update([NODE_PREFIX])
+                    .chain_update(left)
+                    .chain_update(right)
+                    .finalize()
+                    .into(),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+
+    level[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt_signer::ReceiptSigner;
+
+    fn entry(epoch_index: u64, input_index: u64, i: u8) -> BatchEntry {
+        BatchEntry {
+            epoch_index,
+            input_index,
+            device_id: format!("device-{}", i),
+            receipt_hash: hex::encode([i; 32]),
+        }
+    }
+
+    #[test]
+    fn test_take_finalized_only_returns_closed_epochs() {
+        let tracker = EpochTracker::new(BTreeSet::new());
+        tracker.record(entry(1, 0, 1));
+        tracker.record(entry(2, 1, 2));
+        tracker.record(entry(3, 2, 3));
+
+        let finalized = tracker.take_finalized(3, 3);
+        assert_eq!(finalized.iter().map(|(e, _)| *e).collect::<Vec<_>>(), vec![1, 2]);
+
+        // Epoch 3 is still open, and finalized epochs are not returned twice
+        assert!(tracker.take_finalized(3, 3).is_empty());
+        assert_eq!(tracker.take_finalized(4, 3).len(), 1);
+    }
+
+    #[test]
+    fn test_epoch_waits_for_the_watermark_to_pass_its_inputs() {
+        let tracker = EpochTracker::new(BTreeSet::new());
+        tracker.record(entry(1, 4, 1));
+        // Input 6 of the epoch was read but is still pending
+        tracker.observe(1, 6);
+
+        assert!(tracker.take_finalized(2, 6).is_empty());
+        tracker.record(entry(1, 6, 2));
+        let (epoch_index, entries) = tracker.take_finalized(2, 7).pop().unwrap();
+        assert_eq!(epoch_index, 1);
+        assert_eq!(entries.len(), 2);
+    }
+
+    #[test]
+    fn test_summarized_epoch_is_not_reopened() {
+        let tracker = EpochTracker::new(BTreeSet::from([1]));
+        tracker.record(entry(1, 0, 1));
+        tracker.observe(1, 0);
+        assert!(tracker.take_finalized(3, 3).is_empty());
+
+        tracker.record(entry(2, 1, 2));
+        tracker.mark_summarized(2);
+        tracker.record(entry(2, 2, 3));
+        assert!(tracker.take_finalized(3, 3).is_empty());
+    }
+
+    #[test]
+    fn test_epoch_without_receipts_is_dropped() {
+        let tracker = EpochTracker::new(BTreeSet::new());
+        tracker.observe(1, 0);
+        assert!(tracker.take_finalized(2, 1).is_empty());
+        assert!(tracker.epochs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_epoch_summary_counts_and_signs() {
+        let tracker = EpochTracker::new(BTreeSet::new());
+        for i in 0..5 {
+            tracker.record(entry(7, i as u64, i));
+        }
+        tracker.record(entry(8, 0, 99));
+
+        let (epoch_index, entries) = tracker.take_finalized(8, u64::MAX).pop().unwrap();
+        let summary = summarize(epoch_index, &entries).unwrap();
+        assert_eq!(summary.epoch_index, 7);
+        assert_eq!(summary.receipt_count, 5);
+
+        let expected_root = merkle_root(&(0..5).map(|i| [i; 32]).collect::<Vec<_>>());
+        assert_eq!(summary.merkle_root, format!("0x{}", hex::encode(expected_root)));
+
+        let signer = ReceiptSigner::new(&hex::encode([0x11u8; 32])).unwrap();
+        let signed = signer.sign_epoch_summary(summary).await.unwrap();
+        assert_eq!(signed.verifier_address, Some(signer.get_address()));
+        assert!(signer.verify_epoch_summary(&signed));
+    }
+
+    #[test]
+    fn test_batch_root_is_independent_of_order() {
+        // Two devices share input 3 so the device_id tie-break is exercised
+        let mut entries: Vec<BatchEntry> = (0..9).map(|i| entry(4, (i / 2) as u64, i)).collect();
+        let expected = summarize(4, &entries).unwrap().merkle_root;
+
+        entries.reverse();
+        assert_eq!(summarize(4, &entries).unwrap().merkle_root, expected);
+
+        for shift in 1..entries.len() {
+            entries.rotate_left(shift);
+            entries.swap(0, shift);
+            assert_eq!(summarize(4, &entries).unwrap().merkle_root, expected);
+        }
+    }
+
+    fn leaf(byte: u8) -> [u8; 32] {
+        Keccak256::new().chain_update([0x00]).chain_update([byte; 32]).finalize().into()
+    }
+
+    fn node(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+        Keccak256::new().chain_update([0x01]).chain_update(left).chain_update(right).finalize().into()
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        assert_eq!(merkle_root(&[]), [0u8; 32]);
+        assert_eq!(merkle_root(&[[1; 32]]), leaf(1));
+
+        let pair = node(leaf(1), leaf(2));
+        assert_eq!(merkle_root(&[[1; 32], [2; 32]]), pair);
+
+        // Odd leaf is carried up
+        assert_eq!(merkle_root(&[[1; 32], [2; 32], [3; 32]]), node(pair, leaf(3)));
+    }
+
+    #[test]
+    fn test_inner_node_is_not_a_leaf() {
+        // Without leaf and node prefixes, a tree whose leaves are the inner
+        // nodes of another tree would share its root
+        let leaves = [[1; 32], [2; 32], [3; 32], [4; 32]];
+        let inner = [node(leaf(1), leaf(2)), node(leaf(3), leaf(4))];
+        assert_ne!(merkle_root(&inner), merkle_root(&leaves));
+        assert_ne!(merkle_root(&[merkle_root(&leaves)]), merkle_root(&leaves));
+    }
+}
@@ -16,8 +16,8 @@ pub enum VerifierError {
     #[error("Invalid image ID: expected {expected}, got {actual}")]
     InvalidImageId { expected: String, actual: String },
     
-    #[error("Receipt signing error: {0}")]
-    Signing(String),
+    #[error("Receipt signing error: {message}")]
+    Signing { kind: SignerErrorKind, message: String },
     
     #[error("InputBox submission error: {0}")]
     InputBox(String),
@@ -25,6 +25,12 @@ pub enum VerifierError {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
     
+    #[error("Request timed out: {0}")]
+    Timeout(String),
+    
+    #[error("{service} returned HTTP {status}: {body}")]
+    HttpStatus { service: &'static str, status: u16, body: String },
+    
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     
@@ -33,6 +39,146 @@ pub enum VerifierError {
     
     #[error("Receipt too large: {size} bytes exceeds maximum {max} bytes")]
     ReceiptTooLarge { size: usize, max: usize },
+    
+    #[error("Receipt hash mismatch (possible substitution): expected {expected}, got {actual}")]
+    ReceiptHashMismatch { expected: String, actual: String },
+    
+    #[error("Not found: {0}")]
+    NotFound(String),
+    
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+    
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    
+    #[error("Conflict: {0}")]
+    Conflict(String),
+}
+
+/// Whether a signing failure may succeed on a later attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerErrorKind {
+    /// The signer backend was temporarily unavailable (e.g. a remote KMS)
+    Transient,
+    
+    /// The key or signer is misconfigured; retrying cannot help
+    Fatal,
 }
 
-pub type Result<T> = std::result::Result<T, VerifierError>;
+impl VerifierError {
+    /// A signing error that no retry will fix
+    pub fn signing_fatal(message: impl Into<String>) -> Self {
+        VerifierError::Signing { kind: SignerErrorKind::Fatal, message: message.into() }
+    }
+    
+    /// A signing error from a backend that may recover
+    pub fn signing_transient(message: impl Into<String>) -> Self {
+        VerifierError::Signing { kind: SignerErrorKind::Transient, message: message.into() }
+    }
+}
+
+/// Whether an operation that failed with `err` may succeed if retried.
+/// Network failures, timeouts and 5xx responses may; malformed data, client
+/// errors and misconfiguration fail the same way every time.
+pub fn is_retryable(err: &VerifierError) -> bool {
+    match err {
+        VerifierError::Network(e) => match e.status() {
+            Some(status) => is_retryable_status(status.as_u16()),
+            None => !(e.is_builder() || e.is_decode() || e.is_redirect()),
+        },
+        VerifierError::Timeout(_) => true,
+        VerifierError::HttpStatus { status, .. } => is_retryable_status(*status),
+        VerifierError::Signing { kind, .. } => *kind == SignerErrorKind::Transient,
+        VerifierError::Config(_)
+        | VerifierError::GraphQL(_)
+        | VerifierError::ProofVerification(_)
+        | VerifierError::InvalidImageId { .. }
+        | VerifierError::InputBox(_)
+        | VerifierError::Serialization(_)
+        | VerifierError::Hex(_)
+        | VerifierError::ReceiptTooLarge { .. }
+        | VerifierError::ReceiptHashMismatch { .. }
+        | VerifierError::NotFound(_)
+        | VerifierError::InvalidQuery(_)
+        | VerifierError::Unauthorized(_)
+        | VerifierError::Conflict(_) => false,
+    }
+}
+
+/// Whether `err` rejects a proof request itself, so processing it again
+/// fails the same way: an invalid or untrusted proof, or a receipt over the
+/// size limit or not matching its committed hash. Any other failure may be
+/// an outage and is worth another try.
+pub fn is_rejection(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<VerifierError>(),
+        Some(
+            VerifierError::ProofVerification(_)
+                | VerifierError::InvalidImageId { .. }
+                | VerifierError::ReceiptTooLarge { .. }
+                | VerifierError::ReceiptHashMismatch { .. }
+        )
+    )
+}
+
+/// [`is_retryable`] for any error; errors that are not a `VerifierError`
+/// are not classified and so are not retried
+pub fn is_retryable_error(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<VerifierError>().is_some_and(is_retryable)
+}
+
+/// Server errors, plus the client statuses that mean "try again later"
+fn is_retryable_status(status: u16) -> bool {
+    status >= 500 || status == 408 || status == 429
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_retryability_of_each_variant() {
+        let connect_error = reqwest::get("http://127.0.0.1:1/").await.unwrap_err();
+        let builder_error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let http = |status| VerifierError::HttpStatus { service: "InputBox", status, body: String::new() };
+        
+        let retryable = [
+            VerifierError::Network(connect_error),
+            VerifierError::Timeout("GraphQL request".into()),
+            http(500),
+            http(503),
+            http(429),
+            VerifierError::signing_transient("KMS unavailable"),
+        ];
+        let permanent = [
+            VerifierError::Network(builder_error),
+            http(400),
+            http(404),
+            VerifierError::signing_fatal("Invalid private key"),
+            VerifierError::Config("bad".into()),
+            VerifierError::GraphQL("Cannot query field".into()),
+            VerifierError::ProofVerification("bad seal".into()),
+            VerifierError::InvalidImageId { expected: "0x01".into(), actual: "0x02".into() },
+            VerifierError::InputBox("rejected".into()),
+            VerifierError::Serialization(serde_json::from_str::<u32>("x").unwrap_err()),
+            VerifierError::Hex(hex::FromHexError::OddLength),
+            VerifierError::ReceiptTooLarge { size: 2, max: 1 },
+            VerifierError::ReceiptHashMismatch { expected: "0x01".into(), actual: "0x02".into() },
+            VerifierError::NotFound("receipt".into()),
+            VerifierError::InvalidQuery("limit".into()),
+            VerifierError::Unauthorized("token".into()),
+            VerifierError::Conflict("restart needed".into()),
+        ];
+        
+        for err in &retryable {
+            assert!(is_retryable(err), "{} should be retried", err);
+        }
+        for err in &permanent {
+            assert!(!is_retryable(err), "{} should not be retried", err);
+        }
+        
+        assert!(is_retryable_error(&VerifierError::Timeout("x".into()).into()));
+        assert!(!is_retryable_error(&anyhow::anyhow!("unclassified")));
+    }
+}
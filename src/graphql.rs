@@ -1,12 +1,20 @@
 //! GraphQL client for querying Cartesi node
 
 use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tokio::time::{timeout, sleep};
-use tracing::{info, warn, error, debug};
+use tracing::{info, info_span, warn, error, debug, Instrument};
+use crate::circuit_breaker::{BreakerState, CircuitBreaker};
+use crate::config::{InputIndexMismatchPolicy, ProofRequestSource};
 use crate::types::ProofRequest;
-use crate::error::VerifierError;
+use crate::error::{is_retryable_error, VerifierError};
+use crate::proxy::ProxySettings;
 
 #[derive(Serialize)]
 struct GraphQLRequest {
@@ -25,14 +33,53 @@ struct GraphQLError {
     message: String,
 }
 
+/// Kind of output a proof request can arrive in. Reports share the shape
+/// of notices, so both are read into the notice types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum OutputKind {
+    Notice,
+    Report,
+}
+
+impl OutputKind {
+    /// Name of the output's connection in the Cartesi schema
+    fn connection(self) -> &'static str {
+        match self {
+            OutputKind::Notice => "notices",
+            OutputKind::Report => "reports",
+        }
+    }
+}
+
+/// Cursor a capped poll stopped at. The next poll from the same watermark
+/// continues from it.
+struct ResumePoint {
+    /// Watermark the capped poll was read under
+    watermark: u64,
+    cursor: String,
+    /// Input of the last output read, which may have more outputs after the cursor
+    last_input: Option<u64>,
+}
+
 #[derive(Deserialize)]
 struct NoticesData {
+    #[serde(alias = "reports")]
     notices: NoticesConnection,
 }
 
 #[derive(Deserialize)]
 struct NoticesConnection {
     edges: Vec<NoticeEdge>,
+    /// Absent from nodes that do not paginate; treated as the last page
+    #[serde(rename = "pageInfo", default)]
+    page_info: Option<PageInfo>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -42,64 +89,339 @@ struct NoticeEdge {
 
 #[derive(Deserialize)]
 struct NoticeNode {
-    index: String,
+    index: serde_json::Value,
     input: InputNode,
     payload: String,
 }
 
 #[derive(Deserialize)]
 struct InputNode {
-    index: String,
+    /// Int in the Cartesi schema; some node versions serialize it as a string
+    index: serde_json::Value,
+    /// Block timestamp of the input, in seconds; a BigInt, so often a string
+    #[serde(default)]
+    timestamp: serde_json::Value,
+}
+
+impl InputNode {
+    fn parsed_index(&self) -> Option<u64> {
+        parse_index(&self.index)
+    }
+    
+    fn parsed_timestamp(&self) -> Option<u64> {
+        parse_index(&self.timestamp)
+    }
+}
+
+#[derive(Deserialize)]
+struct InputsData {
+    inputs: InputsConnection,
+}
+
+#[derive(Deserialize)]
+struct InputsConnection {
+    edges: Vec<InputEdge>,
+    #[serde(rename = "pageInfo", default)]
+    page_info: Option<PageInfo>,
+}
+
+#[derive(Deserialize)]
+struct InputEdge {
+    node: InputWithNotices,
+}
+
+#[derive(Deserialize)]
+struct InputWithNotices {
+    index: serde_json::Value,
+    #[serde(default)]
+    timestamp: serde_json::Value,
+    #[serde(alias = "reports")]
+    notices: InputNoticesConnection,
+}
+
+#[derive(Deserialize)]
+struct InputNoticesConnection {
+    edges: Vec<InputNoticeEdge>,
+}
+
+#[derive(Deserialize)]
+struct InputNoticeEdge {
+    node: InputNoticeNode,
+}
+
+#[derive(Deserialize)]
+struct InputNoticeNode {
+    index: serde_json::Value,
+    payload: String,
+}
+
+impl From<InputsConnection> for NoticesConnection {
+    /// Flatten each input's notices into notice edges carrying their input index
+    fn from(inputs: InputsConnection) -> Self {
+        let edges = inputs.edges.into_iter()
+            .flat_map(|input| {
+                let (input_index, timestamp) = (input.node.index, input.node.timestamp);
+                input.node.notices.edges.into_iter().map(move |notice| NoticeEdge {
+                    node: NoticeNode {
+                        index: notice.node.index,
+                        input: InputNode { index: input_index.clone(), timestamp: timestamp.clone() },
+                        payload: notice.node.payload,
+                    },
+                })
+            })
+            .collect();
+        
+        Self { edges, page_info: inputs.page_info }
+    }
+}
+
+#[derive(Deserialize)]
+struct EpochsData {
+    epochs: EpochsConnection,
+}
+
+#[derive(Deserialize)]
+struct EpochsConnection {
+    edges: Vec<EpochEdge>,
+}
+
+#[derive(Deserialize)]
+struct EpochEdge {
+    node: EpochNode,
+}
+
+#[derive(Deserialize)]
+struct EpochNode {
+    index: serde_json::Value,
+}
+
+/// Notices requested per page
+const NOTICES_PAGE_SIZE: usize = 100;
+
+/// Identifies the receipt a proof request points at
+fn receipt_reference(request: &ProofRequest) -> (String, String) {
+    let reference = match &request.receipt_inline {
+        Some(inline) if request.receipt_url.is_empty() => inline.clone(),
+        _ => request.receipt_url.clone(),
+    };
+    (request.device_id.clone(), reference)
+}
+
+/// Name of a query document's operation, e.g. `GetProofRequests`
+fn operation_name(query: &str) -> &str {
+    query.trim_start()
+        .strip_prefix("query ")
+        .and_then(|rest| rest.split(|c: char| !c.is_ascii_alphanumeric() && c != '_').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("anonymous")
+}
+
+/// Parse a GraphQL index that may be an Int or a numeric string
+fn parse_index(index: &serde_json::Value) -> Option<u64> {
+    match index {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// A GraphQL replica and when it may next be tried after a failure
+struct Endpoint {
+    url: String,
+    down_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.down_until.lock().unwrap().is_none_or(|until| now >= until)
+    }
+    
+    fn mark_down(&self, cooldown: Duration) {
+        *self.down_until.lock().unwrap() = Some(Instant::now() + cooldown);
+    }
+    
+    fn mark_up(&self) {
+        *self.down_until.lock().unwrap() = None;
+    }
 }
 
 pub struct GraphQLClient {
-    endpoint: String,
+    endpoints: Vec<Endpoint>,
+    next_endpoint: AtomicUsize,
+    reprobe_interval: Duration,
     client: reqwest::Client,
+    proxy: ProxySettings,
     max_retries: u32,
     retry_delay: Duration,
     request_timeout: Duration,
+    input_index_policy: InputIndexMismatchPolicy,
+    /// Most notices read in one `query_proof_requests` call
+    max_notices_per_poll: usize,
+    /// Outputs `query_proof_requests` reads
+    proof_request_source: ProofRequestSource,
+    /// Where a capped poll of each output kind stopped
+    resume_cursors: Mutex<HashMap<OutputKind, ResumePoint>>,
+    /// Input index polls start from: every earlier input is settled. It is
+    /// the first unread input, or the first with requests still pending.
+    last_seen_index: AtomicU64,
+    /// Fails polls fast while the node is down, instead of retrying each one
+    breaker: CircuitBreaker,
 }
 
 impl GraphQLClient {
-    pub fn new(endpoint: &str) -> Result<Self> {
+    /// Create a client over one or more GraphQL replicas.
+    ///
+    /// Queries are spread round-robin across healthy replicas. A replica that
+    /// fails is skipped for `reprobe_interval`, after which the next query
+    /// through it acts as the re-probe.
+    pub fn new(endpoints: &[String], reprobe_interval: Duration) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err(VerifierError::Config("At least one GraphQL endpoint is required".to_string()).into());
+        }
+        
         Ok(Self {
-            endpoint: endpoint.to_string(),
-            client: reqwest::Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()?,
+            endpoints: endpoints.iter()
+                .map(|url| Endpoint { url: url.clone(), down_until: Mutex::new(None) })
+                .collect(),
+            next_endpoint: AtomicUsize::new(0),
+            reprobe_interval,
+            client: ProxySettings::default().client(Duration::from_secs(30))?,
+            proxy: ProxySettings::default(),
             max_retries: 3,
             retry_delay: Duration::from_secs(2),
             request_timeout: Duration::from_secs(30),
+            input_index_policy: InputIndexMismatchPolicy::default(),
+            max_notices_per_poll: 1000,
+            proof_request_source: ProofRequestSource::default(),
+            resume_cursors: Mutex::new(HashMap::new()),
+            last_seen_index: AtomicU64::new(0),
+            breaker: CircuitBreaker::new(5, Duration::from_secs(60)),
         })
     }
     
-    /// Execute GraphQL request with retry logic
+    /// Fail polls fast for `cooldown` after `failure_threshold` consecutive
+    /// failed polls (0 disables the breaker)
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.breaker = CircuitBreaker::new(failure_threshold, cooldown);
+        self
+    }
+    
+    /// State of the circuit breaker around proof request polls
+    pub fn breaker_state(&self) -> BreakerState {
+        self.breaker.state(tokio::time::Instant::now())
+    }
+    
+    /// Bound how many notices one poll reads; the rest are read by later polls
+    pub fn with_max_notices_per_poll(mut self, max: usize) -> Self {
+        self.max_notices_per_poll = max.max(1);
+        self
+    }
+    
+    /// Read proof requests from notices, reports, or both
+    pub fn with_proof_request_source(mut self, source: ProofRequestSource) -> Self {
+        self.proof_request_source = source;
+        self
+    }
+    
+    /// Set how proof requests whose `input_index` disagrees with their notice are handled
+    pub fn with_input_index_policy(mut self, policy: InputIndexMismatchPolicy) -> Self {
+        self.input_index_policy = policy;
+        self
+    }
+    
+    /// Send all queries through the configured proxies
+    pub fn with_proxy(mut self, proxy: &ProxySettings) -> Result<Self> {
+        self.client = proxy.client(self.request_timeout)?;
+        self.proxy = proxy.clone();
+        Ok(self)
+    }
+    
+    /// Give up on a query attempt after `request_timeout`
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Result<Self> {
+        self.client = self.proxy.client(request_timeout)?;
+        self.request_timeout = request_timeout;
+        Ok(self)
+    }
+    
+    /// Input index the next poll starts from
+    pub fn last_seen_index(&self) -> u64 {
+        self.last_seen_index.load(Ordering::Relaxed)
+    }
+    
+    /// Move the watermark up to `index` once every earlier input is settled.
+    /// It never moves backwards, nor past the last input of a capped read,
+    /// whose remaining outputs are still unread.
+    pub fn advance_watermark(&self, index: u64) {
+        let capped = self.resume_cursors.lock().unwrap().values().filter_map(|point| point.last_input).min();
+        let index = capped.map_or(index, |last_input| index.min(last_input));
+        self.last_seen_index.fetch_max(index, Ordering::Relaxed);
+    }
+    
+    /// Pick the next healthy endpoint in round-robin order, or the next one
+    /// regardless of health if every replica is marked down
+    fn select_endpoint(&self) -> &Endpoint {
+        let count = self.endpoints.len();
+        let start = self.next_endpoint.fetch_add(1, Ordering::Relaxed) % count;
+        let now = Instant::now();
+        
+        (0..count)
+            .map(|offset| &self.endpoints[(start + offset) % count])
+            .find(|endpoint| endpoint.is_healthy(now))
+            .unwrap_or(&self.endpoints[start])
+    }
+    
+    /// Execute GraphQL request with retry logic, inside a span named after
+    /// the query's operation
     async fn execute_with_retry<T>(&self, request: &GraphQLRequest) -> Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let span = info_span!("graphql_query", operation = operation_name(&request.query));
+        self.retry_request(request).instrument(span).await
+    }
+    
+    async fn retry_request<T>(&self, request: &GraphQLRequest) -> Result<T>
     where
         T: for<'de> serde::Deserialize<'de>,
     {
         let mut last_error = None;
         
         for attempt in 1..=self.max_retries {
-            debug!("GraphQL attempt {}/{} to {}", attempt, self.max_retries, self.endpoint);
+            let endpoint = self.select_endpoint();
+            debug!("GraphQL attempt {}/{} to {}", attempt, self.max_retries, endpoint.url);
             
-            match timeout(self.request_timeout, self.execute_request::<T>(request)).await {
+            match timeout(self.request_timeout, self.execute_request::<T>(&endpoint.url, request)).await {
                 Ok(Ok(result)) => {
                     if attempt > 1 {
-                        info!("GraphQL request succeeded on attempt {}", attempt);
+                        info!("GraphQL request succeeded on attempt {} via {}", attempt, endpoint.url);
                     }
+                    endpoint.mark_up();
                     return Ok(result);
                 }
+                Ok(Err(e)) if !is_retryable_error(&e) => {
+                    // The same request would fail the same way on any replica
+                    warn!("GraphQL request to {} failed permanently: {}", endpoint.url, e);
+                    return Err(e);
+                }
                 Ok(Err(e)) => {
-                    warn!("GraphQL request failed on attempt {}: {}", attempt, e);
+                    warn!("GraphQL request to {} failed on attempt {}: {}", endpoint.url, attempt, e);
+                    endpoint.mark_down(self.reprobe_interval);
                     last_error = Some(e);
                 }
                 Err(_) => {
-                    warn!("GraphQL request timed out on attempt {}", attempt);
-                    last_error = Some(VerifierError::GraphQL("Request timeout".to_string()).into());
+                    warn!("GraphQL request to {} timed out on attempt {}", endpoint.url, attempt);
+                    endpoint.mark_down(self.reprobe_interval);
+                    last_error = Some(VerifierError::Timeout(
+                        format!("GraphQL request to {} after {:?}", endpoint.url, self.request_timeout)
+                    ).into());
                 }
             }
             
+            // Fail over immediately when another replica is available
+            if self.endpoints.len() > 1 && self.has_healthy_endpoint() {
+                continue;
+            }
+            
             if attempt < self.max_retries {
                 let delay = self.retry_delay * attempt;
                 debug!("Waiting {:?} before retry...", delay);
@@ -111,30 +433,32 @@ impl GraphQLClient {
         Err(last_error.unwrap_or_else(|| VerifierError::GraphQL("All retry attempts failed".to_string()).into()))
     }
     
+    /// Whether any replica is currently eligible for a request
+    fn has_healthy_endpoint(&self) -> bool {
+        let now = Instant::now();
+        self.endpoints.iter().any(|endpoint| endpoint.is_healthy(now))
+    }
+    
     /// Execute single GraphQL request
-    async fn execute_request<T>(&self, request: &GraphQLRequest) -> Result<T>
+    async fn execute_request<T>(&self, endpoint: &str, request: &GraphQLRequest) -> Result<T>
     where
         T: for<'de> serde::Deserialize<'de>,
     {
         let response = self.client
-            .post(&self.endpoint)
+            .post(endpoint)
             .json(request)
             .send()
-            .await?;
+            .await
+            .map_err(VerifierError::Network)?;
         
-        // Check for HTTP errors (502, 503, 504)
         if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            
-            if status.as_u16() >= 502 && status.as_u16() <= 504 {
-                return Err(VerifierError::GraphQL(format!("Server busy ({}): {}", status, error_text)).into());
-            } else {
-                return Err(VerifierError::GraphQL(format!("HTTP error ({}): {}", status, error_text)).into());
-            }
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(VerifierError::HttpStatus { service: "GraphQL endpoint", status, body }.into());
         }
         
-        let graphql_response: GraphQLResponse<T> = response.json().await?;
+        let graphql_response: GraphQLResponse<T> = response.json().await
+            .map_err(VerifierError::Network)?;
         
         if let Some(errors) = graphql_response.errors {
             return Err(VerifierError::GraphQL(
@@ -150,55 +474,318 @@ impl GraphQLClient {
             .ok_or_else(|| VerifierError::GraphQL("No data in response".to_string()).into())
     }
     
-    /// Query for proof request notices
+    /// Query for proof requests from the watermark on, in the outputs
+    /// `proof_request_source` selects. Fails fast while the circuit breaker
+    /// is open.
     pub async fn query_proof_requests(&self) -> Result<Vec<ProofRequest>> {
-        // Query for notices with risc0_proof_request payload
+        if let Err(wait) = self.breaker.try_acquire(tokio::time::Instant::now()) {
+            return Err(VerifierError::GraphQL(format!(
+                "GraphQL circuit open after repeated failures; next probe in {}s", wait.as_secs()
+            )).into());
+        }
+        
+        let result = self.poll_proof_requests().await;
+        match &result {
+            // Only failures a retry could fix say the node is down; one that
+            // answered with an error is up
+            Err(e) if is_retryable_error(e) => {
+                if self.breaker.record_failure(tokio::time::Instant::now()) {
+                    warn!("GraphQL circuit opened: failing polls fast until the next probe");
+                }
+            }
+            _ => {
+                if self.breaker.record_success() {
+                    info!("GraphQL circuit closed: the node answered the probe");
+                }
+            }
+        }
+        result
+    }
+    
+    async fn poll_proof_requests(&self) -> Result<Vec<ProofRequest>> {
+        match self.proof_request_source {
+            ProofRequestSource::Notices => self.query_proof_requests_since(self.last_seen_index()).await,
+            ProofRequestSource::Reports => self.query_proof_requests_from_reports().await,
+            ProofRequestSource::Both => {
+                let mut requests = self.query_proof_requests_since(self.last_seen_index()).await?;
+                let reports = self.query_proof_requests_from_reports().await?;
+                
+                // A dapp emitting the same request as a notice and a report
+                // must not have it verified twice
+                let seen: HashSet<_> = requests.iter().map(|r| (r.epoch_index, r.input_index)).collect();
+                requests.extend(reports.into_iter().filter(|r| !seen.contains(&(r.epoch_index, r.input_index))));
+                requests.sort_by_key(|r| r.input_index);
+                Ok(requests)
+            }
+        }
+    }
+    
+    /// Query for proof request notices of inputs at or after `index`
+    pub async fn query_proof_requests_since(&self, index: u64) -> Result<Vec<ProofRequest>> {
+        let edges = self.query_outputs(OutputKind::Notice, index).await?;
+        self.parse_proof_requests(edges)
+    }
+    
+    /// Query for the proof request notices of epochs in `epochs`, reading
+    /// from the first input without using or moving the watermark. Epochs
+    /// are only known from the notice payloads, so every notice is read.
+    pub async fn query_proof_requests_in_epochs(&self, epochs: RangeInclusive<u64>) -> Result<Vec<ProofRequest>> {
+        let mut requests = Vec::new();
+        loop {
+            let edges = self.query_outputs(OutputKind::Notice, 0).await?;
+            requests.extend(self.parse_proof_requests(edges)?.into_iter().filter(|r| epochs.contains(&r.epoch_index)));
+            
+            // A read capped at `max_notices_per_poll` leaves a cursor to continue from
+            if !self.resume_cursors.lock().unwrap().contains_key(&OutputKind::Notice) {
+                return Ok(requests);
+            }
+        }
+    }
+    
+    /// Query for proof request reports from the watermark on
+    pub async fn query_proof_requests_from_reports(&self) -> Result<Vec<ProofRequest>> {
+        let edges = self.query_outputs(OutputKind::Report, self.last_seen_index()).await?;
+        self.parse_proof_requests(edges)
+    }
+    
+    /// Parse notice or report payloads, keeping those that are proof requests
+    fn parse_proof_requests(&self, edges: Vec<NoticeEdge>) -> Result<Vec<ProofRequest>> {
+        // Parse notices and filter for proof requests
+        let mut requests = Vec::new();
+        // Overlapping pages can return the same notice (or the same receipt
+        // republished) more than once; keep only the first occurrence
+        let mut seen_notices = HashSet::new();
+        let mut seen_receipts = HashSet::new();
+        
+        for edge in edges {
+            let payload_hex = edge.node.payload.trim_start_matches("0x");
+            
+            // Decode hex payload
+            let payload_bytes = hex::decode(payload_hex)?;
+            let payload_str = String::from_utf8(payload_bytes)
+                .map_err(|e| VerifierError::GraphQL(format!("Invalid UTF-8 in payload: {}", e)))?;
+            
+            // Try to parse as JSON
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&payload_str) {
+                // Check if this is a proof request
+                if json.get("type").and_then(|v| v.as_str()) == Some("risc0_proof_request") {
+                    if let Ok(mut request) = serde_json::from_value::<ProofRequest>(json["data"].clone()) {
+                        let notice_key = edge.node.input.parsed_index()
+                            .zip(parse_index(&edge.node.index));
+                        if notice_key.is_some_and(|key| !seen_notices.insert(key)) {
+                            debug!("Skipping duplicate notice {:?} for device {}", notice_key, request.device_id);
+                            continue;
+                        }
+                        
+                        request.input_timestamp = edge.node.input.parsed_timestamp();
+                        if let Some(request) = self.reconcile_input_index(request, &edge.node.input) {
+                            if !seen_receipts.insert(receipt_reference(&request)) {
+                                debug!("Skipping duplicate proof request for device {}", request.device_id);
+                                continue;
+                            }
+                            requests.push(request);
+                        }
+                    }
+                }
+            }
+        }
+        
+        Ok(requests)
+    }
+    
+    /// Read notices or reports of inputs from `since` on, page by page,
+    /// following cursors until the last page or `max_notices_per_poll`. A
+    /// capped poll leaves a cursor for the next poll from the same watermark
+    /// to continue from; a poll that reaches the last page starts the next
+    /// one from the watermark.
+    async fn query_outputs(&self, kind: OutputKind, since: u64) -> Result<Vec<NoticeEdge>> {
+        let mut after = self.resume_cursors.lock().unwrap().remove(&kind)
+            .filter(|point| point.watermark == since)
+            .map(|point| point.cursor);
+        let mut edges = Vec::new();
+        
+        loop {
+            let first = NOTICES_PAGE_SIZE.min(self.max_notices_per_poll - edges.len());
+            let page = self.query_output_page(kind, since, first, after.take()).await?;
+            edges.extend(page.edges);
+            
+            let next = page.page_info
+                .filter(|page| page.has_next_page)
+                .and_then(|page| page.end_cursor);
+            let Some(cursor) = next else {
+                return Ok(edges);
+            };
+            
+            if edges.len() >= self.max_notices_per_poll {
+                warn!(
+                    "Read {} {} this poll (max_notices_per_poll); continuing after cursor {} next poll",
+                    edges.len(), kind.connection(), cursor
+                );
+                let last_input = edges.last().and_then(|edge| edge.node.input.parsed_index());
+                self.resume_cursors.lock().unwrap().insert(kind, ResumePoint { watermark: since, cursor, last_input });
+                return Ok(edges);
+            }
+            after = Some(cursor);
+        }
+    }
+    
+    /// Fetch one page of notices or reports. From the first input on this
+    /// lists the outputs directly; past it, inputs are filtered by index and
+    /// their outputs flattened, since the Cartesi schema only filters inputs.
+    async fn query_output_page(
+        &self,
+        kind: OutputKind,
+        since: u64,
+        first: usize,
+        after: Option<String>,
+    ) -> Result<NoticesConnection> {
+        if since > 0 {
+            let query = r#"
+                query GetProofRequestsSince($first: Int!, $after: String, $since: Int!) {
+                    inputs(first: $first, after: $after, where: { indexGreaterThan: $since }) {
+                        edges {
+                            node {
+                                index
+                                timestamp
+                                notices {
+                                    edges {
+                                        node {
+                                            index
+                                            payload
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        pageInfo {
+                            hasNextPage
+                            endCursor
+                        }
+                    }
+                }
+            "#;
+            
+            let request = GraphQLRequest {
+                query: query.replace("notices", kind.connection()),
+                variables: Some(serde_json::json!({ "first": first, "after": after, "since": since - 1 })),
+            };
+            let data: InputsData = self.execute_with_retry(&request).await?;
+            return Ok(data.inputs.into());
+        }
+        
         let query = r#"
-            query GetProofRequests {
-                notices(first: 100) {
+            query GetProofRequests($first: Int!, $after: String) {
+                notices(first: $first, after: $after) {
                     edges {
                         node {
                             index
                             input {
                                 index
+                                timestamp
                             }
                             payload
                         }
                     }
+                    pageInfo {
+                        hasNextPage
+                        endCursor
+                    }
                 }
             }
         "#;
         
         let request = GraphQLRequest {
-            query: query.to_string(),
-            variables: None,
+            query: query.replace("notices", kind.connection()),
+            variables: Some(serde_json::json!({ "first": first, "after": after })),
         };
-        
         let data: NoticesData = self.execute_with_retry(&request).await?;
+        Ok(data.notices)
+    }
+    
+    /// Check a request's embedded `input_index` against the notice's actual input,
+    /// returning the request to process (if any) under the configured policy
+    fn reconcile_input_index(&self, mut request: ProofRequest, input: &InputNode) -> Option<ProofRequest> {
+        let policy = self.input_index_policy;
         
-        // Parse notices and filter for proof requests
-        let mut requests = Vec::new();
+        let Some(notice_index) = input.parsed_index() else {
+            return match policy {
+                InputIndexMismatchPolicy::PreferNotice => {
+                    warn!(
+                        "Unparseable notice input index {} for device {}; keeping payload input_index {}",
+                        input.index, request.device_id, request.input_index
+                    );
+                    Some(request)
+                }
+                InputIndexMismatchPolicy::Reject => {
+                    warn!("Rejecting proof request from device {}: unparseable notice input index {}", request.device_id, input.index);
+                    None
+                }
+            };
+        };
         
-        for edge in data.notices.edges {
-            let payload_hex = edge.node.payload.trim_start_matches("0x");
-            
-            // Decode hex payload
-            let payload_bytes = hex::decode(payload_hex)?;
-            let payload_str = String::from_utf8(payload_bytes)
-                .map_err(|e| VerifierError::GraphQL(format!("Invalid UTF-8 in payload: {}", e)))?;
-            
-            // Try to parse as JSON
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&payload_str) {
-                // Check if this is a proof request
-                if json.get("type").and_then(|v| v.as_str()) == Some("risc0_proof_request") {
-                    if let Ok(request) = serde_json::from_value::<ProofRequest>(json["data"].clone()) {
-                        requests.push(request);
+        if notice_index == request.input_index {
+            return Some(request);
+        }
+        
+        match policy {
+            InputIndexMismatchPolicy::PreferNotice => {
+                warn!(
+                    "Proof request from device {} claims input_index {} but its notice is from input {}; using {}",
+                    request.device_id, request.input_index, notice_index, notice_index
+                );
+                request.input_index = notice_index;
+                Some(request)
+            }
+            InputIndexMismatchPolicy::Reject => {
+                warn!(
+                    "Rejecting proof request from device {}: claims input_index {} but its notice is from input {}",
+                    request.device_id, request.input_index, notice_index
+                );
+                None
+            }
+        }
+    }
+    
+    /// Query the index of the node's latest epoch, if it has any
+    pub async fn query_latest_epoch(&self) -> Result<Option<u64>> {
+        let query = r#"
+            query LatestEpoch {
+                epochs(last: 1) {
+                    edges {
+                        node {
+                            index
+                        }
                     }
                 }
             }
-        }
+        "#;
         
-        Ok(requests)
+        let request = GraphQLRequest {
+            query: query.to_string(),
+            variables: None,
+        };
+        
+        let data: EpochsData = self.execute_with_retry(&request).await?;
+        
+        data.epochs.edges.last()
+            .map(|edge| parse_index(&edge.node.index)
+                .ok_or_else(|| VerifierError::GraphQL(format!("Unparseable epoch index: {}", edge.node.index)).into()))
+            .transpose()
+    }
+    
+    /// Whether any replica answers a trivial query, without retries
+    pub async fn ping(&self) -> bool {
+        let request = GraphQLRequest {
+            query: "query Ping { __typename }".to_string(),
+            variables: None,
+        };
+        
+        for endpoint in &self.endpoints {
+            if self.execute_request::<serde_json::Value>(&endpoint.url, &request).await.is_ok() {
+                return true;
+            }
+        }
+        false
     }
     
     /// Check if a receipt has already been processed
@@ -235,4 +822,401 @@ impl GraphQLClient {
         
         Ok(false)
     }
-}
\ No newline at end of file
+}
+
+/// Where the processing loop reads proof requests and on-chain state from
+#[async_trait]
+pub trait NoticeSource: Send + Sync {
+    /// Proof requests from the watermark on
+    async fn query_proof_requests(&self) -> Result<Vec<ProofRequest>>;
+    
+    /// Proof requests of the epochs in `epochs`, wherever the watermark is
+    async fn query_proof_requests_in_epochs(&self, epochs: RangeInclusive<u64>) -> Result<Vec<ProofRequest>>;
+    
+    /// Whether a receipt with this hash is already on chain
+    async fn check_receipt_processed(&self, receipt_hash: &str) -> Result<bool>;
+    
+    /// Index of the newest epoch, if the node has any
+    async fn query_latest_epoch(&self) -> Result<Option<u64>>;
+    
+    /// Input index the next poll starts from
+    fn last_seen_index(&self) -> u64;
+    
+    /// Move the watermark up to `index`; it never moves backwards
+    fn advance_watermark(&self, index: u64);
+    
+    /// Whether the source answers at all
+    async fn ping(&self) -> bool;
+    
+    /// State of any circuit breaker around polls
+    fn breaker_state(&self) -> BreakerState {
+        BreakerState::Closed
+    }
+}
+
+#[async_trait]
+impl NoticeSource for GraphQLClient {
+    async fn query_proof_requests(&self) -> Result<Vec<ProofRequest>> {
+        GraphQLClient::query_proof_requests(self).await
+    }
+    
+    async fn query_proof_requests_in_epochs(&self, epochs: RangeInclusive<u64>) -> Result<Vec<ProofRequest>> {
+        GraphQLClient::query_proof_requests_in_epochs(self, epochs).await
+    }
+    
+    async fn check_receipt_processed(&self, receipt_hash: &str) -> Result<bool> {
+        GraphQLClient::check_receipt_processed(self, receipt_hash).await
+    }
+    
+    async fn query_latest_epoch(&self) -> Result<Option<u64>> {
+        GraphQLClient::query_latest_epoch(self).await
+    }
+    
+    fn last_seen_index(&self) -> u64 {
+        GraphQLClient::last_seen_index(self)
+    }
+    
+    fn advance_watermark(&self, index: u64) {
+        GraphQLClient::advance_watermark(self, index)
+    }
+    
+    async fn ping(&self) -> bool {
+        GraphQLClient::ping(self).await
+    }
+    
+    fn breaker_state(&self) -> BreakerState {
+        GraphQLClient::breaker_state(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn notices_body(payloads: &[serde_json::Value]) -> String {
+        let edges: Vec<_> = payloads.iter().enumerate().map(|(i, payload)| {
+            serde_json::json!({
+                "node": {
+                    "index": i.to_string(),
+                    "input": { "index": i.to_string() },
+                    "payload": format!("0x{}", hex::encode(payload.to_string())),
+                }
+            })
+        }).collect();
+        
+        serde_json::json!({ "data": { "notices": { "edges": edges } } }).to_string()
+    }
+    
+    fn proof_request_payload(device_id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "risc0_proof_request",
+            "data": {
+                "device_id": device_id,
+                "proof_type": "iot_validation",
+                "receipt_url": "ipfs://Qm",
+                "expected_image_id": "0x00",
+                "epoch_index": 0,
+                "input_index": 0,
+            }
+        })
+    }
+    
+    fn test_client(endpoints: &[String]) -> GraphQLClient {
+        let mut client = GraphQLClient::new(endpoints, Duration::from_secs(60)).unwrap();
+        client.retry_delay = Duration::from_millis(1);
+        client
+    }
+    
+    #[tokio::test]
+    async fn test_failover_to_healthy_endpoint() {
+        let mut down = mockito::Server::new_async().await;
+        let down_mock = down.mock("POST", "/graphql")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let mut up = mockito::Server::new_async().await;
+        let up_mock = up.mock("POST", "/graphql")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(notices_body(&[proof_request_payload("device-1")]))
+            .expect(2)
+            .create_async()
+            .await;
+        
+        let client = test_client(&[
+            format!("{}/graphql", down.url()),
+            format!("{}/graphql", up.url()),
+        ]);
+        
+        let requests = client.query_proof_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].device_id, "device-1");
+        
+        // The failed replica is skipped until its re-probe interval elapses
+        client.query_proof_requests().await.unwrap();
+        
+        down_mock.assert_async().await;
+        up_mock.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_only_retryable_errors_are_retried() {
+        let mut server = mockito::Server::new_async().await;
+        let rejected = server.mock("POST", "/graphql")
+            .with_status(400)
+            .with_body("Cannot query field")
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let client = test_client(&[format!("{}/graphql", server.url())]);
+        let err = client.query_proof_requests().await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::HttpStatus { status: 400, .. })));
+        rejected.assert_async().await;
+        
+        rejected.remove_async().await;
+        let busy = server.mock("POST", "/graphql")
+            .with_status(503)
+            .expect(client.max_retries as usize)
+            .create_async()
+            .await;
+        client.query_proof_requests().await.unwrap_err();
+        busy.assert_async().await;
+    }
+    
+    #[test]
+    fn test_failed_endpoint_is_reprobed() {
+        let endpoints = ["http://a/graphql".to_string(), "http://b/graphql".to_string()];
+        let client = GraphQLClient::new(&endpoints, Duration::from_secs(60)).unwrap();
+        
+        // While marked down, every selection lands on the healthy replica
+        client.endpoints[0].mark_down(Duration::from_secs(60));
+        for _ in 0..4 {
+            assert_eq!(client.select_endpoint().url, "http://b/graphql");
+        }
+        
+        // Once the cooldown has elapsed the replica is eligible again
+        client.endpoints[0].mark_down(Duration::ZERO);
+        let selected: Vec<_> = (0..2).map(|_| client.select_endpoint().url.clone()).collect();
+        assert!(selected.contains(&"http://a/graphql".to_string()));
+    }
+    
+    #[tokio::test]
+    async fn test_notice_pages_are_followed() {
+        let page = |device_id: &str, input: u64, next: Option<&str>| serde_json::json!({ "data": { "notices": {
+            "edges": [{ "node": {
+                "index": 0,
+                "input": { "index": input },
+                "payload": format!("0x{}", hex::encode(proof_request_payload(device_id).to_string())),
+            } }],
+            "pageInfo": { "hasNextPage": next.is_some(), "endCursor": next },
+        } } }).to_string();
+        
+        let mut server = mockito::Server::new_async().await;
+        let first = server.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "variables": { "after": null } })))
+            .with_body(page("device-1", 0, Some("cursor-1")))
+            .expect(2)
+            .create_async()
+            .await;
+        let second = server.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "variables": { "after": "cursor-1" } })))
+            .with_body(page("device-2", 1, None))
+            .expect(2)
+            .create_async()
+            .await;
+        
+        let client = test_client(&[format!("{}/graphql", server.url())]);
+        let requests = client.query_proof_requests().await.unwrap();
+        let devices: Vec<_> = requests.iter().map(|r| r.device_id.as_str()).collect();
+        assert_eq!(devices, ["device-1", "device-2"]);
+        
+        // With a cap of one notice, each poll reads one page and the next
+        // poll continues where it stopped
+        let client = test_client(&[format!("{}/graphql", server.url())]).with_max_notices_per_poll(1);
+        assert_eq!(client.query_proof_requests().await.unwrap()[0].device_id, "device-1");
+        // Input 0 may have more notices past the cursor, so the watermark
+        // cannot pass it yet
+        client.advance_watermark(1);
+        assert_eq!(client.last_seen_index(), 0);
+        assert_eq!(client.query_proof_requests().await.unwrap()[0].device_id, "device-2");
+        
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_watermark_filters_inputs() {
+        let mut server = mockito::Server::new_async().await;
+        let since = server.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "variables": { "since": 4 } })))
+            .with_body(serde_json::json!({ "data": { "inputs": {
+                "edges": [{ "node": {
+                    "index": 5,
+                    "timestamp": 1700000000,
+                    "notices": { "edges": [{ "node": {
+                        "index": 0,
+                        "payload": format!("0x{}", hex::encode(proof_request_payload("device-5").to_string())),
+                    } }] },
+                } }],
+                "pageInfo": { "hasNextPage": false, "endCursor": null },
+            } } }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let client = test_client(&[format!("{}/graphql", server.url())]);
+        client.advance_watermark(5);
+        client.advance_watermark(3);
+        assert_eq!(client.last_seen_index(), 5);
+        
+        let requests = client.query_proof_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!((requests[0].device_id.as_str(), requests[0].input_index), ("device-5", 5));
+        assert_eq!(requests[0].input_timestamp, Some(1700000000));
+        since.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_proof_request_sources() {
+        let page = |connection: &str, requests: &[(&str, u64)]| {
+            let edges: Vec<_> = requests.iter().map(|(device_id, input)| serde_json::json!({ "node": {
+                "index": 0,
+                "input": { "index": input },
+                "payload": format!("0x{}", hex::encode(proof_request_payload(device_id).to_string())),
+            } })).collect();
+            serde_json::json!({ "data": { connection: { "edges": edges } } }).to_string()
+        };
+        
+        let mut server = mockito::Server::new_async().await;
+        let notices = server.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex(r"notices\(".to_string()))
+            .with_body(page("notices", &[("notice-1", 1), ("notice-3", 3)]))
+            .expect(2)
+            .create_async()
+            .await;
+        let reports = server.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex(r"reports\(".to_string()))
+            .with_body(page("reports", &[("report-1", 1), ("report-2", 2)]))
+            .expect(2)
+            .create_async()
+            .await;
+        
+        let devices = |source| {
+            let client = test_client(&[format!("{}/graphql", server.url())]).with_proof_request_source(source);
+            async move {
+                let requests = client.query_proof_requests().await.unwrap();
+                requests.into_iter().map(|r| r.device_id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(devices(ProofRequestSource::Notices).await, ["notice-1", "notice-3"]);
+        assert_eq!(devices(ProofRequestSource::Reports).await, ["report-1", "report-2"]);
+        // Input 1 has a notice and a report; the notice is kept
+        assert_eq!(devices(ProofRequestSource::Both).await, ["notice-1", "report-2", "notice-3"]);
+        
+        notices.assert_async().await;
+        reports.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_input_index_mismatch_policy() {
+        let mut server = mockito::Server::new_async().await;
+        // Payloads claim input 0; the notices come from inputs 0 and 1
+        let _mock = server.mock("POST", "/graphql")
+            .with_header("content-type", "application/json")
+            .with_body(notices_body(&[proof_request_payload("agrees"), proof_request_payload("disagrees")]))
+            .create_async()
+            .await;
+        let endpoints = [format!("{}/graphql", server.url())];
+        
+        let client = test_client(&endpoints);
+        let requests = client.query_proof_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!((requests[0].device_id.as_str(), requests[0].input_index), ("agrees", 0));
+        assert_eq!((requests[1].device_id.as_str(), requests[1].input_index), ("disagrees", 1));
+        
+        let client = test_client(&endpoints).with_input_index_policy(InputIndexMismatchPolicy::Reject);
+        let requests = client.query_proof_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].device_id, "agrees");
+    }
+    
+    #[tokio::test]
+    async fn test_duplicate_notice_is_returned_once() {
+        let payload = proof_request_payload("device-1");
+        let notice = serde_json::json!({
+            "node": {
+                "index": 0,
+                "input": { "index": 0 },
+                "payload": format!("0x{}", hex::encode(payload.to_string())),
+            }
+        });
+        let body = serde_json::json!({ "data": { "notices": { "edges": [notice.clone(), notice] } } });
+        
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server.mock("POST", "/graphql")
+            .with_header("content-type", "application/json")
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+        
+        let client = test_client(&[format!("{}/graphql", server.url())]);
+        let requests = client.query_proof_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].device_id, "device-1");
+    }
+    
+    #[tokio::test]
+    async fn test_circuit_breaker_fails_polls_fast() {
+        let mut server = mockito::Server::new_async().await;
+        let down = server.mock("POST", "/graphql")
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        
+        let mut client = test_client(&[format!("{}/graphql", server.url())])
+            .with_circuit_breaker(2, Duration::from_millis(200));
+        client.max_retries = 1;
+        
+        // Closed until the second consecutive failure
+        client.query_proof_requests().await.unwrap_err();
+        assert_eq!(client.breaker_state(), BreakerState::Closed);
+        client.query_proof_requests().await.unwrap_err();
+        assert_eq!(client.breaker_state(), BreakerState::Open);
+        
+        // Open: the poll fails without reaching the node
+        let err = client.query_proof_requests().await.unwrap_err();
+        assert!(err.to_string().contains("circuit open"), "{}", err);
+        down.assert_async().await;
+        
+        // After the cooldown one probe goes through and closes it
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(client.breaker_state(), BreakerState::HalfOpen);
+        down.remove_async().await;
+        let up = server.mock("POST", "/graphql")
+            .with_body(notices_body(&[proof_request_payload("device-1")]))
+            .expect(1)
+            .create_async()
+            .await;
+        assert_eq!(client.query_proof_requests().await.unwrap().len(), 1);
+        assert_eq!(client.breaker_state(), BreakerState::Closed);
+        up.assert_async().await;
+    }
+    
+    #[test]
+    fn test_input_index_parsing() {
+        let parse = |index| InputNode { index, timestamp: serde_json::Value::Null }.parsed_index();
+        assert_eq!(parse(serde_json::json!(7)), Some(7));
+        assert_eq!(parse(serde_json::json!("7")), Some(7));
+        assert_eq!(parse(serde_json::json!("seven")), None);
+        assert_eq!(parse(serde_json::json!(-1)), None);
+    }
+    
+    #[test]
+    fn test_requires_an_endpoint() {
+        assert!(GraphQLClient::new(&[], Duration::from_secs(1)).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Idle heartbeat for quiet deployments
+//!
+//! With no proof requests the poll loop is silent, which looks the same as a
+//! stuck service. After a run of empty polls an info-level heartbeat is
+//! logged, then again after each further run, until activity resets it.
+
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// Counts consecutive empty polls and decides when a heartbeat is due
+pub struct IdleHeartbeat {
+    /// Empty polls between heartbeats; 0 disables the heartbeat
+    threshold: u64,
+    empty_polls: u64,
+    started: Instant,
+    last_activity: Option<DateTime<Utc>>,
+}
+
+/// A due heartbeat
+#[derive(Debug)]
+pub struct Heartbeat {
+    pub uptime: Duration,
+    pub empty_polls: u64,
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+impl IdleHeartbeat {
+    pub fn new(threshold: u64) -> Self {
+        Self {
+            threshold,
+            empty_polls: 0,
+            started: Instant::now(),
+            last_activity: None,
+        }
+    }
+
+    /// Record a poll cycle that processed `processed` requests, returning a
+    /// heartbeat when one is due
+    pub fn record(&mut self, processed: usize) -> Option<Heartbeat> {
+        if processed > 0 {
+            self.empty_polls = 0;
+            self.last_activity = Some(Utc::now());
+            return None;
+        }
+
+        self.empty_polls += 1;
+        if self.threshold == 0 || !self.empty_polls.is_multiple_of(self.threshold) {
+            return None;
+        }
+
+        Some(Heartbeat {
+            uptime: self.started.elapsed(),
+            empty_polls: self.empty_polls,
+            last_activity: self.last_activity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heartbeat_fires_after_configured_empty_polls() {
+        let mut heartbeat = IdleHeartbeat::new(3);
+
+        assert!(heartbeat.record(0).is_none());
+        assert!(heartbeat.record(0).is_none());
+        let beat = heartbeat.record(0).expect("heartbeat after 3 empty polls");
+        assert_eq!(beat.empty_polls, 3);
+        assert!(beat.last_activity.is_none());
+
+        // Repeats once per further run rather than every poll
+        assert!(heartbeat.record(0).is_none());
+        assert!(heartbeat.record(0).is_none());
+        assert_eq!(heartbeat.record(0).unwrap().empty_polls, 6);
+    }
+
+    #[test]
+    fn test_activity_resets_the_count() {
+        let mut heartbeat = IdleHeartbeat::new(2);
+
+        assert!(heartbeat.record(0).is_none());
+        assert!(heartbeat.record(5).is_none());
+        assert!(heartbeat.record(0).is_none());
+
+        let beat = heartbeat.record(0).unwrap();
+        assert_eq!(beat.empty_polls, 2);
+        assert!(beat.last_activity.is_some());
+    }
+
+    #[test]
+    fn test_zero_threshold_disables() {
+        let mut heartbeat = IdleHeartbeat::new(0);
+        assert!((0..100).all(|_| heartbeat.record(0).is_none()));
+    }
+}
@@ -0,0 +1,135 @@
+//! Shared plumbing for the service's HTTP endpoints
+//!
+//! Handlers reject with [`ApiError`]; [`handle_rejection`] turns every
+//! rejection into a JSON error body with a status matching the error category.
+
+use std::convert::Infallible;
+use warp::http::StatusCode;
+use warp::{Rejection, Reply};
+use crate::error::VerifierError;
+
+/// A `VerifierError` carried through warp's rejection machinery
+#[derive(Debug)]
+pub struct ApiError(pub VerifierError);
+
+impl warp::reject::Reject for ApiError {}
+
+impl From<VerifierError> for ApiError {
+    fn from(err: VerifierError) -> Self {
+        ApiError(err)
+    }
+}
+
+/// Reject a request with `err`
+pub fn reject(err: VerifierError) -> Rejection {
+    warp::reject::custom(ApiError(err))
+}
+
+/// HTTP status for each error category
+pub fn status_for(err: &VerifierError) -> StatusCode {
+    match err {
+        VerifierError::NotFound(_) => StatusCode::NOT_FOUND,
+        VerifierError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+        VerifierError::Conflict(_) => StatusCode::CONFLICT,
+        VerifierError::InvalidQuery(_) | VerifierError::Hex(_) => StatusCode::BAD_REQUEST,
+        VerifierError::ReceiptTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        VerifierError::ProofVerification(_)
+        | VerifierError::InvalidImageId { .. }
+        | VerifierError::ReceiptHashMismatch { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        // Upstream dependencies (Cartesi node, InputBox, receipt sources)
+        VerifierError::GraphQL(_)
+        | VerifierError::InputBox(_)
+        | VerifierError::Network(_)
+        | VerifierError::Timeout(_)
+        | VerifierError::HttpStatus { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        VerifierError::Config(_)
+        | VerifierError::Signing { .. }
+        | VerifierError::Serialization(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Map any rejection to a JSON `{"error": ...}` reply with the right status
+pub async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Infallible> {
+    let (status, message) = if let Some(ApiError(err)) = rejection.find::<ApiError>() {
+        (status_for(err), err.to_string())
+    } else if rejection.is_not_found() {
+        (StatusCode::NOT_FOUND, "Not found".to_string())
+    } else if let Some(err) = rejection.find::<warp::reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, err.to_string())
+    } else if let Some(err) = rejection.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, err.to_string())
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed".to_string())
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("Unhandled rejection: {:?}", rejection))
+    };
+
+    let body = warp::reply::json(&serde_json::json!({ "error": message }));
+    Ok(warp::reply::with_status(body, status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+
+    async fn status_of(err: VerifierError) -> StatusCode {
+        let err = std::sync::Arc::new(std::sync::Mutex::new(Some(err)));
+        let route = warp::path("query")
+            .and_then(move || {
+                let err = err.lock().unwrap().take().expect("route is hit once");
+                async move { Err::<String, _>(reject(err)) }
+            })
+            .recover(handle_rejection);
+
+        warp::test::request().path("/query").reply(&route).await.status()
+    }
+
+    #[tokio::test]
+    async fn test_error_categories_map_to_statuses() {
+        assert_eq!(status_of(VerifierError::NotFound("receipt 0x01".into())).await, StatusCode::NOT_FOUND);
+        assert_eq!(status_of(VerifierError::InvalidQuery("limit".into())).await, StatusCode::BAD_REQUEST);
+        assert_eq!(status_of(VerifierError::Unauthorized("token".into())).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status_of(VerifierError::Conflict("restart needed".into())).await, StatusCode::CONFLICT);
+        assert_eq!(status_of(VerifierError::GraphQL("down".into())).await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status_of(VerifierError::InputBox("down".into())).await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            status_of(VerifierError::ProofVerification("bad".into())).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            status_of(VerifierError::ReceiptTooLarge { size: 2, max: 1 }).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            status_of(VerifierError::Config("bad".into())).await,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warp_rejections_map_to_statuses() {
+        #[derive(serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Params {
+            limit: u32,
+        }
+
+        let route = warp::get()
+            .and(warp::path("query"))
+            .and(warp::query::<Params>())
+            .map(|_| "ok")
+            .recover(handle_rejection);
+
+        let reply = warp::test::request().path("/missing").reply(&route).await;
+        assert_eq!(reply.status(), StatusCode::NOT_FOUND);
+
+        let reply = warp::test::request().path("/query?limit=many").reply(&route).await;
+        assert_eq!(reply.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_slice(reply.body()).unwrap();
+        assert!(body["error"].is_string());
+
+        let reply = warp::test::request().method("POST").path("/query?limit=1").reply(&route).await;
+        assert_eq!(reply.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -1,15 +1,29 @@
 //! Client for submitting to Cartesi InputBox
 
 use anyhow::Result;
+use async_trait::async_trait;
 use reqwest::Client;
-use crate::types::{VerifiedReceipt, InputBoxPayload};
-use crate::error::VerifierError;
-use tracing::{info, debug};
+use std::time::Duration;
+use crate::types::{EpochSummary, VerifiedReceipt, InputBoxPayload};
+use crate::error::{is_retryable_error, VerifierError};
+use crate::inputbox_rpc::InputBoxRpcClient;
+use crate::proxy::ProxySettings;
+use crate::rate_limit::SubmissionRateLimiter;
+use crate::receipt_signer::check_signed_receipt;
+use crate::sequencer::SequencerClient;
+use tracing::{info, debug, warn};
 
 pub struct InputBoxClient {
     endpoint: String,
     dapp_address: String,
     client: Client,
+    proxy: ProxySettings,
+    request_timeout: Duration,
+    rate_limiter: Option<SubmissionRateLimiter>,
+    sequencer: Option<SequencerClient>,
+    direct_rpc: Option<InputBoxRpcClient>,
+    max_retries: u32,
+    retry_delay: Duration,
 }
 
 impl InputBoxClient {
@@ -24,62 +38,232 @@ impl InputBoxClient {
         Ok(Self {
             endpoint: endpoint.to_string(),
             dapp_address,
-            client: Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
-                .build()?,
+            client: ProxySettings::default().client(Duration::from_secs(30))?,
+            proxy: ProxySettings::default(),
+            request_timeout: Duration::from_secs(30),
+            rate_limiter: None,
+            sequencer: None,
+            direct_rpc: None,
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
         })
     }
     
+    /// Send all submissions through the configured proxies
+    pub fn with_proxy(mut self, proxy: &ProxySettings) -> Result<Self> {
+        self.client = proxy.client(self.request_timeout)?;
+        self.proxy = proxy.clone();
+        Ok(self)
+    }
+    
+    /// Give up on a submission attempt after `request_timeout`
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Result<Self> {
+        self.client = self.proxy.client(request_timeout)?;
+        self.request_timeout = request_timeout;
+        Ok(self)
+    }
+    
+    /// Cap submissions to `per_sec` across all callers, queueing any excess
+    pub fn with_rate_limit(mut self, per_sec: f64) -> Self {
+        self.rate_limiter = Some(SubmissionRateLimiter::new(per_sec));
+        self
+    }
+    
+    /// Submit through a sequencer instead of the InputBox HTTP endpoint
+    pub fn with_sequencer(mut self, sequencer: SequencerClient) -> Self {
+        self.sequencer = Some(sequencer);
+        self
+    }
+    
+    /// Send `addInput` transactions to the InputBox contract instead of
+    /// posting to the HTTP relay
+    pub fn with_direct_rpc(mut self, direct_rpc: InputBoxRpcClient) -> Self {
+        self.direct_rpc = Some(direct_rpc);
+        self
+    }
+    
+    /// Make up to `max_retries` attempts per submission, waiting
+    /// `retry_delay * attempt` after each transient failure
+    pub fn with_retry(mut self, max_retries: u32, retry_delay: Duration) -> Self {
+        self.max_retries = max_retries.max(1);
+        self.retry_delay = retry_delay;
+        self
+    }
+    
     /// Submit a verified receipt to the InputBox
     pub async fn submit_verified_receipt(&self, receipt: &VerifiedReceipt) -> Result<()> {
-        // Create command wrapper
-        let command = serde_json::json!({
-            "command": "submit_verified_receipt",
-            "data": receipt
-        });
-        
-        // Encode as hex
-        let payload_json = serde_json::to_string(&command)?;
-        let payload_hex = format!("0x{}", hex::encode(payload_json));
-        
-        // Create InputBox payload
-        let input_payload = InputBoxPayload {
-            address: self.dapp_address.clone(),
-            payload: payload_hex,
-        };
+        // Never put a malformed signature on chain
+        check_signed_receipt(receipt)?;
         
-        debug!("Submitting verified receipt to InputBox: {}", self.endpoint);
-        debug!("DApp address: {}", self.dapp_address);
         debug!("Receipt hash: {}", receipt.receipt_hash);
         
-        // Submit to InputBox
-        let response = self.client
-            .post(&self.endpoint)
-            .json(&input_payload)
-            .send()
-            .await
-            .map_err(|e| VerifierError::InputBox(format!("Failed to send request: {}", e)))?;
+        // The receipt hash lets the relay drop a retry of a submission it
+        // already accepted
+        let index = self.submit_command("submit_verified_receipt", receipt, Some(&receipt.receipt_hash)).await?;
         
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        if let Some(index) = index {
+            info!("Verified receipt submitted successfully with index: {}", index);
+        } else {
+            info!("Verified receipt submitted successfully");
+        }
+        
+        Ok(())
+    }
+    
+    /// Submit verified receipts as one JSON array of inputs, in order,
+    /// returning how many the relay accepted. A relay that takes only part of
+    /// a batch replies `{"accepted": n}` for the first `n`; the caller
+    /// resubmits the rest.
+    pub async fn submit_verified_receipts_batch(&self, receipts: &[VerifiedReceipt]) -> Result<usize> {
+        if self.sequencer.is_some() {
+            return Err(VerifierError::InputBox(
+                "Batched submission is not supported through a sequencer".to_string()
+            ).into());
+        }
+        if self.direct_rpc.is_some() {
             return Err(VerifierError::InputBox(
-                format!("InputBox returned error {}: {}", status, error_text)
+                "Batched submission is not supported by the direct_rpc backend".to_string()
             ).into());
         }
         
-        // Parse response to get input index
-        let response_data: serde_json::Value = response.json().await?;
+        let mut inputs = Vec::with_capacity(receipts.len());
+        for receipt in receipts {
+            check_signed_receipt(receipt)?;
+            inputs.push(self.input_payload(&command_payload("submit_verified_receipt", receipt)?));
+        }
+        if let Some(limiter) = &self.rate_limiter {
+            for _ in receipts {
+                limiter.acquire().await;
+            }
+        }
         
-        if let Some(index) = response_data.get("index") {
-            info!("Verified receipt submitted successfully with index: {}", index);
+        debug!("Submitting batch of {} receipts to InputBox: {}", receipts.len(), self.endpoint);
+        let response = self.with_retries(|| self.post_json(&inputs, None)).await?;
+        let accepted = response.get("accepted")
+            .and_then(serde_json::Value::as_u64)
+            .map_or(receipts.len(), |accepted| (accepted as usize).min(receipts.len()));
+        
+        info!("Batch of {} receipts submitted, {} accepted", receipts.len(), accepted);
+        Ok(accepted)
+    }
+    
+    /// Check a verified receipt and log the input that would be submitted
+    /// for it, without sending anything (dry-run mode)
+    pub fn log_would_submit(&self, receipt: &VerifiedReceipt) -> Result<()> {
+        check_signed_receipt(receipt)?;
+        
+        let payload_json = command_payload("submit_verified_receipt", receipt)?;
+        info!("Dry run: would submit to {} for DApp {}: {}", self.endpoint, self.dapp_address, payload_json);
+        
+        Ok(())
+    }
+    
+    /// Submit a signed epoch summary to the InputBox
+    pub async fn submit_epoch_summary(&self, summary: &EpochSummary) -> Result<()> {
+        debug!("Epoch {} merkle root: {}", summary.epoch_index, summary.merkle_root);
+        
+        let index = self.submit_command("submit_epoch_summary", summary, None).await?;
+        
+        if let Some(index) = index {
+            info!("Epoch {} summary submitted successfully with index: {}", summary.epoch_index, index);
         } else {
-            info!("Verified receipt submitted successfully");
+            info!("Epoch {} summary submitted successfully", summary.epoch_index);
         }
         
         Ok(())
     }
     
+    /// Wrap `data` in a command envelope and post it, returning the input
+    /// index (or sequencer reference, or transaction hash) if reported
+    async fn submit_command<T: serde::Serialize>(
+        &self,
+        command: &str,
+        data: &T,
+        idempotency_key: Option<&str>,
+    ) -> Result<Option<serde_json::Value>> {
+        if let Some(limiter) = &self.rate_limiter {
+            let queued = limiter.queue_depth();
+            if queued > 0 {
+                debug!("Submission queued behind rate limiter ({} waiting)", queued);
+            }
+            limiter.acquire().await;
+        }
+        
+        let payload_json = command_payload(command, data)?;
+        
+        if let Some(sequencer) = &self.sequencer {
+            debug!("Submitting to sequencer for DApp {}", self.dapp_address);
+            return sequencer.submit(&self.client, payload_json.as_bytes()).await.map(Some);
+        }
+        
+        if let Some(direct_rpc) = &self.direct_rpc {
+            debug!("Sending addInput transaction for DApp {}", self.dapp_address);
+            return direct_rpc.submit(&self.client, payload_json.as_bytes()).await.map(Some);
+        }
+        
+        let input_payload = self.input_payload(&payload_json);
+        
+        debug!("Submitting to InputBox: {}", self.endpoint);
+        debug!("DApp address: {}", self.dapp_address);
+        
+        let response = self.with_retries(|| self.post_json(&input_payload, idempotency_key)).await?;
+        
+        Ok(response.get("index").cloned())
+    }
+    
+    /// InputBox input carrying a hex-encoded command envelope
+    fn input_payload(&self, payload_json: &str) -> InputBoxPayload {
+        InputBoxPayload {
+            address: self.dapp_address.clone(),
+            payload: format!("0x{}", hex::encode(payload_json)),
+        }
+    }
+    
+    /// Run `attempt` until it succeeds, fails for good, or runs out of attempts
+    async fn with_retries<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut attempt_number = 1;
+        loop {
+            match attempt().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt_number < self.max_retries && is_retryable_error(&e) => {
+                    let delay = self.retry_delay * attempt_number;
+                    warn!("InputBox attempt {}/{} failed: {}; retrying in {:?}", attempt_number, self.max_retries, e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt_number += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
+    /// Post `body` to the InputBox, returning the parsed response
+    async fn post_json<B: serde::Serialize>(
+        &self,
+        body: &B,
+        idempotency_key: Option<&str>,
+    ) -> Result<serde_json::Value> {
+        let mut request = self.client
+            .post(&self.endpoint)
+            .json(body);
+        if let Some(key) = idempotency_key {
+            request = request.header("Idempotency-Key", key);
+        }
+        
+        let response = request.send().await.map_err(VerifierError::Network)?;
+        
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(VerifierError::HttpStatus { service: "InputBox", status, body }.into());
+        }
+        
+        Ok(response.json().await.map_err(VerifierError::Network)?)
+    }
+    
     /// Health check for InputBox
     pub async fn health_check(&self) -> Result<bool> {
         let health_url = format!("{}/health", self.endpoint.trim_end_matches("/input"));
@@ -91,9 +275,70 @@ impl InputBoxClient {
     }
 }
 
+/// Where the processing loop submits signed receipts and epoch summaries
+#[async_trait]
+pub trait InputBoxSink: Send + Sync {
+    /// Submit one verified receipt
+    async fn submit_verified_receipt(&self, receipt: &VerifiedReceipt) -> Result<()>;
+    
+    /// Submit receipts in order, returning how many were accepted
+    async fn submit_verified_receipts_batch(&self, receipts: &[VerifiedReceipt]) -> Result<usize>;
+    
+    /// Submit a signed epoch summary
+    async fn submit_epoch_summary(&self, summary: &EpochSummary) -> Result<()>;
+    
+    /// Log what would be submitted for a receipt, without submitting it
+    fn log_would_submit(&self, receipt: &VerifiedReceipt) -> Result<()>;
+    
+    /// Whether the sink is reachable
+    async fn health_check(&self) -> Result<bool>;
+    
+    /// Submissions waiting on the submission rate limit
+    fn rate_limit_queue_depth(&self) -> usize {
+        0
+    }
+}
+
+#[async_trait]
+impl InputBoxSink for InputBoxClient {
+    async fn submit_verified_receipt(&self, receipt: &VerifiedReceipt) -> Result<()> {
+        InputBoxClient::submit_verified_receipt(self, receipt).await
+    }
+    
+    async fn submit_verified_receipts_batch(&self, receipts: &[VerifiedReceipt]) -> Result<usize> {
+        InputBoxClient::submit_verified_receipts_batch(self, receipts).await
+    }
+    
+    async fn submit_epoch_summary(&self, summary: &EpochSummary) -> Result<()> {
+        InputBoxClient::submit_epoch_summary(self, summary).await
+    }
+    
+    fn log_would_submit(&self, receipt: &VerifiedReceipt) -> Result<()> {
+        InputBoxClient::log_would_submit(self, receipt)
+    }
+    
+    async fn health_check(&self) -> Result<bool> {
+        InputBoxClient::health_check(self).await
+    }
+    
+    fn rate_limit_queue_depth(&self) -> usize {
+        self.rate_limiter.as_ref().map_or(0, SubmissionRateLimiter::queue_depth)
+    }
+}
+
+/// The JSON command envelope the DApp receives as its input
+fn command_payload<T: serde::Serialize>(command: &str, data: &T) -> Result<String> {
+    let command = serde_json::json!({
+        "command": command,
+        "data": data
+    });
+    Ok(serde_json::to_string(&command)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::ProofKind;
     
     #[test]
     fn test_dapp_address_normalization() {
@@ -107,4 +352,194 @@ mod tests {
             .unwrap();
         assert_eq!(client2.dapp_address, "0x1234567890abcdef1234567890abcdef12345678");
     }
+    
+    #[tokio::test]
+    async fn test_malformed_signature_rejected_before_submission() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/input")
+            .expect(0)
+            .create_async()
+            .await;
+        
+        let client = InputBoxClient::new(
+            &format!("{}/input", server.url()),
+            "0x1234567890abcdef1234567890abcdef12345678",
+        ).unwrap();
+        
+        let receipt = VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0x1234".to_string(),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: format!("0x{}", "ab".repeat(64)), // 64 bytes, missing v
+            timestamp: None,
+            verifier_address: Some("0x1234567890abcdef1234567890abcdef12345678".to_string()),
+            bls_signature: None,
+            bls_public_key: None,
+        };
+        
+        let err = client.submit_verified_receipt(&receipt).await.unwrap_err();
+        assert!(err.to_string().contains("Malformed signature"));
+        mock.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_sequencer_transport_replaces_inputbox() {
+        let dapp = "0x1234567890abcdef1234567890abcdef12345678";
+        let mut server = mockito::Server::new_async().await;
+        let inputbox = server.mock("POST", "/input").expect(0).create_async().await;
+        let _nonce = server.mock("POST", "/sequencer/nonce")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"nonce": 0}"#)
+            .create_async()
+            .await;
+        // The payload is the same command envelope the InputBox would receive
+        let command_prefix = hex::encode(r#"{"command":"submit_verified_receipt""#);
+        let submit = server.mock("POST", "/sequencer/submit")
+            .match_body(mockito::Matcher::Regex(format!(r#""data":"0x{}"#, command_prefix)))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id": "0xfeed"}"#)
+            .create_async()
+            .await;
+        
+        let key = "11".repeat(32);
+        let client = InputBoxClient::new(&format!("{}/input", server.url()), dapp).unwrap()
+            .with_sequencer(SequencerClient::new(&format!("{}/sequencer", server.url()), 31337, 1, 10, dapp, &key).unwrap());
+        
+        client.submit_verified_receipt(&signed_receipt(&key).await).await.unwrap();
+        submit.assert_async().await;
+        inputbox.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_transient_failures_are_retried_with_idempotency_key() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server.mock("POST", "/input")
+            .match_header("Idempotency-Key", "0x1234")
+            .with_status(503)
+            .with_body("relay restarting")
+            .expect(2)
+            .create_async()
+            .await;
+        let accepted = server.mock("POST", "/input")
+            .match_header("Idempotency-Key", "0x1234")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"index": 9}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let client = InputBoxClient::new(&format!("{}/input", server.url()), "0x1234567890abcdef1234567890abcdef12345678")
+            .unwrap()
+            .with_retry(3, Duration::from_millis(1));
+        
+        client.submit_verified_receipt(&signed_receipt(&"11".repeat(32)).await).await.unwrap();
+        unavailable.assert_async().await;
+        accepted.assert_async().await;
+        
+        // Once the attempts run out the error is returned, so the request is
+        // held for the next poll
+        accepted.remove_async().await;
+        let err = client.submit_verified_receipt(&signed_receipt(&"11".repeat(32)).await).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::HttpStatus { status: 503, .. })
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_batch_posts_array_and_reports_accepted_prefix() {
+        let mut server = mockito::Server::new_async().await;
+        let dapp = "0x1234567890abcdef1234567890abcdef12345678";
+        let batch = server.mock("POST", "/input")
+            .match_body(mockito::Matcher::Regex(format!(
+                r#"^\[\{{"address":"{0}","payload":"0x[0-9a-f]+"\}},\{{"address":"{0}","payload":"0x[0-9a-f]+"\}}\]$"#,
+                dapp
+            )))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"accepted": 1}"#)
+            .create_async()
+            .await;
+        
+        let client = InputBoxClient::new(&format!("{}/input", server.url()), dapp).unwrap();
+        let receipt = signed_receipt(&"11".repeat(32)).await;
+        let accepted = client.submit_verified_receipts_batch(&[receipt.clone(), receipt]).await.unwrap();
+        assert_eq!(accepted, 1);
+        batch.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_slow_inputbox_hits_request_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        let _slow = server.mock("POST", "/input")
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_secs(3));
+                w.write_all(br#"{"index": 1}"#)
+            })
+            .create_async()
+            .await;
+        
+        let client = InputBoxClient::new(&format!("{}/input", server.url()), "0x1234567890abcdef1234567890abcdef12345678")
+            .unwrap()
+            .with_request_timeout(Duration::from_secs(1))
+            .unwrap()
+            .with_retry(1, Duration::from_millis(1));
+        
+        let started = std::time::Instant::now();
+        let err = client.submit_verified_receipt(&signed_receipt(&"11".repeat(32)).await).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::Network(e)) if e.is_timeout()),
+            "{}", err
+        );
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_queue_depth_is_reported() {
+        let client = std::sync::Arc::new(
+            InputBoxClient::new("http://localhost:8080/input", "0x1234567890abcdef1234567890abcdef12345678")
+                .unwrap()
+                .with_rate_limit(1.0)
+        );
+        let sink: &dyn InputBoxSink = client.as_ref();
+        assert_eq!(sink.rate_limit_queue_depth(), 0);
+        
+        client.rate_limiter.as_ref().unwrap().acquire().await;
+        let waiter = {
+            let client = client.clone();
+            tokio::spawn(async move { client.rate_limiter.as_ref().unwrap().acquire().await })
+        };
+        tokio::task::yield_now().await;
+        assert_eq!(sink.rate_limit_queue_depth(), 1);
+        
+        waiter.await.unwrap();
+        assert_eq!(sink.rate_limit_queue_depth(), 0);
+    }
+    
+    async fn signed_receipt(key: &str) -> VerifiedReceipt {
+        let signer = crate::receipt_signer::ReceiptSigner::new(key).unwrap();
+        signer.sign_receipt(VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0x1234".to_string(),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: None,
+            verifier_address: Some(signer.get_address()),
+            bls_signature: None,
+            bls_public_key: None,
+        }).await.unwrap()
+    }
 }
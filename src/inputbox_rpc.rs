@@ -0,0 +1,663 @@
+//! Submission straight to the InputBox contract over JSON-RPC
+//!
+//! Instead of posting to an HTTP relay, each input is sent as an Ethereum
+//! transaction calling `InputBox.addInput(address appContract, bytes payload)`
+//! and signed with the verifier key:
+//!
+//! 1. `eth_estimateGas` and the fee strategy's price queries fill in the
+//!    transaction, which takes the next nonce from the `NonceManager`.
+//! 2. The transaction is signed for the configured chain, as a legacy
+//!    EIP-155 or an EIP-1559 transaction, and sent with
+//!    `eth_sendRawTransaction`, which returns its hash.
+//!
+//! The call is not waited on: a transaction that is sent but later reverts
+//! is not seen here.
+
+use alloy_rlp::{Encodable, Header};
+use alloy_sol_types::SolCall;
+use anyhow::Result;
+use k256::ecdsa::SigningKey;
+use reqwest::Client;
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+use std::future::Future;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+use crate::config::RpcFeeStrategy;
+use crate::eip712::parse_address;
+use crate::error::VerifierError;
+use crate::receipt_signer::derive_address;
+
+alloy_sol_types::sol! {
+    /// The InputBox entry point
+    function addInput(address appContract, bytes payload);
+}
+
+/// Headroom added on top of `eth_estimateGas`, in percent
+const GAS_LIMIT_HEADROOM: u128 = 20;
+
+/// Sends made per submission while the node reports the nonce as too low
+const NONCE_TOO_LOW_ATTEMPTS: u32 = 3;
+
+/// EIP-2718 type byte of EIP-1559 transactions
+const EIP1559_TX_TYPE: u8 = 0x02;
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<serde_json::Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// How a transaction pays for gas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fees {
+    Legacy { gas_price: u128 },
+    Eip1559 { max_fee_per_gas: u128, max_priority_fee_per_gas: u128 },
+}
+
+/// Fee strategy and limits for sent transactions, all amounts in wei
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeePolicy {
+    pub strategy: RpcFeeStrategy,
+    /// Fixed legacy gas price instead of `eth_gasPrice`
+    pub gas_price: Option<u128>,
+    /// Fixed EIP-1559 tip instead of `eth_maxPriorityFeePerGas`
+    pub priority_fee: Option<u128>,
+    /// Most paid per gas: legacy prices above it are refused, EIP-1559 max
+    /// fees are capped to it
+    pub max_fee_per_gas: Option<u128>,
+}
+
+/// An unsigned transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transaction {
+    pub nonce: u64,
+    pub fees: Fees,
+    pub gas_limit: u128,
+    pub to: [u8; 20],
+    pub value: u128,
+    pub data: Vec<u8>,
+}
+
+impl Transaction {
+    /// Raw transaction signed for `chain_id`: EIP-155 RLP for legacy fees,
+    /// an EIP-2718 typed envelope for EIP-1559 fees
+    pub fn sign(&self, signing_key: &SigningKey, chain_id: u64) -> Result<Vec<u8>> {
+        let unsigned = self.encode(chain_id, |out| {
+            if let Fees::Legacy { .. } = self.fees {
+                chain_id.encode(out);
+                0u8.encode(out);
+                0u8.encode(out);
+            }
+        });
+        let digest: [u8; 32] = Keccak256::digest(unsigned).into();
+
+        let (signature, recovery_id) = signing_key.sign_prehash_recoverable(&digest)
+            .map_err(|e| VerifierError::signing_fatal(format!("Failed to sign InputBox transaction: {}", e)))?;
+        let v = match self.fees {
+            Fees::Legacy { .. } => u128::from(recovery_id.to_byte()) + 35 + 2 * u128::from(chain_id),
+            Fees::Eip1559 { .. } => u128::from(recovery_id.to_byte()),
+        };
+        let (r, s) = signature.split_bytes();
+
+        Ok(self.encode(chain_id, |out| {
+            v.encode(out);
+            strip_zeros(&r).encode(out);
+            strip_zeros(&s).encode(out);
+        }))
+    }
+
+    /// The transaction's fields followed by those `trailer` appends, as an
+    /// RLP list in the envelope of its fee type
+    fn encode(&self, chain_id: u64, trailer: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let mut fields = Vec::new();
+        match self.fees {
+            Fees::Legacy { gas_price } => {
+                self.nonce.encode(&mut fields);
+                gas_price.encode(&mut fields);
+            }
+            Fees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas } => {
+                chain_id.encode(&mut fields);
+                self.nonce.encode(&mut fields);
+                max_priority_fee_per_gas.encode(&mut fields);
+                max_fee_per_gas.encode(&mut fields);
+            }
+        }
+        self.gas_limit.encode(&mut fields);
+        self.to.encode(&mut fields);
+        self.value.encode(&mut fields);
+        self.data.as_slice().encode(&mut fields);
+        if let Fees::Eip1559 { .. } = self.fees {
+            // Empty access list
+            Header { list: true, payload_length: 0 }.encode(&mut fields);
+        }
+        trailer(&mut fields);
+
+        let mut out = Vec::new();
+        if let Fees::Eip1559 { .. } = self.fees {
+            out.push(EIP1559_TX_TYPE);
+        }
+        Header { list: true, payload_length: fields.len() }.encode(&mut out);
+        out.extend(fields);
+        out
+    }
+}
+
+/// Hands out sequential nonces for one account. The pending nonce is read
+/// from the node once and then counted up locally, so concurrent
+/// submissions never share a nonce; after a failed send the count is
+/// dropped and read again.
+#[derive(Default)]
+pub struct NonceManager {
+    next: Mutex<Option<u64>>,
+}
+
+impl NonceManager {
+    /// Reserve the next nonce, calling `fetch` for the pending nonce if the
+    /// count is not known
+    pub async fn reserve<F, Fut>(&self, fetch: F) -> Result<u64>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<u64>>,
+    {
+        // Held across the fetch so concurrent callers wait for one read
+        let mut next = self.next.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => fetch().await?,
+        };
+        *next = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Forget the count so the next reservation reads the node again
+    pub async fn resync(&self) {
+        *self.next.lock().await = None;
+    }
+}
+
+/// Sends inputs as signed `addInput` transactions through a JSON-RPC node
+pub struct InputBoxRpcClient {
+    rpc_url: String,
+    chain_id: u64,
+    inputbox: [u8; 20],
+    app: [u8; 20],
+    signing_key: SigningKey,
+    account: String,
+    fee_policy: FeePolicy,
+    nonces: NonceManager,
+}
+
+impl InputBoxRpcClient {
+    pub fn new(
+        rpc_url: &str,
+        chain_id: u64,
+        inputbox_address: &str,
+        dapp_address: &str,
+        private_key_hex: &str,
+    ) -> Result<Self> {
+        let inputbox = parse_address(inputbox_address)?;
+        let app = parse_address(dapp_address)?;
+        let key_bytes = hex::decode(private_key_hex.trim_start_matches("0x"))?;
+        let signing_key = SigningKey::from_slice(&key_bytes)
+            .map_err(|e| VerifierError::Config(format!("Invalid private key: {}", e)))?;
+        let account = derive_address(signing_key.verifying_key());
+
+        Ok(Self {
+            rpc_url: rpc_url.to_string(),
+            chain_id,
+            inputbox,
+            app,
+            signing_key,
+            account,
+            fee_policy: FeePolicy::default(),
+            nonces: NonceManager::default(),
+        })
+    }
+
+    /// Price gas with `fee_policy` instead of legacy node prices
+    pub fn with_fee_policy(mut self, fee_policy: FeePolicy) -> Self {
+        self.fee_policy = fee_policy;
+        self
+    }
+
+    /// Send an input payload, returning the transaction hash
+    pub async fn submit(&self, client: &Client, payload: &[u8]) -> Result<serde_json::Value> {
+        let data = add_input_calldata(&self.app, payload);
+        let call = serde_json::json!({
+            "from": self.account,
+            "to": format!("0x{}", hex::encode(self.inputbox)),
+            "data": format!("0x{}", hex::encode(&data)),
+        });
+        let gas_estimate = self.quantity(client, "eth_estimateGas", serde_json::json!([call])).await?;
+        let fees = self.fees(client).await?;
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let nonce = self.nonces.reserve(|| self.pending_nonce(client)).await?;
+            let transaction = Transaction {
+                nonce,
+                fees,
+                gas_limit: gas_estimate + gas_estimate * GAS_LIMIT_HEADROOM / 100,
+                to: self.inputbox,
+                value: 0,
+                data: data.clone(),
+            };
+            let raw = transaction.sign(&self.signing_key, self.chain_id)?;
+
+            let error = match self.request(client, "eth_sendRawTransaction", serde_json::json!([format!("0x{}", hex::encode(&raw))])).await {
+                Ok(Ok(hash)) => return Ok(hash),
+                Ok(Err(error)) => error,
+                Err(e) => {
+                    self.nonces.resync().await;
+                    return Err(e);
+                }
+            };
+
+            let message = error.message.to_lowercase();
+            // The node already has this exact transaction, e.g. from a send
+            // whose response was lost
+            if message.contains("already known") {
+                debug!("Transaction with nonce {} already known to the node", nonce);
+                return Ok(serde_json::json!(format!("0x{}", hex::encode(Keccak256::digest(&raw)))));
+            }
+
+            self.nonces.resync().await;
+            if message.contains("nonce too low") && attempt < NONCE_TOO_LOW_ATTEMPTS {
+                warn!("Nonce {} was too low; reading the pending nonce again", nonce);
+                continue;
+            }
+            return Err(VerifierError::InputBox(
+                format!("eth_sendRawTransaction failed ({}): {}", error.code, error.message)
+            ).into());
+        }
+    }
+
+    async fn pending_nonce(&self, client: &Client) -> Result<u64> {
+        let nonce = self.quantity(client, "eth_getTransactionCount", serde_json::json!([self.account, "pending"])).await?;
+        u64::try_from(nonce).map_err(|_| VerifierError::InputBox(format!("Nonce out of range: {}", nonce)).into())
+    }
+
+    /// Fees for the next transaction under the fee policy
+    async fn fees(&self, client: &Client) -> Result<Fees> {
+        let policy = self.fee_policy;
+        match policy.strategy {
+            RpcFeeStrategy::Legacy => {
+                let gas_price = match policy.gas_price {
+                    Some(gas_price) => gas_price,
+                    None => self.quantity(client, "eth_gasPrice", serde_json::json!([])).await?,
+                };
+                if let Some(cap) = policy.max_fee_per_gas.filter(|&cap| gas_price > cap) {
+                    return Err(VerifierError::InputBox(
+                        format!("Gas price of {} wei is above the cap of {} wei", gas_price, cap)
+                    ).into());
+                }
+                Ok(Fees::Legacy { gas_price })
+            }
+            RpcFeeStrategy::Eip1559 => {
+                let priority_fee = match policy.priority_fee {
+                    Some(priority_fee) => priority_fee,
+                    None => self.quantity(client, "eth_maxPriorityFeePerGas", serde_json::json!([])).await?,
+                };
+                let base_fee = self.base_fee(client).await?;
+                // Room for the base fee to double before the transaction is priced out
+                let mut max_fee = 2 * base_fee + priority_fee;
+                if let Some(cap) = policy.max_fee_per_gas {
+                    if base_fee + priority_fee > cap {
+                        return Err(VerifierError::InputBox(format!(
+                            "Base fee of {} wei plus tip of {} wei is above the cap of {} wei",
+                            base_fee, priority_fee, cap
+                        )).into());
+                    }
+                    max_fee = max_fee.min(cap);
+                }
+                Ok(Fees::Eip1559 { max_fee_per_gas: max_fee, max_priority_fee_per_gas: priority_fee })
+            }
+        }
+    }
+
+    /// Base fee of the latest block
+    async fn base_fee(&self, client: &Client) -> Result<u128> {
+        let block = self.call(client, "eth_getBlockByNumber", serde_json::json!(["latest", false])).await?;
+        block.get("baseFeePerGas")
+            .and_then(serde_json::Value::as_str)
+            .and_then(parse_quantity)
+            .ok_or_else(|| VerifierError::InputBox("Latest block has no baseFeePerGas; is EIP-1559 active?".to_string()).into())
+    }
+
+    /// Call a method returning a hex quantity
+    async fn quantity(&self, client: &Client, method: &str, params: serde_json::Value) -> Result<u128> {
+        let result = self.call(client, method, params).await?;
+        result.as_str()
+            .and_then(parse_quantity)
+            .ok_or_else(|| VerifierError::InputBox(format!("Invalid {} result: {}", method, result)).into())
+    }
+
+    async fn call(&self, client: &Client, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+        self.request(client, method, params).await?.map_err(|error| {
+            VerifierError::InputBox(format!("{} failed ({}): {}", method, error.code, error.message)).into()
+        })
+    }
+
+    /// Send a JSON-RPC request; the inner result is the node's reply
+    async fn request(
+        &self,
+        client: &Client,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<std::result::Result<serde_json::Value, RpcError>> {
+        let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = client.post(&self.rpc_url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| VerifierError::InputBox(format!("Failed to reach RPC node: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(VerifierError::InputBox(
+                format!("RPC node returned error {} for {}: {}", status, method, error_text)
+            ).into());
+        }
+
+        let response: RpcResponse = response.json().await
+            .map_err(|e| VerifierError::InputBox(format!("Invalid RPC response for {}: {}", method, e)))?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Ok(Err(error)),
+            (Some(result), None) => Ok(Ok(result)),
+            (None, None) => Err(VerifierError::InputBox(format!("Empty RPC response for {}", method)).into()),
+        }
+    }
+}
+
+/// Parse a 0x-prefixed hex quantity
+fn parse_quantity(value: &str) -> Option<u128> {
+    u128::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+/// ABI-encoded call of `addInput(app, payload)`
+pub fn add_input_calldata(app: &[u8; 20], payload: &[u8]) -> Vec<u8> {
+    addInputCall { appContract: (*app).into(), payload: payload.to_vec().into() }.abi_encode()
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rlp::Decodable;
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    use std::sync::Arc;
+
+    const DAPP: &str = "0x1234567890abcdef1234567890abcdef12345678";
+    const INPUTBOX: &str = "0x59b22d57d4f067708ab0c00552767405926dc768";
+
+    fn key() -> String {
+        "11".repeat(32)
+    }
+
+    /// Mock a JSON-RPC method answering with `result`
+    fn mock_rpc(server: &mut mockito::Server, method: &str, result: serde_json::Value) -> mockito::Mock {
+        server.mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "method": method })))
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string())
+    }
+
+    fn mock_rpc_error(server: &mut mockito::Server, method: &str, message: &str) -> mockito::Mock {
+        server.mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "method": method })))
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({
+                "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": message },
+            }).to_string())
+    }
+
+    /// Accept raw transactions, keeping them in the order they arrive
+    async fn mock_send(server: &mut mockito::Server) -> Arc<std::sync::Mutex<Vec<Vec<u8>>>> {
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let captured = sent.clone();
+        server.mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "method": "eth_sendRawTransaction" })))
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |request| {
+                let body: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+                let raw = hex::decode(body["params"][0].as_str().unwrap().trim_start_matches("0x")).unwrap();
+                captured.lock().unwrap().push(raw);
+                br#"{"jsonrpc": "2.0", "id": 1, "result": "0xfeed"}"#.to_vec()
+            })
+            .create_async()
+            .await;
+        sent
+    }
+
+    /// Top-level RLP items of a raw transaction, after any type byte
+    fn decode_fields(raw: &[u8]) -> Vec<Vec<u8>> {
+        let mut buf = raw.strip_prefix(&[EIP1559_TX_TYPE]).unwrap_or(raw);
+        assert!(Header::decode(&mut buf).unwrap().list);
+        let mut fields = Vec::new();
+        while !buf.is_empty() {
+            let start = buf;
+            let header = Header::decode(&mut buf).unwrap();
+            buf = &buf[header.payload_length..];
+            fields.push(start[..start.len() - buf.len()].to_vec());
+        }
+        fields
+    }
+
+    fn nonce_of(raw: &[u8]) -> u64 {
+        u64::decode(&mut decode_fields(raw)[0].as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_add_input_calldata_matches_abi_encoding() {
+        let app = parse_address(DAPP).unwrap();
+        let calldata = add_input_calldata(&app, b"{}");
+        assert_eq!(hex::encode(&calldata[..4]), "1789cd63");
+        assert_eq!(hex::encode(&calldata[4..]), format!(
+            "{:0>64}{:0>64}{:0>64}{:0<64}",
+            DAPP.trim_start_matches("0x"), "40", "2", "7b7d"
+        ));
+
+        // Payloads are padded to whole words
+        assert_eq!(add_input_calldata(&app, &[0xcd; 33]).len(), 4 + 32 * 5);
+    }
+
+    #[test]
+    fn test_eip155_signing_vector() {
+        // The example transaction from EIP-155
+        let transaction = Transaction {
+            nonce: 9,
+            fees: Fees::Legacy { gas_price: 20_000_000_000 },
+            gas_limit: 21_000,
+            to: [0x35; 20],
+            value: 1_000_000_000_000_000_000,
+            data: Vec::new(),
+        };
+        let key = SigningKey::from_slice(&[0x46; 32]).unwrap();
+        assert_eq!(
+            hex::encode(transaction.sign(&key, 1).unwrap()),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+             8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d899\
+             7f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+    }
+
+    #[test]
+    fn test_eip1559_transaction_recovers_to_signer() {
+        let transaction = Transaction {
+            nonce: 3,
+            fees: Fees::Eip1559 { max_fee_per_gas: 30_000_000_000, max_priority_fee_per_gas: 1_000_000_000 },
+            gas_limit: 50_000,
+            to: parse_address(INPUTBOX).unwrap(),
+            value: 0,
+            data: vec![0x17, 0x89, 0xcd, 0x63],
+        };
+        let signing_key = SigningKey::from_slice(&hex::decode(key()).unwrap()).unwrap();
+        let raw = transaction.sign(&signing_key, 11155111).unwrap();
+        assert_eq!(raw[0], EIP1559_TX_TYPE);
+
+        // chain id, nonce, tip, max fee, gas, to, value, data, access list, y, r, s
+        let fields = decode_fields(&raw);
+        assert_eq!(fields.len(), 12);
+        assert_eq!(u64::decode(&mut fields[0].as_slice()).unwrap(), 11155111);
+        assert_eq!(u64::decode(&mut fields[1].as_slice()).unwrap(), 3);
+        assert_eq!(u128::decode(&mut fields[3].as_slice()).unwrap(), 30_000_000_000);
+        assert_eq!(fields[8], [0xc0]);
+
+        let mut unsigned = vec![EIP1559_TX_TYPE];
+        Header { list: true, payload_length: fields[..9].iter().map(Vec::len).sum() }.encode(&mut unsigned);
+        unsigned.extend(fields[..9].concat());
+        let digest = Keccak256::digest(unsigned);
+        let y = u8::decode(&mut fields[9].as_slice()).unwrap();
+        let mut signature = [0u8; 64];
+        for (i, field) in fields[10..].iter().enumerate() {
+            let value = alloy_rlp::Bytes::decode(&mut field.as_slice()).unwrap();
+            signature[32 * i + 32 - value.len()..32 * (i + 1)].copy_from_slice(&value);
+        }
+        let recovered = VerifyingKey::recover_from_prehash(
+            &digest,
+            &Signature::from_slice(&signature).unwrap(),
+            RecoveryId::from_byte(y).unwrap(),
+        ).unwrap();
+        assert_eq!(recovered, *signing_key.verifying_key());
+    }
+
+    #[tokio::test]
+    async fn test_submit_sends_signed_add_input() {
+        let mut server = mockito::Server::new_async().await;
+        let _nonce = mock_rpc(&mut server, "eth_getTransactionCount", "0x7".into()).create_async().await;
+        let _gas_price = mock_rpc(&mut server, "eth_gasPrice", "0x3b9aca00".into()).create_async().await;
+        let _estimate = mock_rpc(&mut server, "eth_estimateGas", "0x7530".into()).create_async().await;
+        let sent = mock_send(&mut server).await;
+
+        let client = InputBoxRpcClient::new(&server.url(), 31337, INPUTBOX, DAPP, &key()).unwrap();
+        assert_eq!(client.submit(&Client::new(), b"{}").await.unwrap(), "0xfeed");
+
+        // The raw transaction is exactly the filled-in addInput call
+        let expected = Transaction {
+            nonce: 7,
+            fees: Fees::Legacy { gas_price: 1_000_000_000 },
+            gas_limit: 36_000,
+            to: parse_address(INPUTBOX).unwrap(),
+            value: 0,
+            data: add_input_calldata(&parse_address(DAPP).unwrap(), b"{}"),
+        };
+        let signing_key = SigningKey::from_slice(&hex::decode(key()).unwrap()).unwrap();
+        assert_eq!(*sent.lock().unwrap(), [expected.sign(&signing_key, 31337).unwrap()]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_submissions_get_sequential_nonces() {
+        let mut server = mockito::Server::new_async().await;
+        let nonce = mock_rpc(&mut server, "eth_getTransactionCount", "0x5".into()).expect(1).create_async().await;
+        let _gas_price = mock_rpc(&mut server, "eth_gasPrice", "0x1".into()).create_async().await;
+        let _estimate = mock_rpc(&mut server, "eth_estimateGas", "0x5208".into()).create_async().await;
+        let sent = mock_send(&mut server).await;
+
+        let client = Arc::new(InputBoxRpcClient::new(&server.url(), 31337, INPUTBOX, DAPP, &key()).unwrap());
+        let mut tasks = tokio::task::JoinSet::new();
+        for i in 0..10 {
+            let client = client.clone();
+            tasks.spawn(async move { client.submit(&Client::new(), format!("{{\"i\":{}}}", i).as_bytes()).await });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap().unwrap();
+        }
+
+        // The pending nonce is read once and every submission gets its own
+        nonce.assert_async().await;
+        let mut nonces: Vec<u64> = sent.lock().unwrap().iter().map(|raw| nonce_of(raw)).collect();
+        nonces.sort_unstable();
+        assert_eq!(nonces, (5..15).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_nonce_too_low_refetches_and_already_known_succeeds() {
+        let mut server = mockito::Server::new_async().await;
+        // Another sender used nonce 5 in the meantime
+        let stale = mock_rpc(&mut server, "eth_getTransactionCount", "0x5".into()).expect(1).create_async().await;
+        let fresh = mock_rpc(&mut server, "eth_getTransactionCount", "0x6".into()).expect(1).create_async().await;
+        let _gas_price = mock_rpc(&mut server, "eth_gasPrice", "0x1".into()).create_async().await;
+        let _estimate = mock_rpc(&mut server, "eth_estimateGas", "0x5208".into()).create_async().await;
+        let too_low = mock_rpc_error(&mut server, "eth_sendRawTransaction", "nonce too low").expect(1).create_async().await;
+        let sent = mock_send(&mut server).await;
+
+        let client = InputBoxRpcClient::new(&server.url(), 31337, INPUTBOX, DAPP, &key()).unwrap();
+        client.submit(&Client::new(), b"{}").await.unwrap();
+        stale.assert_async().await;
+        fresh.assert_async().await;
+        too_low.assert_async().await;
+        assert_eq!(sent.lock().unwrap().iter().map(|raw| nonce_of(raw)).collect::<Vec<_>>(), [6]);
+
+        // A transaction the node already has was sent; its hash is reported
+        // and the local count carries on
+        let known = mock_rpc_error(&mut server, "eth_sendRawTransaction", "already known").expect(1).create_async().await;
+        let hash = client.submit(&Client::new(), b"{}").await.unwrap();
+        known.assert_async().await;
+        assert!(hash.as_str().unwrap().starts_with("0x") && hash.as_str().unwrap().len() == 66);
+
+        known.remove_async().await;
+        client.submit(&Client::new(), b"{}").await.unwrap();
+        assert_eq!(sent.lock().unwrap().iter().map(|raw| nonce_of(raw)).collect::<Vec<_>>(), [6, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_fee_strategies_and_cap() {
+        let mut server = mockito::Server::new_async().await;
+        let _gas_price = mock_rpc(&mut server, "eth_gasPrice", "0x64".into()).create_async().await;
+        let _tip = mock_rpc(&mut server, "eth_maxPriorityFeePerGas", "0x2".into()).create_async().await;
+        let _block = mock_rpc(&mut server, "eth_getBlockByNumber", serde_json::json!({ "baseFeePerGas": "0x28" }))
+            .create_async()
+            .await;
+        let rpc = |policy| InputBoxRpcClient::new(&server.url(), 1, INPUTBOX, DAPP, &key()).unwrap().with_fee_policy(policy);
+        let http = Client::new();
+
+        let legacy = FeePolicy::default();
+        assert_eq!(rpc(legacy).fees(&http).await.unwrap(), Fees::Legacy { gas_price: 100 });
+        let fixed = FeePolicy { gas_price: Some(7), ..legacy };
+        assert_eq!(rpc(fixed).fees(&http).await.unwrap(), Fees::Legacy { gas_price: 7 });
+        let capped = FeePolicy { max_fee_per_gas: Some(99), ..legacy };
+        assert!(rpc(capped).fees(&http).await.is_err());
+
+        // Twice the base fee of 40 plus the tip, capped if a cap is set
+        let eip1559 = FeePolicy { strategy: RpcFeeStrategy::Eip1559, ..legacy };
+        assert_eq!(
+            rpc(eip1559).fees(&http).await.unwrap(),
+            Fees::Eip1559 { max_fee_per_gas: 82, max_priority_fee_per_gas: 2 }
+        );
+        let capped = FeePolicy { max_fee_per_gas: Some(50), priority_fee: Some(5), ..eip1559 };
+        assert_eq!(
+            rpc(capped).fees(&http).await.unwrap(),
+            Fees::Eip1559 { max_fee_per_gas: 50, max_priority_fee_per_gas: 5 }
+        );
+        let priced_out = FeePolicy { max_fee_per_gas: Some(41), ..eip1559 };
+        assert!(rpc(priced_out).fees(&http).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rpc_error_is_reported() {
+        let mut server = mockito::Server::new_async().await;
+        let _error = server.mock("POST", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "error": {"code": -32000, "message": "header not found"}}"#)
+            .create_async()
+            .await;
+
+        let client = InputBoxRpcClient::new(&server.url(), 1, INPUTBOX, DAPP, &key()).unwrap();
+        let err = client.submit(&Client::new(), b"{}").await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::InputBox(_))));
+        assert!(err.to_string().contains("header not found"), "{}", err);
+    }
+}
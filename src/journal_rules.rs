@@ -0,0 +1,221 @@
+//! Configurable comparison rules evaluated against receipt journals
+//!
+//! A rule reads one integer out of the journal and compares it with a
+//! constant, e.g. `u32le@8 >= 20` ("the little-endian u32 at byte offset 8
+//! must be at least 20") or `len <= 256` for the journal length. Rules are
+//! parsed once at startup; there is no arbitrary code or expression nesting.
+
+use anyhow::Result;
+use std::fmt;
+use std::str::FromStr;
+use crate::error::VerifierError;
+
+/// Integer field a rule reads from the journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// Journal length in bytes
+    Len,
+    /// `width` bytes at `offset`, little- or big-endian, signed or unsigned
+    Int { offset: usize, width: usize, signed: bool, little_endian: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalRule {
+    source: String,
+    field: Field,
+    op: CompareOp,
+    value: i128,
+}
+
+impl JournalRule {
+    /// Check the rule against a journal, explaining any failure
+    pub fn check(&self, journal: &[u8]) -> Result<()> {
+        let actual = self.field.read(journal).ok_or_else(|| VerifierError::ProofVerification(format!(
+            "Journal rule `{}` reads past the end of a {}-byte journal", self.source, journal.len()
+        )))?;
+
+        let passed = match self.op {
+            CompareOp::Eq => actual == self.value,
+            CompareOp::Ne => actual != self.value,
+            CompareOp::Lt => actual < self.value,
+            CompareOp::Le => actual <= self.value,
+            CompareOp::Gt => actual > self.value,
+            CompareOp::Ge => actual >= self.value,
+        };
+
+        if !passed {
+            return Err(VerifierError::ProofVerification(format!(
+                "Journal rule `{}` failed (value {})", self.source, actual
+            )).into());
+        }
+
+        Ok(())
+    }
+}
+
+impl Field {
+    fn read(self, journal: &[u8]) -> Option<i128> {
+        match self {
+            Field::Len => Some(journal.len() as i128),
+            Field::Int { offset, width, signed, little_endian } => {
+                let bytes = journal.get(offset..offset.checked_add(width)?)?;
+
+                let mut buf = [0u8; 8];
+                if little_endian {
+                    buf[..width].copy_from_slice(bytes);
+                } else {
+                    for (i, b) in bytes.iter().rev().enumerate() {
+                        buf[i] = *b;
+                    }
+                }
+                let raw = u64::from_le_bytes(buf);
+
+                if signed {
+                    // Sign-extend from `width` bytes
+                    let shift = 64 - width * 8;
+                    Some((((raw << shift) as i64) >> shift) as i128)
+                } else {
+                    Some(raw as i128)
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "len" {
+            return Ok(Field::Len);
+        }
+
+        let (ty, offset) = s.split_once('@')
+            .ok_or_else(|| format!("expected `len` or `<type>@<offset>`, got `{}`", s))?;
+        let offset = offset.parse()
+            .map_err(|_| format!("invalid offset `{}`", offset))?;
+
+        let (signed, rest) = match ty.as_bytes().first() {
+            Some(b'u') => (false, &ty[1..]),
+            Some(b'i') => (true, &ty[1..]),
+            _ => return Err(format!("unknown type `{}`", ty)),
+        };
+        let (bits, little_endian) = if let Some(bits) = rest.strip_suffix("le") {
+            (bits, true)
+        } else if let Some(bits) = rest.strip_suffix("be") {
+            (bits, false)
+        } else {
+            (rest, true)
+        };
+
+        let width = match bits {
+            "8" => 1,
+            "16" => 2,
+            "32" => 4,
+            "64" => 8,
+            _ => return Err(format!("unknown type `{}`", ty)),
+        };
+        if width > 1 && bits == rest {
+            return Err(format!("type `{}` needs an `le` or `be` suffix", ty));
+        }
+
+        Ok(Field::Int { offset, width, signed, little_endian })
+    }
+}
+
+impl FromStr for CompareOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "==" => Ok(CompareOp::Eq),
+            "!=" => Ok(CompareOp::Ne),
+            "<" => Ok(CompareOp::Lt),
+            "<=" => Ok(CompareOp::Le),
+            ">" => Ok(CompareOp::Gt),
+            ">=" => Ok(CompareOp::Ge),
+            _ => Err(format!("unknown operator `{}`", s)),
+        }
+    }
+}
+
+impl FromStr for JournalRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let malformed = |reason: String| VerifierError::Config(format!("Malformed journal rule `{}`: {}", s, reason));
+
+        let tokens: Vec<&str> = s.split_whitespace().collect();
+        let [field, op, value] = tokens[..] else {
+            return Err(malformed("expected `<field> <op> <value>`".to_string()).into());
+        };
+
+        let value = match value.strip_prefix("0x") {
+            Some(hex) => i128::from_str_radix(hex, 16),
+            None => value.parse(),
+        }
+        .map_err(|_| malformed(format!("invalid value `{}`", value)))?;
+
+        Ok(Self {
+            source: s.to_string(),
+            field: field.parse().map_err(malformed)?,
+            op: op.parse().map_err(malformed)?,
+            value,
+        })
+    }
+}
+
+impl fmt::Display for JournalRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal() -> Vec<u8> {
+        let mut journal = vec![0u8; 8];
+        journal.extend_from_slice(&25u32.to_le_bytes());
+        journal.extend_from_slice(&(-3i16).to_be_bytes());
+        journal
+    }
+
+    #[test]
+    fn test_passing_rules() {
+        for rule in ["u32le@8 >= 20", "u32le@8 == 0x19", "i16be@12 < 0", "u8@0 == 0", "len == 14"] {
+            let rule: JournalRule = rule.parse().unwrap();
+            assert!(rule.check(&journal()).is_ok(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_failing_rules() {
+        for rule in ["u32le@8 >= 30", "i16be@12 >= 0", "len > 14"] {
+            let rule: JournalRule = rule.parse().unwrap();
+            assert!(rule.check(&journal()).is_err(), "{}", rule);
+        }
+
+        // Reading past the end of the journal fails rather than panicking
+        let rule: JournalRule = "u64le@10 > 0".parse().unwrap();
+        assert!(rule.check(&journal()).is_err());
+    }
+
+    #[test]
+    fn test_malformed_rules() {
+        for rule in ["u32le@8 >=", "u32@8 >= 1", "f32le@0 > 1", "u32le@x > 1", "u32le@0 ~ 1", "u8@0 > ten", ""] {
+            assert!(rule.parse::<JournalRule>().is_err(), "{:?} should not parse", rule);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+//! Expected journal layout per proof type
+//!
+//! Every registered proof type has a schema (see [`crate::proof_types`]). By
+//! default a schema only bounds the journal size: validation and compute
+//! journals must not be empty, and privacy journals must stay under
+//! `max_privacy_journal_bytes` so raw sensor data cannot leak through them.
+//!
+//! Operators can also give each type the field layout its guest commits,
+//! e.g. `["u64", "digest", "bool"]`. The journal is then decoded with
+//! RISC Zero's word-oriented serde encoding (what `env::commit` writes) and
+//! must hold exactly those fields. The decoded fields are kept as a JSON
+//! summary of the journal for consumers of the verified receipt.
+
+use anyhow::Result;
+use risc0_zkvm::serde::Deserializer;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use crate::error::VerifierError;
+
+/// Proof types the verifier always knows how to validate
+pub const PROOF_TYPES: [&str; 3] = ["iot_validation", "iot_privacy", "iot_compute"];
+
+/// Default cap on `iot_privacy` journals: room for a few commitments, not a
+/// stream of readings
+pub const DEFAULT_MAX_PRIVACY_JOURNAL_BYTES: usize = 256;
+
+/// One committed value, as encoded by `risc0_zkvm::serde`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    /// A `risc0_zkvm::sha::Digest` (eight words)
+    Digest,
+    /// Length-prefixed `Vec<u8>`, one word per byte
+    Bytes,
+    /// Length-prefixed UTF-8 string
+    String,
+}
+
+impl FieldType {
+    /// Decode one value from the front of `words`, advancing past it.
+    /// Digests and byte strings become `0x`-prefixed hex.
+    fn decode(self, words: &mut &[u32]) -> Result<Value, String> {
+        // Length-prefixed values are checked against what is left before
+        // decoding, so a garbage length cannot trigger a huge allocation
+        let needed_words = match self {
+            FieldType::Bytes => words.first().map(|&len| len as usize),
+            FieldType::String => words.first().map(|&len| (len as usize).div_ceil(4)),
+            _ => None,
+        };
+        if needed_words.is_some_and(|needed| needed > words.len() - 1) {
+            return Err(format!("length {} runs past the end of the journal", words[0]));
+        }
+
+        let mut deserializer = Deserializer::new(&mut *words);
+        let decoded = match self {
+            FieldType::Bool => bool::deserialize(&mut deserializer).map(Value::from),
+            FieldType::U8 => u8::deserialize(&mut deserializer).map(Value::from),
+            FieldType::U16 => u16::deserialize(&mut deserializer).map(Value::from),
+            FieldType::U32 => u32::deserialize(&mut deserializer).map(Value::from),
+            FieldType::U64 => u64::deserialize(&mut deserializer).map(Value::from),
+            FieldType::I8 => i8::deserialize(&mut deserializer).map(Value::from),
+            FieldType::I16 => i16::deserialize(&mut deserializer).map(Value::from),
+            FieldType::I32 => i32::deserialize(&mut deserializer).map(Value::from),
+            FieldType::I64 => i64::deserialize(&mut deserializer).map(Value::from),
+            FieldType::Digest => <[u32; 8]>::deserialize(&mut deserializer)
+                .map(|words| json!(format!("0x{}", hex::encode(digest_bytes(&words))))),
+            FieldType::Bytes => Vec::<u8>::deserialize(&mut deserializer)
+                .map(|bytes| json!(format!("0x{}", hex::encode(bytes)))),
+            FieldType::String => String::deserialize(&mut deserializer).map(Value::from),
+        };
+        decoded.map_err(|e| e.to_string())
+    }
+}
+
+/// Little-endian bytes of a digest's words, as `Digest::as_bytes` gives them
+fn digest_bytes(words: &[u32; 8]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+impl FromStr for FieldType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "bool" => FieldType::Bool,
+            "u8" => FieldType::U8,
+            "u16" => FieldType::U16,
+            "u32" => FieldType::U32,
+            "u64" => FieldType::U64,
+            "i8" => FieldType::I8,
+            "i16" => FieldType::I16,
+            "i32" => FieldType::I32,
+            "i64" => FieldType::I64,
+            "digest" => FieldType::Digest,
+            "bytes" => FieldType::Bytes,
+            "string" => FieldType::String,
+            _ => return Err(format!("unknown field type `{}`", s)),
+        })
+    }
+}
+
+/// What a proof type's journal must look like
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalSchema {
+    proof_type: String,
+    /// Reject journals with no bytes
+    non_empty: bool,
+    /// Reject journals longer than this many bytes
+    max_len: Option<usize>,
+    /// Exact sequence of committed fields, when configured
+    layout: Option<Vec<FieldType>>,
+}
+
+impl JournalSchema {
+    /// Schema of `proof_type` with the given size bounds and, optionally,
+    /// the field layout its guest commits
+    pub fn new(proof_type: &str, non_empty: bool, max_len: Option<usize>, layout: Option<&[String]>) -> Result<Self> {
+        let layout = layout
+            .map(|fields| fields.iter().map(|field| field.parse()).collect::<Result<Vec<FieldType>, String>>())
+            .transpose()
+            .map_err(|e| VerifierError::Config(format!("Malformed journal schema for {}: {}", proof_type, e)))?;
+
+        Ok(JournalSchema { proof_type: proof_type.to_string(), non_empty, max_len, layout })
+    }
+
+    /// Schema of a built-in proof type
+    pub fn builtin(proof_type: &str, layout: Option<&[String]>, max_privacy_journal_bytes: usize) -> Result<Self> {
+        let privacy = proof_type == "iot_privacy";
+        Self::new(proof_type, !privacy, privacy.then_some(max_privacy_journal_bytes), layout)
+    }
+
+    /// Check a journal against the schema, explaining any mismatch. With a
+    /// layout configured, returns the decoded fields as a JSON array.
+    pub fn check(&self, journal: &[u8]) -> Result<Option<Value>> {
+        let mismatch = |reason: String| -> anyhow::Error {
+            VerifierError::ProofVerification(format!("{} journal does not match its schema: {}", self.proof_type, reason)).into()
+        };
+
+        if self.non_empty && journal.is_empty() {
+            return Err(mismatch("journal is empty".to_string()));
+        }
+        if let Some(max_len) = self.max_len {
+            if journal.len() > max_len {
+                return Err(mismatch(format!("{} bytes exceeds the maximum of {}", journal.len(), max_len)));
+            }
+        }
+
+        let Some(layout) = &self.layout else {
+            return Ok(None);
+        };
+        let chunks = journal.chunks_exact(4);
+        if !chunks.remainder().is_empty() {
+            return Err(mismatch(format!("{} bytes is not a whole number of words", journal.len())));
+        }
+        let words: Vec<u32> = chunks
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        let mut remaining = words.as_slice();
+        let fields = layout.iter().enumerate().map(|(position, field)| {
+            field.decode(&mut remaining)
+                .map_err(|e| mismatch(format!("field {} ({:?}): {}", position, field, e)))
+        }).collect::<Result<Vec<Value>>>()?;
+        if !remaining.is_empty() {
+            return Err(mismatch(format!("{} words left over after the last field", remaining.len())));
+        }
+
+        Ok(Some(Value::Array(fields)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn registry(layouts: &[(&str, &[&str])]) -> HashMap<String, JournalSchema> {
+        PROOF_TYPES.iter().map(|&proof_type| {
+            let layout = layouts.iter()
+                .find(|(name, _)| *name == proof_type)
+                .map(|(_, fields)| fields.iter().map(|f| f.to_string()).collect::<Vec<_>>());
+            (proof_type.to_string(), JournalSchema::builtin(proof_type, layout.as_deref(), 64).unwrap())
+        }).collect()
+    }
+
+    fn commit<T: serde::Serialize>(value: &T) -> Vec<u8> {
+        risc0_zkvm::serde::to_vec(value).unwrap().iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_well_formed_journals() {
+        let schemas = registry(&[
+            ("iot_validation", &["u64", "digest", "bool"]),
+            ("iot_compute", &["string", "i32", "bytes"]),
+        ]);
+
+        let validation = commit(&(1_700_000_000u64, [7u32; 8], true));
+        assert_eq!(
+            schemas["iot_validation"].check(&validation).unwrap(),
+            Some(json!([1_700_000_000u64, format!("0x{}", "07000000".repeat(8)), true]))
+        );
+        let compute = commit(&("mean".to_string(), -4i32, vec![1u8, 2, 3]));
+        assert_eq!(schemas["iot_compute"].check(&compute).unwrap(), Some(json!(["mean", -4, "0x010203"])));
+
+        // Without a layout there is nothing to decode
+        assert_eq!(schemas["iot_privacy"].check(&[0xab; 32]).unwrap(), None);
+        assert_eq!(schemas["iot_privacy"].check(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_malformed_journals() {
+        let schemas = registry(&[
+            ("iot_validation", &["u64", "digest", "bool"]),
+            ("iot_compute", &["string", "i32"]),
+        ]);
+
+        for (proof_type, journal) in [
+            // Missing the trailing bool
+            ("iot_validation", commit(&(1u64, [7u32; 8]))),
+            // Extra data after the last field
+            ("iot_validation", commit(&(1u64, [7u32; 8], true, 5u32))),
+            // Not a bool
+            ("iot_validation", commit(&(1u64, [7u32; 8], 2u32))),
+            // Not word-aligned
+            ("iot_validation", vec![1, 2, 3]),
+            // String length far past the end of the journal
+            ("iot_compute", commit(&(u32::MAX, -4i32))),
+            ("iot_compute", vec![]),
+            // Privacy journals large enough to carry raw readings
+            ("iot_privacy", vec![0; 65]),
+        ] {
+            let err = schemas[proof_type].check(&journal).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ProofVerification(_))),
+                "{}: {}", proof_type, err
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed_schemas() {
+        let err = JournalSchema::new("iot_validation", true, None, Some(&["f32".to_string()])).unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::Config(_))), "{}", err);
+    }
+}
@@ -0,0 +1,136 @@
+//! Verifier signing key generation
+
+use anyhow::Result;
+use k256::ecdsa::SigningKey;
+use rand_core::OsRng;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use crate::receipt_signer::derive_address;
+
+/// Warning printed alongside any freshly generated key
+pub const KEY_HANDLING_WARNING: &str = "\
+WARNING: The private key above controls this verifier's signing identity.
+         Store it in a secrets manager, never commit it to version control,
+         and remove it from your terminal scrollback once saved.";
+
+/// A freshly generated secp256k1 signing key
+pub struct GeneratedKey {
+    /// Private key as hex (without 0x prefix, as expected by VERIFIER_PRIVATE_KEY)
+    pub private_key: String,
+
+    /// Ethereum address derived from the key
+    pub address: String,
+}
+
+impl GeneratedKey {
+    /// Generate a new random key using the OS entropy source
+    pub fn generate() -> Self {
+        let signing_key = SigningKey::random(&mut OsRng);
+
+        Self {
+            private_key: hex::encode(signing_key.to_bytes()),
+            address: derive_address(signing_key.verifying_key()),
+        }
+    }
+
+    /// Human-readable output for the `generate-key` subcommand
+    pub fn render(&self) -> String {
+        format!("Private key: {}\nAddress:     {}", self.private_key, self.address)
+    }
+
+    /// Env-file formatted contents, suitable for `--env-file` or `source`
+    pub fn to_env_file(&self) -> String {
+        format!(
+            "# Generated by lcore-verifier generate-key\n\
+             # Address: {}\n\
+             VERIFIER_PRIVATE_KEY={}\n",
+            self.address, self.private_key
+        )
+    }
+
+    /// Write the key to a new file readable only by the current user.
+    ///
+    /// Refuses to overwrite an existing file so a key can't be clobbered by accident.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create key file {}: {}", path.display(), e))?;
+        file.write_all(self.to_env_file().as_bytes())?;
+        file.sync_all()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt_signer::ReceiptSigner;
+
+    fn printed_field<'a>(output: &'a str, label: &str) -> &'a str {
+        output.lines()
+            .find_map(|line| line.strip_prefix(label))
+            .map(str::trim)
+            .expect("field missing from output")
+    }
+
+    #[test]
+    fn test_printed_address_matches_printed_key() {
+        let key = GeneratedKey::generate();
+        let output = key.render();
+
+        let private_key = printed_field(&output, "Private key:");
+        let address = printed_field(&output, "Address:");
+
+        let signer = ReceiptSigner::new(private_key).unwrap();
+        assert_eq!(signer.get_address(), address);
+    }
+
+    #[test]
+    fn test_known_key_address() {
+        // Well-known test vector: private key 1
+        let signer = ReceiptSigner::new(
+            "0000000000000000000000000000000000000000000000000000000000000001"
+        ).unwrap();
+        assert_eq!(signer.get_address(), "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf");
+    }
+
+    #[test]
+    fn test_generated_keys_are_unique() {
+        let a = GeneratedKey::generate();
+        let b = GeneratedKey::generate();
+        assert_ne!(a.private_key, b.private_key);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("lcore-keygen-{}.env", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let key = GeneratedKey::generate();
+        key.write_to_file(&path).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains(&format!("VERIFIER_PRIVATE_KEY={}", key.private_key)));
+
+        // Existing files are never overwritten
+        assert!(key.write_to_file(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
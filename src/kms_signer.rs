@@ -0,0 +1,261 @@
+//! Signing with a key held in AWS KMS
+//!
+//! The key is an asymmetric `ECC_SECG_P256K1` KMS key; the private half
+//! never leaves KMS. Its address comes from `GetPublicKey` at startup, and
+//! each digest is signed with `Sign` (`MessageType: DIGEST`,
+//! `ECDSA_SHA_256`). KMS returns a DER signature without a recovery id, so
+//! s is normalized to the low half and the recovery id found by recovering
+//! against the known public key.
+//!
+//! Requests go through `aws-sdk-kms`, routed through the configured proxy.
+//! Credentials and region come from the standard AWS provider chain unless
+//! `kms_region` is set; `kms_endpoint` targets LocalStack or another
+//! KMS-compatible service. SDK retries are off: failures are classified as
+//! transient or fatal and retried by the signing pipeline.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use aws_config::retry::RetryConfig;
+use aws_config::timeout::TimeoutConfig;
+use aws_config::BehaviorVersion;
+use aws_sdk_kms::config::Region;
+use aws_sdk_kms::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::{KeySpec, MessageType, SigningAlgorithmSpec};
+use aws_sdk_kms::Client;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use k256::pkcs8::DecodePublicKey;
+use std::time::Duration;
+use crate::config::Config;
+use crate::error::VerifierError;
+use crate::proxy::ProxySettings;
+use crate::receipt_signer::derive_address;
+use crate::signer_backend::ReceiptSignerBackend;
+
+/// Time allowed for each KMS call
+const KMS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Signs digests with an AWS KMS key
+pub struct KmsSigner {
+    key_id: String,
+    client: Client,
+    public_key: VerifyingKey,
+    address: String,
+}
+
+impl KmsSigner {
+    /// Connect to the KMS key named by `kms_key_id`, reading its public key
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        let key_id = config.kms_key_id.as_deref()
+            .ok_or_else(|| VerifierError::Config("kms_key_id is required by the kms signer backend".to_string()))?;
+
+        let http_client = ProxySettings::from_config(config).sdk_client("KMS", config.kms_endpoint.as_deref())?;
+        let mut loader = aws_config::defaults(BehaviorVersion::latest())
+            .timeout_config(TimeoutConfig::builder().operation_timeout(KMS_TIMEOUT).build())
+            .retry_config(RetryConfig::disabled());
+        if let Some(region) = &config.kms_region {
+            loader = loader.region(Region::new(region.clone()));
+        }
+        let shared = loader.load().await;
+        if shared.region().is_none() {
+            return Err(VerifierError::Config("No AWS region for KMS; set kms_region or AWS_REGION".to_string()).into());
+        }
+        if shared.credentials_provider().is_none() {
+            return Err(VerifierError::Config("No AWS credentials found for KMS".to_string()).into());
+        }
+
+        let mut builder = aws_sdk_kms::config::Builder::from(&shared).http_client(http_client);
+        if let Some(endpoint) = &config.kms_endpoint {
+            builder = builder.endpoint_url(endpoint);
+        }
+
+        Self::connect(Client::from_conf(builder.build()), key_id).await
+    }
+
+    /// Sign with `key_id` through `client`
+    pub async fn connect(client: Client, key_id: &str) -> Result<Self> {
+        let public_key = fetch_public_key(&client, key_id).await?;
+
+        Ok(Self {
+            key_id: key_id.to_string(),
+            client,
+            address: derive_address(&public_key),
+            public_key,
+        })
+    }
+}
+
+/// Public half of a secp256k1 KMS key
+async fn fetch_public_key(client: &Client, key_id: &str) -> Result<VerifyingKey> {
+    let response = client.get_public_key()
+        .key_id(key_id)
+        .send()
+        .await
+        .map_err(|e| classify("GetPublicKey", e))?;
+
+    if let Some(spec) = response.key_spec().filter(|spec| **spec != KeySpec::EccSecgP256K1) {
+        return Err(VerifierError::signing_fatal(format!(
+            "KMS key {} is {}, not an {} key", key_id, spec.as_str(), KeySpec::EccSecgP256K1.as_str()
+        )).into());
+    }
+    let der = response.public_key()
+        .ok_or_else(|| VerifierError::signing_fatal("KMS GetPublicKey returned no public key"))?;
+    VerifyingKey::from_public_key_der(der.as_ref())
+        .map_err(|e| VerifierError::signing_fatal(format!("Invalid KMS public key: {}", e)).into())
+}
+
+/// Throttling, server and transport errors pass; bad keys and denied access do not
+fn classify<E>(operation: &str, error: SdkError<E, aws_sdk_kms::config::http::HttpResponse>) -> anyhow::Error
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    let transient = match &error {
+        SdkError::ServiceError(e) => {
+            e.raw().status().is_server_error() || e.err().code() == Some("ThrottlingException")
+        }
+        SdkError::DispatchFailure(_) | SdkError::TimeoutError(_) | SdkError::ResponseError(_) => true,
+        _ => false,
+    };
+
+    let message = format!("KMS {} failed: {}", operation, DisplayErrorContext(error));
+    if transient {
+        VerifierError::signing_transient(message)
+    } else {
+        VerifierError::signing_fatal(message)
+    }.into()
+}
+
+#[async_trait]
+impl ReceiptSignerBackend for KmsSigner {
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    async fn sign_digest(&self, digest: [u8; 32]) -> Result<[u8; 65]> {
+        let response = self.client.sign()
+            .key_id(&self.key_id)
+            .message(Blob::new(digest))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await
+            .map_err(|e| classify("Sign", e))?;
+
+        let der = response.signature()
+            .ok_or_else(|| VerifierError::signing_fatal("KMS Sign returned no signature"))?;
+        let signature = Signature::from_der(der.as_ref())
+            .map_err(|e| VerifierError::signing_fatal(format!("Invalid KMS signature: {}", e)))?;
+        // Ethereum only accepts the low-s form of each signature
+        let signature = signature.normalize_s().unwrap_or(signature);
+
+        let recovery_id = (0..=1)
+            .filter_map(RecoveryId::from_byte)
+            .find(|&id| VerifyingKey::recover_from_prehash(&digest, &signature, id).ok() == Some(self.public_key))
+            .ok_or_else(|| VerifierError::signing_fatal("KMS signature does not match the key's public key"))?;
+
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.to_bytes());
+        bytes[64] = 27 + recovery_id.to_byte();
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_kms::config::Credentials;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use k256::ecdsa::SigningKey;
+    use k256::pkcs8::EncodePublicKey;
+
+    fn kms_client(endpoint: &str) -> Client {
+        Client::from_conf(aws_sdk_kms::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("AKIDEXAMPLE", "secret", None, None, "test"))
+            .endpoint_url(endpoint)
+            .retry_config(RetryConfig::disabled())
+            .build())
+    }
+
+    /// A KMS that signs with `key`, answering with high-s signatures so the
+    /// normalization is exercised
+    async fn mock_kms(server: &mut mockito::Server, key: SigningKey) {
+        let public_key = key.verifying_key().to_public_key_der().unwrap();
+        server.mock("POST", "/")
+            .match_header("x-amz-target", "TrentService.GetPublicKey")
+            .match_header("authorization", mockito::Matcher::Regex("^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/.*/us-east-1/kms/aws4_request".to_string()))
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "KeyId": "alias/verifier" })))
+            .with_body(serde_json::json!({
+                "KeyId": "alias/verifier",
+                "KeySpec": "ECC_SECG_P256K1",
+                "PublicKey": BASE64.encode(public_key.as_bytes()),
+            }).to_string())
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_header("x-amz-target", "TrentService.Sign")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "MessageType": "DIGEST" })))
+            .with_body_from_request(move |request| {
+                let body: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+                let digest = BASE64.decode(body["Message"].as_str().unwrap()).unwrap();
+                let (signature, _) = key.sign_prehash_recoverable(&digest).unwrap();
+                let (r, s) = signature.split_scalars();
+                let high_s = Signature::from_scalars(r, -*s).unwrap();
+                serde_json::json!({ "Signature": BASE64.encode(high_s.to_der().as_bytes()) }).to_string().into_bytes()
+            })
+            .create_async()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_kms_signatures_recover_to_the_kms_key() {
+        let mut server = mockito::Server::new_async().await;
+        let key = SigningKey::from_slice(&[0x44; 32]).unwrap();
+        mock_kms(&mut server, key.clone()).await;
+
+        let signer = KmsSigner::connect(kms_client(&server.url()), "alias/verifier")
+            .await
+            .unwrap();
+        assert_eq!(signer.address(), derive_address(key.verifying_key()));
+
+        let digest = [0x5a; 32];
+        let signature = signer.sign_digest(digest).await.unwrap();
+        let parsed = Signature::from_slice(&signature[..64]).unwrap();
+        assert!(parsed.normalize_s().is_none(), "s must be in the low half");
+        let recovered = VerifyingKey::recover_from_prehash(
+            &digest,
+            &parsed,
+            RecoveryId::from_byte(signature[64] - 27).unwrap(),
+        ).unwrap();
+        assert_eq!(derive_address(&recovered), signer.address());
+    }
+
+    #[tokio::test]
+    async fn test_kms_errors_are_classified() {
+        let mut server = mockito::Server::new_async().await;
+        let _denied = server.mock("POST", "/")
+            .with_status(400)
+            .with_body(r#"{"__type": "AccessDeniedException", "message": "not authorized"}"#)
+            .create_async()
+            .await;
+        let err = KmsSigner::connect(kms_client(&server.url()), "alias/verifier")
+            .await
+            .err()
+            .unwrap();
+        assert!(!crate::error::is_retryable_error(&err), "{}", err);
+
+        server.reset();
+        let _throttled = server.mock("POST", "/")
+            .with_status(400)
+            .with_body(r#"{"__type": "ThrottlingException", "message": "rate exceeded"}"#)
+            .create_async()
+            .await;
+        let err = KmsSigner::connect(kms_client(&server.url()), "alias/verifier")
+            .await
+            .err()
+            .unwrap();
+        assert!(crate::error::is_retryable_error(&err), "{}", err);
+    }
+}
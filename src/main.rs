@@ -3,37 +3,168 @@
 //! This service runs alongside the Cartesi node to handle RISC Zero proof verification.
 //! It polls for proof requests, verifies proofs, and submits signed receipts.
 
+mod arweave;
+mod audit_log;
+mod bloom;
+#[cfg(feature = "bls")]
+mod bls_signer;
+mod cid;
+mod circuit_breaker;
+mod compression;
 mod config;
+mod eip712;
+mod envelope;
+mod epoch;
 mod error;
 mod graphql;
+mod heartbeat;
+mod http_api;
+mod keygen;
+mod kms_signer;
+mod poll_backoff;
+mod processed;
+mod proxy;
+mod proof_types;
 mod proof_verifier;
+mod rate_limit;
+mod s3;
+mod sandbox;
+mod sequencer;
+mod signer_backend;
+mod state;
+mod status;
+mod streaming;
+mod submission_order;
+mod submission_queue;
+mod telemetry;
+mod receipt_cache;
 mod receipt_signer;
 mod inputbox_client;
+mod inputbox_rpc;
+mod journal_rules;
+mod journal_schema;
 mod types;
 
-use anyhow::Result;
-use clap::Parser;
-use tracing::{info, warn, error};
+#[cfg(test)]
+mod test_fixtures;
+#[cfg(test)]
+mod test_harness;
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use clap::{Parser, Subcommand};
+use tracing::{debug, info, info_span, warn, error, Instrument};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
-use tokio::time::interval;
+use sha3::{Digest, Keccak256};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{interval, interval_at};
+use tokio_util::sync::CancellationToken;
 use warp::Filter;
 
-use crate::config::Config;
-use crate::graphql::GraphQLClient;
-use crate::proof_verifier::ProofVerifier;
+use crate::circuit_breaker::BreakerState;
+use crate::compression::Compression;
+use crate::config::{Config, InputBoxBackend, LogFormat, SignerBackend, SigningScheme, SubmissionTransport};
+use crate::eip712::Eip712Domain;
+use crate::epoch::{BatchEntry, EpochTracker};
+use crate::error::{is_rejection, is_retryable_error, VerifierError};
+use crate::graphql::{GraphQLClient, NoticeSource};
+use crate::proof_verifier::{normalize_image_id, ProofVerifier};
+use crate::audit_log::{AuditLog, SubmissionResult};
+use crate::receipt_cache::ReceiptCache;
 use crate::receipt_signer::ReceiptSigner;
-use crate::inputbox_client::InputBoxClient;
+use crate::inputbox_client::{InputBoxClient, InputBoxSink};
+use crate::inputbox_rpc::{FeePolicy, InputBoxRpcClient};
+use crate::keygen::GeneratedKey;
+use crate::poll_backoff::PollBackoff;
+use crate::processed::{DedupStatus, ProcessedStore};
+use crate::proxy::ProxySettings;
+use crate::rate_limit::DeviceRateLimiter;
+use crate::sandbox::SandboxedVerifier;
+use crate::sequencer::SequencerClient;
+use crate::signer_backend::{LocalKeySigner, ReceiptSignerBackend};
+use crate::state::{FileStateStore, PersistedState};
+use crate::status::RuntimeStatus;
+use crate::submission_order::{SubmissionOrder, Turn};
+use crate::submission_queue::{SubmissionBatcher, SubmissionQueue};
+use crate::telemetry::LogLevel;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to configuration file
-    #[arg(short, long, default_value = "verifier.toml")]
+    #[arg(short, long, default_value = "verifier.toml", global = true)]
     config: String,
     
-    /// Enable verbose logging
-    #[arg(short, long)]
+    /// Log at debug level (overrides LOG_LEVEL)
+    #[arg(short, long, global = true)]
     verbose: bool,
+    
+    /// Log line format, `text` or `json` (overrides LOG_FORMAT)
+    #[arg(long, global = true)]
+    log_format: Option<LogFormat>,
+    
+    /// Verify and sign receipts but only log them instead of submitting
+    /// (same as DRY_RUN=true)
+    #[arg(long, global = true)]
+    dry_run: bool,
+    
+    /// What to do; runs the verifier service when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the verifier service (the default)
+    Run,
+    
+    /// Verify one receipt file and print the result as JSON, without
+    /// running the service. Exits non-zero if the receipt does not verify.
+    Verify {
+        /// Receipt file, in any supported receipt format
+        #[arg(long)]
+        receipt: PathBuf,
+        
+        /// Image ID the receipt must have been proven against
+        #[arg(long)]
+        image_id: String,
+        
+        /// Proof type whose journal schema and rules apply
+        #[arg(long, default_value = "iot_validation")]
+        proof_type: String,
+    },
+    
+    /// Print the address of the configured signing key, e.g. to add it to
+    /// a contract's allow-list
+    Address,
+    
+    /// Reprocess the proof requests of a range of past epochs, e.g. after
+    /// downtime. Receipts already submitted are skipped.
+    Backfill {
+        /// First epoch to reprocess
+        #[arg(long)]
+        from_epoch: u64,
+        
+        /// Last epoch to reprocess (defaults to the latest)
+        #[arg(long)]
+        to_epoch: Option<u64>,
+    },
+    
+    /// Generate a new signing key and print its private key and address
+    GenerateKey {
+        /// Also write the key to this file (created with owner-only permissions)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Internal: verify one receipt read from stdin (sandboxed verification child)
+    #[command(name = "verify-sandboxed", hide = true)]
+    VerifySandboxed,
 }
 
 #[tokio::main]
@@ -41,169 +172,3712 @@ async fn main() -> Result<()> {
     // Parse command line arguments
     let args = Args::parse();
     
+    match &args.command {
+        Some(Command::GenerateKey { output }) => return generate_key(output.as_deref()),
+        // Stdout carries the verdict, so these run before logging is set up
+        Some(Command::VerifySandboxed) => return sandbox::run_child(),
+        Some(Command::Verify { receipt, image_id, proof_type }) => {
+            return verify_receipt_file(&args.config, receipt, image_id, proof_type);
+        }
+        Some(Command::Address) => return print_address(&args.config).await,
+        Some(Command::Run | Command::Backfill { .. }) | None => {}
+    }
+    
+    // Load configuration (prioritize environment variables) before logging,
+    // since it decides where spans are exported
+    let (mut config, load_error) = match Config::load(&args.config) {
+        Ok(config) => (config, None),
+        Err(e) => (
+            Config::from_env().expect("Failed to load configuration from environment variables"),
+            Some(e),
+        ),
+    };
+    let overrides = CliOverrides { verbose: args.verbose, log_format: args.log_format, dry_run: args.dry_run };
+    overrides.apply(&mut config);
+    
     // Initialize logging
-    let filter = if args.verbose { "debug" } else { "info" };
-    tracing_subscriber::fmt()
-        .with_env_filter(filter)
-        .init();
+    let telemetry = telemetry::init(&config.log_level, config.log_format, config.otlp_endpoint.as_deref())?;
     
     info!("Starting L{{CORE}} RISC Zero Proof Verifier");
     
-    // Load configuration (prioritize environment variables)
-    let config = Config::load(&args.config).unwrap_or_else(|e| {
-        warn!("Failed to load config file {}: {}. Using environment variables.", args.config, e);
-        Config::from_env().expect("Failed to load configuration from environment variables")
-    });
+    let config_path = match load_error {
+        Some(e) => {
+            warn!("Failed to load config file {}: {}. Using environment variables.", args.config, e);
+            None
+        }
+        None => Some(args.config.clone()),
+    };
+    for notice in &config.deprecations {
+        warn!("{}", notice);
+    }
     info!("Configuration loaded successfully");
     
+    if config.dry_run {
+        warn!("Dry run: receipts are verified and signed but nothing is submitted to the InputBox");
+    }
+    
+    if let Some(Command::Backfill { from_epoch, to_epoch }) = args.command {
+        return backfill(config, from_epoch, to_epoch).await;
+    }
+    
     // Initialize components
-    let graphql_client = GraphQLClient::new(&config.graphql_endpoint)?;
-    let proof_verifier = ProofVerifier::new(config.allowed_image_ids.clone());
-    let receipt_signer = ReceiptSigner::new(&config.verifier_private_key)?;
-    let inputbox_client = InputBoxClient::new(&config.inputbox_endpoint, &config.dapp_address)?;
+    let mut pipeline = Pipeline::new(config).await?
+        .with_overrides(overrides)
+        .with_log_level(telemetry.log_level());
+    if let Some(path) = config_path {
+        pipeline = pipeline.with_config_path(path);
+    }
+    let pipeline = Arc::new(pipeline);
+    start_submission_drain(&pipeline);
+    let config = &pipeline.config;
     
     info!("All components initialized successfully");
     info!("Polling interval: {} seconds", config.poll_interval_secs);
     
     // Start health check server
-    let health_check = warp::path("health")
-        .map(|| warp::reply::with_status("OK", warp::http::StatusCode::OK));
-    
-    let health_server = warp::serve(health_check)
+    let health_server = warp::serve(service_routes(pipeline.clone()))
         .run(([0, 0, 0, 0], 8080));
     
-    info!("Health check server started on port 8080");
+    info!("Health check server started on port 8080 (/live, /ready, /status)");
+    if config.admin_token.is_some() {
+        info!("Admin endpoints enabled under /admin");
+    }
     
     // Main polling loop
     let mut poll_interval = interval(Duration::from_secs(config.poll_interval_secs));
+    let mut backoff = PollBackoff::new(
+        Duration::from_secs(config.poll_interval_secs),
+        Duration::from_secs(config.max_poll_interval_secs.unwrap_or(0)),
+        config.poll_backoff_multiplier,
+    );
+    let mut idle = heartbeat::IdleHeartbeat::new(config.idle_heartbeat_polls);
+    let shutdown = CancellationToken::new();
     
-    // Run health server and polling loop concurrently
-    tokio::select! {
+    // A SIGHUP or `POST /admin/reload` re-reads the config file; the loop
+    // picks up the new interval
+    let mut reloads = pipeline.poll_interval.subscribe();
+    #[cfg(unix)]
+    if let Some(path) = &pipeline.config_path {
+        spawn_reload_listener(pipeline.clone(), path.clone())?;
+    }
+    
+    let poll_loop = async {
+        loop {
+            tokio::select! {
+                _ = poll_interval.tick() => {}
+                Ok(()) = reloads.changed() => {
+                    let period = *reloads.borrow_and_update();
+                    if period != poll_interval.period() {
+                        info!("Polling interval changed to {} seconds", period.as_secs());
+                        backoff.set_base(period);
+                        pipeline.status.set_poll_interval(period);
+                        poll_interval = interval(period);
+                    }
+                    continue;
+                }
+                _ = shutdown.cancelled() => return Ok(()),
+            }
+            
+            match process_proof_requests(&pipeline, &shutdown).instrument(info_span!("poll_cycle")).await {
+                Ok(count) => {
+                    if count > 0 {
+                        info!("Processed {} proof requests", count);
+                    }
+                    if let Some(beat) = idle.record(count) {
+                        info!(
+                            "Idle: no proof requests in {} polls (uptime {}s, last activity {})",
+                            beat.empty_polls,
+                            beat.uptime.as_secs(),
+                            beat.last_activity.map_or("never".to_string(), |t| t.to_rfc3339()),
+                        );
+                    }
+                    
+                    // Stretch the interval while idle, back to the base once busy
+                    let next = backoff.record(count);
+                    if next != poll_interval.period() {
+                        debug!("Next poll in {} seconds", next.as_secs());
+                        pipeline.status.set_poll_interval(next);
+                        poll_interval = interval_at(tokio::time::Instant::now() + next, next);
+                    }
+                }
+                Err(e) if config.on_signer_error.aborts_on(&e) => {
+                    error!("Stopping on signer error: {:#}", e);
+                    return Err(e);
+                }
+                Err(e) => {
+                    error!("Error processing proof requests: {}", e);
+                }
+            }
+        }
+    };
+    tokio::pin!(poll_loop);
+    
+    // Run health server and polling loop concurrently until either dies or a
+    // shutdown signal arrives
+    let result = tokio::select! {
         _ = health_server => {
             error!("Health server stopped unexpectedly");
+            Ok(())
         }
-        _ = async {
-            loop {
-                poll_interval.tick().await;
-                
-                match process_proof_requests(
-                    &graphql_client,
-                    &proof_verifier,
-                    &receipt_signer,
-                    &inputbox_client,
-                    &config,
-                ).await {
-                    Ok(count) => {
-                        if count > 0 {
-                            info!("Processed {} proof requests", count);
-                        }
-                    }
-                    Err(e) => {
-                        error!("Error processing proof requests: {}", e);
-                    }
+        result = &mut poll_loop => result,
+        _ = shutdown_signal() => {
+            info!("Shutting down gracefully");
+            shutdown.cancel();
+            
+            // Let the in-flight request finish and queued receipts go out,
+            // but never past the grace period
+            let grace = Duration::from_secs(config.shutdown_grace_secs);
+            let finish = async {
+                let result = (&mut poll_loop).await;
+                if let Some(submissions) = &pipeline.submissions {
+                    submissions.flushed().await;
+                }
+                result
+            };
+            match tokio::time::timeout(grace, finish).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warn!("In-flight requests did not finish within {:?}; exiting anyway", grace);
+                    Ok(())
                 }
             }
-        } => {
-            error!("Polling loop stopped unexpectedly");
+        }
+    };
+    
+    if let Some(state) = &pipeline.state {
+        state.flush().await;
+    }
+    result
+}
+
+/// Dependency status reported by the readiness probe
+#[derive(Debug, serde::Serialize)]
+struct Readiness {
+    graphql: bool,
+    inputbox: bool,
+    /// An open circuit makes the node unready without pinging it
+    graphql_circuit: BreakerState,
+}
+
+/// Snapshot of the service served by `/status`
+#[derive(Debug, serde::Serialize)]
+struct StatusReport {
+    verifier_address: String,
+    uptime_secs: u64,
+    last_successful_poll: Option<String>,
+    last_processed_input_index: Option<u64>,
+    /// Requests whose proofs were rejected for good and passed over
+    rejected_requests: u64,
+    last_rejected_input_index: Option<u64>,
+    poll_interval_secs: u64,
+    allowed_image_count: usize,
+    graphql_circuit: BreakerState,
+    /// Submissions waiting on `max_submissions_per_sec`
+    submissions_rate_limited: usize,
+}
+
+impl StatusReport {
+    fn new(pipeline: &Pipeline) -> Self {
+        let status = &pipeline.status;
+        let (rejected_requests, last_rejected_input_index) = status.rejected();
+        Self {
+            verifier_address: pipeline.signer.get_address(),
+            uptime_secs: status.uptime().as_secs(),
+            last_successful_poll: status.last_successful_poll().map(|at| at.to_rfc3339()),
+            last_processed_input_index: status.last_processed_input(),
+            rejected_requests,
+            last_rejected_input_index,
+            poll_interval_secs: status.poll_interval().as_secs(),
+            allowed_image_count: pipeline.verifier.read().unwrap().allowed_image_ids().len(),
+            graphql_circuit: pipeline.notices.breaker_state(),
+            submissions_rate_limited: pipeline.inputbox.rate_limit_queue_depth(),
         }
     }
+}
+
+/// `/live` answers as long as the process runs; `/ready` (and the older
+/// `/health`) also requires the GraphQL node and the InputBox to respond,
+/// and the GraphQL circuit breaker not to be open. `/status` reports what the
+/// poll loop has done, without checking any dependency.
+/// The `/admin` endpoints are served alongside when `admin_token` is set.
+fn service_routes(pipeline: Arc<Pipeline>) -> impl Filter<Extract = (impl warp::Reply,), Error = Infallible> + Clone {
+    let admin = admin_routes(pipeline.clone());
     
-    Ok(())
+    let live = warp::path("live")
+        .and(warp::path::end())
+        .map(|| warp::reply::with_status("OK", warp::http::StatusCode::OK));
+    
+    let status = {
+        let pipeline = pipeline.clone();
+        warp::path("status")
+            .and(warp::path::end())
+            .and(warp::get())
+            .map(move || warp::reply::json(&StatusReport::new(&pipeline)))
+    };
+    
+    let ready = warp::path("ready")
+        .or(warp::path("health"))
+        .unify()
+        .and(warp::path::end())
+        .and(warp::any().map(move || pipeline.clone()))
+        .and_then(|pipeline: Arc<Pipeline>| async move {
+            let readiness = check_readiness(&pipeline).await;
+            let status = if readiness.graphql && readiness.inputbox {
+                warp::http::StatusCode::OK
+            } else {
+                warp::http::StatusCode::SERVICE_UNAVAILABLE
+            };
+            Ok::<_, warp::Rejection>(warp::reply::with_status(warp::reply::json(&readiness), status))
+        });
+    
+    live.map(warp::Reply::into_response)
+        .or(ready.map(warp::Reply::into_response))
+        .unify()
+        .or(status.map(warp::Reply::into_response))
+        .unify()
+        .or(admin)
+        .unify()
+        .recover(http_api::handle_rejection)
 }
 
-/// Process all pending proof requests
-async fn process_proof_requests(
-    graphql: &GraphQLClient,
-    verifier: &ProofVerifier,
-    signer: &ReceiptSigner,
-    inputbox: &InputBoxClient,
-    config: &Config,
-) -> Result<usize> {
-    // Query for proof request notices
-    let requests = graphql.query_proof_requests().await?;
+/// Body of `POST /admin/images`
+#[derive(serde::Deserialize)]
+struct AddImageRequest {
+    image_id: String,
+}
+
+/// Body of `PUT /admin/log-level`
+#[derive(serde::Deserialize)]
+struct LogLevelRequest {
+    level: String,
+}
+
+/// `GET /admin/images` lists the allowed images, `POST /admin/images` adds
+/// one and `DELETE /admin/images/{id}` removes one. Each replies with the
+/// resulting list. Changes last until the next restart or reload.
+/// `POST /admin/reload` re-reads the config file like SIGHUP, replying with
+/// what changed, but refuses (409) a file that changes restart-only fields.
+/// `PUT /admin/log-level` changes the log level until the next restart,
+/// replying with the previous one.
+fn admin_routes(pipeline: Arc<Pipeline>) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    let authorized = warp::header::optional::<String>("authorization")
+        .and(warp::any().map(move || pipeline.clone()))
+        .and_then(|authorization: Option<String>, pipeline: Arc<Pipeline>| async move {
+            authorize_admin(&pipeline.config, authorization.as_deref()).map(|()| pipeline)
+        });
+    let images = warp::path("admin")
+        .and(warp::path("images"))
+        .and(authorized.clone());
     
-    if requests.is_empty() {
-        return Ok(0);
-    }
+    let log_level = warp::path("admin")
+        .and(warp::path("log-level"))
+        .and(warp::path::end())
+        .and(warp::put())
+        .and(authorized.clone())
+        .and(warp::body::json())
+        .and_then(|pipeline: Arc<Pipeline>, request: LogLevelRequest| async move {
+            let log_level = pipeline.log_level.as_ref()
+                .ok_or_else(|| http_api::reject(VerifierError::NotFound("log level control".to_string())))?;
+            let previous = log_level.set(&request.level).map_err(|e| http_api::reject(match e.downcast::<VerifierError>() {
+                Ok(e) => e,
+                Err(e) => VerifierError::Config(e.to_string()),
+            }))?;
+            
+            let level = log_level.current();
+            info!("Admin request changed the log level from {} to {}", previous, level);
+            Ok::<_, warp::Rejection>(warp::Reply::into_response(warp::reply::json(&serde_json::json!({
+                "previous": previous,
+                "level": level,
+            }))))
+        });
     
-    info!("Found {} proof requests to process", requests.len());
+    let reload = warp::path("admin")
+        .and(warp::path("reload"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(authorized)
+        .and_then(|pipeline: Arc<Pipeline>| async move {
+            let path = pipeline.config_path.clone()
+                .ok_or_else(|| http_api::reject(VerifierError::NotFound("config file".to_string())))?;
+            
+            info!("Admin request reloads {}", path);
+            match reload_config(&pipeline, &path, RestartOnlyChanges::Reject) {
+                Ok(report) => Ok(warp::Reply::into_response(warp::reply::json(&report))),
+                Err(e) => {
+                    warn!("Config reload rejected: {}", e);
+                    Err(http_api::reject(match e.downcast::<VerifierError>() {
+                        Ok(e) => e,
+                        Err(e) => VerifierError::Config(e.to_string()),
+                    }))
+                }
+            }
+        });
     
-    let mut processed = 0;
+    let list = images.clone()
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(|pipeline: Arc<Pipeline>| {
+            let allowed_image_ids = pipeline.verifier.read().unwrap().allowed_image_ids().to_vec();
+            allowed_images_reply(allowed_image_ids)
+        });
     
-    for request in requests {
-        match process_single_request(request, verifier, signer, inputbox, config).await {
-            Ok(()) => processed += 1,
+    let add = images.clone()
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and_then(|pipeline: Arc<Pipeline>, request: AddImageRequest| async move {
+            if normalize_image_id(&request.image_id).is_none() {
+                return Err(http_api::reject(VerifierError::InvalidQuery(
+                    format!("{:?} is not a 32-byte hex image ID", request.image_id)
+                )));
+            }
+            if pipeline.verifier.read().unwrap().allows_any_image() {
+                return Err(http_api::reject(VerifierError::InvalidQuery(
+                    "allow_any_image is set, so there is no allow-list to add to".to_string()
+                )));
+            }
+            
+            info!("Admin request adds allowed image {}", request.image_id);
+            let allowed_image_ids = pipeline.update_allowed_images(|verifier| verifier.add_allowed_image(request.image_id));
+            Ok(allowed_images_reply(allowed_image_ids))
+        });
+    
+    let remove = images
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and_then(|pipeline: Arc<Pipeline>, image_id: String| async move {
+            let normalized = normalize_image_id(&image_id).ok_or_else(|| http_api::reject(
+                VerifierError::InvalidQuery(format!("{:?} is not a 32-byte hex image ID", image_id))
+            ))?;
+            
+            let mut found = false;
+            let allowed_image_ids = pipeline.update_allowed_images(|verifier| {
+                found = verifier.is_image_allowed(&normalized);
+                verifier.remove_allowed_image(&image_id);
+            });
+            if !found {
+                return Err(http_api::reject(VerifierError::NotFound(format!("allowed image {}", image_id))));
+            }
+            
+            info!("Admin request removed allowed image {}", image_id);
+            Ok(allowed_images_reply(allowed_image_ids))
+        });
+    
+    list.or(add).unify()
+        .or(remove).unify()
+        .or(reload).unify()
+        .or(log_level).unify()
+}
+
+fn allowed_images_reply(allowed_image_ids: Vec<String>) -> warp::reply::Response {
+    warp::Reply::into_response(warp::reply::json(&serde_json::json!({ "allowed_image_ids": allowed_image_ids })))
+}
+
+/// Accept only `Authorization: Bearer <admin_token>`; without a configured
+/// token the admin endpoints do not exist
+fn authorize_admin(config: &Config, authorization: Option<&str>) -> Result<(), warp::Rejection> {
+    let Some(expected) = config.admin_token.as_deref() else {
+        return Err(warp::reject::not_found());
+    };
+    
+    let presented = authorization.and_then(|value| value.strip_prefix("Bearer ")).unwrap_or_default();
+    if constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(http_api::reject(VerifierError::Unauthorized("missing or invalid admin token".to_string())))
+    }
+}
+
+/// Compare without stopping at the first difference, so the response time
+/// reveals nothing about how much of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Check the GraphQL node and the InputBox concurrently, each bounded by
+/// `health_check_timeout_secs` so the probe itself cannot hang
+async fn check_readiness(pipeline: &Pipeline) -> Readiness {
+    let limit = Duration::from_secs(pipeline.config.health_check_timeout_secs);
+    let graphql_circuit = pipeline.notices.breaker_state();
+    let ping = async {
+        graphql_circuit != BreakerState::Open
+            && tokio::time::timeout(limit, pipeline.notices.ping()).await.unwrap_or(false)
+    };
+    let (graphql, inputbox) = tokio::join!(
+        ping,
+        tokio::time::timeout(limit, pipeline.inputbox.health_check()),
+    );
+    
+    Readiness {
+        graphql,
+        inputbox: matches!(inputbox, Ok(Ok(true))),
+        graphql_circuit,
+    }
+}
+
+/// Resolve on SIGINT, or SIGTERM on Unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
             Err(e) => {
-                warn!("Failed to process request: {}", e);
-                // Continue processing other requests
+                warn!("Failed to install SIGTERM handler: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+}
+
+/// Settings given on the command line, which win over the config file
+#[derive(Debug, Clone, Copy, Default)]
+struct CliOverrides {
+    verbose: bool,
+    log_format: Option<LogFormat>,
+    dry_run: bool,
+}
+
+impl CliOverrides {
+    fn apply(&self, config: &mut Config) {
+        if self.verbose {
+            config.log_level = "debug".to_string();
+        }
+        if let Some(format) = self.log_format {
+            config.log_format = format;
+        }
+        config.dry_run |= self.dry_run;
+    }
+}
+
+/// Identifies a proof request across polls that re-read it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RequestKey {
+    input_index: u64,
+    device_id: String,
+    receipt_url: String,
+}
+
+impl RequestKey {
+    fn of(request: &types::ProofRequest) -> Self {
+        Self {
+            input_index: request.input_index,
+            device_id: request.device_id.clone(),
+            receipt_url: request.receipt_url.clone(),
+        }
+    }
+}
+
+/// Long-lived components shared by every processing cycle
+struct Pipeline {
+    /// The rollup node's GraphQL API, outside tests
+    notices: Box<dyn NoticeSource>,
+    /// Shared with the blocking threads verification runs on; replaced
+    /// when a config reload changes the allowed images
+    verifier: RwLock<Arc<ProofVerifier>>,
+    sandbox: RwLock<Option<Arc<SandboxedVerifier>>>,
+    signer: ReceiptSigner,
+    inputbox: Box<dyn InputBoxSink>,
+    /// Set when receipts are submitted in batches by a drain task
+    submissions: Option<SubmissionQueue>,
+    /// Receiving end of `submissions`, until the drain task takes it
+    batcher: Mutex<Option<SubmissionBatcher>>,
+    processed_store: ProcessedStore,
+    /// Set when `device_rate_limit_per_sec` is
+    device_limiter: Option<DeviceRateLimiter>,
+    /// Receipt hash of each request verified at or past the watermark, so a
+    /// re-read request that was already submitted costs its device nothing
+    request_receipts: Mutex<HashMap<RequestKey, String>>,
+    /// Fetch receipts through the configured proxies
+    receipt_clients: ReceiptClients,
+    /// Fetched `ipfs://` and `ar://` receipts; unset when `receipt_cache_entries` is 0
+    receipt_cache: Option<ReceiptCache>,
+    state: Option<PersistedState>,
+    /// Record of every signed receipt; unset without `audit_log_path`
+    audit_log: Option<AuditLog>,
+    epochs: EpochTracker,
+    /// Served by `/status`
+    status: RuntimeStatus,
+    /// Base poll interval; a reload publishes a new one to the poll loop
+    poll_interval: tokio::sync::watch::Sender<Duration>,
+    /// File a reload re-reads; unset when the config came from the environment only
+    config_path: Option<String>,
+    /// Command-line settings, applied again to every reloaded config
+    overrides: CliOverrides,
+    /// Changed by `PUT /admin/log-level`; unset when no subscriber is installed
+    log_level: Option<LogLevel>,
+    config: Config,
+}
+
+impl Pipeline {
+    async fn new(config: Config) -> Result<Self> {
+        let proxy = ProxySettings::from_config(&config);
+        for url in [&proxy.http, &proxy.https, &proxy.all].into_iter().flatten() {
+            info!("Routing outbound requests through proxy {}", proxy::redact(url));
+        }
+        
+        let request_timeout = Duration::from_secs(config.request_timeout_secs);
+        let graphql = GraphQLClient::new(
+            &config.graphql_endpoints,
+            Duration::from_secs(config.graphql_reprobe_secs),
+        )?
+            .with_request_timeout(request_timeout)?
+            .with_proxy(&proxy)?
+            .with_input_index_policy(config.on_input_index_mismatch)
+            .with_max_notices_per_poll(config.max_notices_per_poll)
+            .with_proof_request_source(config.proof_request_source)
+            .with_circuit_breaker(
+                config.graphql_breaker_threshold,
+                Duration::from_secs(config.graphql_breaker_cooldown_secs),
+            );
+        let (verifier, sandbox) = build_verifiers(&config)?;
+        if sandbox.is_some() {
+            info!("Receipts will be verified in a sandboxed subprocess");
+        }
+        let signer = ReceiptSigner::from_config(&config).await?
+            .with_eip191_prefix(config.use_eip191_prefix);
+        if config.signer_backend == SignerBackend::Kms {
+            info!("Signing receipts with KMS key {} ({})", config.kms_key_id.as_deref().unwrap_or_default(), signer.get_address());
+        }
+        let signer = match &config.bls_private_key {
+            #[cfg(feature = "bls")]
+            Some(key) => {
+                let signer = signer.with_bls(key)?;
+                info!("BLS receipt signatures enabled");
+                signer
             }
+            #[cfg(not(feature = "bls"))]
+            Some(_) => {
+                return Err(VerifierError::Config(
+                    "bls_private_key is set but this build lacks the `bls` feature".to_string()
+                ).into());
+            }
+            None => signer,
+        };
+        
+        let mut inputbox = InputBoxClient::new(&config.inputbox_endpoint, &config.dapp_address)?
+            .with_request_timeout(request_timeout)?
+            .with_proxy(&proxy)?
+            .with_retry(config.inputbox_max_retries, Duration::from_millis(config.inputbox_retry_delay_ms));
+        if let Some(rate) = config.max_submissions_per_sec {
+            inputbox = inputbox.with_rate_limit(rate);
         }
+        if config.submission_transport == SubmissionTransport::Espresso {
+            let endpoint = config.sequencer_endpoint.as_deref()
+                .ok_or_else(|| VerifierError::Config("sequencer_endpoint is required".to_string()))?;
+            info!("Submitting through sequencer {} (namespace {})", endpoint, config.sequencer_namespace);
+            inputbox = inputbox.with_sequencer(SequencerClient::new(
+                endpoint,
+                config.sequencer_chain_id,
+                config.sequencer_namespace,
+                config.sequencer_max_gas_price,
+                &config.dapp_address,
+                &config.verifier_private_key,
+            )?);
+        } else if config.inputbox_backend == InputBoxBackend::DirectRpc {
+            let rpc_url = config.rpc_url.as_deref()
+                .ok_or_else(|| VerifierError::Config("rpc_url is required".to_string()))?;
+            let contract = config.inputbox_contract_address.as_deref()
+                .ok_or_else(|| VerifierError::Config("inputbox_contract_address is required".to_string()))?;
+            info!("Sending addInput transactions to InputBox {} through {} (chain {})", contract, rpc_url, config.rpc_chain_id);
+            inputbox = inputbox.with_direct_rpc(InputBoxRpcClient::new(
+                rpc_url,
+                config.rpc_chain_id,
+                contract,
+                &config.dapp_address,
+                &config.verifier_private_key,
+            )?.with_fee_policy(FeePolicy {
+                strategy: config.rpc_fee_strategy,
+                gas_price: config.rpc_gas_price_wei.map(u128::from),
+                priority_fee: config.rpc_priority_fee_wei.map(u128::from),
+                max_fee_per_gas: config.rpc_max_fee_per_gas_wei.map(u128::from),
+            }));
+        }
+        
+        let (submissions, batcher) = if config.inputbox_batch_size > 1 {
+            info!("Submitting receipts in batches of up to {}", config.inputbox_batch_size);
+            let (queue, batcher) = submission_queue::channel(config.inputbox_batch_size);
+            (Some(queue), Some(batcher))
+        } else {
+            (None, None)
+        };
+        
+        let mut processed_store = ProcessedStore::new(config.dedup_trust_secs, config.processed_retention_secs);
+        if let Some(capacity) = config.dedup_bloom_capacity {
+            processed_store = processed_store.with_bloom_filter(capacity, config.dedup_bloom_fp_rate);
+        }
+        
+        let receipt_cache = if config.receipt_cache_entries > 0 {
+            Some(ReceiptCache::new(config.receipt_cache_entries, config.receipt_cache_dir.as_ref().map(PathBuf::from))?)
+        } else {
+            None
+        };
+        
+        // Resume polling from where the previous run stopped
+        let state = config.state_file.as_ref().map(|path| {
+            let state = PersistedState::load(Box::new(FileStateStore::new(path)));
+            info!("Resuming from input {} (state file {})", state.last_processed_index(), path);
+            graphql.advance_watermark(state.last_processed_index());
+            state
+        });
+        let audit_log = config.audit_log_path.as_ref().map(AuditLog::open).transpose()?;
+        let epochs = EpochTracker::new(state.as_ref().map(PersistedState::submitted_summaries).unwrap_or_default());
+        
+        Ok(Self {
+            notices: Box::new(graphql),
+            verifier: RwLock::new(verifier),
+            sandbox: RwLock::new(sandbox),
+            signer,
+            inputbox: Box::new(inputbox),
+            submissions,
+            batcher: Mutex::new(batcher),
+            processed_store,
+            device_limiter: config.device_rate_limit_per_sec.map(|rate| {
+                DeviceRateLimiter::new(rate, config.device_rate_limit_burst, config.device_rate_limit_max_devices)
+            }),
+            request_receipts: Mutex::new(HashMap::new()),
+            receipt_clients: ReceiptClients::new(&config).await?,
+            receipt_cache,
+            state,
+            audit_log,
+            epochs,
+            status: RuntimeStatus::new(Duration::from_secs(config.poll_interval_secs)),
+            poll_interval: tokio::sync::watch::channel(Duration::from_secs(config.poll_interval_secs)).0,
+            config_path: None,
+            overrides: CliOverrides::default(),
+            log_level: None,
+            config,
+        })
+    }
+    
+    /// Let the admin endpoint change the log level through `log_level`
+    fn with_log_level(mut self, log_level: LogLevel) -> Self {
+        self.log_level = Some(log_level);
+        self
+    }
+    
+    /// Keep command-line `overrides` in force across reloads
+    fn with_overrides(mut self, overrides: CliOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+    
+    /// Re-read `path` on reloads
+    fn with_config_path(mut self, path: impl Into<String>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+    
+    /// Read proof requests from `source` instead of the configured node
+    #[cfg(test)]
+    fn with_notice_source(mut self, source: impl NoticeSource + 'static) -> Self {
+        self.notices = Box::new(source);
+        self
+    }
+    
+    /// Submit to `sink` instead of the configured InputBox
+    #[cfg(test)]
+    fn with_inputbox_sink(mut self, sink: impl InputBoxSink + 'static) -> Self {
+        self.inputbox = Box::new(sink);
+        self
+    }
+    
+    /// Whether the receipt `request` points at was verified earlier and has
+    /// since been submitted or found on chain
+    fn is_request_submitted(&self, request: &types::ProofRequest) -> bool {
+        let receipts = self.request_receipts.lock().unwrap();
+        let Some(receipt_hash) = receipts.get(&RequestKey::of(request)) else {
+            return false;
+        };
+        self.processed_store.status(receipt_hash) != DedupStatus::Unknown
+            || self.state.as_ref().is_some_and(|state| state.is_submitted(receipt_hash))
     }
     
-    Ok(processed)
+    /// Edit the image allow-list of the running verifiers, returning the
+    /// resulting list. Verifications already under way keep the old list.
+    fn update_allowed_images(&self, change: impl FnOnce(&mut ProofVerifier)) -> Vec<String> {
+        let mut verifier = self.verifier.write().unwrap();
+        change(Arc::make_mut(&mut verifier));
+        let allowed_image_ids = verifier.allowed_image_ids().to_vec();
+        
+        if let Some(sandbox) = self.sandbox.write().unwrap().as_mut() {
+            Arc::make_mut(sandbox).set_allowed_images(allowed_image_ids.clone());
+        }
+        allowed_image_ids
+    }
 }
 
-/// Process a single proof request
-async fn process_single_request(
-    request: types::ProofRequest,
-    verifier: &ProofVerifier,
-    signer: &ReceiptSigner,
-    inputbox: &InputBoxClient,
-    config: &Config,
-) -> Result<()> {
-    info!("Processing proof request from device: {}", request.device_id);
+/// Start the task that submits queued receipts, if batching is enabled
+fn start_submission_drain(pipeline: &Arc<Pipeline>) {
+    if let Some(batcher) = pipeline.batcher.lock().unwrap().take() {
+        tokio::spawn(drain_submissions(pipeline.clone(), batcher));
+    }
+}
+
+/// Submit queued receipts in batches until the queue closes. Receipts a
+/// batch did not get through are put back and retried after a pause, unless
+/// the InputBox rejected them outright or they have used up their attempts:
+/// those are dropped, failing their requests so they are read again next poll.
+async fn drain_submissions(pipeline: Arc<Pipeline>, mut batcher: SubmissionBatcher) {
+    while let Some(batch) = batcher.next_batch().await {
+        let receipts: Vec<_> = batch.iter().map(|queued| queued.receipt.clone()).collect();
+        let (accepted, error) = match pipeline.inputbox.submit_verified_receipts_batch(&receipts).await {
+            Ok(accepted) => (accepted, None),
+            Err(e) => {
+                warn!("Failed to submit batch of {} receipts: {}", batch.len(), e);
+                (0, Some(e))
+            }
+        };
+        let reason = error.as_ref().map_or_else(|| "not accepted by the InputBox relay".to_string(), |e| e.to_string());
+        let permanent = error.as_ref().is_some_and(|e| !is_retryable_error(e));
+        
+        let batch_len = batch.len();
+        let mut requeued = Vec::new();
+        for (position, queued) in batch.into_iter().enumerate() {
+            if position < accepted {
+                record_submission(&pipeline, &queued.receipt);
+                batcher.complete(queued);
+                continue;
+            }
+            
+            audit(&pipeline, &queued.receipt, SubmissionResult::Failed(reason.clone()));
+            if permanent || queued.attempts() >= pipeline.config.inputbox_batch_attempts {
+                warn!(
+                    "Dropping queued receipt for input {} after {} attempts: {}",
+                    queued.receipt.input_index, queued.attempts(), reason
+                );
+                let error = VerifierError::InputBox(format!("Dropped from the submission queue: {}", reason));
+                batcher.fail(queued, error.into());
+            } else {
+                requeued.push(queued);
+            }
+        }
+        
+        if !requeued.is_empty() {
+            warn!("Re-queueing {} of {} receipts", requeued.len(), batch_len);
+            batcher.requeue(requeued);
+            tokio::time::sleep(Duration::from_millis(pipeline.config.inputbox_retry_delay_ms)).await;
+        }
+    }
+}
+
+/// In-process and (when enabled) sandboxed verifiers for `config`
+fn build_verifiers(config: &Config) -> Result<(Arc<ProofVerifier>, Option<Arc<SandboxedVerifier>>)> {
+    let verifier = Arc::new(ProofVerifier::from_config(config)?);
+    let sandbox = if config.sandbox_verification {
+        Some(Arc::new(SandboxedVerifier::from_config(config)?))
+    } else {
+        None
+    };
+    Ok((verifier, sandbox))
+}
+
+/// Config fields a reload applies; every other change needs a restart
+const RELOADABLE_FIELDS: [&str; 5] = [
+    "allowed_image_ids", "allow_any_image", "poll_interval_secs", "verifier_private_key", "log_level",
+];
+
+/// What a reload does when fields it cannot apply have changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RestartOnlyChanges {
+    /// Apply the reloadable fields and warn about the rest (SIGHUP)
+    Ignore,
+    /// Apply nothing (`POST /admin/reload`)
+    Reject,
+}
+
+/// A reloadable setting's value before and after a reload
+#[derive(Debug, serde::Serialize)]
+struct ReloadChange {
+    from: serde_json::Value,
+    to: serde_json::Value,
+}
+
+/// What a reload changed, returned by `POST /admin/reload`
+#[derive(Debug, Default, serde::Serialize)]
+struct ReloadReport {
+    /// Reloadable settings whose value changed. A rotated key is reported
+    /// as `verifier_address`, never by the key itself.
+    changed: BTreeMap<&'static str, ReloadChange>,
+}
+
+impl ReloadReport {
+    fn record(&mut self, setting: &'static str, from: impl serde::Serialize, to: impl serde::Serialize) -> Result<()> {
+        let (from, to) = (serde_json::to_value(from)?, serde_json::to_value(to)?);
+        if from != to {
+            self.changed.insert(setting, ReloadChange { from, to });
+        }
+        Ok(())
+    }
+}
+
+/// Re-read the config and apply its reloadable fields, publishing the new
+/// poll interval to the poll loop and switching to the new log level. A new `verifier_private_key` rotates the
+/// signer, so receipts are signed with it from then on; a changed BLS key
+/// rejects the whole reload, since only a restart can switch it.
+fn reload_config(pipeline: &Pipeline, path: &str, restart_only: RestartOnlyChanges) -> Result<ReloadReport> {
+    let mut reloaded = Config::load(path)?;
+    // The file knows nothing of command-line flags; without them every
+    // flag would read as a restart-only change
+    pipeline.overrides.apply(&mut reloaded);
+    
+    // Checked before anything is applied, so a bad key leaves everything as it was
+    let rotate_to = match pipeline.config.signer_backend {
+        SignerBackend::Local => Some(LocalKeySigner::new(&reloaded.verifier_private_key)?.address())
+            .filter(|address| *address != pipeline.signer.get_address()),
+        // KMS keys are rotated in KMS, behind the same key ID
+        SignerBackend::Kms => None,
+    };
     
-    // Fetch the RISC Zero receipt
-    let receipt_bytes = fetch_receipt(&request.receipt_url, config).await?;
+    let changed = restart_only_changes(&pipeline.config, &reloaded)?;
+    if restart_only == RestartOnlyChanges::Reject && !changed.is_empty() {
+        return Err(VerifierError::Conflict(format!(
+            "{} cannot change without a restart; nothing was reloaded", changed.join(", ")
+        )).into());
+    }
+    if let Some(key) = changed.iter().find(|field| field.ends_with("private_key")) {
+        return Err(VerifierError::Config(format!(
+            "{} changed; a new signing key needs a restart, keeping the current configuration", key
+        )).into());
+    }
+    if !changed.is_empty() {
+        warn!("Config reload ignores changes to {} until restart", changed.join(", "));
+    }
     
-    // Verify the proof
-    let receipt = verifier.verify_proof(&receipt_bytes, &request.proof_type)?;
+    let mut applied = pipeline.config.clone();
+    applied.allowed_image_ids = reloaded.allowed_image_ids;
+    applied.allow_any_image = reloaded.allow_any_image;
+    let (verifier, sandbox) = build_verifiers(&applied)?;
+    let previous = std::mem::replace(&mut *pipeline.verifier.write().unwrap(), verifier.clone());
+    *pipeline.sandbox.write().unwrap() = sandbox;
     
-    // Extract journal data
-    let journal_hash = receipt.journal_hash();
+    let mut report = ReloadReport::default();
+    report.record("allowed_image_ids", previous.allowed_image_ids(), verifier.allowed_image_ids())?;
+    report.record("allow_any_image", previous.allows_any_image(), verifier.allows_any_image())?;
     
-    // Create verified receipt
-    let verified_receipt = types::VerifiedReceipt {
-        device_id: request.device_id.clone(),
-        proof_type: request.proof_type.clone(),
-        receipt_hash: hex::encode(receipt.receipt_hash()),
-        image_id: hex::encode(&request.expected_image_id),
-        journal_hash: hex::encode(journal_hash),
-        epoch_index: request.epoch_index,
-        input_index: request.input_index,
-        signature: String::new(), // Will be filled by signer
-        timestamp: Some(chrono::Utc::now().timestamp() as u64),
-        verifier_address: Some(signer.get_address()),
+    let period = Duration::from_secs(reloaded.poll_interval_secs);
+    let previous_period = pipeline.poll_interval.send_replace(period);
+    report.record("poll_interval_secs", previous_period.as_secs(), period.as_secs())?;
+    
+    if let Some(log_level) = &pipeline.log_level {
+        let previous = log_level.set(&reloaded.log_level)?;
+        report.record("log_level", previous, log_level.current())?;
+    }
+    
+    if let Some(new_address) = rotate_to {
+        let previous = pipeline.signer.get_address();
+        pipeline.signer.rotate_key(&reloaded.verifier_private_key)?;
+        info!("Rotated signing key {} -> {} (key {})", previous, new_address, pipeline.signer.key_id());
+        report.record("verifier_address", previous, new_address)?;
+        if pipeline.config.submission_transport == SubmissionTransport::Espresso
+            || pipeline.config.inputbox_backend == InputBoxBackend::DirectRpc
+        {
+            warn!("Submission transactions are signed with the startup key until restart");
+        }
+    }
+    
+    info!(
+        "Reloaded config: {} allowed images, polling every {}s",
+        applied.allowed_image_ids.len(), reloaded.poll_interval_secs
+    );
+    Ok(report)
+}
+
+/// Names (never values) of non-reloadable fields that differ between configs
+fn restart_only_changes(current: &Config, reloaded: &Config) -> Result<Vec<String>> {
+    let (serde_json::Value::Object(current), serde_json::Value::Object(reloaded)) =
+        (serde_json::to_value(current)?, serde_json::to_value(reloaded)?)
+    else {
+        unreachable!("Config serializes to an object");
     };
     
-    // Sign the receipt
-    let signed_receipt = signer.sign_receipt(verified_receipt)?;
+    Ok(current.iter()
+        .filter(|(field, value)| !RELOADABLE_FIELDS.contains(&field.as_str()) && reloaded.get(*field) != Some(value))
+        .map(|(field, _)| field.clone())
+        .collect())
+}
+
+/// Reload the config on every SIGHUP, ignoring changes that need a restart
+#[cfg(unix)]
+fn spawn_reload_listener(pipeline: Arc<Pipeline>, path: String) -> Result<()> {
+    let mut hangups = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
     
-    // Submit to InputBox
-    inputbox.submit_verified_receipt(&signed_receipt).await?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            info!("SIGHUP received, reloading {}", path);
+            if let Err(e) = reload_config(&pipeline, &path, RestartOnlyChanges::Ignore) {
+                warn!("Config reload rejected: {}", e);
+            }
+        }
+    });
+    
+    Ok(())
+}
+
+/// Generate a fresh verifier key, print it, and optionally save it to a file
+fn generate_key(output: Option<&Path>) -> Result<()> {
+    let key = GeneratedKey::generate();
+    
+    println!("{}", key.render());
+    eprintln!();
+    eprintln!("{}", keygen::KEY_HANDLING_WARNING);
     
-    info!("Successfully submitted verified receipt for device: {}", request.device_id);
+    if let Some(path) = output {
+        key.write_to_file(path)?;
+        eprintln!("Key written to {}", path.display());
+    }
     
     Ok(())
 }
 
-/// Fetch receipt from URL (supports IPFS, HTTP, S3)
-async fn fetch_receipt(url: &str, config: &Config) -> Result<Vec<u8>> {
-    if url.starts_with("ipfs://") {
-        // Convert to HTTP gateway URL
-        let hash = url.trim_start_matches("ipfs://");
-        let gateway_url = format!("{}/ipfs/{}", config.ipfs_gateway, hash);
-        
-        info!("Fetching receipt from IPFS: {}", gateway_url);
-        let response = reqwest::get(&gateway_url).await?;
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
-    } else if url.starts_with("http://") || url.starts_with("https://") {
-        info!("Fetching receipt from HTTP: {}", url);
-        let response = reqwest::get(url).await?;
-        let bytes = response.bytes().await?;
-        Ok(bytes.to_vec())
-    } else {
-        Err(anyhow::anyhow!("Unsupported receipt URL scheme: {}", url))
+/// `address` subcommand: print the address receipts are signed by, with the
+/// key loaded as the service would (including `file://` and `env://` keys)
+async fn print_address(config_path: &str) -> Result<()> {
+    let config = Config::load(config_path)?;
+    let signer = ReceiptSigner::from_config(&config).await?;
+    println!("{}", signer.get_address());
+    Ok(())
+}
+
+/// `verify` subcommand: check a receipt file against `image_id` and print
+/// the outcome as JSON, exiting non-zero if it does not verify. The journal
+/// schemas and rules come from the config file when it loads, and are the
+/// defaults otherwise.
+fn verify_receipt_file(config_path: &str, receipt: &Path, image_id: &str, proof_type: &str) -> Result<()> {
+    let config = Config::load(config_path).unwrap_or_else(|e| {
+        eprintln!("Using the default verification policy ({}: {})", config_path, e);
+        Config::default()
+    });
+    let verifier = ProofVerifier::from_config(&Config {
+        allowed_image_ids: vec![image_id.to_string()],
+        allow_any_image: false,
+        ..config
+    })?;
+    
+    let result = std::fs::read(receipt)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", receipt.display(), e))
+        .and_then(|bytes| verifier.verify_proof(&bytes, proof_type, Some(image_id), None));
+    let (report, valid) = match result {
+        Ok(proof) => {
+            let summary = proof.summary();
+            let report = serde_json::json!({
+                "valid": true,
+                "receipt_hash": format!("0x{}", hex::encode(&summary.receipt_hash)),
+                "journal_hash": format!("0x{}", hex::encode(&summary.journal_hash)),
+                "image_id": format!("0x{}", hex::encode(&summary.image_id)),
+                "proof_kind": summary.proof_kind,
+                "warnings": summary.warnings,
+            });
+            (report, true)
+        }
+        Err(e) => (serde_json::json!({ "valid": false, "error": e.to_string() }), false),
+    };
+    
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !valid {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Requests settled by a backfill
+#[derive(Debug, Default, PartialEq, Eq)]
+struct BackfillSummary {
+    processed: usize,
+    /// Already submitted, by this verifier or on chain
+    skipped: usize,
+    failed: usize,
+}
+
+/// `backfill` subcommand: reprocess epochs `from_epoch` to `to_epoch` and
+/// print what became of their requests, exiting non-zero if any failed
+async fn backfill(config: Config, from_epoch: u64, to_epoch: Option<u64>) -> Result<()> {
+    let to_epoch = to_epoch.unwrap_or(u64::MAX);
+    if from_epoch > to_epoch {
+        return Err(anyhow::anyhow!("--from-epoch {} is after --to-epoch {}", from_epoch, to_epoch));
+    }
+    
+    // Receipts are submitted one at a time so each failure is counted
+    let pipeline = Pipeline::new(Config { inputbox_batch_size: 1, ..config }).await?;
+    let summary = backfill_epochs(&pipeline, from_epoch..=to_epoch).await?;
+    
+    println!(
+        "Backfill complete: {} processed, {} skipped, {} failed",
+        summary.processed, summary.skipped, summary.failed
+    );
+    if summary.failed > 0 {
+        std::process::exit(1);
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// Verify, sign and submit the proof requests of `epochs` one at a time in
+/// input order, leaving the watermark where it is
+async fn backfill_epochs(pipeline: &Pipeline, epochs: std::ops::RangeInclusive<u64>) -> Result<BackfillSummary> {
+    let mut requests = pipeline.notices.query_proof_requests_in_epochs(epochs.clone()).await?;
+    requests.sort_by_key(|r| (r.epoch_index, r.input_index));
+    info!("Backfilling {} proof requests from epochs {} to {}", requests.len(), epochs.start(), epochs.end());
+    
+    let mut summary = BackfillSummary::default();
+    for request in requests {
+        let input_index = request.input_index;
+        match process_request_traced(request, pipeline).await {
+            Ok(true) => summary.processed += 1,
+            Ok(false) => summary.skipped += 1,
+            Err(e) => {
+                warn!("Failed to backfill request at input {}: {:#}", input_index, e);
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Process all pending proof requests, fetching up to `max_concurrent_fetches`
+/// receipts and verifying up to `max_concurrent_verifications` at a time.
+/// Once `shutdown` is cancelled the requests in progress are finished and the
+/// rest are left for the next run.
+async fn process_proof_requests(pipeline: &Arc<Pipeline>, shutdown: &CancellationToken) -> Result<usize> {
+    // Drop processed markers that have aged out of the retention window
+    let store = &pipeline.processed_store;
+    let pruned = store.prune();
+    if pruned > 0 {
+        debug!("Pruned {} expired processed markers ({} retained)", pruned, store.len());
+    }
+    if let Some(limiter) = &pipeline.device_limiter {
+        let idle = limiter.prune(tokio::time::Instant::now());
+        if idle > 0 {
+            debug!("Forgot {} idle devices ({} rate limited)", idle, limiter.len());
+        }
+    }
+    
+    // Read before the notices, so every input of an epoch older than this
+    // one is among those the query below lists
+    let chain_epoch = if pipeline.config.submit_epoch_summaries {
+        match pipeline.notices.query_latest_epoch().await {
+            Ok(chain_epoch) => chain_epoch,
+            Err(e) => {
+                warn!("Failed to query the latest epoch, not summarizing epochs this cycle: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    
+    // Query for proof request notices
+    let mut requests = pipeline.notices.query_proof_requests().await?;
+    pipeline.status.record_poll();
+    for request in &requests {
+        pipeline.epochs.observe(request.epoch_index, request.input_index);
+    }
+    
+    // Work through requests in the order their inputs were added on chain
+    requests.sort_by_key(|r| (r.epoch_index, r.input_index));
+    
+    // The watermark may pass every input read this poll unless one of them
+    // is deferred or fails, in which case it stops there to re-read it
+    let highest_input = requests.iter().map(|r| r.input_index).max();
+    let mut pending_input: Option<u64> = None;
+    
+    // Leave requests from epochs that could still be reorged for a later cycle
+    let min_confirmations = pipeline.config.min_epoch_confirmations;
+    if min_confirmations > 0 && !requests.is_empty() {
+        let chain_epoch = pipeline.notices.query_latest_epoch().await?;
+        let (confirmed, deferred): (Vec<_>, Vec<_>) = requests.into_iter()
+            .partition(|r| is_epoch_confirmed(r.epoch_index, chain_epoch, min_confirmations));
+        requests = confirmed;
+        if !deferred.is_empty() {
+            debug!(
+                "Deferring {} proof requests with fewer than {} epoch confirmations",
+                deferred.len(), min_confirmations
+            );
+            pending_input = deferred.iter().map(|r| r.input_index).min();
+        }
+    }
+    
+    // Put off requests from devices over their budget so one device flooding
+    // the feed cannot starve the rest. The watermark stops at the first of
+    // them, so they are read again once the device has budget left. Requests
+    // re-read behind it that were already submitted are not charged again.
+    if let Some(limiter) = &pipeline.device_limiter {
+        let now = tokio::time::Instant::now();
+        let ordered = pipeline.config.ordered_submission;
+        let mut throttled = None;
+        let mut held = 0;
+        requests.retain(|r| {
+            // Nothing may be submitted ahead of a deferred request
+            if ordered && throttled.is_some() {
+                held += 1;
+                return false;
+            }
+            if pipeline.is_request_submitted(r) {
+                return true;
+            }
+            match limiter.try_acquire(&r.device_id, now) {
+                Ok(()) => true,
+                Err(_) => {
+                    warn!(
+                        "Deferring proof request at input {} from device {}: over its rate limit",
+                        r.input_index, r.device_id
+                    );
+                    throttled = Some(throttled.map_or(r.input_index, |first: u64| first.min(r.input_index)));
+                    false
+                }
+            }
+        });
+        if held > 0 {
+            debug!("Holding {} proof requests behind the first rate-limited one (ordered submission)", held);
+        }
+        if let Some(throttled) = throttled {
+            pending_input = Some(pending_input.map_or(throttled, |pending| pending.min(throttled)));
+        }
+    }
+    
+    if requests.is_empty() {
+        advance_watermark(pipeline, highest_input, pending_input);
+        if let Some(chain_epoch) = chain_epoch.filter(|_| !shutdown.is_cancelled()) {
+            submit_epoch_summaries(pipeline, chain_epoch).await;
+        }
+        return Ok(0);
+    }
+    
+    info!("Found {} proof requests to process", requests.len());
+    
+    // Fetching is network-bound and verification CPU-bound, so each stage has
+    // its own concurrency. Fetched receipts wait in a bounded queue; while it
+    // is full fetchers hold their slots, so fetching stalls instead of piling
+    // receipts up in memory.
+    let (fetched_tx, fetched_rx) = mpsc::channel(pipeline.config.max_concurrent_verifications);
+    let stop = shutdown.child_token();
+    let (fetched, verified) = tokio::join!(
+        fetch_receipts(pipeline, requests, fetched_tx, &stop),
+        verify_receipts(pipeline, fetched_rx, &stop),
+    );
+    let mut outcome = fetched.merge(verified);
+    if let Some(pending_input) = pending_input {
+        outcome.hold(pending_input);
+    }
+    
+    advance_watermark(pipeline, highest_input, outcome.pending_input);
+    if let Some(e) = outcome.abort {
+        return Err(e);
+    }
+    
+    if let Some(chain_epoch) = chain_epoch.filter(|_| !shutdown.is_cancelled()) {
+        submit_epoch_summaries(pipeline, chain_epoch).await;
+    }
+    
+    Ok(outcome.processed)
+}
+
+/// A request whose receipt has been fetched, waiting to be verified
+struct FetchedReceipt {
+    request: types::ProofRequest,
+    receipt_bytes: Vec<u8>,
+    /// The request's `process_request` span, carried across both stages
+    span: tracing::Span,
+    /// Its place in the submission order, with `ordered_submission` on
+    turn: Option<Turn>,
+}
+
+/// Fetch the receipts of `requests`, up to `max_concurrent_fetches` at a
+/// time, handing each to the verification stage through `fetched`
+async fn fetch_receipts(
+    pipeline: &Arc<Pipeline>,
+    requests: Vec<types::ProofRequest>,
+    fetched: mpsc::Sender<FetchedReceipt>,
+    stop: &CancellationToken,
+) -> BatchOutcome {
+    let fetchers = Arc::new(Semaphore::new(pipeline.config.max_concurrent_fetches));
+    let mut tasks = JoinSet::new();
+    let mut task_inputs = HashMap::new();
+    let mut outcome = BatchOutcome::default();
+    let order = pipeline.config.ordered_submission
+        .then(|| SubmissionOrder::new(requests.iter().map(|r| r.input_index).collect()));
+    
+    for (position, request) in requests.into_iter().enumerate() {
+        let input_index = request.input_index;
+        // Dropped unfinished on every path that doesn't hand the request on
+        let turn = order.as_ref().map(|order| order.turn(position));
+        let permit = fetchers.clone().acquire_owned().await.expect("fetch semaphore is never closed");
+        while let Some(joined) = tasks.try_join_next_with_id() {
+            outcome.record_fetch(joined, &task_inputs, pipeline);
+        }
+        
+        if stop.is_cancelled() {
+            // Hold the watermark here so the next run picks this request up
+            outcome.hold(input_index);
+            continue;
+        }
+        
+        let (pipeline, fetched) = (pipeline.clone(), fetched.clone());
+        let task = tasks.spawn(async move {
+            let _permit = permit;
+            let span = request_span(&request);
+            match fetch_request_receipt(&request, &pipeline).instrument(span.clone()).await {
+                Ok(receipt_bytes) => fetched.send(FetchedReceipt { request, receipt_bytes, span, turn }).await
+                    .map_err(|_| anyhow::anyhow!("Verification stage stopped before request was handed over")),
+                Err(e) => {
+                    span.record("outcome", "error");
+                    let result = Err(e);
+                    if let Some(turn) = turn {
+                        turn.finish(&result);
+                    }
+                    result
+                }
+            }
+        });
+        task_inputs.insert(task.id(), input_index);
+    }
+    
+    // The verification stage finishes once every sender is gone
+    drop(fetched);
+    while let Some(joined) = tasks.join_next_with_id().await {
+        outcome.record_fetch(joined, &task_inputs, pipeline);
+    }
+    outcome
+}
+
+/// Verify, sign and submit fetched receipts, up to `max_concurrent_verifications`
+/// at a time. Cancels `stop` when a failure aborts the batch.
+async fn verify_receipts(
+    pipeline: &Arc<Pipeline>,
+    mut fetched: mpsc::Receiver<FetchedReceipt>,
+    stop: &CancellationToken,
+) -> BatchOutcome {
+    let workers = Arc::new(Semaphore::new(pipeline.config.max_concurrent_verifications));
+    let mut tasks = JoinSet::new();
+    let mut task_inputs = HashMap::new();
+    let mut outcome = BatchOutcome::default();
+    
+    while let Some(FetchedReceipt { request, receipt_bytes, span, turn }) = fetched.recv().await {
+        let input_index = request.input_index;
+        let permit = workers.clone().acquire_owned().await.expect("worker semaphore is never closed");
+        while let Some(joined) = tasks.try_join_next_with_id() {
+            outcome.record(joined, &task_inputs, pipeline);
+        }
+        if outcome.abort.is_some() {
+            stop.cancel();
+        }
+        
+        // Receipts still queued are drained, not verified, so fetchers blocked
+        // on a full queue can finish
+        if stop.is_cancelled() {
+            outcome.hold(input_index);
+            continue;
+        }
+        
+        let pipeline = pipeline.clone();
+        let task = tasks.spawn(async move {
+            let result = async {
+                let Some(signed_receipt) = verify_and_sign(request, receipt_bytes, &pipeline).await? else {
+                    return Ok(());
+                };
+                // Waiting for its turn, or for its batch to go out, must not
+                // keep the receipts it waits on from being verified
+                if turn.is_some() || pipeline.submissions.is_some() {
+                    drop(permit);
+                }
+                if let Some(turn) = &turn {
+                    turn.wait().await?;
+                }
+                submit_signed(signed_receipt, &pipeline).await
+            }
+            .instrument(span.clone())
+            .await;
+            if let Some(turn) = turn {
+                turn.finish(&result);
+            }
+            span.record("outcome", if result.is_ok() { "ok" } else { "error" });
+            result
+        });
+        task_inputs.insert(task.id(), input_index);
+    }
+    while let Some(joined) = tasks.join_next_with_id().await {
+        outcome.record(joined, &task_inputs, pipeline);
+    }
+    outcome
+}
+
+/// Results of the requests of one poll as their tasks finish
+#[derive(Default)]
+struct BatchOutcome {
+    processed: usize,
+    /// Lowest input index that has to be read again next poll
+    pending_input: Option<u64>,
+    /// First error that stops the batch under `on_signer_error`
+    abort: Option<anyhow::Error>,
+}
+
+impl BatchOutcome {
+    fn record(
+        &mut self,
+        joined: std::result::Result<(tokio::task::Id, Result<()>), tokio::task::JoinError>,
+        task_inputs: &HashMap<tokio::task::Id, u64>,
+        pipeline: &Pipeline,
+    ) {
+        let (id, result) = match joined {
+            Ok((id, result)) => (id, result),
+            Err(e) => (e.id(), Err(anyhow::anyhow!("Request task failed: {}", e))),
+        };
+        
+        // One failure doesn't stop the other requests. A rejected proof fails
+        // the same way every time, so it is passed over rather than held for
+        // the next poll, where it would hold every input after it too.
+        let input_index = task_inputs[&id];
+        match result {
+            Ok(()) => {
+                self.processed += 1;
+                pipeline.status.record_processed(input_index);
+            }
+            Err(e) if is_rejection(&e) => {
+                warn!("Rejected proof request at input {}: {:#}", input_index, e);
+                pipeline.status.record_rejected(input_index);
+            }
+            Err(e) => {
+                self.hold(input_index);
+                if pipeline.config.on_signer_error.aborts_on(&e) {
+                    self.abort.get_or_insert(e);
+                } else {
+                    warn!("Failed to process request: {:#}", e);
+                }
+            }
+        }
+    }
+    
+    /// Record a fetch task; its request counts as processed once verified
+    fn record_fetch(
+        &mut self,
+        joined: std::result::Result<(tokio::task::Id, Result<()>), tokio::task::JoinError>,
+        task_inputs: &HashMap<tokio::task::Id, u64>,
+        pipeline: &Pipeline,
+    ) {
+        if !matches!(joined, Ok((_, Ok(())))) {
+            self.record(joined, task_inputs, pipeline);
+        }
+    }
+    
+    fn hold(&mut self, input_index: u64) {
+        self.pending_input = Some(self.pending_input.map_or(input_index, |pending| pending.min(input_index)));
+    }
+    
+    /// Combine the outcomes of the fetch and verification stages
+    fn merge(mut self, other: BatchOutcome) -> Self {
+        self.processed += other.processed;
+        if let Some(pending_input) = other.pending_input {
+            self.hold(pending_input);
+        }
+        self.abort = self.abort.or(other.abort);
+        self
+    }
+}
+
+/// Move the notice watermark past the inputs settled this poll: to the first
+/// pending input, or past the highest one read when all of them settled
+fn advance_watermark(pipeline: &Pipeline, highest_input: Option<u64>, pending_input: Option<u64>) {
+    let Some(watermark) = pending_input.or(highest_input.map(|input| input + 1)) else {
+        return;
+    };
+    
+    let previous = pipeline.notices.last_seen_index();
+    pipeline.notices.advance_watermark(watermark);
+    let current = pipeline.notices.last_seen_index();
+    if current > previous {
+        info!("Input watermark advanced to {}", current);
+        pipeline.request_receipts.lock().unwrap().retain(|key, _| key.input_index >= current);
+        if let Some(state) = &pipeline.state {
+            state.record_watermark(current);
+        }
+    }
+}
+
+/// Whether `epoch_index` is at least `min_confirmations` epochs behind the chain's latest epoch
+fn is_epoch_confirmed(epoch_index: u64, chain_epoch: Option<u64>, min_confirmations: u64) -> bool {
+    chain_epoch.is_some_and(|latest| latest >= epoch_index && latest - epoch_index >= min_confirmations)
+}
+
+/// Span carrying a request's identity and, once it finishes, its outcome
+fn request_span(request: &types::ProofRequest) -> tracing::Span {
+    info_span!(
+        "process_request",
+        device_id = %request.device_id,
+        proof_type = %request.proof_type,
+        epoch_index = request.epoch_index,
+        input_index = request.input_index,
+        peak_decoded_bytes = tracing::field::Empty,
+        outcome = tracing::field::Empty,
+    )
+}
+
+/// Process a single proof request inside a span carrying its identity and outcome
+async fn process_request_traced(request: types::ProofRequest, pipeline: &Pipeline) -> Result<bool> {
+    let span = request_span(&request);
+    let result = process_single_request(request, pipeline).instrument(span.clone()).await;
+    span.record("outcome", if result.is_ok() { "ok" } else { "error" });
+    
+    result
+}
+
+/// Process a single proof request, returning whether its receipt was
+/// submitted rather than skipped as already submitted
+async fn process_single_request(request: types::ProofRequest, pipeline: &Pipeline) -> Result<bool> {
+    let receipt_bytes = fetch_request_receipt(&request, pipeline).await?;
+    match verify_and_sign(request, receipt_bytes, pipeline).await? {
+        Some(signed_receipt) => submit_signed(signed_receipt, pipeline).await.map(|()| true),
+        None => Ok(false),
+    }
+}
+
+/// Check that a request names a trusted image, then load its receipt
+async fn fetch_request_receipt(request: &types::ProofRequest, pipeline: &Pipeline) -> Result<Vec<u8>> {
+    info!("Processing proof request from device: {}", request.device_id);
+    
+    // Nothing is fetched for a request naming an image we do not trust
+    let verifier = pipeline.verifier.read().unwrap().clone();
+    verifier.check_requested_image(&request.expected_image_id)?;
+    
+    // Load the RISC Zero receipt
+    load_receipt(request, &pipeline.receipt_clients, &pipeline.config, pipeline.receipt_cache.as_ref()).await
+        .with_context(|| stage("fetching receipt", &request.device_id, request.input_index))
+}
+
+/// Error context naming the stage a request failed in, and the request
+fn stage(name: &str, device_id: &str, input_index: u64) -> String {
+    format!("{} for device {} at input {}", name, device_id, input_index)
+}
+
+/// Verify a fetched receipt and sign the verified receipt, or `None` if it
+/// has already been submitted
+async fn verify_and_sign(
+    request: types::ProofRequest,
+    receipt_bytes: Vec<u8>,
+    pipeline: &Pipeline,
+) -> Result<Option<types::VerifiedReceipt>> {
+    let Pipeline { notices, signer, processed_store, state, config, .. } = pipeline;
+    let verifier = pipeline.verifier.read().unwrap().clone();
+    let sandbox = pipeline.sandbox.read().unwrap().clone();
+    let receipt_len = receipt_bytes.len();
+    let verifying = || stage("verifying proof", &request.device_id, request.input_index);
+    
+    // Verify the proof and extract journal data
+    let summary = match sandbox {
+        Some(sandbox) => {
+            sandbox.verify(
+                &receipt_bytes,
+                &request.device_id,
+                &request.proof_type,
+                Some(&request.expected_image_id),
+                request.receipt_format,
+            ).await.with_context(verifying)?
+        }
+        None => {
+            // Verification is CPU-bound for seconds on large receipts; run it
+            // off the async workers so polling and the health server keep going
+            let request = request.clone();
+            let span = tracing::Span::current();
+            tokio::task::spawn_blocking(move || {
+                span.in_scope(|| verifier.verify_device_proof(
+                    &receipt_bytes,
+                    &request.device_id,
+                    &request.proof_type,
+                    Some(&request.expected_image_id),
+                    request.receipt_format,
+                ))
+            })
+            .await
+            .map_err(|e| VerifierError::ProofVerification(format!("Verification task failed: {}", e)).into())
+            .and_then(|verified| verified)
+            .with_context(verifying)?
+            .summary()
+        }
+    };
+    debug!("Device signature for {}: {:?}", request.device_id, summary.device_signature);
+    tracing::Span::current().record("peak_decoded_bytes", summary.peak_decoded_bytes);
+    debug!("Receipt verified with peak decoded size {} of {} bytes", summary.peak_decoded_bytes, receipt_len);
+    if !summary.warnings.is_empty() {
+        warn!("Receipt from device {} verified with warnings: {:?}", request.device_id, summary.warnings);
+    }
+    let journal_hash = summary.journal_hash;
+    let journal_empty = summary.journal_empty;
+    let receipt_hash = hex::encode(summary.receipt_hash);
+    pipeline.request_receipts.lock().unwrap().insert(RequestKey::of(&request), receipt_hash.clone());
+    
+    // Skip receipts we have already submitted
+    match processed_store.status(&receipt_hash) {
+        DedupStatus::Trusted => {
+            info!("Receipt {} already processed, skipping", receipt_hash);
+            return Ok(None);
+        }
+        // The notice may be re-read before the InputBox state catches up
+        DedupStatus::Stale | DedupStatus::Unknown => {
+            if state.as_ref().is_some_and(|state| state.is_submitted(&receipt_hash)) {
+                info!("Receipt {} submitted by an earlier run, skipping", receipt_hash);
+                processed_store.mark(&receipt_hash);
+                return Ok(None);
+            }
+            let on_chain = notices.check_receipt_processed(&receipt_hash).await
+                .with_context(|| stage("checking for a submitted receipt", &request.device_id, request.input_index))?;
+            if on_chain {
+                info!("Receipt {} confirmed processed on chain, skipping", receipt_hash);
+                processed_store.mark(&receipt_hash);
+                return Ok(None);
+            }
+        }
+    }
+    
+    // Create verified receipt
+    let verified_receipt = types::VerifiedReceipt {
+        device_id: request.device_id.clone(),
+        proof_type: request.proof_type.clone(),
+        receipt_hash: receipt_hash.clone(),
+        image_id: format!("0x{}", hex::encode(&summary.image_id)),
+        journal_hash: hex::encode(journal_hash),
+        journal_empty,
+        proof_kind: summary.proof_kind,
+        journal_summary: summary.journal_summary.clone(),
+        epoch_index: request.epoch_index,
+        input_index: request.input_index,
+        signature: String::new(), // Will be filled by signer
+        // When the device submitted, falling back to now for nodes that omit it
+        timestamp: Some(request.input_timestamp.unwrap_or_else(|| chrono::Utc::now().timestamp() as u64)),
+        verifier_address: Some(signer.get_address()),
+        bls_signature: None,
+        bls_public_key: None,
+    };
+    
+    // Sign the receipt
+    let signed_receipt = match config.signing_scheme {
+        SigningScheme::Keccak => signer.sign_receipt(verified_receipt).await,
+        SigningScheme::Abi => signer.sign_receipt_abi(verified_receipt).await,
+        SigningScheme::Eip712 => match Eip712Domain::from_config(config) {
+            Ok(domain) => signer.sign_receipt_eip712(verified_receipt, &domain).await,
+            Err(e) => Err(e),
+        },
+    }
+    .with_context(|| stage("signing receipt", &request.device_id, request.input_index))?;
+    
+    Ok(Some(signed_receipt))
+}
+
+/// Submit a signed receipt, or queue it for submission with batching on
+async fn submit_signed(signed_receipt: types::VerifiedReceipt, pipeline: &Pipeline) -> Result<()> {
+    let Pipeline { inputbox, processed_store, config, .. } = pipeline;
+    let receipt_hash = signed_receipt.receipt_hash.clone();
+    let submitting = || stage("submitting receipt", &signed_receipt.device_id, signed_receipt.input_index);
+    
+    // In a dry run the receipt still counts as processed, but nothing is
+    // submitted or recorded as submitted
+    if config.dry_run {
+        inputbox.log_would_submit(&signed_receipt).with_context(submitting)?;
+        audit(pipeline, &signed_receipt, SubmissionResult::DryRun);
+        processed_store.mark(&receipt_hash);
+        return Ok(());
+    }
+    
+    // With batching on, the drain task submits and records the receipt; the
+    // request is not done until it has
+    if let Some(submissions) = &pipeline.submissions {
+        let context = submitting();
+        let submitted = submissions.enqueue(signed_receipt).await.with_context(|| context.clone())?;
+        debug!("Queued verified receipt {} for submission", receipt_hash);
+        return submitted.wait().await.context(context);
+    }
+    
+    // Submit to InputBox
+    if let Err(e) = inputbox.submit_verified_receipt(&signed_receipt).await {
+        audit(pipeline, &signed_receipt, SubmissionResult::Failed(e.to_string()));
+        return Err(e.context(submitting()));
+    }
+    record_submission(pipeline, &signed_receipt);
+    
+    info!("Successfully submitted verified receipt for device: {}", signed_receipt.device_id);
+    
+    Ok(())
+}
+
+/// Append a signed receipt to the audit log, if one is kept
+fn audit(pipeline: &Pipeline, receipt: &types::VerifiedReceipt, result: SubmissionResult) {
+    if let Some(audit_log) = &pipeline.audit_log {
+        audit_log.record(receipt, &result);
+    }
+}
+
+/// Remember a submitted receipt for deduplication and its epoch summary
+fn record_submission(pipeline: &Pipeline, receipt: &types::VerifiedReceipt) {
+    audit(pipeline, receipt, SubmissionResult::Submitted);
+    pipeline.processed_store.mark(&receipt.receipt_hash);
+    if let Some(state) = &pipeline.state {
+        state.record_submitted(&receipt.receipt_hash, receipt.input_index);
+    }
+    pipeline.epochs.record(BatchEntry {
+        epoch_index: receipt.epoch_index,
+        input_index: receipt.input_index,
+        device_id: receipt.device_id.clone(),
+        receipt_hash: receipt.receipt_hash.clone(),
+    });
+}
+
+/// Sign and submit summaries for every epoch older than `chain_epoch` whose
+/// inputs are all behind the watermark, so no request of it can still be
+/// retried. Each epoch is summarized once; the state file keeps it that way
+/// across restarts.
+async fn submit_epoch_summaries(pipeline: &Pipeline, chain_epoch: u64) {
+    let watermark = pipeline.notices.last_seen_index();
+    for (epoch_index, entries) in pipeline.epochs.take_finalized(chain_epoch, watermark) {
+        let result = async {
+            let summary = epoch::summarize(epoch_index, &entries)?;
+            let summary = pipeline.signer.sign_epoch_summary(summary).await?;
+            pipeline.inputbox.submit_epoch_summary(&summary).await?;
+            Ok::<_, anyhow::Error>(summary)
+        }.await;
+        
+        match result {
+            Ok(summary) => {
+                info!(
+                    "Submitted epoch {} summary: {} receipts, root {}",
+                    epoch_index, summary.receipt_count, summary.merkle_root
+                );
+                pipeline.epochs.mark_summarized(epoch_index);
+                if let Some(state) = &pipeline.state {
+                    state.record_summary(epoch_index);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to submit epoch {} summary: {}", epoch_index, e);
+                // Keep the receipts so the summary is retried next cycle
+                pipeline.epochs.restore(epoch_index, entries);
+            }
+        }
+    }
+}
+
+/// Get the receipt bytes for a request, preferring an inline receipt, then
+/// a cached one, over fetching
+async fn load_receipt(
+    request: &types::ProofRequest,
+    clients: &ReceiptClients,
+    config: &Config,
+    cache: Option<&ReceiptCache>,
+) -> Result<Vec<u8>> {
+    let url = &request.receipt_url;
+    let mut fetched = false;
+    let receipt_bytes = match &request.receipt_inline {
+        Some(inline) => {
+            debug!("Using inline receipt for device: {}", request.device_id);
+            decode_inline_receipt(inline, config.max_receipt_size)?
+        }
+        None => match cache.and_then(|cache| cache.get(url)) {
+            Some(cached) => {
+                debug!("Using cached receipt for {}", url);
+                cached
+            }
+            None => {
+                fetched = true;
+                fetch_receipt(url, clients, config).instrument(info_span!("fetch_receipt", url = %url)).await?
+            }
+        },
+    };
+    
+    if let Some(expected) = &request.expected_receipt_hash {
+        check_receipt_bytes_hash(&receipt_bytes, expected)?;
+    }
+    
+    // Only bytes that passed the hash check are worth keeping
+    if let Some(cache) = cache.filter(|_| fetched) {
+        cache.insert(url, &receipt_bytes);
+    }
+    
+    Ok(receipt_bytes)
+}
+
+/// Reject receipt bytes whose Keccak256 differs from the hash the request committed to
+fn check_receipt_bytes_hash(receipt_bytes: &[u8], expected: &str) -> Result<()> {
+    let actual = hex::encode(Keccak256::digest(receipt_bytes));
+    let expected_hex = expected.trim().trim_start_matches("0x");
+    
+    if !actual.eq_ignore_ascii_case(expected_hex) {
+        return Err(VerifierError::ReceiptHashMismatch {
+            expected: format!("0x{}", expected_hex),
+            actual: format!("0x{}", actual),
+        }.into());
+    }
+    
+    Ok(())
+}
+
+/// Decode an inline receipt (0x-prefixed hex, otherwise base64), enforcing the size limit
+fn decode_inline_receipt(inline: &str, max_size: usize) -> Result<Vec<u8>> {
+    // Reject before decoding; both encodings are at least as long as the bytes
+    let encoded = inline.trim();
+    let approx_size = match encoded.strip_prefix("0x") {
+        Some(hex) => hex.len() / 2,
+        None => encoded.len() / 4 * 3,
+    };
+    if approx_size > max_size {
+        return Err(VerifierError::ReceiptTooLarge { size: approx_size, max: max_size }.into());
+    }
+    
+    let bytes = match encoded.strip_prefix("0x") {
+        Some(hex) => hex::decode(hex)?,
+        None => base64::engine::general_purpose::STANDARD.decode(encoded)
+            .map_err(|e| VerifierError::ProofVerification(format!("Invalid base64 inline receipt: {}", e)))?,
+    };
+    
+    if bytes.len() > max_size {
+        return Err(VerifierError::ReceiptTooLarge { size: bytes.len(), max: max_size }.into());
+    }
+    
+    Ok(bytes)
+}
+
+/// Clients receipts are fetched with, built once and routed through the
+/// configured proxies
+struct ReceiptClients {
+    /// Has no timeout of its own; each fetch sets its scheme's
+    http: reqwest::Client,
+    /// Unset when the configured proxy cannot carry S3 traffic
+    s3: Option<aws_sdk_s3::Client>,
+}
+
+impl ReceiptClients {
+    async fn new(config: &Config) -> Result<Self> {
+        let http = ProxySettings::from_config(config).apply(reqwest::Client::builder())?.build()?;
+        // Only fatal once an s3:// receipt turns up
+        let s3 = match s3::client_from_config(config).await {
+            Ok(client) => Some(client),
+            Err(e) => {
+                warn!("S3 receipt fetches are disabled: {}", e);
+                None
+            }
+        };
+        Ok(Self { http, s3 })
+    }
+}
+
+/// Fetch receipt from URL (supports IPFS, HTTP, S3)
+async fn fetch_receipt(url: &str, clients: &ReceiptClients, config: &Config) -> Result<Vec<u8>> {
+    let client = &clients.http;
+    if url.starts_with("ipfs://") {
+        fetch_ipfs_receipt(url.trim_start_matches("ipfs://"), client, config).await
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        info!("Fetching receipt from HTTP: {}", url);
+        let response = client.get(url)
+            .timeout(config.fetch_timeout(url))
+            .headers(config.receipt_headers()?)
+            .send()
+            .await?;
+        let compression = Compression::from_response(&response, url);
+        let bytes = read_limited(response, config.max_receipt_size).await?;
+        compression::decompress(bytes, compression, config.max_receipt_size)
+    } else if url.starts_with("s3://") {
+        info!("Fetching receipt from S3: {}", url);
+        let s3 = clients.s3.as_ref().ok_or_else(|| VerifierError::Config(
+            "S3 receipt fetches are disabled: the configured proxy cannot carry them".to_string()
+        ))?;
+        let bytes = s3::fetch_s3_receipt(url, s3, config.max_receipt_size).await?;
+        compression::decompress(bytes, Compression::from_url(url), config.max_receipt_size)
+    } else if url.starts_with("ar://") {
+        let bytes = arweave::fetch_arweave_receipt(url, client, config).await?;
+        compression::decompress(bytes, Compression::from_url(url), config.max_receipt_size)
+    } else {
+        Err(anyhow::anyhow!("Unsupported receipt URL scheme: {}", url))
+    }
+}
+
+/// Request an IPFS path from every configured gateway at once and return the
+/// first response that matches its CID. The slower requests are cancelled.
+async fn fetch_ipfs_receipt(path: &str, client: &reqwest::Client, config: &Config) -> Result<Vec<u8>> {
+    let headers = config.receipt_headers()?;
+    let timeout = config.fetch_timeout(&format!("ipfs://{}", path));
+    let mut fetches = JoinSet::new();
+    for gateway in &config.ipfs_gateways {
+        let gateway_url = format!("{}/ipfs/{}", gateway.trim_end_matches('/'), path);
+        let (client, path, gateway, headers) = (client.clone(), path.to_string(), gateway.clone(), headers.clone());
+        let (max, allow_unverifiable) = (config.max_receipt_size, config.ipfs_allow_unverifiable_cids);
+        fetches.spawn(async move {
+            info!("Fetching receipt from IPFS: {}", gateway_url);
+            let result = async {
+                let response = client.get(&gateway_url).timeout(timeout).headers(headers).send().await?;
+                if !response.status().is_success() {
+                    let status = response.status().as_u16();
+                    let body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                    return Err(VerifierError::HttpStatus { service: "IPFS gateway", status, body }.into());
+                }
+                let compression = Compression::from_response(&response, &path);
+                let bytes = read_limited(response, max).await?;
+                
+                // The gateway is not trusted to return what the CID names,
+                // and the CID names the bytes as stored
+                cid::verify_content(&path, &bytes, allow_unverifiable)?;
+                compression::decompress(bytes, compression, max)
+            }.await;
+            (gateway, result)
+        }.in_current_span());
+    }
+    
+    let mut failures = Vec::new();
+    while let Some(joined) = fetches.join_next().await {
+        match joined {
+            // Dropping the set aborts the fetches still running
+            Ok((_, Ok(bytes))) => return Ok(bytes),
+            Ok((gateway, Err(e))) => {
+                debug!("IPFS gateway {} failed: {}", gateway, e);
+                failures.push((gateway, e));
+            }
+            Err(e) => failures.push(("(task)".to_string(), anyhow::anyhow!("IPFS fetch task failed: {}", e))),
+        }
+    }
+    
+    // A lone gateway's error is passed on as is, so its cause stays inspectable
+    if failures.len() == 1 {
+        return Err(failures.remove(0).1);
+    }
+    if failures.is_empty() {
+        return Err(VerifierError::Config("No IPFS gateway configured".to_string()).into());
+    }
+    let details = failures.iter()
+        .map(|(gateway, e)| format!("{}: {}", gateway, e))
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(anyhow::anyhow!("All {} IPFS gateways failed: {}", failures.len(), details))
+}
+
+/// Read a response body, stopping as soon as it exceeds `max` bytes
+async fn read_limited(mut response: reqwest::Response, max: usize) -> Result<Vec<u8>> {
+    // Short-circuit on the advertised length before reading anything
+    if let Some(size) = response.content_length().and_then(|len| usize::try_from(len).ok()) {
+        if size > max {
+            return Err(VerifierError::ReceiptTooLarge { size, max }.into());
+        }
+    }
+    
+    // Servers may omit or understate the length, so enforce it while streaming too
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > max {
+            return Err(VerifierError::ReceiptTooLarge { size: bytes.len() + chunk.len(), max }.into());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn request_with(receipt_url: String, receipt_inline: Option<String>) -> types::ProofRequest {
+        types::ProofRequest {
+            device_id: "device-1".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_url,
+            // The image of the fixture receipts used below
+            expected_image_id: format!("0x{}", hex::encode(risc0_zkvm::sha::Digest::from([1u32; 8]))),
+            epoch_index: 0,
+            input_index: 0,
+            receipt_inline,
+            receipt_format: None,
+            expected_receipt_hash: None,
+            input_timestamp: None,
+        }
+    }
+    
+    /// Receipt clients without proxies or S3
+    fn direct_clients() -> ReceiptClients {
+        ReceiptClients { http: reqwest::Client::new(), s3: None }
+    }
+    
+    #[tokio::test]
+    async fn test_inline_receipt_skips_fetch() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", "/receipt.bin")
+            .expect(0)
+            .create_async()
+            .await;
+        
+        let config = Config::default();
+        let receipt_bytes = vec![1u8, 2, 3, 4, 5];
+        
+        let hex_request = request_with(
+            format!("{}/receipt.bin", server.url()),
+            Some(format!("0x{}", hex::encode(&receipt_bytes))),
+        );
+        assert_eq!(load_receipt(&hex_request, &direct_clients(), &config, None).await.unwrap(), receipt_bytes);
+        
+        let base64_request = request_with(
+            format!("{}/receipt.bin", server.url()),
+            Some(base64::engine::general_purpose::STANDARD.encode(&receipt_bytes)),
+        );
+        assert_eq!(load_receipt(&base64_request, &direct_clients(), &config, None).await.unwrap(), receipt_bytes);
+        
+        mock.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_expected_receipt_hash_matches() {
+        let mut server = mockito::Server::new_async().await;
+        let receipt_bytes = vec![9u8; 32];
+        let _mock = server.mock("GET", "/receipt.bin")
+            .with_body(&receipt_bytes)
+            .create_async()
+            .await;
+        
+        let mut request = request_with(format!("{}/receipt.bin", server.url()), None);
+        request.expected_receipt_hash = Some(format!("0x{}", hex::encode(Keccak256::digest(&receipt_bytes))));
+        
+        assert_eq!(load_receipt(&request, &direct_clients(), &Config::default(), None).await.unwrap(), receipt_bytes);
+    }
+    
+    #[tokio::test]
+    async fn test_content_addressed_receipts_are_fetched_once() {
+        let mut server = mockito::Server::new_async().await;
+        let receipt_bytes = b"hello world\n".to_vec();
+        let ipfs = server.mock("GET", "/ipfs/QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o")
+            .with_body(&receipt_bytes)
+            .expect(1)
+            .create_async()
+            .await;
+        let http = server.mock("GET", "/receipt.bin")
+            .with_body(&receipt_bytes)
+            .expect(2)
+            .create_async()
+            .await;
+        
+        let config = Config { ipfs_gateways: vec![server.url()], ..Config::default() };
+        let cache = ReceiptCache::new(4, None).unwrap();
+        for url in ["ipfs://QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o".to_string(), format!("{}/receipt.bin", server.url())] {
+            let request = request_with(url, None);
+            for _ in 0..2 {
+                assert_eq!(load_receipt(&request, &direct_clients(), &config, Some(&cache)).await.unwrap(), receipt_bytes);
+            }
+        }
+        ipfs.assert_async().await;
+        http.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_expected_receipt_hash_mismatch() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server.mock("GET", "/receipt.bin")
+            .with_body(b"substituted receipt")
+            .create_async()
+            .await;
+        
+        let mut request = request_with(format!("{}/receipt.bin", server.url()), None);
+        request.expected_receipt_hash = Some(format!("0x{}", hex::encode(Keccak256::digest(b"original receipt"))));
+        
+        let err = load_receipt(&request, &direct_clients(), &Config::default(), None).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::ReceiptHashMismatch { .. })
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_ipfs_fetch_uses_ipfs_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        let mut mocks = Vec::new();
+        for path in ["/ipfs/QmSlow", "/slow.bin"] {
+            mocks.push(server.mock("GET", path)
+                .with_chunked_body(|w| {
+                    std::thread::sleep(Duration::from_secs(3));
+                    w.write_all(b"late receipt")
+                })
+                .create_async()
+                .await);
+        }
+        
+        let config = Config {
+            ipfs_gateways: vec![server.url()],
+            ipfs_fetch_timeout_secs: Some(1),
+            http_fetch_timeout_secs: Some(60),
+            ..Config::default()
+        };
+        assert_eq!(config.fetch_timeout("ipfs://QmSlow"), Duration::from_secs(1));
+        assert_eq!(config.fetch_timeout("https://example.com/r.bin"), Duration::from_secs(60));
+        assert_eq!(config.fetch_timeout("s3://bucket/r.bin"), Duration::from_secs(config.request_timeout_secs));
+        
+        // One client serves both schemes, each fetch with its own timeout
+        let clients = ReceiptClients::new(&config).await.unwrap();
+        let started = std::time::Instant::now();
+        let err = fetch_receipt("ipfs://QmSlow", &clients, &config).await.unwrap_err();
+        assert!(err.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout()), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(3));
+        
+        let url = format!("{}/slow.bin", server.url());
+        assert_eq!(fetch_receipt(&url, &clients, &config).await.unwrap(), b"late receipt");
+    }
+    
+    #[tokio::test]
+    async fn test_arweave_fetch_reads_through_gateway() {
+        let txid = "bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt_U";
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", format!("/{}", txid).as_str())
+            .with_body(b"arweave receipt")
+            .create_async()
+            .await;
+        
+        let config = Config { arweave_gateway: format!("{}/", server.url()), ..Config::default() };
+        assert_eq!(config.fetch_timeout(&format!("ar://{}", txid)), config.fetch_timeout("https://example.com/r.bin"));
+        assert_eq!(fetch_receipt(&format!("ar://{}", txid), &direct_clients(), &config).await.unwrap(), b"arweave receipt");
+        mock.assert_async().await;
+        
+        // The HTTP branch's size limit applies too
+        let config = Config { max_receipt_size: 4, ..config };
+        let err = fetch_receipt(&format!("ar://{}", txid), &direct_clients(), &config).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ReceiptTooLarge { .. })));
+    }
+    
+    #[tokio::test]
+    async fn test_receipt_fetches_carry_configured_headers() {
+        let cid = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+        let mut server = mockito::Server::new_async().await;
+        let http = server.mock("GET", "/receipts/r.bin")
+            .match_header("authorization", "Bearer store-token")
+            .match_header("x-api-key", "abc")
+            .with_body(b"http receipt")
+            .create_async()
+            .await;
+        let ipfs = server.mock("GET", format!("/ipfs/{}", cid).as_str())
+            .match_header("authorization", "Bearer store-token")
+            .with_body("hello world\n")
+            .create_async()
+            .await;
+        
+        let config = Config {
+            ipfs_gateways: vec![server.url()],
+            receipt_http_headers: HashMap::from([
+                ("Authorization".to_string(), "Bearer store-token".to_string()),
+                ("X-Api-Key".to_string(), "abc".to_string()),
+            ]),
+            ..Config::default()
+        };
+        let url = format!("{}/receipts/r.bin", server.url());
+        assert_eq!(fetch_receipt(&url, &direct_clients(), &config).await.unwrap(), b"http receipt");
+        assert_eq!(fetch_receipt(&format!("ipfs://{}", cid), &direct_clients(), &config).await.unwrap(), b"hello world\n");
+        http.assert_async().await;
+        ipfs.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_compressed_receipts_are_decompressed() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        use std::io::Write;
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&bincode::serialize(&receipt).unwrap()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        
+        let mut server = mockito::Server::new_async().await;
+        let _by_header = server.mock("GET", "/receipt.bin")
+            .with_header("content-encoding", "gzip")
+            .with_body(&gzipped)
+            .create_async()
+            .await;
+        let _by_suffix = server.mock("GET", "/receipt.bin.gz")
+            .with_body(&gzipped)
+            .create_async()
+            .await;
+        
+        let config = Config::default();
+        for path in ["/receipt.bin", "/receipt.bin.gz"] {
+            let bytes = fetch_receipt(&format!("{}{}", server.url(), path), &direct_clients(), &config).await.unwrap();
+            let decoded = proof_verifier::decode_receipt(&bytes, None).unwrap();
+            assert_eq!(decoded.receipt.journal.bytes, b"journal");
+        }
+        
+        // The limit applies to the decompressed size, not just the bytes on the wire
+        let small = Config { max_receipt_size: gzipped.len() + 1, ..Config::default() };
+        let err = fetch_receipt(&format!("{}/receipt.bin", server.url()), &direct_clients(), &small).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ReceiptTooLarge { .. })), "{}", err);
+    }
+    
+    #[tokio::test]
+    async fn test_ipfs_fetch_falls_back_across_gateways() {
+        let cid = "QmT78zSuBmuS4z925WZfrqQ1qHaJ56DQaTfyMUF7F8ff5o";
+        let mut failing = mockito::Server::new_async().await;
+        let mut healthy = mockito::Server::new_async().await;
+        let timeout = failing.mock("GET", format!("/ipfs/{}", cid).as_str())
+            .with_status(504)
+            .with_body("gateway timeout")
+            .expect(2)
+            .create_async()
+            .await;
+        let served = healthy.mock("GET", format!("/ipfs/{}", cid).as_str())
+            .with_body("hello world\n")
+            .create_async()
+            .await;
+        
+        let config = Config { ipfs_gateways: vec![failing.url(), healthy.url()], ..Config::default() };
+        assert_eq!(fetch_receipt(&format!("ipfs://{}", cid), &direct_clients(), &config).await.unwrap(), b"hello world\n");
+        served.assert_async().await;
+        
+        // With every gateway failing, each failure is reported
+        served.remove_async().await;
+        let _forged = healthy.mock("GET", format!("/ipfs/{}", cid).as_str())
+            .with_body("forged receipt")
+            .create_async()
+            .await;
+        let err = fetch_receipt(&format!("ipfs://{}", cid), &direct_clients(), &config).await.unwrap_err().to_string();
+        assert!(err.starts_with("All 2 IPFS gateways failed"), "{}", err);
+        assert!(err.contains(&format!("{}: IPFS gateway returned HTTP 504", failing.url())), "{}", err);
+        assert!(err.contains("does not match its CID"), "{}", err);
+        timeout.assert_async().await;
+    }
+    
+    /// Span name and its recorded (field, value) pairs
+    type RecordedSpan = (String, Vec<(String, String)>);
+    
+    /// Records the fields of every span
+    #[derive(Clone, Default)]
+    struct SpanRecorder(std::sync::Arc<std::sync::Mutex<Vec<RecordedSpan>>>);
+    
+    struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+    
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push((field.name().to_string(), format!("{:?}", value).trim_matches('"').to_string()));
+        }
+    }
+    
+    impl<S> tracing_subscriber::Layer<S> for SpanRecorder
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = vec![("span_id".to_string(), id.into_u64().to_string())];
+            if let Some(parent) = ctx.span_scope(id).and_then(|mut scope| scope.nth(1)) {
+                fields.push(("parent_id".to_string(), parent.id().into_u64().to_string()));
+            }
+            attrs.record(&mut FieldVisitor(&mut fields));
+            self.0.lock().unwrap().push((attrs.metadata().name().to_string(), fields));
+        }
+        
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut spans = self.0.lock().unwrap();
+            let span_id = id.into_u64().to_string();
+            if let Some((_, fields)) = spans.iter_mut().find(|(_, fields)| fields[0].1 == span_id) {
+                values.record(&mut FieldVisitor(fields));
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_processed_request_emits_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+        
+        let recorder = SpanRecorder::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+        
+        let pipeline = Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", "ab".repeat(32))],
+            ..Config::default()
+        }).await.unwrap();
+        
+        // An undecodable inline receipt fails verification without touching the network
+        let mut request = request_with("ipfs://unused".to_string(), Some("0x00".to_string()));
+        request.epoch_index = 3;
+        request.input_index = 17;
+        assert!(process_request_traced(request, &pipeline).await.is_err());
+        
+        let spans = recorder.0.lock().unwrap();
+        let (_, fields) = spans.iter()
+            .find(|(name, _)| name == "process_request")
+            .expect("process_request span was not created");
+        let field = |name: &str| fields.iter().rev().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+        
+        assert_eq!(field("device_id"), Some("device-1"));
+        assert_eq!(field("proof_type"), Some("iot_validation"));
+        assert_eq!(field("epoch_index"), Some("3"));
+        assert_eq!(field("input_index"), Some("17"));
+        assert_eq!(field("outcome"), Some("error"));
+    }
+    
+    #[tokio::test]
+    async fn test_fetch_and_graphql_spans_nest_under_their_caller() {
+        use tracing_subscriber::layer::SubscriberExt;
+        
+        let recorder = SpanRecorder::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+        
+        let mut server = mockito::Server::new_async().await;
+        let _receipt = server.mock("GET", "/receipt.bin")
+            .with_body("not a receipt")
+            .create_async()
+            .await;
+        let _graphql = server.mock("POST", "/graphql")
+            .with_body(r#"{ "data": { "notices": { "edges": [] } } }"#)
+            .create_async()
+            .await;
+        
+        let request = request_with(format!("{}/receipt.bin", server.url()), None);
+        let pipeline = Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![request.expected_image_id.clone()],
+            graphql_endpoints: vec![format!("{}/graphql", server.url())],
+            ..Config::default()
+        }).await.unwrap();
+        
+        assert!(process_request_traced(request, &pipeline).await.is_err());
+        pipeline.notices.query_proof_requests()
+            .instrument(info_span!("poll_cycle"))
+            .await
+            .unwrap();
+        
+        let spans = recorder.0.lock().unwrap();
+        let span = |name: &str| spans.iter()
+            .find(|(span_name, _)| span_name == name)
+            .map(|(_, fields)| fields.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect::<HashMap<_, _>>())
+            .unwrap_or_else(|| panic!("{} span was not created", name));
+        
+        // Fetch logs carry the request's device and indices through their parent
+        let fetch = span("fetch_receipt");
+        assert_eq!(fetch.get("parent_id"), span("process_request").get("span_id"));
+        assert_eq!(fetch["url"], format!("{}/receipt.bin", server.url()));
+        
+        let query = span("graphql_query");
+        assert_eq!(query.get("parent_id"), span("poll_cycle").get("span_id"));
+        assert_eq!(query["operation"], "GetProofRequests");
+    }
+    
+    #[tokio::test]
+    async fn test_under_confirmed_request_is_deferred() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let mut request = request_with(
+            "ipfs://unused".to_string(),
+            Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+        );
+        request.epoch_index = 5;
+        let notice = serde_json::json!({ "type": "risc0_proof_request", "data": request });
+        let notices = serde_json::json!({ "data": { "notices": { "edges": [{ "node": {
+            "index": 0,
+            "input": { "index": 0 },
+            "payload": format!("0x{}", hex::encode(notice.to_string())),
+        } }] } } });
+        let _notices_mock = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("GetProofRequests".to_string()))
+            .with_body(notices.to_string())
+            .create_async()
+            .await;
+        let epochs_body = |latest: u64| {
+            serde_json::json!({ "data": { "epochs": { "edges": [{ "node": { "index": latest } }] } } }).to_string()
+        };
+        let under_confirmed = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("LatestEpoch".to_string()))
+            .with_body(epochs_body(6))
+            .create_async()
+            .await;
+        
+        let _not_on_chain = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .create_async()
+            .await;
+        
+        let submission = inputbox.mock("POST", "/input")
+            .with_body(r#"{"index": 1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+            min_epoch_confirmations: 2,
+            ..Config::default()
+        }).await.unwrap());
+        
+        // One epoch on top of epoch 5 is not enough
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 0);
+        
+        // Once a second epoch lands the same request is processed
+        under_confirmed.remove_async().await;
+        let _confirmed = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("LatestEpoch".to_string()))
+            .with_body(epochs_body(7))
+            .create_async()
+            .await;
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 1);
+        
+        submission.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_notices_in_verified_receipts_out() {
+        let rollup = crate::test_harness::MockRollup::start().await;
+        rollup.serve_proof_requests(&[
+            crate::test_harness::proof_request("device-1", 0, b"first"),
+            crate::test_harness::proof_request("device-2", 1, b"second"),
+        ]);
+        let pipeline = Arc::new(Pipeline::new(rollup.config()).await.unwrap());
+        
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 2);
+        
+        let mut submitted = rollup.submitted_receipts();
+        submitted.sort_by_key(|receipt| receipt.input_index);
+        assert_eq!(submitted.len(), 2);
+        for (receipt, (device_id, journal)) in submitted.iter().zip([("device-1", &b"first"[..]), ("device-2", &b"second"[..])]) {
+            assert_eq!(receipt.device_id, device_id);
+            assert_eq!(receipt.image_id, crate::test_harness::image_id());
+            assert_eq!(receipt.journal_hash, hex::encode(Keccak256::digest(journal)));
+            assert_eq!(receipt.verifier_address.as_deref(), Some(pipeline.signer.get_address().as_str()));
+            receipt_signer::check_signed_receipt(receipt).unwrap();
+        }
+        
+        // Nothing new on the next poll
+        rollup.serve_proof_requests(&[]);
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 0);
+        assert_eq!(rollup.submitted_receipts().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_backfill_reprocesses_epoch_range() {
+        use crate::test_harness::{proof_request, MockRollup};
+        
+        // Two inputs in epoch 0, two in epoch 1, then one each in epochs 2
+        // and 3; epoch 3's names an image that is not allowed
+        let rollup = MockRollup::start().await;
+        let requests: Vec<_> = [(0, 1), (0, 2), (1, 3), (1, 4), (2, 5), (3, 6)].into_iter().map(|(epoch, input)| {
+            let request = proof_request(&format!("device-{}", input), input, &[input as u8]);
+            let expected_image_id = if epoch == 3 { format!("0x{}", "22".repeat(32)) } else { request.expected_image_id.clone() };
+            types::ProofRequest { epoch_index: epoch, expected_image_id, ..request }
+        }).collect();
+        rollup.serve_proof_requests(&requests);
+        let pipeline = Pipeline::new(rollup.config()).await.unwrap();
+        
+        let summary = backfill_epochs(&pipeline, 1..=2).await.unwrap();
+        assert_eq!(summary, BackfillSummary { processed: 3, skipped: 0, failed: 0 });
+        let submitted: Vec<_> = rollup.submitted_receipts().iter().map(|r| r.input_index).collect();
+        assert_eq!(submitted, vec![3, 4, 5]);
+        
+        // A wider range skips what the first run submitted
+        let summary = backfill_epochs(&pipeline, 0..=u64::MAX).await.unwrap();
+        assert_eq!(summary, BackfillSummary { processed: 2, skipped: 3, failed: 1 });
+        let submitted: Vec<_> = rollup.submitted_receipts().iter().map(|r| r.input_index).collect();
+        assert_eq!(submitted, vec![3, 4, 5, 1, 2]);
+        assert_eq!(pipeline.notices.last_seen_index(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_processing_loop_against_fake_rollup() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        
+        let config = Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            ..Config::default()
+        };
+        let rollup = FakeRollup::default();
+        let pipeline = Arc::new(Pipeline::new(config).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone()));
+        
+        // A failed submission holds the watermark at its input
+        rollup.serve_proof_requests(&[proof_request("device-1", 3, b"first")]);
+        rollup.fail_submissions(true);
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 0);
+        assert!(rollup.submitted_receipts().is_empty());
+        assert_eq!(pipeline.notices.last_seen_index(), 3);
+        
+        // The next poll re-reads it and submits it along with a new request
+        rollup.fail_submissions(false);
+        rollup.serve_proof_requests(&[
+            proof_request("device-1", 3, b"first"),
+            proof_request("device-2", 4, b"second"),
+        ]);
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 2);
+        let mut devices: Vec<_> = rollup.submitted_receipts().into_iter().map(|receipt| receipt.device_id).collect();
+        devices.sort();
+        assert_eq!(devices, ["device-1", "device-2"]);
+        assert_eq!(pipeline.notices.last_seen_index(), 5);
+        
+        // Settled inputs are not read again
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 0);
+        assert_eq!(rollup.submitted_receipts().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_fetching_runs_ahead_of_verification_within_bounds() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        const REQUESTS: u64 = 30;
+        let (fetches, verifications) = (6, 2);
+        
+        // Each receipt URL serves a receipt with its own journal
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let mut store = mockito::Server::new_async().await;
+        let counter = fetched.clone();
+        let _receipts = store.mock("GET", mockito::Matcher::Regex("^/receipts/".to_string()))
+            .with_body_from_request(move |request| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), request.path().as_bytes(), ExitCode::Halted(0));
+                bincode::serialize(&receipt).unwrap()
+            })
+            .create_async()
+            .await;
+        let requests: Vec<_> = (0..REQUESTS).map(|i| types::ProofRequest {
+            receipt_url: format!("{}/receipts/{}", store.url(), i),
+            receipt_inline: None,
+            ..proof_request(&format!("device-{}", i), i, b"")
+        }).collect();
+        
+        let rollup = FakeRollup::default();
+        rollup.serve_proof_requests(&requests);
+        let pipeline = Arc::new(Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            max_concurrent_fetches: fetches,
+            max_concurrent_verifications: verifications,
+            ..Config::default()
+        }).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone()));
+        
+        // With submissions stalled, verification stops making progress
+        let paused = rollup.pause_submissions().await;
+        let processing = tokio::spawn({
+            let pipeline = pipeline.clone();
+            async move { process_proof_requests(&pipeline, &CancellationToken::new()).await }
+        });
+        let mut settled = 0;
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let now = fetched.load(Ordering::SeqCst);
+            if now == settled && now > 0 {
+                break;
+            }
+            settled = now;
+        }
+        
+        // Fetching ran ahead of the stalled verifications, then stopped at
+        // the in-flight fetches, the queue, the one awaiting a worker and the
+        // verifications themselves
+        let bound = fetches + 2 * verifications + 1;
+        assert!(settled > verifications, "only {} receipts fetched", settled);
+        assert!(settled <= bound, "{} receipts fetched, bound is {}", settled, bound);
+        
+        drop(paused);
+        assert_eq!(processing.await.unwrap().unwrap(), REQUESTS as usize);
+        assert_eq!(fetched.load(Ordering::SeqCst), REQUESTS as usize);
+        assert_eq!(rollup.submitted_receipts().len(), REQUESTS as usize);
+    }
+    
+    #[tokio::test]
+    async fn test_requests_are_taken_in_input_order() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        
+        // Shuffled across epochs, as a node may list them
+        let requests: Vec<_> = [(1, 4), (0, 1), (2, 5), (1, 3), (0, 2)].into_iter().map(|(epoch, input)| types::ProofRequest {
+            epoch_index: epoch,
+            ..proof_request(&format!("device-{}", input), input, &[input as u8])
+        }).collect();
+        let rollup = FakeRollup::default();
+        rollup.serve_proof_requests(&requests);
+        let pipeline = Arc::new(Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            max_concurrent_fetches: 1,
+            max_concurrent_verifications: 1,
+            ..Config::default()
+        }).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone()));
+        
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 5);
+        let submitted: Vec<_> = rollup.submitted_receipts().iter().map(|r| (r.epoch_index, r.input_index)).collect();
+        assert_eq!(submitted, vec![(0, 1), (0, 2), (1, 3), (1, 4), (2, 5)]);
+    }
+    
+    #[tokio::test]
+    async fn test_errors_name_the_failed_stage() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        
+        let rollup = FakeRollup::default();
+        let pipeline = Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            ..Config::default()
+        }).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone());
+        
+        // Nothing listens on port 1
+        let request = types::ProofRequest {
+            receipt_url: "http://127.0.0.1:1/receipts/7".to_string(),
+            receipt_inline: None,
+            ..proof_request("device-7", 7, b"")
+        };
+        let err = process_single_request(request, &pipeline).await.unwrap_err();
+        
+        let chain: Vec<_> = err.chain().map(|cause| cause.to_string()).collect();
+        assert_eq!(chain[0], "fetching receipt for device device-7 at input 7");
+        assert!(chain.len() > 1, "{:?}", chain);
+        // The cause is still there for error classification
+        assert!(err.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_connect()), "{:?}", chain);
+        
+        // A failed submission names its own stage
+        rollup.fail_submissions(true);
+        let err = process_single_request(proof_request("device-8", 8, b"eight"), &pipeline).await.unwrap_err();
+        assert_eq!(format!("{:#}", err), "submitting receipt for device device-8 at input 8: InputBox unavailable");
+    }
+    
+    #[tokio::test]
+    async fn test_ordered_submission_waits_for_earlier_inputs() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        #[derive(Clone, Copy, PartialEq)]
+        enum Third {
+            Valid,
+            /// Fails to fetch, which is retried next poll
+            Unreachable,
+            /// Is rejected for good
+            Invalid,
+        }
+        
+        // The earlier the input, the slower its receipt arrives, so
+        // verifications finish in reverse order
+        async fn run(ordered_submission: bool, third: Third) -> (Vec<u64>, u64) {
+            let receipts = warp::path!("receipts" / u64).then(move |input: u64| async move {
+                tokio::time::sleep(Duration::from_millis(50 * (6 - input))).await;
+                if third == Third::Invalid && input == 3 {
+                    return b"not a receipt".to_vec();
+                }
+                let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), &[input as u8], ExitCode::Halted(0));
+                bincode::serialize(&receipt).unwrap()
+            });
+            let (addr, server) = warp::serve(receipts).bind_ephemeral(([127, 0, 0, 1], 0));
+            tokio::spawn(server);
+            
+            let requests: Vec<_> = [4, 1, 5, 3, 2].into_iter().map(|input| types::ProofRequest {
+                // Nothing listens on port 1
+                receipt_url: match (third, input) {
+                    (Third::Unreachable, 3) => "http://127.0.0.1:1/receipts/3".to_string(),
+                    _ => format!("http://{}/receipts/{}", addr, input),
+                },
+                receipt_inline: None,
+                ..proof_request(&format!("device-{}", input), input, b"")
+            }).collect();
+            let rollup = FakeRollup::default();
+            rollup.serve_proof_requests(&requests);
+            let pipeline = Arc::new(Pipeline::new(Config {
+                verifier_private_key: hex::encode([0x11u8; 32]),
+                allowed_image_ids: vec![image_id()],
+                max_concurrent_fetches: 5,
+                max_concurrent_verifications: 5,
+                ordered_submission,
+                ..Config::default()
+            }).await.unwrap()
+                .with_notice_source(rollup.clone())
+                .with_inputbox_sink(rollup.clone()));
+            
+            process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+            let submitted = rollup.submitted_receipts().iter().map(|r| r.input_index).collect();
+            (submitted, rollup.last_seen_index())
+        }
+        
+        // Left alone, receipts go out as their verifications finish
+        let (submitted, _) = run(false, Third::Valid).await;
+        assert_eq!(submitted, vec![5, 4, 3, 2, 1]);
+        
+        let (submitted, watermark) = run(true, Third::Valid).await;
+        assert_eq!(submitted, vec![1, 2, 3, 4, 5]);
+        assert_eq!(watermark, 6);
+        
+        // Inputs after a failed one are held back with it for the next poll
+        let (submitted, watermark) = run(true, Third::Unreachable).await;
+        assert_eq!(submitted, vec![1, 2]);
+        assert_eq!(watermark, 3);
+        
+        // A rejected one is never submitted, so nothing waits for it
+        let (submitted, watermark) = run(true, Third::Invalid).await;
+        assert_eq!(submitted, vec![1, 2, 4, 5]);
+        assert_eq!(watermark, 6);
+    }
+    
+    #[tokio::test]
+    async fn test_rejected_proof_does_not_hold_the_watermark() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        
+        let rollup = FakeRollup::default();
+        rollup.serve_proof_requests(&[
+            proof_request("device-1", 1, b"one"),
+            types::ProofRequest { receipt_inline: Some("0xdeadbeef".to_string()), ..proof_request("device-2", 2, b"") },
+            proof_request("device-3", 3, b"three"),
+        ]);
+        let pipeline = Arc::new(Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            ..Config::default()
+        }).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone()));
+        
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 2);
+        assert_eq!(rollup.last_seen_index(), 4);
+        assert_eq!(pipeline.status.rejected(), (1, Some(2)));
+        
+        // The next poll does not verify it again
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(pipeline.status.rejected(), (1, Some(2)));
+        assert_eq!(rollup.submitted_receipts().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_receipt_already_on_chain_is_not_resubmitted() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let inline = format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()));
+        
+        for (on_chain, expected_submissions) in [(true, 0), (false, 1)] {
+            let mut node = mockito::Server::new_async().await;
+            let mut inputbox = mockito::Server::new_async().await;
+            
+            let edges = if on_chain { serde_json::json!([{ "node": { "index": 3 } }]) } else { serde_json::json!([]) };
+            let check = node.mock("POST", "/graphql")
+                .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+                .with_body(serde_json::json!({ "data": { "inputs": { "edges": edges } } }).to_string())
+                .expect(1)
+                .create_async()
+                .await;
+            let submission = inputbox.mock("POST", "/input")
+                .with_body(r#"{"index": 1}"#)
+                .expect(expected_submissions)
+                .create_async()
+                .await;
+            
+            let pipeline = Pipeline::new(Config {
+                graphql_endpoints: vec![format!("{}/graphql", node.url())],
+                inputbox_endpoint: format!("{}/input", inputbox.url()),
+                verifier_private_key: hex::encode([0x11u8; 32]),
+                allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+                ..Config::default()
+            }).await.unwrap();
+            
+            let request = request_with("ipfs://unused".to_string(), Some(inline.clone()));
+            process_single_request(request, &pipeline).await.unwrap();
+            
+            check.assert_async().await;
+            submission.assert_async().await;
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_readiness_reflects_dependencies() {
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        let _ping = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("Ping".to_string()))
+            .with_body(r#"{"data": {"__typename": "Query"}}"#)
+            .create_async()
+            .await;
+        let inputbox_down = inputbox.mock("GET", "/health")
+            .with_status(503)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec!["0x01".to_string()],
+            ..Config::default()
+        }).await.unwrap());
+        let routes = service_routes(pipeline);
+        
+        let live = warp::test::request().path("/live").reply(&routes).await;
+        assert_eq!(live.status(), warp::http::StatusCode::OK);
+        
+        let ready = warp::test::request().path("/ready").reply(&routes).await;
+        assert_eq!(ready.status(), warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = serde_json::from_slice(ready.body()).unwrap();
+        assert_eq!(body, serde_json::json!({ "graphql": true, "inputbox": false, "graphql_circuit": "closed" }));
+        
+        inputbox_down.remove_async().await;
+        let _inputbox_up = inputbox.mock("GET", "/health")
+            .create_async()
+            .await;
+        let health = warp::test::request().path("/health").reply(&routes).await;
+        assert_eq!(health.status(), warp::http::StatusCode::OK);
+    }
+    
+    #[tokio::test]
+    async fn test_admin_edits_allowed_images() {
+        use warp::http::StatusCode;
+        
+        let (first, second) = (format!("0x{}", "01".repeat(32)), format!("0x{}", "02".repeat(32)));
+        let config = Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![first.clone()],
+            admin_token: Some("admin-secret".to_string()),
+            ..Config::default()
+        };
+        let pipeline = Arc::new(Pipeline::new(config.clone()).await.unwrap());
+        let routes = service_routes(pipeline.clone());
+        let admin = |method: &str, path: &str| warp::test::request()
+            .method(method)
+            .path(path)
+            .header("authorization", "Bearer admin-secret");
+        
+        let added = admin("POST", "/admin/images").json(&serde_json::json!({ "image_id": second })).reply(&routes).await;
+        assert_eq!(added.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(added.body()).unwrap();
+        assert_eq!(body, serde_json::json!({ "allowed_image_ids": [first, second] }));
+        assert!(pipeline.verifier.read().unwrap().is_image_allowed(&[2u8; 32]));
+        
+        let removed = admin("DELETE", &format!("/admin/images/{}", first)).reply(&routes).await;
+        assert_eq!(removed.status(), StatusCode::OK);
+        let listed = admin("GET", "/admin/images").reply(&routes).await;
+        let body: serde_json::Value = serde_json::from_slice(listed.body()).unwrap();
+        assert_eq!(body, serde_json::json!({ "allowed_image_ids": [second] }));
+        assert!(!pipeline.verifier.read().unwrap().is_image_allowed(&[1u8; 32]));
+        
+        let missing = admin("DELETE", &format!("/admin/images/{}", first)).reply(&routes).await;
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+        let malformed = admin("POST", "/admin/images").json(&serde_json::json!({ "image_id": "0x01" })).reply(&routes).await;
+        assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+        
+        // Without a configured token the endpoints do not exist
+        let routes = service_routes(Arc::new(Pipeline::new(Config { admin_token: None, ..config }).await.unwrap()));
+        let disabled = admin("GET", "/admin/images").reply(&routes).await;
+        assert_eq!(disabled.status(), StatusCode::NOT_FOUND);
+    }
+    
+    #[tokio::test]
+    async fn test_admin_rejects_unauthenticated_requests() {
+        use warp::http::StatusCode;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", "01".repeat(32))],
+            admin_token: Some("admin-secret".to_string()),
+            ..Config::default()
+        }).await.unwrap());
+        let routes = service_routes(pipeline.clone());
+        
+        let anonymous = warp::test::request()
+            .method("POST")
+            .path("/admin/images")
+            .json(&serde_json::json!({ "image_id": format!("0x{}", "02".repeat(32)) }))
+            .reply(&routes)
+            .await;
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        
+        for authorization in ["Bearer wrong-secret", "admin-secret", "Basic YWRtaW4tc2VjcmV0"] {
+            let reply = warp::test::request()
+                .path("/admin/images")
+                .header("authorization", authorization)
+                .reply(&routes)
+                .await;
+            assert_eq!(reply.status(), StatusCode::UNAUTHORIZED, "{}", authorization);
+        }
+        assert!(!pipeline.verifier.read().unwrap().is_image_allowed(&[2u8; 32]));
+    }
+    
+    /// An InputBox that takes `delay` to answer each submission
+    async fn slow_inputbox(delay: Duration) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/input", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    // Read the headers and Content-Length bytes of body
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    loop {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                        let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+                            continue;
+                        };
+                        let headers = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                        let length: usize = headers.lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .and_then(|value| value.trim().parse().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                    
+                    tokio::time::sleep(delay).await;
+                    let body = r#"{"index": 1}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(), body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        url
+    }
+    
+    #[tokio::test]
+    async fn test_batch_is_processed_concurrently() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        const REQUESTS: u32 = 4;
+        let delay = Duration::from_millis(400);
+        
+        let mut node = mockito::Server::new_async().await;
+        let edges: Vec<_> = (0..REQUESTS).map(|i| {
+            let journal = format!("journal-{}", i);
+            let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), journal.as_bytes(), ExitCode::Halted(0));
+            let mut request = request_with(
+                format!("ipfs://unused-{}", i),
+                Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+            );
+            request.input_index = i as u64;
+            let notice = serde_json::json!({ "type": "risc0_proof_request", "data": request });
+            serde_json::json!({ "node": {
+                "index": 0,
+                "input": { "index": i },
+                "payload": format!("0x{}", hex::encode(notice.to_string())),
+            } })
+        }).collect();
+        let _notices = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("GetProofRequests".to_string()))
+            .with_body(serde_json::json!({ "data": { "notices": { "edges": edges } } }).to_string())
+            .create_async()
+            .await;
+        let _not_on_chain = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: slow_inputbox(delay).await,
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+            max_concurrent_verifications: REQUESTS as usize,
+            ..Config::default()
+        }).await.unwrap());
+        
+        let started = std::time::Instant::now();
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), REQUESTS as usize);
+        let elapsed = started.elapsed();
+        assert!(elapsed < delay * REQUESTS, "batch took {:?}", elapsed);
+        assert_eq!(pipeline.notices.last_seen_index(), REQUESTS as u64);
+    }
+    
+    #[tokio::test]
+    async fn test_noisy_device_is_rate_limited() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        
+        let devices = ["noisy", "noisy", "noisy", "quiet", "noisy"];
+        let requests: Vec<_> = devices.iter().enumerate()
+            .map(|(i, device)| proof_request(device, i as u64, format!("journal-{}", i).as_bytes()))
+            .collect();
+        let rollup = FakeRollup::default();
+        rollup.serve_proof_requests(&requests);
+        let pipeline = Arc::new(Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            device_rate_limit_per_sec: Some(10.0),
+            device_rate_limit_burst: 2,
+            ..Config::default()
+        }).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone()));
+        let submitted = || {
+            let mut inputs: Vec<_> = rollup.submitted_receipts().iter().map(|r| r.input_index).collect();
+            inputs.sort();
+            inputs
+        };
+        
+        // The noisy device gets its burst of two; the quiet one is unaffected
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 3);
+        assert_eq!(submitted(), vec![0, 1, 3]);
+        assert_eq!(pipeline.status.last_processed_input(), Some(3));
+        // Deferred requests are read again from the first of them
+        assert_eq!(rollup.last_seen_index(), 2);
+        
+        // Once the device has budget again they are processed
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(submitted(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(rollup.last_seen_index(), 5);
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limited_request_holds_its_place() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        
+        async fn start(ordered_submission: bool) -> (FakeRollup, Arc<Pipeline>) {
+            let rollup = FakeRollup::default();
+            let pipeline = Arc::new(Pipeline::new(Config {
+                verifier_private_key: hex::encode([0x11u8; 32]),
+                allowed_image_ids: vec![image_id()],
+                // Slow enough that nothing refills during the test
+                device_rate_limit_per_sec: Some(0.01),
+                device_rate_limit_burst: 2,
+                ordered_submission,
+                ..Config::default()
+            }).await.unwrap()
+                .with_notice_source(rollup.clone())
+                .with_inputbox_sink(rollup.clone()));
+            (rollup, pipeline)
+        }
+        let requests = |devices: &[&str]| -> Vec<_> {
+            devices.iter().enumerate()
+                .map(|(i, device)| proof_request(device, i as u64, format!("journal-{}", i).as_bytes()))
+                .collect()
+        };
+        let submitted = |rollup: &FakeRollup| {
+            let mut inputs: Vec<_> = rollup.submitted_receipts().iter().map(|r| r.input_index).collect();
+            inputs.sort();
+            inputs
+        };
+        
+        // Re-reading the quiet device's submitted request behind the deferred
+        // one does not spend its budget, so its next request still gets in
+        let (rollup, pipeline) = start(false).await;
+        rollup.serve_proof_requests(&requests(&["noisy", "noisy", "noisy", "quiet"]));
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(submitted(&rollup), vec![0, 1, 3]);
+        assert_eq!(rollup.last_seen_index(), 2);
+        
+        rollup.serve_proof_requests(&requests(&["noisy", "noisy", "noisy", "quiet", "quiet"]));
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(submitted(&rollup), vec![0, 1, 3, 4]);
+        assert_eq!(rollup.last_seen_index(), 2);
+        
+        // With ordered submission, nothing goes out ahead of the deferred request
+        let (rollup, pipeline) = start(true).await;
+        rollup.serve_proof_requests(&requests(&["noisy", "noisy", "noisy", "quiet"]));
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(submitted(&rollup), vec![0, 1]);
+        assert_eq!(rollup.last_seen_index(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_health_stays_responsive_during_verification() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let verification_time = Duration::from_secs(1);
+        
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        let _not_on_chain = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .create_async()
+            .await;
+        let _submission = inputbox.mock("POST", "/input")
+            .with_body(r#"{"index": 1}"#)
+            .create_async()
+            .await;
+        
+        let config = Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+            ..Config::default()
+        };
+        let mut pipeline = Pipeline::new(config.clone()).await.unwrap();
+        // Stands in for the seconds of CPU a large receipt takes
+        pipeline.verifier = RwLock::new(Arc::new(ProofVerifier::from_config(&config).unwrap().with_receipt_check(|_, _| {
+            std::thread::sleep(Duration::from_secs(1));
+            Ok(())
+        })));
+        let pipeline = Arc::new(pipeline);
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let request = request_with(
+            "ipfs://unused".to_string(),
+            Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+        );
+        let started = std::time::Instant::now();
+        let processing = tokio::spawn({
+            let pipeline = pipeline.clone();
+            async move { process_single_request(request, &pipeline).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        
+        // The test runtime has a single thread, so this only answers while
+        // verification is running if verification is not on that thread
+        let live = warp::test::request().path("/live").reply(&service_routes(pipeline)).await;
+        assert_eq!(live.status(), warp::http::StatusCode::OK);
+        assert!(started.elapsed() < verification_time, "health answered after {:?}", started.elapsed());
+        
+        processing.await.unwrap().unwrap();
+        assert!(started.elapsed() >= verification_time);
+    }
+    
+    #[tokio::test]
+    async fn test_shutdown_leaves_requests_for_next_run() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let request = request_with(
+            "ipfs://unused".to_string(),
+            Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+        );
+        let notice = serde_json::json!({ "type": "risc0_proof_request", "data": request });
+        let _notices = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("GetProofRequests".to_string()))
+            .with_body(serde_json::json!({ "data": { "notices": { "edges": [{ "node": {
+                "index": 0,
+                "input": { "index": 0 },
+                "payload": format!("0x{}", hex::encode(notice.to_string())),
+            } }] } } }).to_string())
+            .create_async()
+            .await;
+        let submission = inputbox.mock("POST", "/input")
+            .expect(0)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+            ..Config::default()
+        }).await.unwrap());
+        
+        let shutdown = CancellationToken::new();
+        shutdown.cancel();
+        assert_eq!(process_proof_requests(&pipeline, &shutdown).await.unwrap(), 0);
+        assert_eq!(pipeline.notices.last_seen_index(), 0);
+        submission.assert_async().await;
+    }
+    
+    fn write_config(path: &std::path::Path, private_key: u8, poll_interval_secs: u64, image_ids: &[&str]) {
+        std::fs::write(path, format!(
+            "verifier_private_key = \"{}\"\npoll_interval_secs = {}\nallowed_image_ids = {:?}\n",
+            hex::encode([private_key; 32]), poll_interval_secs, image_ids,
+        )).unwrap();
+    }
+    
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sighup_reloads_interval_and_images() {
+        let old_image = format!("0x{}", hex::encode([1u8; 32]));
+        let new_image = format!("0x{}", hex::encode([2u8; 32]));
+        let path = std::env::temp_dir().join(format!("lcore-reload-{}.toml", std::process::id()));
+        write_config(&path, 0x11, 10, &[&old_image]);
+        
+        let pipeline = Arc::new(Pipeline::new(Config::load(path.to_str().unwrap()).unwrap()).await.unwrap());
+        let mut reloads = pipeline.poll_interval.subscribe();
+        spawn_reload_listener(pipeline.clone(), path.to_str().unwrap().to_string()).unwrap();
+        assert_eq!(*reloads.borrow_and_update(), Duration::from_secs(10));
+        
+        write_config(&path, 0x11, 3, &[&old_image, &new_image]);
+        // SAFETY: raise only delivers a signal, and the listener above handles SIGHUP
+        assert_eq!(unsafe { libc::raise(libc::SIGHUP) }, 0);
+        tokio::time::timeout(Duration::from_secs(5), reloads.changed()).await.unwrap().unwrap();
+        
+        assert_eq!(*reloads.borrow(), Duration::from_secs(3));
+        assert!(pipeline.verifier.read().unwrap().is_image_allowed(&[2u8; 32]));
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_reload_rotates_signing_key() {
+        let image = format!("0x{}", hex::encode([1u8; 32]));
+        let path = std::env::temp_dir().join(format!("lcore-reload-key-{}.toml", std::process::id()));
+        write_config(&path, 0x11, 10, &[&image]);
+        let pipeline = Pipeline::new(Config::load(path.to_str().unwrap()).unwrap()).await.unwrap();
+        let old_address = pipeline.signer.get_address();
+        
+        // An invalid key rejects the whole reload
+        write_config(&path, 0xff, 3, &[&image, &format!("0x{}", hex::encode([2u8; 32]))]);
+        let err = reload_config(&pipeline, path.to_str().unwrap(), RestartOnlyChanges::Ignore).unwrap_err();
+        assert!(!err.to_string().contains(&"ff".repeat(32)), "{}", err);
+        assert!(!pipeline.verifier.read().unwrap().is_image_allowed(&[2u8; 32]));
+        assert_eq!(pipeline.signer.get_address(), old_address);
+        
+        // A valid one is signed with from then on
+        write_config(&path, 0x22, 3, &[&image]);
+        reload_config(&pipeline, path.to_str().unwrap(), RestartOnlyChanges::Ignore).unwrap();
+        let new_address = ReceiptSigner::new(&"22".repeat(32)).unwrap().get_address();
+        assert_eq!(pipeline.signer.get_address(), new_address);
+        assert_eq!(pipeline.signer.key_id(), 1);
+        
+        // Reloading the same key again is not another rotation
+        reload_config(&pipeline, path.to_str().unwrap(), RestartOnlyChanges::Ignore).unwrap();
+        assert_eq!(pipeline.signer.key_id(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_admin_reload_applies_new_images() {
+        let old_image = format!("0x{}", hex::encode([1u8; 32]));
+        let new_image = format!("0x{}", hex::encode([2u8; 32]));
+        let path = std::env::temp_dir().join(format!("lcore-admin-reload-{}.toml", std::process::id()));
+        let write = |poll_interval_secs: u64, image_ids: &[&str], extra: &str| {
+            write_config(&path, 0x11, poll_interval_secs, image_ids);
+            let mut file = std::fs::read_to_string(&path).unwrap();
+            file.push_str(&format!("admin_token = \"admin-secret\"\n{}", extra));
+            std::fs::write(&path, file).unwrap();
+        };
+        write(10, &[&old_image], "");
+        
+        let pipeline = Arc::new(Pipeline::new(Config::load(path.to_str().unwrap()).unwrap()).await.unwrap()
+            .with_config_path(path.to_str().unwrap()));
+        let routes = service_routes(pipeline.clone());
+        let reload = |authorization: &str| warp::test::request()
+            .method("POST")
+            .path("/admin/reload")
+            .header("authorization", authorization);
+        
+        write(3, &[&old_image, &new_image], "");
+        assert_eq!(reload("Bearer wrong").reply(&routes).await.status(), 401);
+        assert!(!pipeline.verifier.read().unwrap().is_image_allowed(&[2u8; 32]));
+        
+        let reply = reload("Bearer admin-secret").reply(&routes).await;
+        assert_eq!(reply.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(reply.body()).unwrap();
+        assert_eq!(body["changed"]["poll_interval_secs"], serde_json::json!({ "from": 10, "to": 3 }));
+        assert_eq!(body["changed"]["allowed_image_ids"]["to"].as_array().unwrap().len(), 2);
+        assert!(body["changed"].get("verifier_address").is_none());
+        assert!(pipeline.verifier.read().unwrap().is_image_allowed(&[2u8; 32]));
+        assert_eq!(*pipeline.poll_interval.borrow(), Duration::from_secs(3));
+        
+        // A restart-only change rejects the whole reload
+        write(3, &[&old_image], "max_receipt_size = 1024\n");
+        let reply = reload("Bearer admin-secret").reply(&routes).await;
+        assert_eq!(reply.status(), 409);
+        let body: serde_json::Value = serde_json::from_slice(reply.body()).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("max_receipt_size"), "{}", body);
+        assert!(pipeline.verifier.read().unwrap().is_image_allowed(&[2u8; 32]));
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_reload_keeps_command_line_overrides() {
+        let image = format!("0x{}", hex::encode([1u8; 32]));
+        let path = std::env::temp_dir().join(format!("lcore-reload-overrides-{}.toml", std::process::id()));
+        write_config(&path, 0x11, 10, &[&image]);
+        
+        let overrides = CliOverrides { verbose: true, log_format: Some(LogFormat::Json), dry_run: true };
+        let mut config = Config::load(path.to_str().unwrap()).unwrap();
+        overrides.apply(&mut config);
+        let pipeline = Pipeline::new(config).await.unwrap().with_overrides(overrides);
+        
+        // None of the flags is in the file, so none reads as a change
+        write_config(&path, 0x11, 3, &[&image]);
+        let report = reload_config(&pipeline, path.to_str().unwrap(), RestartOnlyChanges::Reject).unwrap();
+        assert_eq!(report.changed.keys().copied().collect::<Vec<_>>(), ["poll_interval_secs"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_reload_applies_log_level() {
+        let image = format!("0x{}", hex::encode([1u8; 32]));
+        let path = std::env::temp_dir().join(format!("lcore-reload-log-level-{}.toml", std::process::id()));
+        write_config(&path, 0x11, 10, &[&image]);
+        let (_filter, log_level) = LogLevel::new("info");
+        let pipeline = Pipeline::new(Config::load(path.to_str().unwrap()).unwrap()).await.unwrap()
+            .with_log_level(log_level.clone());
+        
+        let mut file = std::fs::read_to_string(&path).unwrap();
+        file.push_str("log_level = \"debug\"\n");
+        std::fs::write(&path, &file).unwrap();
+        let report = reload_config(&pipeline, path.to_str().unwrap(), RestartOnlyChanges::Reject).unwrap();
+        assert_eq!(report.changed.keys().copied().collect::<Vec<_>>(), ["log_level"]);
+        assert_eq!(report.changed["log_level"].from, "info");
+        assert_eq!(report.changed["log_level"].to, "debug");
+        assert_eq!(log_level.current(), "debug");
+        
+        // An invalid level fails the load, leaving the running level alone
+        std::fs::write(&path, file.replace("debug", "loud")).unwrap();
+        assert!(reload_config(&pipeline, path.to_str().unwrap(), RestartOnlyChanges::Reject).is_err());
+        assert_eq!(log_level.current(), "debug");
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_admin_sets_log_level() {
+        let (_filter, log_level) = LogLevel::new("info");
+        let pipeline = Pipeline::new(Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            admin_token: Some("admin-secret".to_string()),
+            ..Config::default()
+        }).await.unwrap().with_log_level(log_level.clone());
+        let routes = service_routes(Arc::new(pipeline));
+        let set = |level: &str| warp::test::request()
+            .method("PUT")
+            .path("/admin/log-level")
+            .header("authorization", "Bearer admin-secret")
+            .json(&serde_json::json!({ "level": level }));
+        
+        let reply = set("debug").reply(&routes).await;
+        assert_eq!(reply.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(reply.body()).unwrap();
+        assert_eq!(body, serde_json::json!({ "previous": "info", "level": "debug" }));
+        
+        assert_eq!(set("verbose").reply(&routes).await.status(), 400);
+        assert_eq!(log_level.current(), "debug");
+    }
+    
+    #[tokio::test]
+    async fn test_dry_run_does_not_submit() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let request = request_with(
+            "ipfs://unused".to_string(),
+            Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+        );
+        let notice = serde_json::json!({ "type": "risc0_proof_request", "data": request });
+        let _notices = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("GetProofRequests".to_string()))
+            .with_body(serde_json::json!({ "data": { "notices": { "edges": [{ "node": {
+                "index": 0,
+                "input": { "index": 0 },
+                "payload": format!("0x{}", hex::encode(notice.to_string())),
+            } }] } } }).to_string())
+            .create_async()
+            .await;
+        let _not_on_chain = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .create_async()
+            .await;
+        let submission = inputbox.mock("POST", mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+            submit_epoch_summaries: true,
+            dry_run: true,
+            ..Config::default()
+        }).await.unwrap());
+        
+        // Counted as processed, so throughput metrics still move
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 1);
+        submission.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_receipt_carries_input_timestamp() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let request = types::ProofRequest {
+            // Only the node's input timestamp counts, never the payload's
+            input_timestamp: Some(1),
+            ..request_with(
+                "ipfs://unused".to_string(),
+                Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+            )
+        };
+        let notice = serde_json::json!({ "type": "risc0_proof_request", "data": request });
+        let _notices = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("GetProofRequests".to_string()))
+            .with_body(serde_json::json!({ "data": { "notices": { "edges": [{ "node": {
+                "index": 0,
+                "input": { "index": 0, "timestamp": "1700000000" },
+                "payload": format!("0x{}", hex::encode(notice.to_string())),
+            } }] } } }).to_string())
+            .create_async()
+            .await;
+        let _not_on_chain = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .create_async()
+            .await;
+        let submission = inputbox.mock("POST", "/input")
+            .match_body(mockito::Matcher::Regex(hex::encode(r#""timestamp":1700000000"#)))
+            .with_body(r#"{"index": 1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+            ..Config::default()
+        }).await.unwrap());
+        
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 1);
+        submission.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_status_reports_poll_progress() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let request = types::ProofRequest {
+            input_index: 7,
+            ..request_with(
+                "ipfs://unused".to_string(),
+                Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+            )
+        };
+        let notice = serde_json::json!({ "type": "risc0_proof_request", "data": request });
+        let _notices = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("GetProofRequests".to_string()))
+            .with_body(serde_json::json!({ "data": { "notices": { "edges": [{ "node": {
+                "index": 0,
+                "input": { "index": 7 },
+                "payload": format!("0x{}", hex::encode(notice.to_string())),
+            } }] } } }).to_string())
+            .create_async()
+            .await;
+        let _not_on_chain = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .create_async()
+            .await;
+        let _submission = inputbox.mock("POST", "/input")
+            .with_body(r#"{"index": 8}"#)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8]))), "0x02".to_string()],
+            poll_interval_secs: 15,
+            ..Config::default()
+        }).await.unwrap());
+        let routes = service_routes(pipeline.clone());
+        
+        let status = warp::test::request().path("/status").reply(&routes).await;
+        assert_eq!(status.status(), warp::http::StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_slice(status.body()).unwrap();
+        assert_eq!(body["verifier_address"], pipeline.signer.get_address());
+        assert!(body["uptime_secs"].is_u64());
+        assert_eq!(body["last_successful_poll"], serde_json::Value::Null);
+        assert_eq!(body["last_processed_input_index"], serde_json::Value::Null);
+        assert_eq!(body["poll_interval_secs"], 15);
+        assert_eq!(body["allowed_image_count"], 2);
+        assert_eq!(body["graphql_circuit"], "closed");
+        assert_eq!(body["submissions_rate_limited"], 0);
+        
+        assert_eq!(process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap(), 1);
+        
+        let status = warp::test::request().path("/status").reply(&routes).await;
+        let body: serde_json::Value = serde_json::from_slice(status.body()).unwrap();
+        let polled_at = body["last_successful_poll"].as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(polled_at).is_ok());
+        assert_eq!(body["last_processed_input_index"], 7);
+    }
+    
+    #[tokio::test]
+    async fn test_partially_accepted_batch_is_requeued() {
+        let mut inputbox = mockito::Server::new_async().await;
+        let partial = inputbox.mock("POST", "/input")
+            .match_body(mockito::Matcher::Regex(r#"^\[\{.*\},\{.*\}\]$"#.to_string()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"accepted": 1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let remainder = inputbox.mock("POST", "/input")
+            .match_body(mockito::Matcher::Regex(r#"^\[\{[^\]]*\}\]$"#.to_string()))
+            .with_header("content-type", "application/json")
+            .with_body("{}")
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let pipeline = Arc::new(Pipeline::new(Config {
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allow_any_image: true,
+            inputbox_batch_size: 2,
+            inputbox_retry_delay_ms: 1,
+            ..Config::default()
+        }).await.unwrap());
+        start_submission_drain(&pipeline);
+        
+        let submissions = pipeline.submissions.as_ref().unwrap();
+        for (input_index, receipt_hash) in [(4, "bb"), (3, "aa")] {
+            let receipt = pipeline.signer.sign_receipt(types::VerifiedReceipt {
+                device_id: "device123".to_string(),
+                proof_type: "iot_validation".to_string(),
+                receipt_hash: receipt_hash.to_string(),
+                image_id: "0x5678".to_string(),
+                journal_hash: "abcd".to_string(),
+                journal_empty: false,
+                proof_kind: types::ProofKind::Composite,
+                journal_summary: None,
+                epoch_index: 1,
+                input_index,
+                signature: String::new(),
+                timestamp: None,
+                verifier_address: Some(pipeline.signer.get_address()),
+                bls_signature: None,
+                bls_public_key: None,
+            }).await.unwrap();
+            submissions.enqueue(receipt).await.unwrap();
+        }
+        
+        tokio::time::timeout(Duration::from_secs(5), submissions.flushed()).await.unwrap();
+        partial.assert_async().await;
+        remainder.assert_async().await;
+        
+        // Both receipts count as submitted once the re-queued one goes through
+        for receipt_hash in ["aa", "bb"] {
+            assert!(matches!(pipeline.processed_store.status(receipt_hash), DedupStatus::Trusted));
+        }
+        let epoch = pipeline.epochs.take_finalized(2, 5);
+        assert_eq!(epoch.len(), 1);
+        assert_eq!(epoch[0].1.iter().map(|entry| entry.input_index).collect::<Vec<_>>(), [3, 4]);
+    }
+    
+    #[tokio::test]
+    async fn test_epoch_is_summarized_once_all_its_inputs_are_settled() {
+        use crate::test_harness::{image_id, proof_request, FakeRollup};
+        
+        // Epoch 0 holds inputs 0 and 1 from one device, epoch 1 input 2 from another
+        let requests: Vec<_> = [("noisy", 0, 0), ("noisy", 0, 1), ("quiet", 1, 2)].into_iter()
+            .map(|(device, epoch, input)| types::ProofRequest {
+                epoch_index: epoch,
+                ..proof_request(device, input, &[input as u8])
+            })
+            .collect();
+        let rollup = FakeRollup::default();
+        rollup.serve_proof_requests(&requests);
+        rollup.set_latest_epoch(1);
+        let state_file = std::env::temp_dir().join(format!("lcore-epoch-state-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&state_file);
+        let config = Config {
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            submit_epoch_summaries: true,
+            device_rate_limit_per_sec: Some(10.0),
+            device_rate_limit_burst: 1,
+            state_file: Some(state_file.to_string_lossy().into_owned()),
+            ..Config::default()
+        };
+        let pipeline = Arc::new(Pipeline::new(config.clone()).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone()));
+        let summaries = || rollup.submitted_summaries().iter()
+            .map(|summary| (summary.epoch_index, summary.receipt_count))
+            .collect::<Vec<_>>();
+        
+        // Input 1 is deferred, so epoch 0 stays open although the chain is past it
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(rollup.last_seen_index(), 1);
+        assert!(summaries().is_empty());
+        
+        // Once it is submitted epoch 0 is summarized whole; epoch 1 is still the chain's latest
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(rollup.last_seen_index(), 3);
+        assert_eq!(summaries(), [(0, 2)]);
+        
+        // With no new requests, epoch 1 is summarized once the chain moves on
+        rollup.set_latest_epoch(2);
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        process_proof_requests(&pipeline, &CancellationToken::new()).await.unwrap();
+        assert_eq!(summaries(), [(0, 2), (1, 1)]);
+        pipeline.state.as_ref().unwrap().flush().await;
+        
+        // A restarted verifier re-reading epoch 1 does not summarize it again
+        let restarted = Arc::new(Pipeline::new(config).await.unwrap()
+            .with_notice_source(rollup.clone())
+            .with_inputbox_sink(rollup.clone()));
+        restarted.epochs.record(BatchEntry {
+            epoch_index: 1,
+            input_index: 2,
+            device_id: "quiet".to_string(),
+            receipt_hash: hex::encode([2u8; 32]),
+        });
+        process_proof_requests(&restarted, &CancellationToken::new()).await.unwrap();
+        assert_eq!(summaries(), [(0, 2), (1, 1)]);
+        std::fs::remove_file(&state_file).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_restart_resumes_from_saved_state() {
+        use risc0_zkvm::{sha::Digest, ExitCode};
+        
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let inline = format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()));
+        let state_file = std::env::temp_dir().join(format!("lcore-restart-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&state_file);
+        
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        // Only the first run asks the chain and submits
+        let check = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let submission = inputbox.mock("POST", "/input")
+            .with_body(r#"{"index": 1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let config = Config {
+            graphql_endpoints: vec![format!("{}/graphql", node.url())],
+            inputbox_endpoint: format!("{}/input", inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![format!("0x{}", hex::encode(Digest::from([1u32; 8])))],
+            state_file: Some(state_file.to_string_lossy().into_owned()),
+            ..Config::default()
+        };
+        
+        let request = types::ProofRequest { input_index: 7, ..request_with("ipfs://unused".to_string(), Some(inline)) };
+        let pipeline = Pipeline::new(config.clone()).await.unwrap();
+        process_single_request(request.clone(), &pipeline).await.unwrap();
+        // The poll stopped at input 7, e.g. on a later request of that input
+        advance_watermark(&pipeline, Some(7), Some(7));
+        pipeline.state.as_ref().unwrap().flush().await;
+        
+        let restarted = Pipeline::new(config).await.unwrap();
+        assert_eq!(restarted.notices.last_seen_index(), 7);
+        process_single_request(request, &restarted).await.unwrap();
+        
+        check.assert_async().await;
+        submission.assert_async().await;
+        std::fs::remove_file(&state_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_fetch_is_refused() {
+        let mut server = mockito::Server::new_async().await;
+        let _advertised = server.mock("GET", "/advertised.bin")
+            .with_body(vec![0u8; 4096])
+            .create_async()
+            .await;
+        // Chunked responses carry no Content-Length, so the limit applies mid-stream
+        let _chunked = server.mock("GET", "/ipfs/QmChunked")
+            .with_chunked_body(|w| {
+                for _ in 0..8 {
+                    w.write_all(&[0u8; 512])?;
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+        
+        let config = Config {
+            ipfs_gateways: vec![server.url()],
+            max_receipt_size: 1024,
+            ..Config::default()
+        };
+        
+        let err = fetch_receipt(&format!("{}/advertised.bin", server.url()), &direct_clients(), &config).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::ReceiptTooLarge { size: 4096, max: 1024 })
+        ));
+        
+        let err = fetch_receipt("ipfs://QmChunked", &direct_clients(), &config).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::ReceiptTooLarge { max: 1024, .. })
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_advertised_length_is_refused_before_the_body() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // Advertise 1 GiB, send a few bytes and then stall: only a check of
+        // the Content-Length header can answer without waiting on the body
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/huge.bin", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let _ = socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 1073741824\r\n\r\n\0\0\0\0").await;
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+        
+        let config = Config { max_receipt_size: 1024, ..Config::default() };
+        let err = tokio::time::timeout(Duration::from_secs(5), fetch_receipt(&url, &direct_clients(), &config))
+            .await
+            .expect("the oversized body was waited on")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::ReceiptTooLarge { size: 1073741824, max: 1024 })
+        ));
+    }
+    
+    #[test]
+    fn test_epoch_confirmation_depth() {
+        assert!(!is_epoch_confirmed(5, None, 1));
+        assert!(!is_epoch_confirmed(5, Some(5), 1));
+        assert!(is_epoch_confirmed(5, Some(6), 1));
+        assert!(is_epoch_confirmed(5, Some(5), 0));
+        assert!(!is_epoch_confirmed(7, Some(5), 0));
+    }
+    
+    #[test]
+    fn test_inline_receipt_size_limit() {
+        let inline = format!("0x{}", "00".repeat(100));
+        
+        let err = decode_inline_receipt(&inline, 64).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::ReceiptTooLarge { size: 100, max: 64 })
+        ));
+        assert_eq!(decode_inline_receipt(&inline, 100).unwrap().len(), 100);
+    }
+}
@@ -0,0 +1,75 @@
+//! Adaptive poll interval
+//!
+//! A short interval keeps latency low while proof requests arrive, but costs
+//! a query per tick when nothing does. Each empty poll stretches the interval
+//! by a multiplier, up to a cap; the first poll that finds requests brings it
+//! back to the base interval.
+
+use std::time::Duration;
+
+/// Decides how long to wait before the next poll
+pub struct PollBackoff {
+    base: Duration,
+    max: Duration,
+    multiplier: f64,
+    current: Duration,
+}
+
+impl PollBackoff {
+    /// Start at `base`, growing by `multiplier` per empty poll up to `max`;
+    /// a `max` at or below `base` keeps the interval fixed
+    pub fn new(base: Duration, max: Duration, multiplier: f64) -> Self {
+        Self {
+            base,
+            max: max.max(base),
+            multiplier: multiplier.max(1.0),
+            current: base,
+        }
+    }
+
+    /// Record a poll that processed `processed` requests, returning the
+    /// interval to wait before the next one
+    pub fn record(&mut self, processed: usize) -> Duration {
+        self.current = if processed > 0 {
+            self.base
+        } else {
+            self.current.mul_f64(self.multiplier).min(self.max)
+        };
+        self.current
+    }
+
+    /// Use a new base interval (e.g. after a config reload), starting over from it
+    pub fn set_base(&mut self, base: Duration) {
+        self.base = base;
+        self.max = self.max.max(base);
+        self.current = base;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_grows_when_idle_and_resets_when_busy() {
+        let mut backoff = PollBackoff::new(Duration::from_secs(5), Duration::from_secs(60), 2.0);
+
+        let idle: Vec<_> = (0..5).map(|_| backoff.record(0).as_secs()).collect();
+        assert_eq!(idle, [10, 20, 40, 60, 60]);
+
+        assert_eq!(backoff.record(3), Duration::from_secs(5));
+        assert_eq!(backoff.record(0), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_max_at_base_keeps_the_interval_fixed() {
+        let mut backoff = PollBackoff::new(Duration::from_secs(10), Duration::from_secs(10), 2.0);
+        for _ in 0..3 {
+            assert_eq!(backoff.record(0), Duration::from_secs(10));
+        }
+
+        // A reload to a longer base interval raises the cap with it
+        backoff.set_base(Duration::from_secs(30));
+        assert_eq!(backoff.record(0), Duration::from_secs(30));
+    }
+}
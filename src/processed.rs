@@ -0,0 +1,208 @@
+//! Tracking of receipts the verifier has already submitted
+//!
+//! Markers have two horizons: within `trust` a marker alone is enough to skip
+//! a receipt; past `trust` but within `retention` the marker is kept for audit
+//! but the caller must re-check the chain before skipping; past `retention`
+//! the marker is pruned.
+//!
+//! An optional bloom filter sits in front of the marker map so that receipts
+//! which were definitely never marked skip the map lookup entirely. Pruning
+//! rebuilds it from the retained markers, so pruned receipts stop counting
+//! towards its false-positive rate.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use crate::bloom::BloomFilter;
+
+/// Result of looking up a receipt hash in the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupStatus {
+    /// Marked recently enough to skip without further checks
+    Trusted,
+
+    /// Marked, but too long ago to trust; re-check against the chain
+    Stale,
+
+    /// Never marked, or the marker has been pruned
+    Unknown,
+}
+
+pub struct ProcessedStore {
+    trust: Duration,
+    retention: Duration,
+    /// Receipt hash -> unix timestamp (seconds) it was marked processed
+    markers: Mutex<HashMap<String, u64>>,
+    bloom: Option<Mutex<BloomFilter>>,
+}
+
+impl ProcessedStore {
+    pub fn new(trust_secs: u64, retention_secs: u64) -> Self {
+        Self {
+            trust: Duration::from_secs(trust_secs),
+            retention: Duration::from_secs(retention_secs),
+            markers: Mutex::new(HashMap::new()),
+            bloom: None,
+        }
+    }
+
+    /// Put a bloom filter sized for `capacity` receipts in front of the store
+    pub fn with_bloom_filter(mut self, capacity: usize, false_positive_rate: f64) -> Self {
+        self.bloom = Some(Mutex::new(BloomFilter::new(capacity, false_positive_rate)));
+        self
+    }
+
+    /// Look up a receipt hash
+    pub fn status(&self, receipt_hash: &str) -> DedupStatus {
+        self.status_at(receipt_hash, now_secs())
+    }
+
+    /// Record a receipt hash as processed
+    pub fn mark(&self, receipt_hash: &str) {
+        self.mark_at(receipt_hash, now_secs());
+    }
+
+    /// Drop every marker older than the retention window, returning how many were removed
+    pub fn prune(&self) -> usize {
+        self.prune_at(now_secs())
+    }
+
+    /// Number of retained markers
+    pub fn len(&self) -> usize {
+        self.markers.lock().unwrap().len()
+    }
+
+    fn status_at(&self, receipt_hash: &str, now: u64) -> DedupStatus {
+        if let Some(bloom) = &self.bloom {
+            if !bloom.lock().unwrap().might_contain(receipt_hash) {
+                return DedupStatus::Unknown;
+            }
+        }
+
+        let mut markers = self.markers.lock().unwrap();
+
+        let Some(&marked_at) = markers.get(receipt_hash) else {
+            return DedupStatus::Unknown;
+        };
+
+        let age = now.saturating_sub(marked_at);
+        if age > self.retention.as_secs() {
+            markers.remove(receipt_hash);
+            DedupStatus::Unknown
+        } else if age > self.trust.as_secs() {
+            DedupStatus::Stale
+        } else {
+            DedupStatus::Trusted
+        }
+    }
+
+    fn mark_at(&self, receipt_hash: &str, now: u64) {
+        // Held across both inserts so a concurrent prune cannot rebuild the
+        // filter in between and leave this marker out of it
+        let mut markers = self.markers.lock().unwrap();
+        if let Some(bloom) = &self.bloom {
+            bloom.lock().unwrap().insert(receipt_hash);
+        }
+        markers.insert(receipt_hash.to_string(), now);
+    }
+
+    fn prune_at(&self, now: u64) -> usize {
+        let retention = self.retention.as_secs();
+        let mut markers = self.markers.lock().unwrap();
+        let before = markers.len();
+        markers.retain(|_, marked_at| now.saturating_sub(*marked_at) <= retention);
+        let pruned = before - markers.len();
+
+        if let Some(bloom) = self.bloom.as_ref().filter(|_| pruned > 0) {
+            let mut bloom = bloom.lock().unwrap();
+            bloom.clear();
+            for receipt_hash in markers.keys() {
+                bloom.insert(receipt_hash);
+            }
+        }
+        pruned
+    }
+}
+
+fn now_secs() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRUST: u64 = 60;
+    const RETENTION: u64 = 3600;
+
+    #[test]
+    fn test_marker_within_trust_is_skipped() {
+        let store = ProcessedStore::new(TRUST, RETENTION);
+        store.mark_at("0xabc", 1_000);
+
+        assert_eq!(store.status_at("0xabc", 1_000 + TRUST), DedupStatus::Trusted);
+    }
+
+    #[test]
+    fn test_marker_past_trust_is_rechecked_but_retained() {
+        let store = ProcessedStore::new(TRUST, RETENTION);
+        store.mark_at("0xabc", 1_000);
+
+        assert_eq!(store.status_at("0xabc", 1_000 + TRUST + 1), DedupStatus::Stale);
+        assert_eq!(store.prune_at(1_000 + TRUST + 1), 0);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_marker_past_retention_is_pruned() {
+        let store = ProcessedStore::new(TRUST, RETENTION);
+        store.mark_at("0xabc", 1_000);
+        store.mark_at("0xdef", 1_000 + RETENTION);
+
+        assert_eq!(store.prune_at(1_000 + RETENTION + 1), 1);
+        assert_eq!(store.status_at("0xabc", 1_000 + RETENTION + 1), DedupStatus::Unknown);
+        assert_eq!(store.status_at("0xdef", 1_000 + RETENTION + 1), DedupStatus::Trusted);
+    }
+
+    #[test]
+    fn test_bloom_filter_never_hides_a_marked_receipt() {
+        let store = ProcessedStore::new(TRUST, RETENTION).with_bloom_filter(100, 0.01);
+
+        for i in 0..1_000 {
+            store.mark_at(&format!("0x{:064x}", i), 1_000);
+        }
+        for i in 0..1_000 {
+            assert_eq!(store.status_at(&format!("0x{:064x}", i), 1_000), DedupStatus::Trusted);
+        }
+
+        assert_eq!(store.status_at("0xnever", 1_000), DedupStatus::Unknown);
+    }
+
+    #[test]
+    fn test_bloom_false_positive_rate_stays_bounded_across_prunes() {
+        let store = ProcessedStore::new(TRUST, RETENTION).with_bloom_filter(1_000, 0.01);
+
+        // Ten generations of 1,000 receipts, each pruned once the next is in
+        for generation in 0..10u64 {
+            let now = 1_000 + generation * (RETENTION + 1);
+            for i in 0..1_000 {
+                store.mark_at(&format!("0x{:032x}{:032x}", generation, i), now);
+            }
+            store.prune_at(now);
+            assert_eq!(store.len(), 1_000);
+        }
+
+        let bloom = store.bloom.as_ref().unwrap().lock().unwrap();
+        let false_positives = (0..10_000)
+            .filter(|i| bloom.might_contain(&format!("unseen-{}", i)))
+            .count();
+        // Allow generous slack over the 1% target
+        assert!(false_positives < 300, "false positives: {}", false_positives);
+    }
+
+    #[test]
+    fn test_unknown_receipt() {
+        let store = ProcessedStore::new(TRUST, RETENTION);
+        assert_eq!(store.status("0xnever"), DedupStatus::Unknown);
+    }
+}
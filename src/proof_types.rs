@@ -0,0 +1,216 @@
+//! Validation policy per proof type
+//!
+//! Each proof type a request may name maps to a handler: the journal schema
+//! its guest commits to, whether the guest may halt with a non-zero exit code
+//! or commit an empty journal, and any further journal checks. The built-in
+//! `iot_*` types are always registered; operators add their own under
+//! `[proof_types]`, and code can register more with
+//! [`ProofTypeRegistry::register`]. Receipts of unregistered types are rejected.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::config::Config;
+use crate::error::VerifierError;
+use crate::journal_rules::JournalRule;
+use crate::journal_schema::{JournalSchema, PROOF_TYPES};
+use crate::proof_verifier::VerificationPolicy;
+
+/// A check run on the journal of every receipt of one proof type
+pub type JournalCheck = Arc<dyn Fn(&[u8]) -> Result<()> + Send + Sync>;
+
+/// How receipts of one proof type are validated
+#[derive(Clone)]
+pub struct ProofTypeHandler {
+    schema: JournalSchema,
+    /// Accept guests that halt with a non-zero exit code
+    allow_nonzero_exit: bool,
+    /// Accept receipts whose journal is empty
+    allow_empty_journal: bool,
+    /// Run in order after the schema check
+    checks: Vec<JournalCheck>,
+}
+
+impl ProofTypeHandler {
+    /// Validate journals against `schema` only, rejecting non-zero exits and empty journals
+    pub fn new(schema: JournalSchema) -> Self {
+        Self {
+            schema,
+            allow_nonzero_exit: false,
+            allow_empty_journal: false,
+            checks: Vec::new(),
+        }
+    }
+
+    /// Accept guests of this type that halt with a non-zero exit code
+    pub fn with_nonzero_exit(mut self, allow: bool) -> Self {
+        self.allow_nonzero_exit = allow;
+        self
+    }
+
+    /// Accept receipts of this type whose journal is empty
+    pub fn with_empty_journal(mut self, allow: bool) -> Self {
+        self.allow_empty_journal = allow;
+        self
+    }
+
+    /// Add a check on the journal, run after the schema check
+    pub fn with_check(mut self, check: impl Fn(&[u8]) -> Result<()> + Send + Sync + 'static) -> Self {
+        self.checks.push(Arc::new(check));
+        self
+    }
+
+    /// Add operator-configured journal rules
+    pub fn with_rules(self, rules: Vec<JournalRule>) -> Self {
+        if rules.is_empty() {
+            return self;
+        }
+        self.with_check(move |journal| rules.iter().try_for_each(|rule| rule.check(journal)))
+    }
+
+    pub fn allows_nonzero_exit(&self) -> bool {
+        self.allow_nonzero_exit
+    }
+
+    /// Check a journal, returning its decoded fields when the schema has a layout
+    pub fn check_journal(&self, journal: &[u8]) -> Result<Option<Value>> {
+        if journal.is_empty() && !self.allow_empty_journal {
+            return Err(VerifierError::ProofVerification("Receipt has empty journal".to_string()).into());
+        }
+
+        let summary = self.schema.check(journal)?;
+        for check in &self.checks {
+            check(journal)?;
+        }
+        Ok(summary)
+    }
+}
+
+/// Handlers by proof type
+#[derive(Clone, Default)]
+pub struct ProofTypeRegistry {
+    handlers: HashMap<String, ProofTypeHandler>,
+}
+
+impl ProofTypeRegistry {
+    /// The built-in proof types with default settings
+    pub fn builtin() -> Self {
+        Self::from_config(&Config::default()).expect("default proof types are valid")
+    }
+
+    /// The built-in proof types plus those under `proof_types`, with the
+    /// configured schemas, exit code policy and journal rules
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Self::from_policy(&VerificationPolicy::from_config(config))
+    }
+
+    /// The proof types of a verification policy, as in [`Self::from_config`]
+    pub fn from_policy(policy: &VerificationPolicy) -> Result<Self> {
+        if let Some(unknown) = policy.journal_schemas.keys().find(|proof_type| !PROOF_TYPES.contains(&proof_type.as_str())) {
+            return Err(VerifierError::Config(
+                format!("journal_schemas has a layout for unknown proof type `{}`", unknown)
+            ).into());
+        }
+
+        let mut registry = Self::default();
+        for proof_type in PROOF_TYPES {
+            let layout = policy.journal_schemas.get(proof_type).map(Vec::as_slice);
+            let schema = JournalSchema::builtin(proof_type, layout, policy.max_privacy_journal_bytes)?;
+            let handler = ProofTypeHandler::new(schema)
+                .with_empty_journal(proof_type == "iot_privacy" && policy.allow_empty_privacy_journals);
+            registry.register(proof_type, handler);
+        }
+        for (proof_type, custom) in &policy.proof_types {
+            if registry.handlers.contains_key(proof_type) {
+                return Err(VerifierError::Config(
+                    format!("proof_types cannot redefine proof type {:?}", proof_type)
+                ).into());
+            }
+            let schema = JournalSchema::new(
+                proof_type,
+                !custom.allow_empty_journal,
+                custom.max_journal_bytes,
+                custom.journal_schema.as_deref(),
+            )?;
+            let handler = ProofTypeHandler::new(schema)
+                .with_empty_journal(custom.allow_empty_journal)
+                .with_nonzero_exit(custom.allow_nonzero_exit);
+            registry.register(proof_type, handler);
+        }
+
+        for proof_type in &policy.nonzero_exit_proof_types {
+            if let Some(handler) = registry.handlers.get_mut(proof_type) {
+                handler.allow_nonzero_exit = true;
+            }
+        }
+        for (proof_type, rules) in &policy.journal_rules {
+            let rules = rules.iter().map(|rule| rule.parse()).collect::<Result<Vec<JournalRule>>>()?;
+            if let Some(handler) = registry.handlers.get_mut(proof_type) {
+                *handler = handler.clone().with_rules(rules);
+            }
+        }
+
+        Ok(registry)
+    }
+
+    /// Register a handler for `proof_type`, returning the one it replaces
+    pub fn register(&mut self, proof_type: impl Into<String>, handler: ProofTypeHandler) -> Option<ProofTypeHandler> {
+        self.handlers.insert(proof_type.into(), handler)
+    }
+
+    /// Handler of `proof_type`, or an error if it is not registered
+    pub fn get(&self, proof_type: &str) -> Result<&ProofTypeHandler> {
+        self.handlers.get(proof_type).ok_or_else(|| {
+            VerifierError::ProofVerification(format!("Unknown proof type: {}", proof_type)).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProofTypeConfig;
+
+    #[test]
+    fn test_config_populates_registry() {
+        let registry = ProofTypeRegistry::from_config(&Config {
+            proof_types: HashMap::from([("iot_telemetry".to_string(), ProofTypeConfig {
+                max_journal_bytes: Some(4),
+                allow_nonzero_exit: true,
+                ..ProofTypeConfig::default()
+            })]),
+            nonzero_exit_proof_types: vec!["iot_compute".to_string()],
+            journal_rules: HashMap::from([("iot_telemetry".to_string(), vec!["u8@0 == 1".to_string()])]),
+            ..Config::default()
+        }).unwrap();
+
+        let telemetry = registry.get("iot_telemetry").unwrap();
+        assert!(telemetry.allows_nonzero_exit());
+        assert!(telemetry.check_journal(&[1, 0]).is_ok());
+        // Size bound, journal rule and non-empty requirement all apply
+        assert!(telemetry.check_journal(&[1; 5]).is_err());
+        assert!(telemetry.check_journal(&[2]).is_err());
+        assert!(telemetry.check_journal(&[]).is_err());
+
+        assert!(registry.get("iot_compute").unwrap().allows_nonzero_exit());
+        assert!(!registry.get("iot_validation").unwrap().allows_nonzero_exit());
+        let err = registry.get("iot_other").err().unwrap();
+        assert!(err.to_string().contains("Unknown proof type: iot_other"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_schema_and_redefined_types_are_refused() {
+        let unknown_layout = Config {
+            journal_schemas: HashMap::from([("iot_telemetry".to_string(), vec!["u32".to_string()])]),
+            ..Config::default()
+        };
+        assert!(ProofTypeRegistry::from_config(&unknown_layout).is_err());
+
+        let redefined = Config {
+            proof_types: HashMap::from([("iot_validation".to_string(), ProofTypeConfig::default())]),
+            ..Config::default()
+        };
+        assert!(ProofTypeRegistry::from_config(&redefined).is_err());
+    }
+}
@@ -1,111 +1,788 @@
 //! RISC Zero proof verification logic
 
 use anyhow::Result;
-use risc0_zkvm::Receipt;
+use bincode::Options;
+use std::collections::{HashMap, HashSet};
+use risc0_zkp::verify::VerificationError;
+use risc0_zkvm::{
+    Assumptions, ExitCode, InnerReceipt, MaybePruned, Output, Receipt, ReceiptClaim, SegmentReceipt,
+    VerifierContext,
+};
+use risc0_zkvm::sha::{Digest as Risc0Digest, Digestible};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
+use tracing::{debug, error, warn};
+use crate::config::{Config, ProofTypeConfig};
+use crate::envelope::Envelope;
 use crate::error::VerifierError;
+use crate::proof_types::ProofTypeRegistry;
+use crate::streaming::{check_continuation, next_pre_state, stream_composite, SegmentCheck};
+use crate::types::{ProofKind, ReceiptFormat};
 
+#[derive(Clone)]
 pub struct ProofVerifier {
     allowed_image_ids: Vec<String>,
+    /// Normalized form of `allowed_image_ids` for O(1) membership checks
+    allowed_image_set: HashSet<[u8; 32]>,
+    /// Skip the image ID check entirely (only valid with an empty allow-list)
+    allow_any_image: bool,
+    expected_post_state: Option<Risc0Digest>,
+    /// Accept executions that paused rather than halted
+    accept_paused: bool,
+    /// Journal and exit code policy of each accepted proof type
+    proof_types: ProofTypeRegistry,
+    /// Image legacy receipts are verified against when their claim cannot be extracted
+    claim_fallback_image: Option<Risc0Digest>,
+    /// Registered signing address of each device, for enveloped receipts
+    device_keys: HashMap<String, String>,
+    /// Decode composite receipts one segment at a time
+    streaming: bool,
+    /// Seal check for individual segments when streaming or on the claim fallback path
+    segment_integrity: SegmentCheck,
+    /// Full check of a decoded receipt against its image ID
+    receipt_integrity: ReceiptCheck,
+}
+
+/// Everything that decides whether a receipt is accepted, taken from the
+/// configuration. Serializable so the sandboxed child builds its verifier
+/// from exactly the policy the in-process one uses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerificationPolicy {
+    pub allowed_image_ids: Vec<String>,
+    pub allow_any_image: bool,
+    pub expected_post_state_digest: Option<String>,
+    pub accept_paused_receipts: bool,
+    pub nonzero_exit_proof_types: Vec<String>,
+    pub fallback_image_id: Option<String>,
+    pub allow_claim_fallback: bool,
+    pub device_keys: HashMap<String, String>,
+    pub allow_empty_privacy_journals: bool,
+    pub streaming_verification: bool,
+    pub journal_rules: HashMap<String, Vec<String>>,
+    pub journal_schemas: HashMap<String, Vec<String>>,
+    pub max_privacy_journal_bytes: usize,
+    pub proof_types: HashMap<String, ProofTypeConfig>,
+}
+
+impl VerificationPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            allowed_image_ids: config.allowed_image_ids.clone(),
+            allow_any_image: config.allow_any_image,
+            expected_post_state_digest: config.expected_post_state_digest.clone(),
+            accept_paused_receipts: config.accept_paused_receipts,
+            nonzero_exit_proof_types: config.nonzero_exit_proof_types.clone(),
+            fallback_image_id: config.fallback_image_id.clone(),
+            allow_claim_fallback: config.allow_claim_fallback,
+            device_keys: config.device_keys.clone(),
+            allow_empty_privacy_journals: config.allow_empty_privacy_journals,
+            streaming_verification: config.streaming_verification,
+            journal_rules: config.journal_rules.clone(),
+            journal_schemas: config.journal_schemas.clone(),
+            max_privacy_journal_bytes: config.max_privacy_journal_bytes,
+            proof_types: config.proof_types.clone(),
+        }
+    }
+}
+
+impl Default for VerificationPolicy {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
+/// Seal, image ID, exit status and journal check of a whole receipt
+pub type ReceiptCheck = fn(&Receipt, Risc0Digest) -> Result<(), VerificationError>;
+
+fn verify_segment_integrity(segment: &SegmentReceipt) -> Result<(), VerificationError> {
+    segment.verify_integrity_with_context(&VerifierContext::default())
+}
+
+/// `Receipt::verify` without its success-only exit code requirement; exit
+/// codes are judged by [`ClaimState::validate`] under the configured policy
+fn verify_receipt(receipt: &Receipt, image_id: Risc0Digest) -> Result<(), VerificationError> {
+    receipt.verify_integrity_with_context(&VerifierContext::default())?;
+    if receipt.get_claim()?.pre.digest() != image_id {
+        return Err(VerificationError::ImageVerificationError);
+    }
+    Ok(())
+}
+
+/// Check that a receipt's claim commits to its journal with no assumptions,
+/// as `Receipt::verify` does, for receipts whose seals were already checked
+fn check_journal_binding(receipt: &Receipt) -> Result<(), VerificationError> {
+    let claim = receipt.get_claim()?;
+    let expected_output = Output {
+        journal: MaybePruned::Pruned(receipt.journal.digest()),
+        assumptions: Assumptions(vec![]).into(),
+    };
+    
+    let empty_output = claim.output.is_none() && receipt.journal.bytes.is_empty();
+    if claim.output.digest() != expected_output.digest() && !empty_output {
+        return Err(VerificationError::JournalDigestMismatch);
+    }
+    Ok(())
+}
+
+/// Claim-level condition on a receipt that verified but is worth surfacing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimWarning {
+    /// The guest paused (to be resumed later) rather than halting
+    Paused { user_exit: u32 },
+    
+    /// The guest halted with a non-zero user exit code, which its proof
+    /// type allows
+    NonZeroExit { user_exit: u32 },
+}
+
+/// System state transition and exit status proven by a receipt claim
+#[derive(Debug, Clone)]
+pub struct ClaimState {
+    pub pre_state_digest: Risc0Digest,
+    pub post_state_digest: Risc0Digest,
+    pub exit_code: ExitCode,
+}
+
+impl ClaimState {
+    pub fn from_claim(claim: &ReceiptClaim) -> Self {
+        Self {
+            pre_state_digest: claim.pre.digest(),
+            post_state_digest: claim.post.digest(),
+            exit_code: claim.exit_code,
+        }
+    }
+    
+    /// Reject claims whose post-state indicates an unhalted, failed or
+    /// anomalous execution, returning warnings for accepted but noteworthy exits
+    pub fn validate(
+        &self,
+        expected_post_state: Option<&Risc0Digest>,
+        accept_paused: bool,
+        allow_nonzero_exit: bool,
+    ) -> Result<Vec<ClaimWarning>> {
+        let mut warnings = Vec::new();
+        
+        match self.exit_code {
+            // Executions that stopped on a split or the session limit never terminated
+            ExitCode::SystemSplit | ExitCode::SessionLimit => {
+                return Err(VerifierError::ProofVerification(
+                    format!("Execution did not terminate (exit code {:?})", self.exit_code)
+                ).into());
+            }
+            ExitCode::Paused(user_exit) if !accept_paused => {
+                return Err(VerifierError::ProofVerification(
+                    format!("Execution paused (user exit {}) and paused receipts are not accepted", user_exit)
+                ).into());
+            }
+            ExitCode::Paused(user_exit) => warnings.push(ClaimWarning::Paused { user_exit }),
+            ExitCode::Halted(0) => {}
+            // A guest that failed an assertion halts non-zero; its journal proves nothing
+            ExitCode::Halted(user_exit) if !allow_nonzero_exit => {
+                return Err(VerifierError::ProofVerification(
+                    format!("guest exited with code {}", user_exit)
+                ).into());
+            }
+            ExitCode::Halted(user_exit) => warnings.push(ClaimWarning::NonZeroExit { user_exit }),
+        }
+        
+        // A terminated execution always advances the system state
+        if self.post_state_digest == self.pre_state_digest {
+            return Err(VerifierError::ProofVerification(
+                "Post-state is identical to pre-state".to_string()
+            ).into());
+        }
+        
+        if let Some(expected) = expected_post_state {
+            if &self.post_state_digest != expected {
+                return Err(VerifierError::ProofVerification(format!(
+                    "Unexpected post-state digest: expected 0x{}, got 0x{}",
+                    hex::encode(expected), hex::encode(self.post_state_digest)
+                )).into());
+            }
+        }
+        
+        Ok(warnings)
+    }
+}
+
+/// A receipt decoded from its wire format
+pub struct DecodedReceipt {
+    pub receipt: Receipt,
+    pub format: ReceiptFormat,
+    /// Whether the format had to be found by probing rather than taken from the hint
+    pub probed: bool,
+}
+
+impl ReceiptFormat {
+    fn decode(self, bytes: &[u8]) -> Result<Receipt> {
+        let receipt = match self {
+            ReceiptFormat::Bincode => bincode_options(bytes.len()).deserialize(bytes)?,
+            ReceiptFormat::Risc0Serde => {
+                let chunks = bytes.chunks_exact(4);
+                if !chunks.remainder().is_empty() {
+                    return Err(anyhow::anyhow!("length {} is not a multiple of 4", bytes.len()));
+                }
+                let words: Vec<u32> = chunks
+                    .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+                    .collect();
+                risc0_zkvm::serde::from_slice(&words)?
+            }
+            ReceiptFormat::Postcard => postcard::from_bytes(bytes)?,
+            ReceiptFormat::Json => serde_json::from_slice(bytes)?,
+            ReceiptFormat::Cbor => ciborium::from_reader(bytes)?,
+        };
+        Ok(receipt)
+    }
+}
+
+/// bincode as `bincode::deserialize` reads it, but never reading more than
+/// `limit` bytes in total. A length prefix larger than what is left of the
+/// input then fails before anything is allocated for it, however large the
+/// collection it claims.
+pub fn bincode_options(limit: usize) -> impl Options + Copy {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit as u64)
+}
+
+/// Decode a receipt, trying the hinted format first and probing the rest on failure
+pub fn decode_receipt(bytes: &[u8], hint: Option<ReceiptFormat>) -> Result<DecodedReceipt> {
+    let mut failures = Vec::new();
+    
+    if let Some(format) = hint {
+        match format.decode(bytes) {
+            Ok(receipt) => return Ok(DecodedReceipt { receipt, format, probed: false }),
+            Err(e) => {
+                warn!("Receipt is not valid {:?} as hinted ({}), probing other formats", format, e);
+                failures.push(format!("{:?}: {}", format, e));
+            }
+        }
+    }
+    
+    // bincode first, then whatever the leading byte suggests, then the rest
+    let mut probes = Vec::new();
+    let candidates = std::iter::once(ReceiptFormat::Bincode)
+        .chain(ReceiptFormat::sniff(bytes))
+        .chain(ReceiptFormat::ALL);
+    for format in candidates {
+        if Some(format) != hint && !probes.contains(&format) {
+            probes.push(format);
+        }
+    }
+    
+    for format in probes {
+        match format.decode(bytes) {
+            Ok(receipt) => return Ok(DecodedReceipt { receipt, format, probed: true }),
+            Err(e) => failures.push(format!("{:?}: {}", format, e)),
+        }
+    }
+    
+    Err(VerifierError::ProofVerification(
+        format!("Failed to deserialize receipt ({})", failures.join("; "))
+    ).into())
+}
+
+/// Normalize a hex image ID (with or without 0x, any case) to its raw bytes
+pub fn normalize_image_id(image_id: &str) -> Option<[u8; 32]> {
+    let bytes = hex::decode(image_id.trim().trim_start_matches("0x")).ok()?;
+    bytes.try_into().ok()
+}
+
+/// Parse a 0x-prefixed (or bare) hex string into a RISC Zero digest
+pub fn parse_digest(digest_hex: &str) -> Result<Risc0Digest> {
+    let bytes = hex::decode(digest_hex.trim_start_matches("0x"))?;
+    Risc0Digest::try_from(bytes.as_slice())
+        .map_err(|_| VerifierError::Config(format!("Digest must be 32 bytes, got {}", bytes.len())).into())
+}
+
+/// Canonical journal hash of an empty journal: Keccak256 of zero bytes.
+///
+/// Receipts carrying it also set `journal_empty`, so "no journal" is never
+/// confused with a missing hash.
+pub const EMPTY_JOURNAL_HASH: [u8; 32] = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
+/// Whether a receipt came with a device signature
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceSignature {
+    /// Bare receipt with no envelope
+    #[default]
+    Absent,
+    
+    /// Enveloped and signed by the device's registered key
+    Verified,
 }
 
 pub struct VerifiedProof {
     receipt: Receipt,
+    warnings: Vec<ClaimWarning>,
+    receipt_hash: Vec<u8>,
+    image_id: [u8; 32],
+    peak_decoded_bytes: u64,
+    device_signature: DeviceSignature,
+    proof_kind: ProofKind,
+    exit_code: ExitCode,
+    journal_summary: Option<serde_json::Value>,
+}
+
+/// What the pipeline needs from a verified receipt, whether verified in
+/// process or in the sandbox
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationSummary {
+    pub receipt_hash: Vec<u8>,
+    /// Image ID the receipt was proven against
+    #[serde(default)]
+    pub image_id: Vec<u8>,
+    pub journal_hash: Vec<u8>,
+    #[serde(default)]
+    pub journal_empty: bool,
+    #[serde(default)]
+    pub warnings: Vec<ClaimWarning>,
+    /// Most encoded receipt bytes decoded into memory at once
+    #[serde(default)]
+    pub peak_decoded_bytes: u64,
+    #[serde(default)]
+    pub device_signature: DeviceSignature,
+    #[serde(default)]
+    pub proof_kind: ProofKind,
+    /// Journal fields decoded with the proof type's configured layout
+    #[serde(default)]
+    pub journal_summary: Option<serde_json::Value>,
 }
 
 impl VerifiedProof {
     /// Get the journal hash (Keccak256 of journal bytes)
+    ///
+    /// An empty journal always hashes to [`EMPTY_JOURNAL_HASH`].
     pub fn journal_hash(&self) -> Vec<u8> {
+        if self.journal_empty() {
+            return EMPTY_JOURNAL_HASH.to_vec();
+        }
+        
         let mut hasher = Keccak256::new();
         hasher.update(&self.receipt.journal.bytes);
         hasher.finalize().to_vec()
     }
     
+    /// Whether the guest committed nothing to the journal
+    pub fn journal_empty(&self) -> bool {
+        self.receipt.journal.bytes.is_empty()
+    }
+    
+    /// Decode the journal as the guest committed it with `env::commit`
+    #[allow(dead_code)]
+    pub fn decode_journal<T: DeserializeOwned>(&self) -> Result<T> {
+        self.receipt.journal.decode().map_err(|e| {
+            VerifierError::ProofVerification(format!("Failed to decode journal: {}", e)).into()
+        })
+    }
+    
+    /// How the guest execution ended; non-zero halts only pass for proof
+    /// types that allow them
+    #[allow(dead_code)]
+    pub fn exit_code(&self) -> ExitCode {
+        self.exit_code
+    }
+    
+    /// Conditions on the claim that were accepted but should be surfaced
+    #[allow(dead_code)]
+    pub fn warnings(&self) -> &[ClaimWarning] {
+        &self.warnings
+    }
+    
+    pub fn summary(&self) -> VerificationSummary {
+        VerificationSummary {
+            receipt_hash: self.receipt_hash(),
+            image_id: self.image_id.to_vec(),
+            journal_hash: self.journal_hash(),
+            journal_empty: self.journal_empty(),
+            warnings: self.warnings.clone(),
+            peak_decoded_bytes: self.peak_decoded_bytes,
+            device_signature: self.device_signature,
+            proof_kind: self.proof_kind,
+            journal_summary: self.journal_summary.clone(),
+        }
+    }
+    
     /// Get the receipt hash (Keccak256 of serialized receipt)
     pub fn receipt_hash(&self) -> Vec<u8> {
-        let receipt_bytes = bincode::serialize(&self.receipt)
-            .expect("Failed to serialize receipt");
-        
-        let mut hasher = Keccak256::new();
-        hasher.update(&receipt_bytes);
-        hasher.finalize().to_vec()
+        self.receipt_hash.clone()
     }
 }
 
+/// Keccak256 of the bincode serialization of a receipt
+fn hash_receipt(receipt: &Receipt) -> Vec<u8> {
+    let receipt_bytes = bincode::serialize(receipt)
+        .expect("Failed to serialize receipt");
+    
+    let mut hasher = Keccak256::new();
+    hasher.update(&receipt_bytes);
+    hasher.finalize().to_vec()
+}
+
 impl ProofVerifier {
     pub fn new(allowed_image_ids: Vec<String>) -> Self {
-        Self { allowed_image_ids }
+        let allowed_image_set = allowed_image_ids.iter()
+            .filter_map(|id| {
+                let normalized = normalize_image_id(id);
+                if normalized.is_none() {
+                    warn!("Ignoring malformed allowed image ID: {}", id);
+                }
+                normalized
+            })
+            .collect();
+        
+        Self {
+            allowed_image_ids,
+            allowed_image_set,
+            allow_any_image: false,
+            expected_post_state: None,
+            accept_paused: false,
+            proof_types: ProofTypeRegistry::builtin(),
+            claim_fallback_image: None,
+            device_keys: HashMap::new(),
+            streaming: false,
+            segment_integrity: verify_segment_integrity,
+            receipt_integrity: verify_receipt,
+        }
     }
     
-    /// Verify a RISC Zero proof
-    pub fn verify_proof(&self, receipt_bytes: &[u8], proof_type: &str) -> Result<VerifiedProof> {
-        // Deserialize the receipt
-        let receipt: Receipt = bincode::deserialize(receipt_bytes)
-            .map_err(|e| VerifierError::ProofVerification(format!("Failed to deserialize receipt: {}", e)))?;
-        
-        // Extract image ID from receipt claim
-        let _claim = receipt.get_claim().map_err(|e| VerifierError::ProofVerification(format!("Failed to get claim: {}", e)))?;
-        
-        // For RISC Zero 0.21, we'll use a placeholder image ID validation
-        // In production, this would need proper image ID extraction from the receipt
-        let image_id = "placeholder_image_id";
+    /// Whether an image ID is on the allow-list
+    pub fn is_image_allowed(&self, image_id: &[u8; 32]) -> bool {
+        self.allowed_image_set.contains(image_id)
+    }
+    
+    /// Reject a proof request naming an image this verifier does not trust,
+    /// so a request cannot vouch for its own image
+    pub fn check_requested_image(&self, expected_image_id: &str) -> Result<()> {
+        if self.allow_any_image {
+            return Ok(());
+        }
         
-        // Check if image ID is allowed (simplified for now)
-        if !self.allowed_image_ids.is_empty() && !self.allowed_image_ids.iter().any(|allowed| allowed.contains("placeholder")) {
+        let trusted = normalize_image_id(expected_image_id).is_some_and(|image_id| {
+            self.is_image_allowed(&image_id)
+                || self.claim_fallback_image.is_some_and(|fallback| fallback.as_bytes() == image_id)
+        });
+        if !trusted {
             return Err(VerifierError::InvalidImageId {
                 expected: self.allowed_image_ids.join(", "),
-                actual: image_id.to_string(),
+                actual: expected_image_id.to_string(),
             }.into());
         }
         
-        // Verify the proof (simplified verification for deployment)
-        // In production, this would use proper image ID verification
-        if receipt.journal.bytes.is_empty() {
-            return Err(VerifierError::ProofVerification("Receipt has empty journal".to_string()).into());
+        Ok(())
+    }
+    
+    /// Create a verifier with all policy options taken from the configuration
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Self::from_policy(&VerificationPolicy::from_config(config))
+    }
+    
+    /// Create a verifier enforcing `policy`
+    pub fn from_policy(policy: &VerificationPolicy) -> Result<Self> {
+        let expected_post_state = policy.expected_post_state_digest
+            .as_deref()
+            .map(parse_digest)
+            .transpose()?;
+        
+        let claim_fallback_image = match (&policy.fallback_image_id, policy.allow_claim_fallback) {
+            (Some(image_id), true) => Some(parse_digest(image_id)?),
+            _ => None,
+        };
+        
+        if risc0_zkvm::is_dev_mode() {
+            warn!("RISC0_DEV_MODE is set: fake receipts will pass verification");
         }
         
-        // Additional validation based on proof type
-        match proof_type {
-            "iot_validation" => {
-                // Ensure journal contains expected validation data
-                if receipt.journal.bytes.is_empty() {
-                    return Err(VerifierError::ProofVerification("Validation proof has empty journal".to_string()).into());
-                }
+        Ok(Self {
+            allow_any_image: policy.allow_any_image,
+            expected_post_state,
+            accept_paused: policy.accept_paused_receipts,
+            proof_types: ProofTypeRegistry::from_policy(policy)?,
+            claim_fallback_image,
+            device_keys: policy.device_keys.clone(),
+            streaming: policy.streaming_verification,
+            ..Self::new(policy.allowed_image_ids.clone())
+        })
+    }
+    
+    /// Replace the whole-receipt check, e.g. with a deliberately slow one
+    #[cfg(test)]
+    pub fn with_receipt_check(mut self, check: ReceiptCheck) -> Self {
+        self.receipt_integrity = check;
+        self
+    }
+    
+    /// Verify a receipt from `device_id`, which may be wrapped in a
+    /// device-signed envelope; the envelope signature is checked against the
+    /// device registry before the proof itself
+    pub fn verify_device_proof(
+        &self,
+        receipt_bytes: &[u8],
+        device_id: &str,
+        proof_type: &str,
+        expected_image_id: Option<&str>,
+        format_hint: Option<ReceiptFormat>,
+    ) -> Result<VerifiedProof> {
+        let Some(envelope) = Envelope::parse(receipt_bytes)? else {
+            return self.verify_proof(receipt_bytes, proof_type, expected_image_id, format_hint);
+        };
+        
+        let registered = self.device_keys.get(device_id).ok_or_else(|| VerifierError::ProofVerification(
+            format!("Enveloped receipt from device {} which has no registered key", device_id)
+        ))?;
+        let signer = envelope.signer()?;
+        if !signer.eq_ignore_ascii_case(registered) {
+            return Err(VerifierError::ProofVerification(format!(
+                "Envelope signed by {}, not the registered key {} of device {}", signer, registered, device_id
+            )).into());
+        }
+        
+        let mut proof = self.verify_proof(envelope.payload, proof_type, expected_image_id, format_hint)?;
+        proof.device_signature = DeviceSignature::Verified;
+        Ok(proof)
+    }
+    
+    /// Verify a RISC Zero proof, optionally requiring the image ID the
+    /// proof request named in addition to the allow-list
+    pub fn verify_proof(
+        &self,
+        receipt_bytes: &[u8],
+        proof_type: &str,
+        expected_image_id: Option<&str>,
+        format_hint: Option<ReceiptFormat>,
+    ) -> Result<VerifiedProof> {
+        // Checked before the receipt is even decoded
+        if let Some(expected) = expected_image_id {
+            self.check_requested_image(expected)?;
+        }
+        let handler = self.proof_types.get(proof_type)?;
+        
+        // Stream bincode composite receipts when enabled, so segment seals are
+        // verified and dropped one at a time
+        let streamed = match format_hint {
+            Some(ReceiptFormat::Bincode) | None if self.streaming => {
+                stream_composite(receipt_bytes, self.segment_integrity)?
             }
-            "iot_privacy" => {
-                // Privacy proofs should have minimal journal data
-                // (actual sensor data should be hidden)
+            _ => None,
+        };
+        
+        let (receipt, receipt_hash, peak_decoded_bytes, seals_verified) = match streamed {
+            Some(streamed) => {
+                debug!("Streamed composite receipt: {:?}", streamed.stats);
+                (streamed.receipt, streamed.receipt_hash, streamed.stats.peak_segment_bytes as u64, true)
             }
-            "iot_compute" => {
-                // Compute proofs should have computation results in journal
-                if receipt.journal.bytes.is_empty() {
-                    return Err(VerifierError::ProofVerification("Compute proof has empty journal".to_string()).into());
+            None => {
+                // Deserialize the receipt
+                let decoded = decode_receipt(receipt_bytes, format_hint)?;
+                if decoded.probed {
+                    debug!("Receipt format detected by probing: {:?}", decoded.format);
                 }
+                let receipt_hash = hash_receipt(&decoded.receipt);
+                (decoded.receipt, receipt_hash, receipt_bytes.len() as u64, false)
             }
-            _ => {
-                return Err(VerifierError::ProofVerification(format!("Unknown proof type: {}", proof_type)).into());
+        };
+        
+        // Extract image ID from receipt claim
+        let (claim_state, via_fallback) = match receipt.get_claim() {
+            Ok(claim) => (ClaimState::from_claim(&claim), false),
+            Err(e) => match self.claim_fallback_image {
+                Some(fallback) => (self.verify_claim_fallback(&receipt, fallback, e, seals_verified)?, true),
+                None => return Err(VerifierError::ProofVerification(format!("Failed to get claim: {}", e)).into()),
+            },
+        };
+        
+        // Reject executions that did not terminate in a well-formed state
+        let warnings = claim_state.validate(self.expected_post_state.as_ref(), self.accept_paused, handler.allows_nonzero_exit())?;
+        for warning in &warnings {
+            warn!("Accepting receipt with claim warning: {:?}", warning);
+        }
+        
+        // The image ID is the digest of the pre-execution system state
+        let image_id: [u8; 32] = claim_state.pre_state_digest.as_bytes().try_into()
+            .expect("digest is 32 bytes");
+        
+        // The receipt must be for the image the request asked to be verified
+        if let Some(expected) = expected_image_id {
+            if normalize_image_id(expected) != Some(image_id) {
+                return Err(VerifierError::InvalidImageId {
+                    expected: expected.to_string(),
+                    actual: format!("0x{}", hex::encode(image_id)),
+                }.into());
+            }
+        }
+        
+        // Check if image ID is allowed; an empty allow-list accepts nothing
+        // unless `allow_any_image` was set explicitly. The fallback image was
+        // already matched on the fallback path.
+        if !via_fallback && !self.allow_any_image && !self.is_image_allowed(&image_id) {
+            return Err(VerifierError::InvalidImageId {
+                expected: self.allowed_image_ids.join(", "),
+                actual: format!("0x{}", hex::encode(image_id)),
+            }.into());
+        }
+        
+        // Verify the proof itself. Streamed receipts had their segment seals
+        // and assumptions checked while streaming and fallback receipts were
+        // checked on that path, so only the journal binding is left for the former.
+        let proof_check = if via_fallback {
+            Ok(())
+        } else if seals_verified {
+            check_journal_binding(&receipt)
+        } else {
+            (self.receipt_integrity)(&receipt, claim_state.pre_state_digest)
+        };
+        proof_check.map_err(|e| VerifierError::ProofVerification(format!("Receipt failed verification: {}", e)))?;
+        
+        // The journal must have the shape its proof type commits to and
+        // pass the type's rules; only some types accept an empty journal
+        let journal_summary = handler.check_journal(&receipt.journal.bytes)?;
+        
+        let proof_kind = ProofKind::of(&receipt.inner);
+        debug!("Verified {:?} receipt", proof_kind);
+        
+        Ok(VerifiedProof {
+            receipt,
+            warnings,
+            receipt_hash,
+            image_id,
+            peak_decoded_bytes,
+            device_signature: DeviceSignature::Absent,
+            proof_kind,
+            exit_code: claim_state.exit_code,
+            journal_summary,
+        })
+    }
+    
+    /// Verify a receipt whose claim could not be extracted directly against
+    /// the configured fallback image, segment by segment.
+    ///
+    /// Only composite receipts qualify: every segment seal must verify, the
+    /// segments must chain, the first must start from the fallback image and
+    /// the last must commit to the receipt's journal with no assumptions.
+    fn verify_claim_fallback(
+        &self,
+        receipt: &Receipt,
+        fallback: Risc0Digest,
+        claim_error: VerificationError,
+        seals_verified: bool,
+    ) -> Result<ClaimState> {
+        let reject = |reason: &str| -> anyhow::Error {
+            VerifierError::ProofVerification(format!(
+                "Failed to get claim ({}) and fallback verification failed: {}", claim_error, reason
+            )).into()
+        };
+        
+        let InnerReceipt::Composite(composite) = &receipt.inner else {
+            return Err(reject("only composite receipts can use the claim fallback"));
+        };
+        let (Some(first), Some(last)) = (composite.segments.first(), composite.segments.last()) else {
+            return Err(reject("receipt has no segments"));
+        };
+        
+        // Streamed receipts had their seals checked (and dropped) while decoding
+        let mut expected_pre_state = None;
+        for segment in &composite.segments {
+            if !seals_verified {
+                (self.segment_integrity)(segment)
+                    .map_err(|e| reject(&format!("segment {} seal: {}", segment.index, e)))?;
             }
+            
+            check_continuation(segment, expected_pre_state).map_err(|e| reject(&e.to_string()))?;
+            expected_pre_state = next_pre_state(segment);
+        }
+        
+        if first.claim.pre.digest() != fallback {
+            return Err(reject(&format!(
+                "receipt starts from image 0x{}, not the fallback image", hex::encode(first.claim.pre.digest())
+            )));
+        }
+        
+        // Bind the journal through the final segment's own output
+        let output = last.claim.output.as_value().ok()
+            .and_then(|output| output.as_ref())
+            .ok_or_else(|| reject("final segment has no output"))?;
+        if output.journal.digest() != receipt.journal.digest() {
+            return Err(reject("journal does not match the final segment's output"));
         }
+        if output.assumptions.digest() != Assumptions(vec![]).digest() {
+            return Err(reject("receipt has unresolved assumptions"));
+        }
+        
+        error!(
+            "CLAIM FALLBACK USED: receipt claim could not be extracted ({}); verified against fallback image 0x{}",
+            claim_error, hex::encode(fallback)
+        );
         
-        Ok(VerifiedProof { receipt })
+        Ok(ClaimState {
+            pre_state_digest: fallback,
+            post_state_digest: last.claim.post.digest(),
+            exit_code: last.claim.exit_code,
+        })
+    }
+    
+    /// Allowed image IDs as configured or added
+    pub fn allowed_image_ids(&self) -> &[String] {
+        &self.allowed_image_ids
+    }
+    
+    /// Whether the image ID check is skipped entirely
+    pub fn allows_any_image(&self) -> bool {
+        self.allow_any_image
     }
     
     /// Add a new allowed image ID
     pub fn add_allowed_image(&mut self, image_id: String) {
-        if !self.allowed_image_ids.contains(&image_id) {
+        let Some(normalized) = normalize_image_id(&image_id) else {
+            warn!("Ignoring malformed allowed image ID: {}", image_id);
+            return;
+        };
+        
+        if self.allowed_image_set.insert(normalized) {
             self.allowed_image_ids.push(image_id);
         }
     }
     
     /// Remove an allowed image ID
     pub fn remove_allowed_image(&mut self, image_id: &str) {
-        self.allowed_image_ids.retain(|id| id != image_id);
+        if let Some(normalized) = normalize_image_id(image_id) {
+            self.allowed_image_set.remove(&normalized);
+            self.allowed_image_ids.retain(|id| normalize_image_id(id) != Some(normalized));
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_fixtures::fake_receipt;
+    
+    /// A verifier that accepts receipts from any image
+    fn any_image_verifier() -> ProofVerifier {
+        ProofVerifier {
+            allow_any_image: true,
+            ..ProofVerifier::new(vec![])
+        }
+    }
+    
+    #[test]
+    fn test_empty_allow_list() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        // Without the flag an empty allow-list rejects every image
+        let err = ProofVerifier::new(vec![]).verify_proof(&bytes, "iot_validation", None, None).err().unwrap();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::InvalidImageId { .. })));
+        
+        assert!(any_image_verifier().verify_proof(&bytes, "iot_validation", None, None).is_ok());
+    }
     
     #[test]
     fn test_image_id_validation() {
@@ -116,4 +793,605 @@ mod tests {
         // For now, just verify the structure compiles
         assert_eq!(verifier.allowed_image_ids.len(), 2);
     }
+    
+    #[test]
+    fn test_allowlist_normalization() {
+        let id = "ab".repeat(32);
+        let mut verifier = ProofVerifier::new(vec![format!("0x{}", id.to_uppercase())]);
+        let image_id = normalize_image_id(&id).unwrap();
+        
+        assert!(verifier.is_image_allowed(&image_id));
+        
+        // Adding the same ID in another spelling does not duplicate it
+        verifier.add_allowed_image(id.clone());
+        assert_eq!(verifier.allowed_image_ids.len(), 1);
+        
+        verifier.remove_allowed_image(&format!("0x{}", id));
+        assert!(!verifier.is_image_allowed(&image_id));
+        assert!(verifier.allowed_image_ids.is_empty());
+    }
+    
+    #[test]
+    fn test_large_allowlist_lookups() {
+        let ids: Vec<_> = (0..100_000u32).map(|i| format!("0x{:064x}", i)).collect();
+        let verifier = ProofVerifier::new(ids);
+        
+        // Every entry lands in the set, so lookups are hashed rather than scanned
+        let set: &HashSet<[u8; 32]> = &verifier.allowed_image_set;
+        assert_eq!(set.len(), 100_000);
+        
+        for i in [0, 1, 50_000, 99_999] {
+            assert!(verifier.is_image_allowed(&normalize_image_id(&format!("{:064x}", i)).unwrap()));
+        }
+        assert!(!verifier.is_image_allowed(&normalize_image_id(&format!("{:064x}", 100_000)).unwrap()));
+        assert!(!verifier.is_image_allowed(&[0xff; 32]));
+    }
+    
+    #[test]
+    fn test_disallowed_image_rejected() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        let verifier = ProofVerifier::new(vec![format!("0x{}", "ab".repeat(32))]);
+        let err = verifier.verify_proof(&bytes, "iot_validation", None, None).err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<VerifierError>(),
+            Some(VerifierError::InvalidImageId { .. })
+        ));
+    }
+    
+    #[test]
+    fn test_expected_image_id_must_match() {
+        let image = Risc0Digest::from([1u32; 8]);
+        let bytes = bincode::serialize(&fake_receipt(image, b"journal", ExitCode::Halted(0))).unwrap();
+        let image_hex = format!("0x{}", hex::encode(image));
+        let other = format!("0x{}", "ab".repeat(32));
+        let verifier = ProofVerifier::new(vec![image_hex.clone(), other.clone()]);
+        
+        let proof = verifier.verify_proof(&bytes, "iot_validation", Some(&image_hex), None).unwrap();
+        assert_eq!(proof.summary().image_id, image.as_bytes().to_vec());
+        
+        // Allowed, but not the image this request asked for
+        let err = verifier.verify_proof(&bytes, "iot_validation", Some(&other), None).err().unwrap();
+        match err.downcast_ref::<VerifierError>() {
+            Some(VerifierError::InvalidImageId { expected, actual }) => {
+                assert_eq!(expected, &other);
+                assert_eq!(actual, &image_hex);
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+        
+        // Requesting the image does not bypass the allow-list
+        let err = ProofVerifier::new(vec![other]).verify_proof(&bytes, "iot_validation", Some(&image_hex), None)
+            .err().unwrap();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::InvalidImageId { .. })));
+    }
+    
+    #[test]
+    fn test_requested_image_must_be_allowed() {
+        let image = Risc0Digest::from([1u32; 8]);
+        let image_hex = format!("0x{}", hex::encode(image));
+        // A receipt whose image matches the request, but the request names an
+        // image nobody allowed
+        let bytes = bincode::serialize(&fake_receipt(image, b"journal", ExitCode::Halted(0))).unwrap();
+        
+        let allowed = format!("0x{}", "ab".repeat(32));
+        let err = ProofVerifier::new(vec![allowed.clone()])
+            .verify_proof(&bytes, "iot_validation", Some(&image_hex), None)
+            .err().unwrap();
+        match err.downcast_ref::<VerifierError>() {
+            Some(VerifierError::InvalidImageId { expected, actual }) => {
+                assert_eq!(expected, &allowed);
+                assert_eq!(actual, &image_hex);
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+        
+        // Checked before decoding: not even a receipt is needed
+        let err = ProofVerifier::new(vec![allowed]).verify_proof(b"not a receipt", "iot_validation", Some(&image_hex), None)
+            .err().unwrap();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::InvalidImageId { .. })));
+        
+        // allow_any_image trusts every image, so only the receipt match is left
+        assert!(any_image_verifier().verify_proof(&bytes, "iot_validation", Some(&image_hex), None).is_ok());
+    }
+    
+    fn claim_state(pre: Risc0Digest, post: Risc0Digest, exit_code: ExitCode) -> ClaimState {
+        ClaimState {
+            pre_state_digest: pre,
+            post_state_digest: post,
+            exit_code,
+        }
+    }
+    
+    #[test]
+    fn test_claim_state_expected_post_state() {
+        let pre = Risc0Digest::from([1u32; 8]);
+        let post = Risc0Digest::from([2u32; 8]);
+        let state = claim_state(pre, post, ExitCode::Halted(0));
+        
+        assert!(state.validate(None, false, false).is_ok());
+        assert!(state.validate(Some(&post), false, false).is_ok());
+    }
+    
+    #[test]
+    fn test_claim_state_anomalous_post_state() {
+        let pre = Risc0Digest::from([1u32; 8]);
+        let post = Risc0Digest::from([2u32; 8]);
+        
+        // Post-state differs from the configured expectation
+        let state = claim_state(pre, post, ExitCode::Halted(0));
+        assert!(state.validate(Some(&Risc0Digest::from([3u32; 8])), false, false).is_err());
+        
+        // No state transition
+        let state = claim_state(pre, pre, ExitCode::Halted(0));
+        assert!(state.validate(None, false, false).is_err());
+        
+        // Unhalted execution
+        let state = claim_state(pre, post, ExitCode::SystemSplit);
+        assert!(state.validate(None, false, false).is_err());
+    }
+    
+    #[test]
+    fn test_halted_execution_is_accepted() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        let proof = any_image_verifier().verify_proof(&bytes, "iot_validation", None, None).unwrap();
+        assert!(proof.warnings().is_empty());
+        
+        assert_eq!(proof.exit_code(), ExitCode::Halted(0));
+    }
+    
+    #[test]
+    fn test_nonzero_exit_is_rejected_unless_allowed() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(3));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        // A failed guest assertion halts non-zero
+        let err = any_image_verifier().verify_proof(&bytes, "iot_validation", None, None).err().unwrap();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ProofVerification(_))));
+        assert!(err.to_string().contains("guest exited with code 3"), "{}", err);
+        
+        // Proof types that opt in accept it, surfaced as a warning
+        let verifier = ProofVerifier::from_config(&Config {
+            nonzero_exit_proof_types: vec!["iot_compute".to_string()],
+            allow_any_image: true,
+            ..Config::default()
+        }).unwrap();
+        let proof = verifier.verify_proof(&bytes, "iot_compute", None, None).unwrap();
+        assert_eq!(proof.exit_code(), ExitCode::Halted(3));
+        assert_eq!(proof.warnings(), &[ClaimWarning::NonZeroExit { user_exit: 3 }]);
+        assert!(verifier.verify_proof(&bytes, "iot_validation", None, None).is_err());
+    }
+    
+    #[test]
+    fn test_paused_execution_is_configurable() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Paused(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        let strict = any_image_verifier();
+        assert!(strict.verify_proof(&bytes, "iot_validation", None, None).is_err());
+        
+        let lenient = ProofVerifier {
+            accept_paused: true,
+            ..any_image_verifier()
+        };
+        let proof = lenient.verify_proof(&bytes, "iot_validation", None, None).unwrap();
+        assert_eq!(proof.warnings(), &[ClaimWarning::Paused { user_exit: 0 }]);
+    }
+    
+    #[test]
+    fn test_journal_rules() {
+        let mut journal = vec![0u8; 8];
+        journal.extend_from_slice(&25u32.to_le_bytes());
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), &journal, ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        let verifier_with = |rule: &str| ProofVerifier::from_config(&Config {
+            journal_rules: HashMap::from([("iot_validation".to_string(), vec![rule.to_string()])]),
+            allow_any_image: true,
+            ..Config::default()
+        });
+        
+        let passing = verifier_with("u32le@8 >= 20").unwrap();
+        assert!(passing.verify_proof(&bytes, "iot_validation", None, None).is_ok());
+        
+        let failing = verifier_with("u32le@8 >= 30").unwrap();
+        assert!(failing.verify_proof(&bytes, "iot_validation", None, None).is_err());
+        // Rules only apply to their own proof type
+        assert!(failing.verify_proof(&bytes, "iot_compute", None, None).is_ok());
+        
+        let err = verifier_with("u32le@8 >= ").err().unwrap();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::Config(_))));
+    }
+    
+    #[test]
+    fn test_journal_schemas() {
+        let image = Risc0Digest::from([1u32; 8]);
+        let commit = |words: Vec<u32>| words.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<u8>>();
+        let verifier = ProofVerifier::from_config(&Config {
+            journal_schemas: HashMap::from([
+                ("iot_validation".to_string(), vec!["u64".to_string(), "bool".to_string()]),
+                ("iot_compute".to_string(), vec!["i32".to_string()]),
+            ]),
+            max_privacy_journal_bytes: 8,
+            allow_any_image: true,
+            ..Config::default()
+        }).unwrap();
+        let verify = |journal: &[u8], proof_type: &str| {
+            let receipt = fake_receipt(image, journal, ExitCode::Halted(0));
+            verifier.verify_proof(&bincode::serialize(&receipt).unwrap(), proof_type, None, None)
+        };
+        
+        let validation = commit(risc0_zkvm::serde::to_vec(&(42u64, true)).unwrap());
+        let verified = verify(&validation, "iot_validation").unwrap();
+        assert_eq!(verified.decode_journal::<(u64, bool)>().unwrap(), (42, true));
+        assert!(verified.decode_journal::<(u64, bool, u32)>().is_err());
+        assert_eq!(verified.summary().journal_summary, Some(serde_json::json!([42, true])));
+        
+        let compute = verify(&commit(vec![7]), "iot_compute").unwrap();
+        assert_eq!(compute.summary().journal_summary, Some(serde_json::json!([7])));
+        // Without a layout the journal is only size-checked
+        assert_eq!(verify(&[0xab; 8], "iot_privacy").unwrap().summary().journal_summary, None);
+        
+        for (journal, proof_type) in [
+            (b"garbage".to_vec(), "iot_validation"),
+            (validation, "iot_compute"),
+            (vec![0xab; 9], "iot_privacy"),
+        ] {
+            let err = verify(&journal, proof_type).err().unwrap();
+            assert!(err.to_string().contains("does not match its schema"), "{}: {}", proof_type, err);
+        }
+        
+        let err = verify(b"journal", "iot_telemetry").err().unwrap();
+        assert!(err.to_string().contains("Unknown proof type"), "{}", err);
+    }
+    
+    #[test]
+    fn test_custom_proof_type_routing() {
+        use crate::journal_schema::JournalSchema;
+        use crate::proof_types::ProofTypeHandler;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        
+        let checked = Arc::new(AtomicUsize::new(0));
+        let mut proof_types = ProofTypeRegistry::builtin();
+        let schema = JournalSchema::new("iot_telemetry", true, Some(16), Some(&["u32".to_string()])).unwrap();
+        let handler = ProofTypeHandler::new(schema).with_nonzero_exit(true).with_check({
+            let checked = checked.clone();
+            move |journal| {
+                checked.fetch_add(1, Ordering::SeqCst);
+                match journal[0] {
+                    0 => Err(VerifierError::ProofVerification("reading of zero".to_string()).into()),
+                    _ => Ok(()),
+                }
+            }
+        });
+        assert!(proof_types.register("iot_telemetry", handler).is_none());
+        let verifier = ProofVerifier { proof_types, ..any_image_verifier() };
+        let verify = |journal: &[u8], exit_code: ExitCode, proof_type: &str| {
+            let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), journal, exit_code);
+            verifier.verify_proof(&bincode::serialize(&receipt).unwrap(), proof_type, None, None)
+        };
+        
+        // The custom type gets its own schema, exit code policy and check
+        let proof = verify(&7u32.to_le_bytes(), ExitCode::Halted(2), "iot_telemetry").unwrap();
+        assert_eq!(proof.summary().journal_summary, Some(serde_json::json!([7])));
+        assert_eq!(proof.warnings(), &[ClaimWarning::NonZeroExit { user_exit: 2 }]);
+        let err = verify(&0u32.to_le_bytes(), ExitCode::Halted(0), "iot_telemetry").err().unwrap();
+        assert!(err.to_string().contains("reading of zero"), "{}", err);
+        assert!(verify(b"journal", ExitCode::Halted(0), "iot_telemetry").is_err());
+        assert_eq!(checked.load(Ordering::SeqCst), 2);
+        
+        // Built-in types are unaffected by it
+        assert!(verify(&0u32.to_le_bytes(), ExitCode::Halted(0), "iot_validation").is_ok());
+        assert!(verify(b"journal", ExitCode::Halted(2), "iot_validation").is_err());
+        assert_eq!(checked.load(Ordering::SeqCst), 2);
+        
+        let err = verify(b"journal", ExitCode::Halted(0), "iot_unregistered").err().unwrap();
+        assert!(err.to_string().contains("Unknown proof type: iot_unregistered"), "{}", err);
+    }
+    
+    /// A composite receipt without a journal digest, as produced by older
+    /// provers; `get_claim` rejects it as malformed
+    fn legacy_composite_receipt(image_id: Risc0Digest, journal: &[u8]) -> Receipt {
+        use risc0_zkvm::{CompositeReceipt, MaybePruned, Output, SystemState};
+        
+        let segment = SegmentReceipt {
+            seal: vec![],
+            index: 0,
+            hashfn: "poseidon2".to_string(),
+            claim: ReceiptClaim {
+                pre: MaybePruned::Pruned(image_id),
+                post: MaybePruned::Value(SystemState { pc: 0x0020_0000, merkle_root: Risc0Digest::from([7u32; 8]) }),
+                exit_code: ExitCode::Halted(0),
+                input: Risc0Digest::ZERO,
+                output: MaybePruned::Value(Some(Output {
+                    journal: MaybePruned::Value(journal.to_vec()),
+                    assumptions: MaybePruned::Value(Assumptions(vec![])),
+                })),
+            },
+        };
+        let composite = CompositeReceipt { segments: vec![segment], assumptions: vec![], journal_digest: None };
+        
+        Receipt::new(InnerReceipt::Composite(composite), journal.to_vec())
+    }
+    
+    #[test]
+    fn test_claim_fallback_for_legacy_receipt() {
+        let image = Risc0Digest::from([3u32; 8]);
+        let receipt = legacy_composite_receipt(image, b"journal");
+        assert!(receipt.get_claim().is_err());
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        // Stand in for the segment seal check, which needs a real proof
+        let fallback_verifier = |fallback: Risc0Digest| ProofVerifier {
+            claim_fallback_image: Some(fallback),
+            segment_integrity: |_| Ok(()),
+            ..ProofVerifier::new(vec![format!("0x{}", hex::encode([9u8; 32]))])
+        };
+        
+        // Without the fallback the claim error is fatal
+        let err = ProofVerifier::new(vec![]).verify_proof(&bytes, "iot_validation", None, None).err().unwrap();
+        assert!(err.to_string().contains("Failed to get claim"), "{}", err);
+        
+        let proof = fallback_verifier(image).verify_proof(&bytes, "iot_validation", None, None).unwrap();
+        assert!(proof.warnings().is_empty());
+        
+        // A different fallback image does not match the receipt
+        let err = fallback_verifier(Risc0Digest::from([4u32; 8]))
+            .verify_proof(&bytes, "iot_validation", None, None).err().unwrap();
+        assert!(err.to_string().contains("not the fallback image"), "{}", err);
+        
+        // The seal must still verify
+        let verifier = ProofVerifier {
+            segment_integrity: |_| Err(VerificationError::InvalidProof),
+            ..fallback_verifier(image)
+        };
+        assert!(verifier.verify_proof(&bytes, "iot_validation", None, None).is_err());
+        
+        // And the journal must be the one the final segment committed to
+        let mut tampered = receipt.clone();
+        tampered.journal.bytes = b"tampered".to_vec();
+        let bytes = bincode::serialize(&tampered).unwrap();
+        let err = fallback_verifier(image).verify_proof(&bytes, "iot_validation", None, None).err().unwrap();
+        assert!(err.to_string().contains("journal"), "{}", err);
+    }
+    
+    #[test]
+    fn test_receipt_seal_and_journal_are_verified() {
+        let image = Risc0Digest::from([1u32; 8]);
+        
+        // A dev-mode receipt whose claim matches its journal verifies
+        let good = fake_receipt(image, b"journal", ExitCode::Halted(0));
+        assert!(any_image_verifier().verify_proof(&bincode::serialize(&good).unwrap(), "iot_validation", None, None).is_ok());
+        
+        // A journal swapped after proving no longer matches the claim
+        let mut tampered = good.clone();
+        tampered.journal.bytes = b"tampered".to_vec();
+        let err = any_image_verifier().verify_proof(&bincode::serialize(&tampered).unwrap(), "iot_validation", None, None)
+            .err().unwrap();
+        assert!(err.to_string().contains("Receipt failed verification"), "{}", err);
+        
+        // Segment seals that are not real proofs are rejected
+        let forged = crate::test_fixtures::segmented_receipt(2, b"journal");
+        let err = any_image_verifier().verify_proof(&bincode::serialize(&forged).unwrap(), "iot_validation", None, None)
+            .err().unwrap();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ProofVerification(_))));
+        
+        // Streamed receipts still have their journal bound to the claim
+        let mut tampered = forged;
+        tampered.journal.bytes = b"tampered".to_vec();
+        let verifier = ProofVerifier { streaming: true, segment_integrity: |_| Ok(()), ..any_image_verifier() };
+        let err = verifier.verify_proof(&bincode::serialize(&tampered).unwrap(), "iot_validation", None, None)
+            .err().unwrap();
+        assert!(err.to_string().contains("Receipt failed verification"), "{}", err);
+    }
+    
+    #[test]
+    fn test_receipt_kinds_are_classified() {
+        use crate::test_fixtures::{groth16_receipt, segmented_receipt, succinct_receipt};
+        
+        let image = Risc0Digest::from([1u32; 8]);
+        // Placeholder seals stand in for real proofs of each kind
+        let verifier = ProofVerifier { receipt_integrity: |_, _| Ok(()), ..any_image_verifier() };
+        for (receipt, kind) in [
+            (segmented_receipt(2, b"journal"), ProofKind::Composite),
+            (succinct_receipt(image, b"journal"), ProofKind::Succinct),
+            (groth16_receipt(image, b"journal"), ProofKind::Groth16),
+            (fake_receipt(image, b"journal", ExitCode::Halted(0)), ProofKind::Fake),
+        ] {
+            let bytes = bincode::serialize(&receipt).unwrap();
+            let summary = verifier.verify_proof(&bytes, "iot_validation", None, None).unwrap().summary();
+            assert_eq!(summary.proof_kind, kind);
+        }
+        
+        // The real check runs the Groth16 verifier, which rejects a placeholder seal
+        let bytes = bincode::serialize(&groth16_receipt(image, b"journal")).unwrap();
+        let err = any_image_verifier().verify_proof(&bytes, "iot_validation", None, None).err().unwrap();
+        assert!(err.to_string().contains("Receipt failed verification"), "{}", err);
+    }
+    
+    #[test]
+    fn test_streaming_verification_of_segmented_receipt() {
+        let receipt = crate::test_fixtures::segmented_receipt(4, b"journal");
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        // Fake seals stand in for real segment proofs
+        let whole = ProofVerifier { receipt_integrity: |_, _| Ok(()), ..any_image_verifier() }
+            .verify_proof(&bytes, "iot_validation", None, None).unwrap().summary();
+        assert_eq!(whole.peak_decoded_bytes, bytes.len() as u64);
+        
+        let verifier = ProofVerifier { streaming: true, segment_integrity: |_| Ok(()), ..any_image_verifier() };
+        let streamed = verifier.verify_proof(&bytes, "iot_validation", None, None).unwrap().summary();
+        
+        assert!(streamed.peak_decoded_bytes * 3 < bytes.len() as u64);
+        assert_eq!(streamed.receipt_hash, whole.receipt_hash);
+        assert_eq!(streamed.journal_hash, whole.journal_hash);
+    }
+    
+    #[test]
+    fn test_empty_privacy_journal_uses_sentinel() {
+        assert_eq!(EMPTY_JOURNAL_HASH.to_vec(), Keccak256::digest([]).to_vec());
+        
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"", ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        // Rejected unless explicitly allowed, and only for privacy proofs
+        assert!(any_image_verifier().verify_proof(&bytes, "iot_privacy", None, None).is_err());
+        let verifier = ProofVerifier::from_config(&Config {
+            allow_empty_privacy_journals: true,
+            allow_any_image: true,
+            ..Config::default()
+        }).unwrap();
+        assert!(verifier.verify_proof(&bytes, "iot_validation", None, None).is_err());
+        
+        let summary = verifier.verify_proof(&bytes, "iot_privacy", None, None).unwrap().summary();
+        assert!(summary.journal_empty);
+        assert_eq!(summary.journal_hash, EMPTY_JOURNAL_HASH.to_vec());
+        
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let summary = verifier.verify_proof(&bincode::serialize(&receipt).unwrap(), "iot_privacy", None, None)
+            .unwrap().summary();
+        assert!(!summary.journal_empty);
+        assert_ne!(summary.journal_hash, EMPTY_JOURNAL_HASH.to_vec());
+    }
+    
+    #[test]
+    fn test_device_envelopes() {
+        let device_key = k256::ecdsa::SigningKey::from_slice(&[0x22; 32]).unwrap();
+        let verifier = ProofVerifier {
+            device_keys: HashMap::from([(
+                "device-1".to_string(),
+                crate::receipt_signer::derive_address(device_key.verifying_key()),
+            )]),
+            ..any_image_verifier()
+        };
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bare = bincode::serialize(&receipt).unwrap();
+        
+        // A validly enveloped receipt verifies and records the signature
+        let enveloped = crate::envelope::seal(&device_key, &bare);
+        let summary = verifier.verify_device_proof(&enveloped, "device-1", "iot_validation", None, None).unwrap().summary();
+        assert_eq!(summary.device_signature, DeviceSignature::Verified);
+        let bare_summary = verifier.verify_proof(&bare, "iot_validation", None, None).unwrap().summary();
+        assert_eq!(summary.receipt_hash, bare_summary.receipt_hash);
+        
+        // Signed by someone else, or claimed by an unregistered device
+        let other_key = k256::ecdsa::SigningKey::from_slice(&[0x33; 32]).unwrap();
+        let forged = crate::envelope::seal(&other_key, &bare);
+        let err = verifier.verify_device_proof(&forged, "device-1", "iot_validation", None, None).err().unwrap();
+        assert!(err.to_string().contains("not the registered key"), "{}", err);
+        assert!(verifier.verify_device_proof(&enveloped, "device-2", "iot_validation", None, None).is_err());
+        
+        // Bare receipts still verify, without a device signature
+        let summary = verifier.verify_device_proof(&bare, "device-1", "iot_validation", None, None).unwrap().summary();
+        assert_eq!(summary.device_signature, DeviceSignature::Absent);
+    }
+    
+    #[test]
+    fn test_format_hint_is_honored() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bytes = postcard::to_allocvec(&receipt).unwrap();
+        
+        let decoded = decode_receipt(&bytes, Some(ReceiptFormat::Postcard)).unwrap();
+        assert_eq!(decoded.format, ReceiptFormat::Postcard);
+        assert!(!decoded.probed);
+        assert_eq!(decoded.receipt.journal.bytes, b"journal");
+    }
+    
+    #[test]
+    fn test_wrong_format_hint_falls_back_to_probing() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        let decoded = decode_receipt(&bytes, Some(ReceiptFormat::Postcard)).unwrap();
+        assert_eq!(decoded.format, ReceiptFormat::Bincode);
+        assert!(decoded.probed);
+    }
+    
+    #[test]
+    fn test_risc0_serde_receipt() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let words = risc0_zkvm::serde::to_vec(&receipt).unwrap();
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        
+        let decoded = decode_receipt(&bytes, Some(ReceiptFormat::Risc0Serde)).unwrap();
+        assert_eq!(decoded.format, ReceiptFormat::Risc0Serde);
+        assert!(!decoded.probed);
+    }
+    
+    #[test]
+    fn test_inflated_length_prefix_is_refused() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        
+        // Claim the journal holds u64::MAX / 2 bytes
+        let prefixed = [&7u64.to_le_bytes()[..], b"journal"].concat();
+        let inflated = [&(u64::MAX / 2).to_le_bytes()[..], b"journal"].concat();
+        let at = bytes.windows(prefixed.len()).rposition(|window| window == prefixed).unwrap();
+        let mut crafted = bytes.clone();
+        crafted[at..at + inflated.len()].copy_from_slice(&inflated);
+        
+        let err = decode_receipt(&crafted, Some(ReceiptFormat::Bincode)).err().unwrap();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::ProofVerification(_))));
+        
+        // The unmodified receipt still decodes under the limit
+        assert!(decode_receipt(&bytes, Some(ReceiptFormat::Bincode)).is_ok());
+        
+        // Reading from a stream, as streaming verification does, sizes byte
+        // buffers from their prefix; the limit is checked before that
+        let string = [&(u64::MAX / 2).to_le_bytes()[..], b"tail"].concat();
+        let err = bincode_options(string.len()).deserialize_from::<_, String>(&string[..]).unwrap_err();
+        assert!(err.to_string().contains("size limit"), "{}", err);
+    }
+    
+    #[test]
+    fn test_json_and_cbor_receipts_are_detected() {
+        let receipt = fake_receipt(Risc0Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bincode_bytes = bincode::serialize(&receipt).unwrap();
+        let json_bytes = serde_json::to_vec(&receipt).unwrap();
+        let mut cbor_bytes = Vec::new();
+        ciborium::into_writer(&receipt, &mut cbor_bytes).unwrap();
+        
+        assert_eq!(ReceiptFormat::sniff(&bincode_bytes), None);
+        assert_eq!(ReceiptFormat::sniff(&json_bytes), Some(ReceiptFormat::Json));
+        assert_eq!(ReceiptFormat::sniff(&cbor_bytes), Some(ReceiptFormat::Cbor));
+        
+        // The same receipt verifies to the same hash whichever way it arrives
+        let verifier = any_image_verifier();
+        let mut hashes = Vec::new();
+        for (bytes, format) in [
+            (&bincode_bytes, ReceiptFormat::Bincode),
+            (&json_bytes, ReceiptFormat::Json),
+            (&cbor_bytes, ReceiptFormat::Cbor),
+        ] {
+            let decoded = decode_receipt(bytes, None).unwrap();
+            assert_eq!(decoded.format, format);
+            assert_eq!(decoded.receipt.journal.bytes, b"journal");
+            assert!(!decode_receipt(bytes, Some(format)).unwrap().probed);
+            
+            let proof = verifier.verify_proof(bytes, "iot_validation", None, None).unwrap();
+            hashes.push(proof.summary().receipt_hash);
+        }
+        assert!(hashes.iter().all(|hash| *hash == hashes[0]));
+    }
+    
+    #[test]
+    fn test_undecodable_receipt_lists_attempted_formats() {
+        let err = decode_receipt(b"garbage", None).err().unwrap().to_string();
+        for format in ["Bincode", "Risc0Serde", "Postcard", "Json", "Cbor"] {
+            assert!(err.contains(format), "{}", err);
+        }
+    }
+    
+    #[test]
+    fn test_parse_digest() {
+        let digest = parse_digest(&format!("0x{}", "ab".repeat(32))).unwrap();
+        assert_eq!(hex::encode(digest), "ab".repeat(32));
+        
+        assert!(parse_digest("0xabcd").is_err());
+        assert!(parse_digest("not hex").is_err());
+    }
 }
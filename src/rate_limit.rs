@@ -0,0 +1,271 @@
+//! Token-bucket rate limiting
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Instant};
+
+/// Classic token bucket: holds up to `capacity` tokens, refilled continuously
+/// at `refill_per_sec`
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    pub fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take a token, or return how long until one becomes available
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec))
+        }
+    }
+
+    /// Whether the bucket has refilled completely by `now`
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens + elapsed * self.refill_per_sec >= self.capacity
+    }
+}
+
+/// Per-device limiter for proof requests.
+///
+/// Unlike [`SubmissionRateLimiter`], requests over a device's budget are
+/// rejected rather than queued, so one noisy device cannot hold up the others.
+/// A device whose bucket has refilled is indistinguishable from one never
+/// seen, so idle devices are dropped; past `max_devices` the least recently
+/// active ones are evicted as well.
+pub struct DeviceRateLimiter {
+    per_sec: f64,
+    burst: f64,
+    max_devices: usize,
+    buckets: std::sync::Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl DeviceRateLimiter {
+    /// Allow each device `per_sec` requests per second with bursts of up to
+    /// `burst`, tracking at most `max_devices` devices
+    pub fn new(per_sec: f64, burst: u32, max_devices: usize) -> Self {
+        Self {
+            per_sec,
+            burst: f64::from(burst.max(1)),
+            max_devices: max_devices.max(1),
+            buckets: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `device_id`, or return how long until it has one
+    pub fn try_acquire(&self, device_id: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if !buckets.contains_key(device_id) && buckets.len() >= self.max_devices {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+            if buckets.len() >= self.max_devices {
+                let oldest = buckets.iter()
+                    .min_by_key(|(_, bucket)| bucket.last_refill)
+                    .map(|(device, _)| device.clone());
+                if let Some(oldest) = oldest {
+                    buckets.remove(&oldest);
+                }
+            }
+        }
+
+        let (burst, per_sec) = (self.burst, self.per_sec);
+        buckets.entry(device_id.to_string())
+            .or_insert_with(|| TokenBucket { last_refill: now, ..TokenBucket::new(burst, per_sec) })
+            .try_take(now)
+    }
+
+    /// Drop the devices that have been idle long enough to refill, returning how many
+    pub fn prune(&self, now: Instant) -> usize {
+        let mut buckets = self.buckets.lock().unwrap();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| !bucket.is_full(now));
+        before - buckets.len()
+    }
+
+    /// Number of devices currently tracked
+    pub fn len(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+}
+
+/// Global limiter for InputBox submissions.
+///
+/// Callers over the rate wait in FIFO order for a token instead of being
+/// rejected, so bursts are smoothed out rather than dropped.
+pub struct SubmissionRateLimiter {
+    bucket: Mutex<TokenBucket>,
+    waiting: AtomicUsize,
+}
+
+impl SubmissionRateLimiter {
+    /// Allow `per_sec` submissions per second, with up to one second's worth of burst
+    pub fn new(per_sec: f64) -> Self {
+        Self {
+            bucket: Mutex::new(TokenBucket::new(per_sec.max(1.0), per_sec)),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Wait until a submission is allowed
+    pub async fn acquire(&self) {
+        // Counted until this returns or the caller gives up waiting
+        let _waiting = Waiting::enter(&self.waiting);
+
+        // tokio's Mutex is fair, so waiters are served in arrival order
+        let mut bucket = self.bucket.lock().await;
+        while let Err(wait) = bucket.try_take(Instant::now()) {
+            sleep(wait).await;
+        }
+    }
+
+    /// Number of submissions currently queued behind the limiter
+    pub fn queue_depth(&self) -> usize {
+        self.waiting.load(Ordering::Relaxed)
+    }
+}
+
+/// One caller counted in `SubmissionRateLimiter::waiting`, uncounted on drop
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn enter(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::Relaxed);
+        Self(waiting)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_stays_under_cap() {
+        let limiter = Arc::new(SubmissionRateLimiter::new(10.0));
+        let start = Instant::now();
+
+        let tasks: Vec<_> = (0..50).map(|_| {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                limiter.acquire().await;
+                Instant::now()
+            })
+        }).collect();
+
+        let mut completed = Vec::new();
+        for task in tasks {
+            completed.push(task.await.unwrap());
+        }
+
+        // Nothing was dropped, and after the initial one-second burst the
+        // remaining 40 submissions are spread over at least 4 seconds
+        assert_eq!(completed.len(), 50);
+        let elapsed = completed.iter().max().unwrap().duration_since(start);
+        assert!(elapsed >= Duration::from_millis(3_990), "elapsed {:?}", elapsed);
+
+        // No one-second window ever admits more than burst + rate
+        completed.sort();
+        for (i, at) in completed.iter().enumerate() {
+            let in_window = completed[i..].iter()
+                .take_while(|t| t.duration_since(*at) < Duration::from_secs(1))
+                .count();
+            assert!(in_window <= 20, "{} submissions within one second", in_window);
+        }
+        assert_eq!(limiter.queue_depth(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queue_depth_reports_waiters() {
+        let limiter = Arc::new(SubmissionRateLimiter::new(1.0));
+        limiter.acquire().await;
+
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire().await })
+        };
+        tokio::task::yield_now().await;
+        assert_eq!(limiter.queue_depth(), 1);
+
+        waiter.await.unwrap();
+        assert_eq!(limiter.queue_depth(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_abandoned_wait_leaves_the_queue() {
+        let limiter = Arc::new(SubmissionRateLimiter::new(1.0));
+        limiter.acquire().await;
+
+        // One waiter sleeps for a token, the other waits for the lock
+        let waiters: Vec<_> = (0..2).map(|_| {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire().await })
+        }).collect();
+        tokio::task::yield_now().await;
+        assert_eq!(limiter.queue_depth(), 2);
+
+        for waiter in waiters {
+            waiter.abort();
+            assert!(waiter.await.unwrap_err().is_cancelled());
+        }
+        assert_eq!(limiter.queue_depth(), 0);
+    }
+
+    #[test]
+    fn test_noisy_device_is_throttled_alone() {
+        let limiter = DeviceRateLimiter::new(1.0, 3, 100);
+        let start = Instant::now();
+
+        let noisy: Vec<_> = (0..5).map(|_| limiter.try_acquire("noisy", start).is_ok()).collect();
+        assert_eq!(noisy, [true, true, true, false, false]);
+        assert!(limiter.try_acquire("quiet", start).is_ok());
+
+        // The noisy device earns one request per second back
+        assert_eq!(limiter.try_acquire("noisy", start), Err(Duration::from_secs(1)));
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.try_acquire("noisy", later).is_ok());
+        assert!(limiter.try_acquire("noisy", later).is_err());
+    }
+
+    #[test]
+    fn test_idle_devices_are_evicted() {
+        let limiter = DeviceRateLimiter::new(1.0, 2, 2);
+        let start = Instant::now();
+        assert!(limiter.try_acquire("a", start).is_ok());
+        assert!(limiter.try_acquire("b", start + Duration::from_millis(100)).is_ok());
+
+        // At the cap, the least recently active device makes room
+        assert!(limiter.try_acquire("c", start + Duration::from_millis(200)).is_ok());
+        assert_eq!(limiter.len(), 2);
+
+        // Once their buckets refill, devices are forgotten
+        assert_eq!(limiter.prune(start + Duration::from_millis(500)), 0);
+        assert_eq!(limiter.prune(start + Duration::from_secs(2)), 2);
+        assert_eq!(limiter.len(), 0);
+    }
+}
@@ -0,0 +1,175 @@
+//! Cache of fetched receipts
+//!
+//! A notice that is read again (after a failed submission, a restart or an
+//! epoch deferral) would otherwise download its receipt again, which is slow
+//! on public IPFS gateways. Only content-addressed URLs are cached: the data
+//! behind `ipfs://` and `ar://` can never change, so entries need no
+//! invalidation, while `http(s)://` and `s3://` objects may be replaced.
+//!
+//! Entries are kept in memory up to `capacity`, least recently used first
+//! out. With a directory configured they are also written to disk, so a
+//! restart does not start cold; evicted entries are removed from disk too.
+
+use anyhow::Result;
+use sha3::{Digest, Keccak256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing::warn;
+
+/// Fixed-capacity map evicting the least recently used key
+struct Lru {
+    capacity: usize,
+    /// URL -> (last use, receipt bytes)
+    entries: HashMap<String, (u64, Vec<u8>)>,
+    /// Last use -> URL, oldest first
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Lru {
+    fn touch(&mut self, url: &str) -> Option<&Vec<u8>> {
+        self.tick += 1;
+        let (last_use, bytes) = self.entries.get_mut(url)?;
+        self.order.remove(last_use);
+        *last_use = self.tick;
+        self.order.insert(self.tick, url.to_string());
+        Some(bytes)
+    }
+
+    /// Insert `url`, returning the URL evicted to make room, if any
+    fn insert(&mut self, url: String, bytes: Vec<u8>) -> Option<String> {
+        self.tick += 1;
+        if let Some((last_use, _)) = self.entries.insert(url.clone(), (self.tick, bytes)) {
+            self.order.remove(&last_use);
+        }
+        self.order.insert(self.tick, url);
+
+        if self.entries.len() <= self.capacity {
+            return None;
+        }
+        let (_, oldest) = self.order.pop_first()?;
+        self.entries.remove(&oldest);
+        Some(oldest)
+    }
+}
+
+pub struct ReceiptCache {
+    dir: Option<PathBuf>,
+    lru: Mutex<Lru>,
+}
+
+impl ReceiptCache {
+    /// Keep up to `capacity` receipts, also on disk under `dir` if given
+    pub fn new(capacity: usize, dir: Option<PathBuf>) -> Result<Self> {
+        if let Some(dir) = &dir {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| anyhow::anyhow!("Failed to create receipt cache directory {}: {}", dir.display(), e))?;
+        }
+
+        Ok(Self {
+            dir,
+            lru: Mutex::new(Lru { capacity, entries: HashMap::new(), order: BTreeMap::new(), tick: 0 }),
+        })
+    }
+
+    /// Whether the data behind `url` is immutable, and so safe to cache
+    pub fn is_cacheable(url: &str) -> bool {
+        url.starts_with("ipfs://") || url.starts_with("ar://")
+    }
+
+    /// Cached receipt for `url`, from memory or else from disk
+    pub fn get(&self, url: &str) -> Option<Vec<u8>> {
+        if let Some(bytes) = self.lru.lock().unwrap().touch(url) {
+            return Some(bytes.clone());
+        }
+
+        let path = self.path_for(url)?;
+        let bytes = std::fs::read(path).ok()?;
+        self.remember(url, bytes.clone());
+        Some(bytes)
+    }
+
+    /// Cache a fetched receipt; URLs that are not content-addressed are ignored
+    pub fn insert(&self, url: &str, bytes: &[u8]) {
+        if !Self::is_cacheable(url) {
+            return;
+        }
+
+        if let Some(path) = self.path_for(url) {
+            // Write beside the target and rename, so a crash mid-write never
+            // leaves a truncated receipt to be served later
+            let temp = path.with_extension("tmp");
+            if let Err(e) = std::fs::write(&temp, bytes).and_then(|()| std::fs::rename(&temp, &path)) {
+                warn!("Failed to write receipt cache entry {}: {}", path.display(), e);
+            }
+        }
+        self.remember(url, bytes.to_vec());
+    }
+
+    fn remember(&self, url: &str, bytes: Vec<u8>) {
+        let evicted = self.lru.lock().unwrap().insert(url.to_string(), bytes);
+        if let Some(path) = evicted.and_then(|evicted| self.path_for(&evicted)) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// File an entry is kept in on disk, named by the hash of its URL
+    fn path_for(&self, url: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(dir.join(hex::encode(Keccak256::digest(url.as_bytes()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit_and_miss() {
+        let cache = ReceiptCache::new(4, None).unwrap();
+        assert_eq!(cache.get("ipfs://QmA"), None);
+
+        cache.insert("ipfs://QmA", b"receipt a");
+        assert_eq!(cache.get("ipfs://QmA").as_deref(), Some(&b"receipt a"[..]));
+        assert_eq!(cache.get("ipfs://QmB"), None);
+
+        // Mutable locations are never cached
+        cache.insert("https://example.com/r.bin", b"receipt");
+        cache.insert("s3://bucket/r.bin", b"receipt");
+        assert_eq!(cache.get("https://example.com/r.bin"), None);
+        assert_eq!(cache.get("s3://bucket/r.bin"), None);
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = ReceiptCache::new(2, None).unwrap();
+        cache.insert("ipfs://QmA", b"a");
+        cache.insert("ipfs://QmB", b"b");
+
+        // Reading A makes B the least recently used
+        assert!(cache.get("ipfs://QmA").is_some());
+        cache.insert("ipfs://QmC", b"c");
+
+        assert!(cache.get("ipfs://QmA").is_some());
+        assert_eq!(cache.get("ipfs://QmB"), None);
+        assert!(cache.get("ipfs://QmC").is_some());
+    }
+
+    #[test]
+    fn test_disk_entries_survive_restart() {
+        let dir = std::env::temp_dir().join(format!("lcore-receipt-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let cache = ReceiptCache::new(1, Some(dir.clone())).unwrap();
+        cache.insert("ar://tx-a", b"a");
+        let restarted = ReceiptCache::new(1, Some(dir.clone())).unwrap();
+        assert_eq!(restarted.get("ar://tx-a").as_deref(), Some(&b"a"[..]));
+
+        // Eviction removes the file as well
+        restarted.insert("ar://tx-b", b"b");
+        assert_eq!(ReceiptCache::new(1, Some(dir.clone())).unwrap().get("ar://tx-a"), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,85 +1,297 @@
 //! Receipt signing with ECDSA
 
 use anyhow::Result;
-use k256::{
-    ecdsa::{SigningKey, Signature, signature::Signer},
-    SecretKey,
-};
+use k256::ecdsa::VerifyingKey;
 use sha3::{Digest, Keccak256};
-use crate::types::VerifiedReceipt;
+use std::sync::{Arc, RwLock};
+use crate::config::{Config, SignerBackend};
+use crate::eip712::{parse_address, parse_bytes32, word, Eip712Domain};
+use crate::signer_backend::{LocalKeySigner, ReceiptSignerBackend};
+use crate::types::{EpochSummary, VerifiedReceipt};
 use crate::error::VerifierError;
 
-pub struct ReceiptSigner {
-    signing_key: SigningKey,
+/// EIP-712 type of a verified receipt, matching the Solidity struct
+///
+/// ```solidity
+/// struct VerifiedReceipt {
+///     string deviceId;
+///     string proofType;
+///     bytes32 receiptHash;
+///     bytes32 imageId;
+///     bytes32 journalHash;
+///     uint64 epochIndex;
+///     uint64 inputIndex;
+///     uint64 timestamp;
+///     address verifier;
+/// }
+/// ```
+pub const VERIFIED_RECEIPT_TYPE: &str = "VerifiedReceipt(string deviceId,string proofType,bytes32 receiptHash,\
+bytes32 imageId,bytes32 journalHash,uint64 epochIndex,uint64 inputIndex,uint64 timestamp,address verifier)";
+
+/// The key receipts are signed with until the next rotation
+struct ActiveKey {
+    backend: Box<dyn ReceiptSignerBackend>,
     address: String,
+    /// 0 for the key the signer was created with, incremented by each rotation
+    key_id: u64,
+}
+
+pub struct ReceiptSigner {
+    /// Swapped whole by [`ReceiptSigner::rotate_key`]; each signature takes
+    /// one snapshot so its address and signature always match
+    active: RwLock<Arc<ActiveKey>>,
+    /// Sign the EIP-191 prefixed hash instead of the raw digest
+    eip191_prefix: bool,
+    #[cfg(feature = "bls")]
+    bls: Option<crate::bls_signer::BlsSigner>,
 }
 
 impl ReceiptSigner {
     /// Create a new signer from private key hex
     pub fn new(private_key_hex: &str) -> Result<Self> {
-        let private_key_hex = private_key_hex.trim_start_matches("0x");
-        let private_key_bytes = hex::decode(private_key_hex)
-            .map_err(|e| VerifierError::Signing(format!("Invalid private key hex: {}", e)))?;
-        
-        let secret_key = SecretKey::from_slice(&private_key_bytes)
-            .map_err(|e| VerifierError::Signing(format!("Invalid private key: {}", e)))?;
-        
-        let signing_key = SigningKey::from(secret_key);
-        
-        // Derive Ethereum address from public key
-        let public_key = signing_key.verifying_key();
-        let public_key_bytes = public_key.to_encoded_point(false);
-        let public_key_bytes = &public_key_bytes.as_bytes()[1..]; // Skip the 0x04 prefix
-        
-        let mut hasher = Keccak256::new();
-        hasher.update(public_key_bytes);
-        let hash = hasher.finalize();
-        
-        let address = format!("0x{}", hex::encode(&hash[12..]));
+        Ok(Self::from_backend(Box::new(LocalKeySigner::new(private_key_hex)?)))
+    }
+    
+    /// Create a signer whose key is held by `backend`
+    pub fn from_backend(backend: Box<dyn ReceiptSignerBackend>) -> Self {
+        let address = backend.address();
         
-        Ok(Self {
-            signing_key,
-            address,
-        })
+        Self {
+            active: RwLock::new(Arc::new(ActiveKey { backend, address, key_id: 0 })),
+            eip191_prefix: true,
+            #[cfg(feature = "bls")]
+            bls: None,
+        }
+    }
+    
+    /// Create a signer on the backend `signer_backend` selects
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        match config.signer_backend {
+            SignerBackend::Local => Self::new(&config.verifier_private_key),
+            SignerBackend::Kms => {
+                let backend = crate::kms_signer::KmsSigner::from_config(config).await?;
+                Ok(Self::from_backend(Box::new(backend)))
+            }
+        }
+    }
+    
+    /// Choose between signing the EIP-191 `personal_sign` hash (the default)
+    /// and the raw signing hash
+    pub fn with_eip191_prefix(mut self, enabled: bool) -> Self {
+        self.eip191_prefix = enabled;
+        self
+    }
+    
+    /// Also sign receipts with a BLS key so signatures can be aggregated
+    #[cfg(feature = "bls")]
+    pub fn with_bls(mut self, bls_private_key_hex: &str) -> Result<Self> {
+        self.bls = Some(crate::bls_signer::BlsSigner::new(bls_private_key_hex)?);
+        Ok(self)
     }
     
     /// Get the signer's Ethereum address
     pub fn get_address(&self) -> String {
-        self.address.clone()
+        self.active().address.clone()
+    }
+    
+    /// Identifier of the active key: 0 at startup, then one more per rotation
+    pub fn key_id(&self) -> u64 {
+        self.active().key_id
+    }
+    
+    /// Sign everything from now on with a new key, returning its address.
+    /// Signatures already made keep the old key's address, so consumers can
+    /// accept both during an overlap window.
+    pub fn rotate_key(&self, new_key_hex: &str) -> Result<String> {
+        let backend = LocalKeySigner::new(new_key_hex)?;
+        let address = backend.address();
+        
+        let mut active = self.active.write().unwrap();
+        let key_id = active.key_id + 1;
+        *active = Arc::new(ActiveKey { backend: Box::new(backend), address: address.clone(), key_id });
+        Ok(address)
+    }
+    
+    fn active(&self) -> Arc<ActiveKey> {
+        self.active.read().unwrap().clone()
     }
     
     /// Sign a verified receipt
-    pub fn sign_receipt(&self, mut receipt: VerifiedReceipt) -> Result<VerifiedReceipt> {
-        // Set verifier address if not already set
-        if receipt.verifier_address.is_none() {
-            receipt.verifier_address = Some(self.address.clone());
-        }
+    pub async fn sign_receipt(&self, receipt: VerifiedReceipt) -> Result<VerifiedReceipt> {
+        self.sign_receipt_with(receipt, |receipt| Ok(compute_receipt_hash(receipt))).await
+    }
+    
+    /// Sign a verified receipt over its ABI-encoded fields, so a contract
+    /// can rebuild the signing hash with [`compute_receipt_abi_hash`]'s layout
+    pub async fn sign_receipt_abi(&self, receipt: VerifiedReceipt) -> Result<VerifiedReceipt> {
+        self.sign_receipt_with(receipt, compute_receipt_abi_hash).await
+    }
+    
+    /// Set the verifier address of a receipt to the active key's, then sign
+    /// the hash `signing_hash` computes over it
+    async fn sign_receipt_with(
+        &self,
+        mut receipt: VerifiedReceipt,
+        signing_hash: impl FnOnce(&VerifiedReceipt) -> Result<[u8; 32]>,
+    ) -> Result<VerifiedReceipt> {
+        let key = self.active();
+        receipt.verifier_address = Some(key.address.clone());
         
-        // Compute signing hash
-        let signing_hash = compute_receipt_hash(&receipt);
+        let signing_hash = signing_hash(&receipt)?;
+        receipt.signature = sign_prehash(&key, self.digest_to_sign(&signing_hash)).await?;
         
-        // Sign the hash
-        let signature: Signature = self.signing_key.sign(&signing_hash);
-        let signature_bytes = signature.to_bytes();
+        #[cfg(feature = "bls")]
+        if let Some(bls) = &self.bls {
+            receipt.bls_signature = Some(bls.sign(&signing_hash));
+            receipt.bls_public_key = Some(bls.public_key().to_string());
+        }
         
-        // Convert to recoverable signature format (65 bytes with recovery ID)
-        // For Ethereum compatibility, we need to add the recovery ID
-        let mut sig_with_recovery = vec![0u8; 65];
-        sig_with_recovery[..64].copy_from_slice(&signature_bytes);
+        Ok(receipt)
+    }
+    
+    /// Sign a verified receipt as EIP-712 typed data under `domain`, so it
+    /// can be checked with OpenZeppelin's `EIP712._hashTypedDataV4` and `ECDSA.recover`
+    pub async fn sign_receipt_eip712(&self, mut receipt: VerifiedReceipt, domain: &Eip712Domain) -> Result<VerifiedReceipt> {
+        let key = self.active();
+        receipt.verifier_address = Some(key.address.clone());
         
-        // For simplicity, we'll use recovery ID 27 (v = 27)
-        // In production, this should be calculated properly
-        sig_with_recovery[64] = 27;
+        // The 0x1901 domain prefix takes the place of EIP-191's
+        let signing_hash = domain.signing_hash(&compute_receipt_struct_hash(&receipt)?);
+        receipt.signature = sign_prehash(&key, signing_hash).await?;
         
-        // Set the signature on the receipt
-        receipt.signature = format!("0x{}", hex::encode(sig_with_recovery));
+        #[cfg(feature = "bls")]
+        if let Some(bls) = &self.bls {
+            receipt.bls_signature = Some(bls.sign(&signing_hash));
+            receipt.bls_public_key = Some(bls.public_key().to_string());
+        }
         
         Ok(receipt)
     }
+    
+    /// Sign an epoch summary attestation
+    pub async fn sign_epoch_summary(&self, mut summary: EpochSummary) -> Result<EpochSummary> {
+        let key = self.active();
+        summary.verifier_address = Some(key.address.clone());
+        
+        let signing_hash = compute_epoch_summary_hash(&summary)?;
+        summary.signature = sign_prehash(&key, self.digest_to_sign(&signing_hash)).await?;
+        
+        Ok(summary)
+    }
+    
+    /// Check an epoch summary signature against this signer's key
+    #[cfg(test)]
+    pub fn verify_epoch_summary(&self, summary: &EpochSummary) -> bool {
+        let Ok(signing_hash) = compute_epoch_summary_hash(summary) else {
+            return false;
+        };
+        let Ok(signature_bytes) = hex::decode(summary.signature.trim_start_matches("0x")) else {
+            return false;
+        };
+        
+        // The backend may not expose its key, so compare recovered addresses
+        recover_address(&signature_bytes, &self.digest_to_sign(&signing_hash))
+            .is_some_and(|address| address == self.active().address)
+    }
+    
+    /// The digest actually signed for a signing hash
+    fn digest_to_sign(&self, hash: &[u8; 32]) -> [u8; 32] {
+        if self.eip191_prefix {
+            eip191_hash(hash)
+        } else {
+            *hash
+        }
+    }
+}
+
+/// Sign a 32-byte digest as an Ethereum signature: 0x-prefixed r || s || v
+/// with v = 27 + recovery id, so `ecrecover` yields the key's address
+async fn sign_prehash(key: &ActiveKey, digest: [u8; 32]) -> Result<String> {
+    let signature = key.backend.sign_digest(digest).await?;
+    
+    // A wrong recovery id or hash would otherwise only show up on-chain
+    let recovered = recover_address(&signature, &digest);
+    if recovered.as_deref() != Some(key.address.as_str()) {
+        return Err(VerifierError::signing_fatal(format!(
+            "Signature does not recover to the signer address {}: recovered {:?}", key.address, recovered
+        )).into());
+    }
+    
+    Ok(format!("0x{}", hex::encode(signature)))
+}
+
+/// Address `ecrecover` yields for an r || s || v signature over `digest`,
+/// or `None` if the signature is malformed
+fn recover_address(signature: &[u8], digest: &[u8; 32]) -> Option<String> {
+    use k256::ecdsa::{RecoveryId, Signature};
+    
+    if signature.len() != 65 {
+        return None;
+    }
+    let recovery_id = signature[64].checked_sub(27).and_then(RecoveryId::from_byte)?;
+    let signature = Signature::from_slice(&signature[..64]).ok()?;
+    VerifyingKey::recover_from_prehash(digest, &signature, recovery_id)
+        .ok()
+        .map(|key| derive_address(&key))
+}
+
+/// EIP-191 `personal_sign` hash of a 32-byte message, as `ecrecover`-based
+/// contracts compute it with `toEthSignedMessageHash`
+pub fn eip191_hash(hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(b"\x19Ethereum Signed Message:\n32");
+    hasher.update(hash);
+    hasher.finalize().into()
+}
+
+/// Compute the Keccak256 hash of epoch summary fields for signing
+fn compute_epoch_summary_hash(summary: &EpochSummary) -> Result<[u8; 32]> {
+    let merkle_root = hex::decode(summary.merkle_root.trim_start_matches("0x"))?;
+    
+    let mut hasher = Keccak256::new();
+    hasher.update(summary.epoch_index.to_le_bytes());
+    hasher.update(summary.receipt_count.to_le_bytes());
+    hasher.update(&merkle_root);
+    
+    Ok(hasher.finalize().into())
+}
+
+/// Check that a signed receipt carries a 65-byte `0x`-prefixed signature and a
+/// 20-byte verifier address, catching signer regressions before submission
+pub fn check_signed_receipt(receipt: &VerifiedReceipt) -> Result<()> {
+    if !is_hex_of_len(&receipt.signature, 65) {
+        return Err(VerifierError::signing_fatal(format!(
+            "Malformed signature: expected 0x + 130 hex chars, got {:?}", receipt.signature
+        )).into());
+    }
+    
+    match &receipt.verifier_address {
+        Some(address) if is_hex_of_len(address, 20) => Ok(()),
+        other => Err(VerifierError::signing_fatal(format!(
+            "Malformed verifier address: expected 0x + 40 hex chars, got {:?}", other
+        )).into()),
+    }
+}
+
+fn is_hex_of_len(value: &str, byte_len: usize) -> bool {
+    value.strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == byte_len * 2 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+/// Derive the Ethereum address (0x-prefixed, lowercase hex) of a public key
+pub fn derive_address(public_key: &VerifyingKey) -> String {
+    let public_key_bytes = public_key.to_encoded_point(false);
+    let public_key_bytes = &public_key_bytes.as_bytes()[1..]; // Skip the 0x04 prefix
+    
+    let mut hasher = Keccak256::new();
+    hasher.update(public_key_bytes);
+    let hash = hasher.finalize();
+    
+    format!("0x{}", hex::encode(&hash[12..]))
 }
 
 /// Compute the Keccak256 hash of receipt fields for signing
-fn compute_receipt_hash(receipt: &VerifiedReceipt) -> [u8; 32] {
+pub fn compute_receipt_hash(receipt: &VerifiedReceipt) -> [u8; 32] {
     let mut hasher = Keccak256::new();
     
     // Hash all fields in deterministic order (excluding signature itself)
@@ -88,15 +300,76 @@ fn compute_receipt_hash(receipt: &VerifiedReceipt) -> [u8; 32] {
     hasher.update(receipt.receipt_hash.as_bytes());
     hasher.update(receipt.image_id.as_bytes());
     hasher.update(receipt.journal_hash.as_bytes());
-    hasher.update(&receipt.epoch_index.to_le_bytes());
-    hasher.update(&receipt.input_index.to_le_bytes());
+    hasher.update(receipt.epoch_index.to_le_bytes());
+    hasher.update(receipt.input_index.to_le_bytes());
     
     hasher.finalize().into()
 }
 
+/// Signing hash of the `abi` scheme: the receipt fields decoded from hex and
+/// ABI-encoded, equal to
+///
+/// ```solidity
+/// keccak256(abi.encode(
+///     deviceId,     // string
+///     proofType,    // string
+///     receiptHash,  // bytes32
+///     imageId,      // bytes32
+///     journalHash,  // bytes32
+///     epochIndex,   // uint64
+///     inputIndex    // uint64
+/// ))
+/// ```
+pub fn compute_receipt_abi_hash(receipt: &VerifiedReceipt) -> Result<[u8; 32]> {
+    let strings = [&receipt.device_id, &receipt.proof_type];
+    
+    // Head: an offset word per string, then the static values in place
+    let mut encoded = Vec::new();
+    let mut tail_offset = 7 * 32;
+    for string in strings {
+        encoded.extend_from_slice(&word(&(tail_offset as u64).to_be_bytes()));
+        tail_offset += 32 + string.len().div_ceil(32) * 32;
+    }
+    encoded.extend_from_slice(&parse_bytes32(&receipt.receipt_hash)?);
+    encoded.extend_from_slice(&parse_bytes32(&receipt.image_id)?);
+    encoded.extend_from_slice(&parse_bytes32(&receipt.journal_hash)?);
+    encoded.extend_from_slice(&word(&receipt.epoch_index.to_be_bytes()));
+    encoded.extend_from_slice(&word(&receipt.input_index.to_be_bytes()));
+    
+    // Tail: each string's length, then its bytes zero-padded to whole words
+    for string in strings {
+        encoded.extend_from_slice(&word(&(string.len() as u64).to_be_bytes()));
+        encoded.extend_from_slice(string.as_bytes());
+        encoded.resize(encoded.len().div_ceil(32) * 32, 0);
+    }
+    
+    Ok(Keccak256::digest(&encoded).into())
+}
+
+/// EIP-712 `hashStruct` of a receipt as a [`VERIFIED_RECEIPT_TYPE`]
+pub fn compute_receipt_struct_hash(receipt: &VerifiedReceipt) -> Result<[u8; 32]> {
+    let verifier = receipt.verifier_address.as_deref()
+        .ok_or_else(|| VerifierError::signing_fatal("Receipt has no verifier address"))?;
+    
+    let mut hasher = Keccak256::new();
+    hasher.update(Keccak256::digest(VERIFIED_RECEIPT_TYPE));
+    hasher.update(Keccak256::digest(&receipt.device_id));
+    hasher.update(Keccak256::digest(&receipt.proof_type));
+    hasher.update(parse_bytes32(&receipt.receipt_hash)?);
+    hasher.update(parse_bytes32(&receipt.image_id)?);
+    hasher.update(parse_bytes32(&receipt.journal_hash)?);
+    hasher.update(word(&receipt.epoch_index.to_be_bytes()));
+    hasher.update(word(&receipt.input_index.to_be_bytes()));
+    hasher.update(word(&receipt.timestamp.unwrap_or(0).to_be_bytes()));
+    hasher.update(word(&parse_address(verifier)?));
+    
+    Ok(hasher.finalize().into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::ProofKind;
     
     fn get_test_private_key() -> String {
         // Use environment variable for testing, or generate a random key
@@ -120,8 +393,8 @@ mod tests {
         assert!(signer.get_address().starts_with("0x"));
     }
     
-    #[test]
-    fn test_receipt_signing() {
+    #[tokio::test]
+    async fn test_receipt_signing() {
         let private_key = get_test_private_key();
         let signer = ReceiptSigner::new(&private_key).unwrap();
         
@@ -131,23 +404,238 @@ mod tests {
             receipt_hash: "0x1234".to_string(),
             image_id: "0x5678".to_string(),
             journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
             epoch_index: 1,
             input_index: 2,
             signature: String::new(),
             timestamp: Some(1234567890),
             verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
         };
         
-        let signed = signer.sign_receipt(receipt).unwrap();
+        let signed = signer.sign_receipt(receipt).await.unwrap();
         
         assert!(!signed.signature.is_empty());
         assert!(signed.signature.starts_with("0x"));
         assert_eq!(signed.signature.len(), 132); // 0x + 65 bytes * 2
         assert_eq!(signed.verifier_address, Some(signer.get_address()));
+        
+        // ecrecover over the EIP-191 hash yields the verifier's address
+        let signing_hash = compute_receipt_hash(&signed);
+        assert_eq!(recover(&signed.signature, &eip191_hash(&signing_hash)), signer.get_address());
     }
     
-    #[test]
-    fn test_deterministic_signing() {
+    /// Address recovered from a 0x-prefixed r || s || v signature over `digest`
+    fn recover(signature: &str, digest: &[u8; 32]) -> String {
+        let signature = hex::decode(signature.trim_start_matches("0x")).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            digest,
+            &k256::ecdsa::Signature::from_slice(&signature[..64]).unwrap(),
+            k256::ecdsa::RecoveryId::from_byte(signature[64] - 27).unwrap(),
+        ).unwrap();
+        derive_address(&recovered)
+    }
+    
+    #[tokio::test]
+    async fn test_raw_digest_signing() {
+        let signer = ReceiptSigner::new(&get_test_private_key()).unwrap().with_eip191_prefix(false);
+        
+        let signed = signer.sign_receipt(VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0x1234".to_string(),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: Some(1234567890),
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        }).await.unwrap();
+        
+        // Without the prefix the raw signing hash is what recovers the address
+        let signing_hash = compute_receipt_hash(&signed);
+        assert_eq!(recover(&signed.signature, &signing_hash), signer.get_address());
+        assert_ne!(recover(&signed.signature, &eip191_hash(&signing_hash)), signer.get_address());
+    }
+    
+    #[tokio::test]
+    async fn test_rotated_key_signs_new_receipts() {
+        let signer = ReceiptSigner::new(&get_test_private_key()).unwrap();
+        let receipt = VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0x1234".to_string(),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: Some(1234567890),
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        };
+        let before = signer.sign_receipt(receipt.clone()).await.unwrap();
+        let old_address = signer.get_address();
+        
+        let new_address = signer.rotate_key(&"22".repeat(32)).unwrap();
+        assert_ne!(new_address, old_address);
+        assert_eq!(signer.get_address(), new_address);
+        assert_eq!(signer.key_id(), 1);
+        
+        // A receipt carrying the old address is re-addressed to the signing key
+        let after = signer.sign_receipt(VerifiedReceipt { verifier_address: Some(old_address.clone()), ..receipt }).await.unwrap();
+        assert_eq!(after.verifier_address, Some(new_address.clone()));
+        let signing_hash = eip191_hash(&compute_receipt_hash(&after));
+        assert_eq!(recover(&after.signature, &signing_hash), new_address);
+        
+        // Receipts signed before the rotation still recover to the old key
+        assert_eq!(before.verifier_address, Some(old_address.clone()));
+        let signing_hash = eip191_hash(&compute_receipt_hash(&before));
+        assert_eq!(recover(&before.signature, &signing_hash), old_address);
+        
+        assert!(signer.rotate_key("not a key").is_err());
+        assert_eq!(signer.get_address(), new_address);
+    }
+    
+    mod solidity {
+        alloy_sol_types::sol! {
+            struct VerifiedReceipt {
+                string deviceId;
+                string proofType;
+                bytes32 receiptHash;
+                bytes32 imageId;
+                bytes32 journalHash;
+                uint64 epochIndex;
+                uint64 inputIndex;
+                uint64 timestamp;
+                address verifier;
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_eip712_receipt_signing() {
+        use alloy_primitives::{Address, FixedBytes};
+        use alloy_sol_types::SolStruct;
+        
+        let signer = ReceiptSigner::new(&get_test_private_key()).unwrap();
+        let contract = "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC";
+        let domain = Eip712Domain::new("LCoreVerifier", "1", 31337, contract).unwrap();
+        
+        let signed = signer.sign_receipt_eip712(VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "11".repeat(32),
+            image_id: format!("0x{}", "22".repeat(32)),
+            journal_hash: "33".repeat(32),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: Some(1234567890),
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        }, &domain).await.unwrap();
+        
+        // The same struct and domain hashed by an independent EIP-712 implementation
+        let reference = solidity::VerifiedReceipt {
+            deviceId: "device123".to_string(),
+            proofType: "iot_validation".to_string(),
+            receiptHash: FixedBytes([0x11; 32]),
+            imageId: FixedBytes([0x22; 32]),
+            journalHash: FixedBytes([0x33; 32]),
+            epochIndex: 1,
+            inputIndex: 2,
+            timestamp: 1234567890,
+            verifier: signer.get_address().parse::<Address>().unwrap(),
+        };
+        let reference_domain = alloy_sol_types::eip712_domain! {
+            name: "LCoreVerifier",
+            version: "1",
+            chain_id: 31337,
+            verifying_contract: contract.parse::<Address>().unwrap(),
+        };
+        assert_eq!(VERIFIED_RECEIPT_TYPE, solidity::VerifiedReceipt::eip712_encode_type());
+        assert_eq!(domain.separator(), reference_domain.separator().0);
+        
+        let digest = domain.signing_hash(&compute_receipt_struct_hash(&signed).unwrap());
+        assert_eq!(digest, reference.eip712_signing_hash(&reference_domain).0);
+        assert_eq!(recover(&signed.signature, &digest), signer.get_address());
+        
+        // Fields that are not 32-byte hex values cannot be typed
+        let mut malformed = signed;
+        malformed.receipt_hash = "0x1234".to_string();
+        assert!(signer.sign_receipt_eip712(malformed, &domain).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_abi_receipt_signing() {
+        use alloy_primitives::FixedBytes;
+        use alloy_sol_types::SolValue;
+        
+        let signer = ReceiptSigner::new(&get_test_private_key()).unwrap();
+        let signed = signer.sign_receipt_abi(VerifiedReceipt {
+            // Longer than one word, so the second string's offset moves
+            device_id: "device-with-a-name-longer-than-32-bytes".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "11".repeat(32),
+            image_id: format!("0x{}", "22".repeat(32)),
+            journal_hash: "33".repeat(32),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: Some(1234567890),
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        }).await.unwrap();
+        
+        // abi.encode of the same values by an independent implementation
+        let reference = (
+            "device-with-a-name-longer-than-32-bytes".to_string(),
+            "iot_validation".to_string(),
+            FixedBytes([0x11; 32]),
+            FixedBytes([0x22; 32]),
+            FixedBytes([0x33; 32]),
+            1u64,
+            2u64,
+        ).abi_encode_params();
+        let signing_hash = compute_receipt_abi_hash(&signed).unwrap();
+        assert_eq!(signing_hash, <[u8; 32]>::from(Keccak256::digest(&reference)));
+        // Pinned so contract tests can use the same vector
+        assert_eq!(
+            hex::encode(signing_hash),
+            "54af6f7413b867d0e8f8831599adb5c7f8d7c4d08d80f7e36dd404a4037cdd15"
+        );
+        assert_eq!(recover(&signed.signature, &eip191_hash(&signing_hash)), signer.get_address());
+        
+        // Fields that are not 32-byte hex values cannot be encoded
+        let mut malformed = signed;
+        malformed.journal_hash = "0xabcd".to_string();
+        assert!(signer.sign_receipt_abi(malformed).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_deterministic_signing() {
         let private_key = get_test_private_key();
         let signer = ReceiptSigner::new(&private_key).unwrap();
         
@@ -157,17 +645,124 @@ mod tests {
             receipt_hash: "0xtest".to_string(),
             image_id: "0ximage".to_string(),
             journal_hash: "0xjournal".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
             epoch_index: 1,
             input_index: 1,
             signature: String::new(),
             timestamp: Some(1234567890),
             verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
         };
         
-        let signed1 = signer.sign_receipt(receipt.clone()).unwrap();
-        let signed2 = signer.sign_receipt(receipt).unwrap();
+        let signed1 = signer.sign_receipt(receipt.clone()).await.unwrap();
+        let signed2 = signer.sign_receipt(receipt).await.unwrap();
         
         // Same input should produce same signature
         assert_eq!(signed1.signature, signed2.signature);
     }
+    
+    #[tokio::test]
+    async fn test_check_signed_receipt() {
+        let signer = ReceiptSigner::new(&get_test_private_key()).unwrap();
+        
+        let receipt = VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0x1234".to_string(),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: None,
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        };
+        
+        // Unsigned receipts are rejected
+        assert!(check_signed_receipt(&receipt).is_err());
+        
+        let signed = signer.sign_receipt(receipt).await.unwrap();
+        assert!(check_signed_receipt(&signed).is_ok());
+        
+        let mut truncated = signed.clone();
+        truncated.signature.pop();
+        assert!(check_signed_receipt(&truncated).is_err());
+        
+        let mut no_prefix = signed.clone();
+        no_prefix.signature = no_prefix.signature.trim_start_matches("0x").to_string();
+        assert!(check_signed_receipt(&no_prefix).is_err());
+        
+        let mut bad_address = signed;
+        bad_address.verifier_address = Some("0x1234".to_string());
+        assert!(check_signed_receipt(&bad_address).is_err());
+    }
+    
+    /// Signs with a local key, then damages the signature with `corrupt`
+    struct CorruptingBackend {
+        key: LocalKeySigner,
+        corrupt: fn(&mut [u8; 65]),
+    }
+    
+    #[async_trait::async_trait]
+    impl ReceiptSignerBackend for CorruptingBackend {
+        fn address(&self) -> String {
+            self.key.address()
+        }
+        
+        async fn sign_digest(&self, digest: [u8; 32]) -> Result<[u8; 65]> {
+            let mut signature = self.key.sign_digest(digest).await?;
+            (self.corrupt)(&mut signature);
+            Ok(signature)
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_signature_self_check() {
+        let receipt = VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0x1234".to_string(),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Succinct,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: None,
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        };
+        
+        let corruptions: [fn(&mut [u8; 65]); 3] = [
+            // The other recovery id yields a different key
+            |signature| signature[64] ^= 1,
+            |signature| signature[64] = 0,
+            |signature| signature[0] ^= 0x01,
+        ];
+        for corrupt in corruptions {
+            let backend = CorruptingBackend { key: LocalKeySigner::new(&get_test_private_key()).unwrap(), corrupt };
+            let signer = ReceiptSigner::from_backend(Box::new(backend));
+            
+            let err = signer.sign_receipt(receipt.clone()).await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<VerifierError>(),
+                Some(VerifierError::Signing { kind: crate::error::SignerErrorKind::Fatal, .. })
+            ), "{}", err);
+            assert!(err.to_string().contains("does not recover"), "{}", err);
+        }
+        
+        let signer = ReceiptSigner::new(&get_test_private_key()).unwrap();
+        assert!(signer.sign_receipt(receipt).await.is_ok());
+    }
 }
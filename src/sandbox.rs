@@ -0,0 +1,291 @@
+//! Out-of-process receipt verification
+//!
+//! Deserializing and verifying a receipt parses untrusted bytes. In sandboxed
+//! mode that step runs in a short-lived child process (the verifier binary's
+//! hidden `verify-sandboxed` subcommand), so a crash, hang or runaway
+//! allocation kills the child rather than the service. Only the structured
+//! verdict crosses back, as one line of JSON on the child's stdout.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use crate::config::Config;
+use crate::error::VerifierError;
+use crate::proof_verifier::{ProofVerifier, VerificationPolicy, VerificationSummary};
+use crate::types::ReceiptFormat;
+
+/// Subcommand the child process is started with
+pub const CHILD_SUBCOMMAND: &str = "verify-sandboxed";
+
+/// Work sent to the child on stdin
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SandboxRequest {
+    pub policy: VerificationPolicy,
+    #[serde(default)]
+    pub device_id: String,
+    pub proof_type: String,
+    #[serde(default)]
+    pub expected_image_id: Option<String>,
+    pub format_hint: Option<ReceiptFormat>,
+    /// Hex-encoded receipt bytes
+    pub receipt: String,
+}
+
+/// Outcome reported by the child on stdout
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxResponse {
+    Verified(VerificationSummary),
+    Rejected { message: String },
+}
+
+/// Runs verification in a child process
+#[derive(Clone)]
+pub struct SandboxedVerifier {
+    program: PathBuf,
+    args: Vec<String>,
+    timeout: Duration,
+    memory_limit_bytes: Option<u64>,
+    policy: VerificationPolicy,
+}
+
+impl SandboxedVerifier {
+    /// Verify through this binary's `verify-sandboxed` subcommand
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self {
+            program: std::env::current_exe()?,
+            args: vec![CHILD_SUBCOMMAND.to_string()],
+            timeout: Duration::from_secs(config.sandbox_timeout_secs),
+            memory_limit_bytes: config.sandbox_memory_limit_mb.map(|mb| mb * 1024 * 1024),
+            policy: VerificationPolicy::from_config(config),
+        })
+    }
+
+    /// Replace the image allow-list sent to each child
+    pub fn set_allowed_images(&mut self, allowed_image_ids: Vec<String>) {
+        self.policy.allowed_image_ids = allowed_image_ids;
+    }
+    
+    /// Verify a receipt in a fresh child
+    pub async fn verify(
+        &self,
+        receipt_bytes: &[u8],
+        device_id: &str,
+        proof_type: &str,
+        expected_image_id: Option<&str>,
+        format_hint: Option<ReceiptFormat>,
+    ) -> Result<VerificationSummary> {
+        let request = SandboxRequest {
+            policy: self.policy.clone(),
+            device_id: device_id.to_string(),
+            proof_type: proof_type.to_string(),
+            expected_image_id: expected_image_id.map(str::to_string),
+            format_hint,
+            receipt: hex::encode(receipt_bytes),
+        };
+
+        let mut command = Command::new(&self.program);
+        command.args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true);
+
+        #[cfg(unix)]
+        if let Some(limit) = self.memory_limit_bytes {
+            // SAFETY: setrlimit is async-signal-safe and touches no parent state
+            unsafe {
+                command.pre_exec(move || {
+                    let rlimit = libc::rlimit { rlim_cur: limit, rlim_max: limit };
+                    if libc::setrlimit(libc::RLIMIT_AS, &rlimit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        let mut child = command.spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to start sandboxed verifier: {}", e))?;
+
+        let mut stdin = child.stdin.take().expect("child stdin is piped");
+        let payload = serde_json::to_vec(&request)?;
+
+        let output = tokio::time::timeout(self.timeout, async move {
+            // A child that dies early closes its stdin; its exit status is what matters
+            let _ = stdin.write_all(&payload).await;
+            drop(stdin);
+            child.wait_with_output().await
+        })
+        .await
+        .map_err(|_| VerifierError::ProofVerification(format!(
+            "Sandboxed verifier timed out after {:?}", self.timeout
+        )))??;
+
+        let response = serde_json::from_slice::<SandboxResponse>(&output.stdout).ok();
+
+        match response {
+            Some(SandboxResponse::Verified(summary)) if output.status.success() => Ok(summary),
+            Some(SandboxResponse::Rejected { message }) => Err(VerifierError::ProofVerification(message).into()),
+            _ => Err(VerifierError::ProofVerification(format!(
+                "Sandboxed verifier crashed ({})", output.status
+            )).into()),
+        }
+    }
+}
+
+/// Child side: read one request from stdin, write one response to stdout
+pub fn run_child() -> Result<()> {
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input)?;
+    let request: SandboxRequest = serde_json::from_slice(&input)?;
+
+    let response = handle_request(request);
+
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer(&mut stdout, &response)?;
+    stdout.write_all(b"\n")?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn handle_request(request: SandboxRequest) -> SandboxResponse {
+    let result = (|| {
+        let verifier = ProofVerifier::from_policy(&request.policy)?;
+        let receipt_bytes = hex::decode(&request.receipt)?;
+
+        let proof = verifier.verify_device_proof(
+            &receipt_bytes,
+            &request.device_id,
+            &request.proof_type,
+            request.expected_image_id.as_deref(),
+            request.format_hint,
+        )?;
+        Ok::<_, anyhow::Error>(proof.summary())
+    })();
+
+    match result {
+        Ok(summary) => SandboxResponse::Verified(summary),
+        // The parent re-wraps the message as a ProofVerification error
+        Err(e) => SandboxResponse::Rejected {
+            message: match e.downcast_ref::<VerifierError>() {
+                Some(VerifierError::ProofVerification(message)) => message.clone(),
+                _ => e.to_string(),
+            },
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell_verifier(script: &str) -> SandboxedVerifier {
+        SandboxedVerifier {
+            program: PathBuf::from("/bin/sh"),
+            args: vec!["-c".to_string(), script.to_string()],
+            timeout: Duration::from_secs(5),
+            memory_limit_bytes: None,
+            policy: VerificationPolicy::default(),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_child_crash_does_not_take_down_parent() {
+        // Simulates the deserializer segfaulting on malformed input
+        let sandbox = shell_verifier("cat > /dev/null; kill -SEGV $$");
+
+        let err = sandbox.verify(b"malformed", "device-1", "iot_validation", None, None).await.unwrap_err();
+        assert!(err.to_string().contains("crashed"), "{}", err);
+
+        // The parent keeps working after the child died
+        let sandbox = shell_verifier(
+            r#"cat > /dev/null; echo '{"verified":{"receipt_hash":[1],"journal_hash":[2]}}'"#
+        );
+        let summary = sandbox.verify(b"receipt", "device-1", "iot_validation", None, None).await.unwrap();
+        assert_eq!((summary.receipt_hash, summary.journal_hash), (vec![1], vec![2]));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hung_child_times_out() {
+        let mut sandbox = shell_verifier("sleep 10");
+        sandbox.timeout = Duration::from_millis(200);
+
+        let err = sandbox.verify(b"receipt", "device-1", "iot_validation", None, None).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+    }
+
+    #[test]
+    fn test_malformed_receipt_is_rejected_by_child() {
+        let response = handle_request(SandboxRequest {
+            policy: VerificationPolicy::default(),
+            device_id: "device-1".to_string(),
+            proof_type: "iot_validation".to_string(),
+            expected_image_id: None,
+            format_hint: None,
+            receipt: hex::encode(b"definitely not a receipt"),
+        });
+
+        assert!(matches!(response, SandboxResponse::Rejected { .. }));
+    }
+
+    #[test]
+    fn test_child_and_in_process_verifier_agree() {
+        use crate::test_fixtures::fake_receipt;
+        use risc0_zkvm::sha::Digest;
+        use risc0_zkvm::ExitCode;
+
+        let image = Digest::from([1u32; 8]);
+        let image_hex = format!("0x{}", hex::encode(image));
+        let other = format!("0x{}", "ab".repeat(32));
+        let receipt = |journal: &[u8], exit_code| bincode::serialize(&fake_receipt(image, journal, exit_code)).unwrap();
+        let halted = receipt(b"journal", ExitCode::Halted(0));
+        let device_key = k256::ecdsa::SigningKey::from_slice(&[1u8; 32]).unwrap();
+
+        let allowed = VerificationPolicy { allowed_image_ids: vec![image_hex.clone()], ..VerificationPolicy::default() };
+        let with_rule = VerificationPolicy {
+            journal_rules: [("iot_validation".to_string(), vec!["len == 3".to_string()])].into(),
+            ..allowed.clone()
+        };
+        let cases = [
+            (allowed.clone(), halted.clone(), "iot_validation", None, true),
+            (allowed.clone(), b"definitely not a receipt".to_vec(), "iot_validation", None, false),
+            (VerificationPolicy::default(), halted.clone(), "iot_validation", None, false),
+            (allowed.clone(), halted.clone(), "iot_validation", Some(other.clone()), false),
+            (allowed.clone(), halted.clone(), "iot_telemetry", None, false),
+            (allowed.clone(), receipt(b"journal", ExitCode::Halted(3)), "iot_validation", None, false),
+            (allowed.clone(), receipt(b"journal", ExitCode::Paused(0)), "iot_validation", None, false),
+            (allowed.clone(), crate::envelope::seal(&device_key, &halted), "iot_validation", None, false),
+            (with_rule, halted.clone(), "iot_validation", None, false),
+        ];
+
+        for (i, (policy, bytes, proof_type, expected_image_id, accepted)) in cases.into_iter().enumerate() {
+            let in_process = ProofVerifier::from_policy(&policy).unwrap()
+                .verify_device_proof(&bytes, "device-1", proof_type, expected_image_id.as_deref(), None)
+                .map(|proof| proof.summary());
+            let child = handle_request(SandboxRequest {
+                policy,
+                device_id: "device-1".to_string(),
+                proof_type: proof_type.to_string(),
+                expected_image_id,
+                format_hint: None,
+                receipt: hex::encode(&bytes),
+            });
+
+            assert_eq!(in_process.is_ok(), accepted, "case {}: {:?}", i, in_process.err());
+            match (in_process, child) {
+                (Ok(expected), SandboxResponse::Verified(summary)) => assert_eq!(summary, expected, "case {}", i),
+                (Err(_), SandboxResponse::Rejected { .. }) => {}
+                (in_process, child) => panic!("case {}: in-process {:?}, child {:?}", i, in_process.map(|_| ()), child),
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! Submission through an Espresso-style sequencer
+//!
+//! Instead of posting to the InputBox HTTP endpoint, the input is signed as
+//! EIP-712 typed data and posted to the sequencer, which orders it into the
+//! DApp's namespace:
+//!
+//! 1. `POST {endpoint}/nonce` with `{"msg_sender", "app_contract"}` returns
+//!    the account's next `{"nonce"}`. It is read once and counted up by a
+//!    `NonceManager`, so concurrent submissions never share a nonce.
+//! 2. `POST {endpoint}/submit` with `{"typedData", "account", "signature",
+//!    "namespace"}` returns the sequencer-assigned `{"id"}`.
+
+use anyhow::Result;
+use k256::ecdsa::SigningKey;
+use reqwest::Client;
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+use crate::eip712::{word, Eip712Domain};
+use crate::error::VerifierError;
+use crate::inputbox_rpc::NonceManager;
+use crate::receipt_signer::derive_address;
+
+const DOMAIN_NAME: &str = "Cartesi";
+const DOMAIN_VERSION: &str = "0.1.0";
+const MESSAGE_TYPE: &str = "CartesiMessage(address app,uint64 nonce,uint128 max_gas_price,bytes data)";
+
+#[derive(Deserialize)]
+struct NonceResponse {
+    nonce: u64,
+}
+
+#[derive(Deserialize)]
+struct SubmitResponse {
+    id: serde_json::Value,
+}
+
+/// Signs inputs as EIP-712 typed data and submits them to a sequencer
+pub struct SequencerClient {
+    endpoint: String,
+    domain: Eip712Domain,
+    namespace: u64,
+    max_gas_price: u64,
+    app: [u8; 20],
+    signing_key: SigningKey,
+    account: String,
+    nonces: NonceManager,
+}
+
+impl SequencerClient {
+    pub fn new(
+        endpoint: &str,
+        chain_id: u64,
+        namespace: u64,
+        max_gas_price: u64,
+        dapp_address: &str,
+        private_key_hex: &str,
+    ) -> Result<Self> {
+        let app = hex::decode(dapp_address.trim_start_matches("0x"))?
+            .try_into()
+            .map_err(|_| VerifierError::Config(format!("Invalid DApp address: {}", dapp_address)))?;
+        let key_bytes = hex::decode(private_key_hex.trim_start_matches("0x"))?;
+        let signing_key = SigningKey::from_slice(&key_bytes)
+            .map_err(|e| VerifierError::Config(format!("Invalid private key: {}", e)))?;
+        let account = derive_address(signing_key.verifying_key());
+
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            domain: Eip712Domain {
+                name: DOMAIN_NAME.to_string(),
+                version: DOMAIN_VERSION.to_string(),
+                chain_id,
+                verifying_contract: [0u8; 20],
+            },
+            namespace,
+            max_gas_price,
+            app,
+            signing_key,
+            account,
+            nonces: NonceManager::default(),
+        })
+    }
+
+    /// Submit an input payload, returning the sequencer-assigned reference.
+    /// A failed submission makes the next one read the nonce again.
+    pub async fn submit(&self, client: &Client, payload: &[u8]) -> Result<serde_json::Value> {
+        let nonce = self.nonces.reserve(|| self.fetch_nonce(client)).await?;
+        let digest = self.typed_data_hash(nonce, payload);
+
+        let (signature, recovery_id) = self.signing_key.sign_prehash_recoverable(&digest)
+            .map_err(|e| VerifierError::signing_fatal(format!("Failed to sign sequencer input: {}", e)))?;
+        let mut signature_bytes = signature.to_bytes().to_vec();
+        signature_bytes.push(recovery_id.to_byte() + 27);
+
+        let body = serde_json::json!({
+            "typedData": self.typed_data(nonce, payload),
+            "account": self.account,
+            "signature": format!("0x{}", hex::encode(signature_bytes)),
+            "namespace": self.namespace,
+        });
+
+        match self.post::<SubmitResponse>(client, "submit", &body).await {
+            Ok(response) => Ok(response.id),
+            Err(e) => {
+                self.nonces.resync().await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn fetch_nonce(&self, client: &Client) -> Result<u64> {
+        let body = serde_json::json!({
+            "msg_sender": self.account,
+            "app_contract": format!("0x{}", hex::encode(self.app)),
+        });
+        let response: NonceResponse = self.post(client, "nonce", &body).await?;
+        Ok(response.nonce)
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(
+        &self,
+        client: &Client,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<T> {
+        let url = format!("{}/{}", self.endpoint, path);
+        let response = client.post(&url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| VerifierError::InputBox(format!("Failed to reach sequencer: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(VerifierError::InputBox(
+                format!("Sequencer returned error {} for {}: {}", status, path, error_text)
+            ).into());
+        }
+
+        response.json().await
+            .map_err(|e| VerifierError::InputBox(format!("Invalid sequencer response for {}: {}", path, e)).into())
+    }
+
+    /// The typed data as submitted, in the `eth_signTypedData_v4` layout
+    fn typed_data(&self, nonce: u64, payload: &[u8]) -> serde_json::Value {
+        serde_json::json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" },
+                ],
+                "CartesiMessage": [
+                    { "name": "app", "type": "address" },
+                    { "name": "nonce", "type": "uint64" },
+                    { "name": "max_gas_price", "type": "uint128" },
+                    { "name": "data", "type": "bytes" },
+                ],
+            },
+            "primaryType": "CartesiMessage",
+            "domain": {
+                "name": DOMAIN_NAME,
+                "version": DOMAIN_VERSION,
+                "chainId": self.domain.chain_id,
+                "verifyingContract": format!("0x{}", hex::encode([0u8; 20])),
+            },
+            "message": {
+                "app": format!("0x{}", hex::encode(self.app)),
+                "nonce": nonce,
+                "max_gas_price": self.max_gas_price.to_string(),
+                "data": format!("0x{}", hex::encode(payload)),
+            },
+        })
+    }
+
+    /// EIP-712 signing hash of the typed data
+    fn typed_data_hash(&self, nonce: u64, payload: &[u8]) -> [u8; 32] {
+        let mut message = Keccak256::new();
+        message.update(Keccak256::digest(MESSAGE_TYPE));
+        message.update(word(&self.app));
+        message.update(word(&nonce.to_be_bytes()));
+        message.update(word(&u128::from(self.max_gas_price).to_be_bytes()));
+        message.update(Keccak256::digest(payload));
+        let struct_hash: [u8; 32] = message.finalize().into();
+
+        self.domain.signing_hash(&struct_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    use std::sync::{Arc, Mutex};
+
+    const DAPP: &str = "0x1234567890abcdef1234567890abcdef12345678";
+
+    #[tokio::test]
+    async fn test_submits_signed_typed_input() {
+        let mut server = mockito::Server::new_async().await;
+        let nonce = server.mock("POST", "/nonce")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "app_contract": DAPP })))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"nonce": 7}"#)
+            .create_async()
+            .await;
+
+        let submitted = Arc::new(Mutex::new(None));
+        let captured = submitted.clone();
+        let submit = server.mock("POST", "/submit")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "namespace": 51025,
+                "typedData": {
+                    "primaryType": "CartesiMessage",
+                    "domain": { "name": "Cartesi", "version": "0.1.0", "chainId": 31337 },
+                    "message": { "app": DAPP, "nonce": 7, "max_gas_price": "10", "data": "0x7b7d" },
+                },
+            })))
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |request| {
+                *captured.lock().unwrap() = Some(request.body().unwrap().clone());
+                br#"{"id": "0xfeed"}"#.to_vec()
+            })
+            .create_async()
+            .await;
+
+        let client = SequencerClient::new(&server.url(), 31337, 51025, 10, DAPP, &"11".repeat(32)).unwrap();
+        let reference = client.submit(&Client::new(), b"{}").await.unwrap();
+        assert_eq!(reference, "0xfeed");
+        nonce.assert_async().await;
+        submit.assert_async().await;
+
+        // The signature recovers to the submitting account over the EIP-712 hash
+        let body: serde_json::Value = serde_json::from_slice(&submitted.lock().unwrap().take().unwrap()).unwrap();
+        assert_eq!(body["account"], client.account.as_str());
+        let signature = hex::decode(body["signature"].as_str().unwrap().trim_start_matches("0x")).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            &client.typed_data_hash(7, b"{}"),
+            &Signature::from_slice(&signature[..64]).unwrap(),
+            RecoveryId::from_byte(signature[64] - 27).unwrap(),
+        ).unwrap();
+        assert_eq!(derive_address(&recovered), client.account);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_submissions_get_sequential_nonces() {
+        let mut server = mockito::Server::new_async().await;
+        let nonce = server.mock("POST", "/nonce")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"nonce": 5}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let nonces = Arc::new(Mutex::new(Vec::new()));
+        let captured = nonces.clone();
+        let _submit = server.mock("POST", "/submit")
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |request| {
+                let body: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+                captured.lock().unwrap().push(body["typedData"]["message"]["nonce"].as_u64().unwrap());
+                br#"{"id": "0xfeed"}"#.to_vec()
+            })
+            .create_async()
+            .await;
+
+        let client = Arc::new(SequencerClient::new(&server.url(), 1, 1, 10, DAPP, &"11".repeat(32)).unwrap());
+        let mut tasks = tokio::task::JoinSet::new();
+        for i in 0..10 {
+            let client = client.clone();
+            tasks.spawn(async move { client.submit(&Client::new(), format!("{{\"i\":{}}}", i).as_bytes()).await });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result.unwrap().unwrap();
+        }
+
+        // The nonce is read once and every submission gets its own
+        nonce.assert_async().await;
+        let mut nonces = nonces.lock().unwrap().clone();
+        nonces.sort_unstable();
+        assert_eq!(nonces, (5..15).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_sequencer_error_is_reported() {
+        let mut server = mockito::Server::new_async().await;
+        let _nonce = server.mock("POST", "/nonce")
+            .with_status(503)
+            .with_body("unavailable")
+            .create_async()
+            .await;
+
+        let client = SequencerClient::new(&server.url(), 1, 1, 10, DAPP, &"11".repeat(32)).unwrap();
+        let err = client.submit(&Client::new(), b"{}").await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<VerifierError>(), Some(VerifierError::InputBox(_))));
+    }
+}
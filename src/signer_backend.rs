@@ -0,0 +1,126 @@
+//! Where the verifier key lives
+//!
+//! `ReceiptSigner` hashes receipts and summaries; a backend holds the key and
+//! turns a 32-byte digest into an Ethereum signature. The `local` backend
+//! keeps the key in process memory, the `kms` backend ([`crate::kms_signer`])
+//! never sees it.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use k256::ecdsa::SigningKey;
+use k256::SecretKey;
+use crate::error::VerifierError;
+use crate::receipt_signer::derive_address;
+
+/// Signs digests with the verifier key
+#[async_trait]
+pub trait ReceiptSignerBackend: Send + Sync {
+    /// Ethereum address of the key, 0x-prefixed lowercase hex
+    fn address(&self) -> String;
+
+    /// Sign a digest as it is, returning r || s || v with low s and
+    /// v = 27 + recovery id, so `ecrecover` yields [`Self::address`]
+    async fn sign_digest(&self, digest: [u8; 32]) -> Result<[u8; 65]>;
+}
+
+/// A secp256k1 key held in memory
+pub struct LocalKeySigner {
+    signing_key: SigningKey,
+    address: String,
+}
+
+impl LocalKeySigner {
+    pub fn new(private_key_hex: &str) -> Result<Self> {
+        let private_key_bytes = hex::decode(private_key_hex.trim_start_matches("0x"))
+            .map_err(|e| VerifierError::signing_fatal(format!("Invalid private key hex: {}", e)))?;
+
+        let secret_key = SecretKey::from_slice(&private_key_bytes)
+            .map_err(|e| VerifierError::signing_fatal(format!("Invalid private key: {}", e)))?;
+
+        let signing_key = SigningKey::from(secret_key);
+        let address = derive_address(signing_key.verifying_key());
+        Ok(Self { signing_key, address })
+    }
+}
+
+#[async_trait]
+impl ReceiptSignerBackend for LocalKeySigner {
+    fn address(&self) -> String {
+        self.address.clone()
+    }
+
+    async fn sign_digest(&self, digest: [u8; 32]) -> Result<[u8; 65]> {
+        let (signature, recovery_id) = self.signing_key.sign_prehash_recoverable(&digest)
+            .map_err(|e| VerifierError::signing_fatal(format!("Local key failed to sign: {}", e)))?;
+
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.to_bytes());
+        bytes[64] = 27 + recovery_id.to_byte();
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt_signer::{compute_receipt_hash, eip191_hash, ReceiptSigner};
+    use crate::types::{ProofKind, VerifiedReceipt};
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Signs with a local key and counts the digests it is given
+    struct MockBackend {
+        key: LocalKeySigner,
+        digests: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ReceiptSignerBackend for MockBackend {
+        fn address(&self) -> String {
+            self.key.address()
+        }
+
+        async fn sign_digest(&self, digest: [u8; 32]) -> Result<[u8; 65]> {
+            self.digests.fetch_add(1, Ordering::SeqCst);
+            self.key.sign_digest(digest).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_receipt_goes_through_the_backend() {
+        let digests = Arc::new(AtomicUsize::new(0));
+        let backend = MockBackend { key: LocalKeySigner::new(&"33".repeat(32)).unwrap(), digests: digests.clone() };
+        let address = backend.address();
+        let signer = ReceiptSigner::from_backend(Box::new(backend));
+        assert_eq!(signer.get_address(), address);
+
+        let signed = signer.sign_receipt(VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: "0x1234".to_string(),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Succinct,
+            journal_summary: None,
+            epoch_index: 1,
+            input_index: 2,
+            signature: String::new(),
+            timestamp: Some(1234567890),
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        }).await.unwrap();
+        assert_eq!(digests.load(Ordering::SeqCst), 1);
+        assert_eq!(signed.verifier_address, Some(address.clone()));
+
+        let signature = hex::decode(signed.signature.trim_start_matches("0x")).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            &eip191_hash(&compute_receipt_hash(&signed)),
+            &Signature::from_slice(&signature[..64]).unwrap(),
+            RecoveryId::from_byte(signature[64] - 27).unwrap(),
+        ).unwrap();
+        assert_eq!(derive_address(&recovered), address);
+    }
+}
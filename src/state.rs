@@ -0,0 +1,273 @@
+//! Processing state that survives restarts
+//!
+//! Without it a restarted verifier re-reads every notice from the first input
+//! and has to ask the chain about each receipt it already submitted. The
+//! state holds the input watermark and the hashes of receipts submitted for
+//! inputs from the watermark on; a hash is dropped once the watermark passes
+//! its input, since polls never read that input again. It also holds the
+//! epochs whose summaries were submitted, so none is sent twice. The state
+//! is saved after every change, on a blocking thread so request tasks never
+//! wait on the disk.
+//!
+//! A missing or unreadable state file is not fatal: the verifier starts from
+//! empty state and falls back to the on-chain checks.
+
+use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// What the verifier remembers between runs
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessingState {
+    /// Input index polls resume from
+    #[serde(default)]
+    pub last_processed_index: u64,
+    /// Hashes of receipts submitted to the InputBox, with their input index
+    #[serde(default, deserialize_with = "submitted_receipts")]
+    pub submitted_receipts: BTreeMap<String, u64>,
+    /// Epochs whose summaries were submitted
+    #[serde(default)]
+    pub submitted_summaries: BTreeSet<u64>,
+}
+
+/// Read `submitted_receipts` as a map, or as the bare list of hashes older
+/// versions wrote. Hashes without an input go with the next watermark move.
+fn submitted_receipts<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Submitted {
+        WithInputs(BTreeMap<String, u64>),
+        Hashes(Vec<String>),
+    }
+
+    Ok(match Submitted::deserialize(deserializer)? {
+        Submitted::WithInputs(receipts) => receipts,
+        Submitted::Hashes(hashes) => hashes.into_iter().map(|hash| (hash, 0)).collect(),
+    })
+}
+
+/// Where processing state is kept
+pub trait StateStore: Send + Sync {
+    /// Load the saved state, or empty state if there is none usable
+    fn load(&self) -> ProcessingState;
+
+    /// Replace the saved state
+    fn save(&self, state: &ProcessingState) -> Result<()>;
+}
+
+/// Keeps processing state in a JSON file
+pub struct FileStateStore {
+    path: PathBuf,
+}
+
+impl FileStateStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn load(&self) -> ProcessingState {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return ProcessingState::default(),
+            Err(e) => {
+                warn!("Failed to read state file {}: {}; starting from empty state", self.path.display(), e);
+                return ProcessingState::default();
+            }
+        };
+
+        serde_json::from_slice(&contents).unwrap_or_else(|e| {
+            warn!("Corrupt state file {}: {}; starting from empty state", self.path.display(), e);
+            ProcessingState::default()
+        })
+    }
+
+    fn save(&self, state: &ProcessingState) -> Result<()> {
+        // Write beside the target and rename, so a crash mid-write never
+        // leaves a truncated state file behind
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, serde_json::to_vec(state)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write state file {}: {}", temp.display(), e))?;
+        std::fs::rename(&temp, &self.path)
+            .map_err(|e| anyhow::anyhow!("Failed to replace state file {}: {}", self.path.display(), e))?;
+        Ok(())
+    }
+}
+
+/// The current state in memory, saved to its store on every change
+pub struct PersistedState {
+    writer: Arc<StateWriter>,
+    /// The state, and how many changes have been made to it
+    state: Mutex<(ProcessingState, u64)>,
+}
+
+/// Saves snapshots of the state, skipping any older than one already saved
+struct StateWriter {
+    store: Box<dyn StateStore>,
+    /// Change count of the last snapshot saved
+    saved: Mutex<u64>,
+}
+
+impl StateWriter {
+    /// Save failures are logged rather than returned: the submission already
+    /// happened, and the on-chain checks still catch repeats.
+    fn save(&self, state: &ProcessingState, version: u64) {
+        let mut saved = self.saved.lock().unwrap();
+        if version <= *saved {
+            return;
+        }
+        match self.store.save(state) {
+            Ok(()) => *saved = version,
+            Err(e) => warn!("Failed to save processing state: {}", e),
+        }
+    }
+}
+
+impl PersistedState {
+    pub fn load(store: Box<dyn StateStore>) -> Self {
+        let state = store.load();
+        Self {
+            writer: Arc::new(StateWriter { store, saved: Mutex::new(0) }),
+            state: Mutex::new((state, 0)),
+        }
+    }
+
+    /// Saved input watermark
+    pub fn last_processed_index(&self) -> u64 {
+        self.state.lock().unwrap().0.last_processed_index
+    }
+
+    /// Whether this receipt was submitted by an earlier run (or this one)
+    pub fn is_submitted(&self, receipt_hash: &str) -> bool {
+        self.state.lock().unwrap().0.submitted_receipts.contains_key(receipt_hash)
+    }
+
+    /// Record a receipt submitted for `input_index`
+    pub fn record_submitted(&self, receipt_hash: &str, input_index: u64) {
+        self.update(|state| state.submitted_receipts.insert(receipt_hash.to_string(), input_index).is_none());
+    }
+
+    /// Epochs whose summaries were submitted by an earlier run (or this one)
+    pub fn submitted_summaries(&self) -> BTreeSet<u64> {
+        self.state.lock().unwrap().0.submitted_summaries.clone()
+    }
+
+    /// Record a submitted summary of `epoch_index`
+    pub fn record_summary(&self, epoch_index: u64) {
+        self.update(|state| state.submitted_summaries.insert(epoch_index));
+    }
+
+    /// Record the input watermark, forgetting receipts of inputs before it
+    pub fn record_watermark(&self, index: u64) {
+        self.update(|state| {
+            let submitted = state.submitted_receipts.len();
+            state.submitted_receipts.retain(|_, input_index| *input_index >= index);
+            let changed = state.last_processed_index != index || state.submitted_receipts.len() != submitted;
+            state.last_processed_index = index;
+            changed
+        });
+    }
+
+    /// Save the current state and wait for it to be written
+    pub async fn flush(&self) {
+        let (snapshot, version) = self.state.lock().unwrap().clone();
+        let writer = self.writer.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || writer.save(&snapshot, version)).await {
+            warn!("Failed to save processing state: {}", e);
+        }
+    }
+
+    /// Apply `change` and, if it reports a change, save a snapshot on a
+    /// blocking thread
+    fn update(&self, change: impl FnOnce(&mut ProcessingState) -> bool) {
+        let (snapshot, version) = {
+            let mut state = self.state.lock().unwrap();
+            if !change(&mut state.0) {
+                return;
+            }
+            state.1 += 1;
+            state.clone()
+        };
+        let writer = self.writer.clone();
+        tokio::task::spawn_blocking(move || writer.save(&snapshot, version));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lcore-state-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_state_round_trips() {
+        let path = temp_path("round-trip");
+        let state = PersistedState::load(Box::new(FileStateStore::new(&path)));
+        state.record_watermark(42);
+        state.record_submitted("abc", 42);
+        state.record_summary(3);
+        state.flush().await;
+
+        let reloaded = FileStateStore::new(&path).load();
+        assert_eq!(reloaded, ProcessingState {
+            last_processed_index: 42,
+            submitted_receipts: BTreeMap::from([("abc".to_string(), 42)]),
+            submitted_summaries: BTreeSet::from([3]),
+        });
+
+        let state = PersistedState::load(Box::new(FileStateStore::new(&path)));
+        assert_eq!(state.last_processed_index(), 42);
+        assert!(state.is_submitted("abc"));
+        assert!(!state.is_submitted("def"));
+        assert_eq!(state.submitted_summaries(), BTreeSet::from([3]));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watermark_prunes_passed_receipts() {
+        let path = temp_path("prune");
+        let state = PersistedState::load(Box::new(FileStateStore::new(&path)));
+        state.record_submitted("abc", 3);
+        state.record_submitted("def", 5);
+
+        state.record_watermark(4);
+        assert!(!state.is_submitted("abc"));
+        assert!(state.is_submitted("def"));
+        state.flush().await;
+        assert_eq!(FileStateStore::new(&path).load().submitted_receipts, BTreeMap::from([("def".to_string(), 5)]));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_loads_hash_list_of_older_versions() {
+        let state: ProcessingState = serde_json::from_str(r#"{"last_processed_index": 9, "submitted_receipts": ["abc"]}"#).unwrap();
+        assert_eq!(state.submitted_receipts, BTreeMap::from([("abc".to_string(), 0)]));
+    }
+
+    #[test]
+    fn test_missing_or_corrupt_file_loads_empty_state() {
+        let path = temp_path("corrupt");
+        assert_eq!(FileStateStore::new(&path).load(), ProcessingState::default());
+
+        std::fs::write(&path, b"{\"last_processed_index\": tru").unwrap();
+        assert_eq!(FileStateStore::new(&path).load(), ProcessingState::default());
+
+        // Saving over the garbage recovers the file
+        let store = FileStateStore::new(&path);
+        let state = ProcessingState { last_processed_index: 7, ..ProcessingState::default() };
+        store.save(&state).unwrap();
+        assert_eq!(store.load(), state);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
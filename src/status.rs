@@ -0,0 +1,72 @@
+//! Runtime state behind the `/status` endpoint
+
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// What the poll loop has done so far, updated as it runs
+pub struct RuntimeStatus {
+    started: Instant,
+    last_successful_poll: Mutex<Option<DateTime<Utc>>>,
+    /// Highest input index whose proof request was processed
+    last_processed_input: Mutex<Option<u64>>,
+    /// Proof requests rejected for good, and the input of the latest one
+    rejected: Mutex<(u64, Option<u64>)>,
+    poll_interval_secs: AtomicU64,
+}
+
+impl RuntimeStatus {
+    pub fn new(poll_interval: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            last_successful_poll: Mutex::new(None),
+            last_processed_input: Mutex::new(None),
+            rejected: Mutex::new((0, None)),
+            poll_interval_secs: AtomicU64::new(poll_interval.as_secs()),
+        }
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Note a poll that read the node successfully
+    pub fn record_poll(&self) {
+        *self.last_successful_poll.lock().unwrap() = Some(Utc::now());
+    }
+
+    pub fn last_successful_poll(&self) -> Option<DateTime<Utc>> {
+        *self.last_successful_poll.lock().unwrap()
+    }
+
+    /// Note a processed request; requests finish out of order, so the
+    /// highest index is kept
+    pub fn record_processed(&self, input_index: u64) {
+        let mut last = self.last_processed_input.lock().unwrap();
+        *last = Some(last.map_or(input_index, |last| last.max(input_index)));
+    }
+
+    pub fn last_processed_input(&self) -> Option<u64> {
+        *self.last_processed_input.lock().unwrap()
+    }
+
+    /// Note a request rejected for good, which is passed over rather than retried
+    pub fn record_rejected(&self, input_index: u64) {
+        let mut rejected = self.rejected.lock().unwrap();
+        *rejected = (rejected.0 + 1, Some(input_index));
+    }
+
+    /// Requests rejected so far, and the input of the latest one
+    pub fn rejected(&self) -> (u64, Option<u64>) {
+        *self.rejected.lock().unwrap()
+    }
+
+    pub fn set_poll_interval(&self, interval: Duration) {
+        self.poll_interval_secs.store(interval.as_secs(), Ordering::Relaxed);
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs.load(Ordering::Relaxed))
+    }
+}
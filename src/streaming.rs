@@ -0,0 +1,226 @@
+//! Segment-by-segment decoding of bincode composite receipts
+//!
+//! A composite receipt is mostly segment seals. Decoding it whole makes a
+//! second, decoded copy of every seal next to the encoded bytes; streaming
+//! decodes one segment at a time, checks its seal and chaining, then keeps
+//! only its (small) claim. The encoded receipt (and, for inline receipts, its
+//! hex form) is still held in full, so this saves the decoded copy only; it
+//! does not bound the memory a receipt takes.
+//!
+//! Assumption receipts are verified whole once the segments are done, as
+//! `CompositeReceipt::verify_integrity_with_context` does.
+
+use anyhow::Result;
+use bincode::Options;
+use risc0_zkp::verify::VerificationError;
+use risc0_zkvm::sha::{Digest, Digestible};
+use risc0_zkvm::{CompositeReceipt, ExitCode, InnerReceipt, Journal, Receipt, SegmentReceipt, VerifierContext};
+use sha3::Keccak256;
+use std::io::Cursor;
+use crate::error::VerifierError;
+use crate::proof_verifier::bincode_options;
+
+/// Seal check applied to each segment as it is decoded
+pub type SegmentCheck = fn(&SegmentReceipt) -> Result<(), VerificationError>;
+
+/// bincode variant index of `InnerReceipt::Composite`
+const COMPOSITE_TAG: u32 = 0;
+
+/// Memory figures for one streamed verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamStats {
+    pub segments: usize,
+    /// Encoded size of the largest segment, the most held decoded at once
+    pub peak_segment_bytes: usize,
+    pub total_bytes: usize,
+}
+
+/// A composite receipt whose segment seals were verified while streaming
+pub struct StreamedReceipt {
+    /// The receipt with every segment seal dropped; claims are kept
+    pub receipt: Receipt,
+    /// Keccak256 of the encoded receipt, equal to hashing the full decode
+    pub receipt_hash: Vec<u8>,
+    pub stats: StreamStats,
+}
+
+/// Stream a bincode composite receipt, verifying each segment as it goes
+/// and then each assumption.
+///
+/// Returns `None` when the bytes are not a bincode composite receipt, so the
+/// caller can fall back to a full decode.
+pub fn stream_composite(bytes: &[u8], check: SegmentCheck) -> Result<Option<StreamedReceipt>> {
+    let mut cursor = Cursor::new(bytes);
+    // Each read is bounded by the whole input, so no length prefix can make
+    // a read allocate more than the receipt could hold
+    let options = bincode_options(bytes.len());
+
+    let Ok(COMPOSITE_TAG) = options.deserialize_from::<_, u32>(&mut cursor) else {
+        return Ok(None);
+    };
+    let Ok(count) = options.deserialize_from::<_, u64>(&mut cursor) else {
+        return Ok(None);
+    };
+    // Every segment takes at least a few bytes; reject absurd counts before allocating
+    if count > bytes.len() as u64 {
+        return Ok(None);
+    }
+
+    let mut segments = Vec::with_capacity(count as usize);
+    let mut peak_segment_bytes = 0;
+    let mut expected_pre_state = None;
+
+    for position in 0..count {
+        let start = cursor.position();
+        let Ok(segment) = options.deserialize_from::<_, SegmentReceipt>(&mut cursor) else {
+            return Ok(None);
+        };
+        peak_segment_bytes = peak_segment_bytes.max((cursor.position() - start) as usize);
+
+        check(&segment).map_err(|e| VerifierError::ProofVerification(
+            format!("Segment {} failed verification: {}", segment.index, e)
+        ))?;
+        check_continuation(&segment, expected_pre_state)?;
+
+        // Every segment but the last must end on a split with no output
+        let is_last = position + 1 == count;
+        if !is_last && (segment.claim.exit_code != ExitCode::SystemSplit || !segment.claim.output.is_none()) {
+            return Err(VerifierError::ProofVerification(
+                format!("Segment {} ends the execution early", segment.index)
+            ).into());
+        }
+        expected_pre_state = next_pre_state(&segment);
+
+        // The seal has been checked; only the claim is needed from here on
+        segments.push(SegmentReceipt { seal: Vec::new(), ..segment });
+    }
+
+    let Ok(assumptions) = options.deserialize_from::<_, Vec<InnerReceipt>>(&mut cursor) else {
+        return Ok(None);
+    };
+    // The claim treats assumptions as resolved, so their receipts must verify
+    for (position, assumption) in assumptions.iter().enumerate() {
+        assumption.verify_integrity_with_context(&VerifierContext::default()).map_err(|e| {
+            VerifierError::ProofVerification(format!("Assumption {} failed verification: {}", position, e))
+        })?;
+    }
+    let Ok(journal_digest) = options.deserialize_from::<_, Option<Digest>>(&mut cursor) else {
+        return Ok(None);
+    };
+    let Ok(journal) = options.deserialize_from::<_, Journal>(&mut cursor) else {
+        return Ok(None);
+    };
+    if cursor.position() != bytes.len() as u64 {
+        return Ok(None);
+    }
+
+    // Canonical bincode with no trailing bytes re-encodes identically
+    let receipt_hash = <Keccak256 as sha3::Digest>::digest(bytes).to_vec();
+
+    Ok(Some(StreamedReceipt {
+        receipt: Receipt {
+            inner: InnerReceipt::Composite(CompositeReceipt { segments, assumptions, journal_digest }),
+            journal,
+        },
+        receipt_hash,
+        stats: StreamStats { segments: count as usize, peak_segment_bytes, total_bytes: bytes.len() },
+    }))
+}
+
+/// Reject a segment that does not start where its predecessor stopped
+pub fn check_continuation(segment: &SegmentReceipt, expected_pre_state: Option<Digest>) -> Result<()> {
+    if expected_pre_state.is_some_and(|digest| digest != segment.claim.pre.digest()) {
+        return Err(VerifierError::ProofVerification(
+            format!("Segment {} does not continue its predecessor", segment.index)
+        ).into());
+    }
+    Ok(())
+}
+
+/// Pre-state the segment after this one must start from
+pub fn next_pre_state(segment: &SegmentReceipt) -> Option<Digest> {
+    let mut post = segment.claim.post.as_value().ok()?.clone();
+    // Post-state PC is stored as the actual value plus 4
+    post.pc = post.pc.checked_sub(4)?;
+    Some(post.digest())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risc0_zkvm::{Assumptions, MaybePruned};
+    use crate::test_fixtures::{segmented_receipt, segment_state, SEAL_WORDS};
+
+    #[test]
+    fn test_segments_are_streamed_one_at_a_time() {
+        let receipt = segmented_receipt(4, b"journal");
+        let bytes = bincode::serialize(&receipt).unwrap();
+
+        let streamed = stream_composite(&bytes, |segment| {
+            assert!(!segment.seal.is_empty());
+            Ok(())
+        }).unwrap().unwrap();
+
+        assert_eq!(streamed.stats.segments, 4);
+        assert_eq!(streamed.stats.total_bytes, bytes.len());
+        // Only one segment's worth of seal was decoded at a time
+        assert!(streamed.stats.peak_segment_bytes * 3 < streamed.stats.total_bytes);
+        assert!(streamed.stats.peak_segment_bytes >= SEAL_WORDS * 4);
+
+        let InnerReceipt::Composite(composite) = &streamed.receipt.inner else { panic!("not composite") };
+        assert!(composite.segments.iter().all(|segment| segment.seal.is_empty()));
+        assert_eq!(streamed.receipt.get_claim().unwrap().digest(), receipt.get_claim().unwrap().digest());
+
+        let full_hash = <Keccak256 as sha3::Digest>::digest(bincode::serialize(&receipt).unwrap()).to_vec();
+        assert_eq!(streamed.receipt_hash, full_hash);
+    }
+
+    #[test]
+    fn test_failed_segment_stops_the_stream() {
+        let bytes = bincode::serialize(&segmented_receipt(3, b"journal")).unwrap();
+
+        let err = stream_composite(&bytes, |segment| match segment.index {
+            1 => Err(VerificationError::InvalidProof),
+            _ => Ok(()),
+        }).err().unwrap();
+        assert!(err.to_string().contains("Segment 1"), "{}", err);
+    }
+
+    #[test]
+    fn test_forged_assumption_is_rejected() {
+        // An assumption with a placeholder seal that the last segment's
+        // output commits to, so the claim itself is consistent
+        let assumption = crate::test_fixtures::succinct_receipt(Digest::from([2u32; 8]), b"assumed").inner;
+        let mut receipt = segmented_receipt(2, b"journal");
+        let InnerReceipt::Composite(composite) = &mut receipt.inner else { unreachable!() };
+        let last = composite.segments.last_mut().unwrap();
+        let MaybePruned::Value(Some(output)) = &mut last.claim.output else { unreachable!() };
+        output.assumptions = MaybePruned::Value(Assumptions(vec![assumption.get_claim().unwrap().into()]));
+        composite.assumptions = vec![assumption];
+        assert!(receipt.get_claim().is_ok());
+        let bytes = bincode::serialize(&receipt).unwrap();
+
+        let err = stream_composite(&bytes, |_| Ok(())).err().unwrap();
+        assert!(err.to_string().contains("Assumption 0 failed verification"), "{}", err);
+    }
+
+    #[test]
+    fn test_broken_chain_is_rejected() {
+        let mut receipt = segmented_receipt(3, b"journal");
+        let InnerReceipt::Composite(composite) = &mut receipt.inner else { unreachable!() };
+        composite.segments[2].claim.pre = MaybePruned::Value(segment_state(0xdead));
+        let bytes = bincode::serialize(&receipt).unwrap();
+
+        let err = stream_composite(&bytes, |_| Ok(())).err().unwrap();
+        assert!(err.to_string().contains("does not continue"), "{}", err);
+    }
+
+    #[test]
+    fn test_other_receipts_are_not_streamed() {
+        let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), b"journal", ExitCode::Halted(0));
+        let bytes = bincode::serialize(&receipt).unwrap();
+        assert!(stream_composite(&bytes, |_| Ok(())).unwrap().is_none());
+
+        assert!(stream_composite(b"garbage", |_| Ok(())).unwrap().is_none());
+    }
+}
@@ -0,0 +1,137 @@
+//! Strictly ordered submission within a poll
+//!
+//! Some consumer contracts require receipts to arrive in `(epoch_index,
+//! input_index)` order, but requests are verified concurrently and finish in
+//! any order. Each request of a poll takes a [`Turn`] at its position in the
+//! sorted batch and waits for it before submitting. Once every earlier
+//! request has finished it may go ahead, unless one of them failed and is to
+//! be retried: then it is held back too, to be read again with its
+//! predecessor next poll. A rejected request is never submitted, so it holds
+//! nothing back.
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use crate::error::is_rejection;
+
+/// Tracks which requests of a sorted batch have finished
+pub struct SubmissionOrder {
+    /// Input index at each position
+    inputs: Vec<u64>,
+    /// Whether the request at each position settled, rather than failing to be
+    /// retried, once it has finished
+    finished: Mutex<Vec<Option<bool>>>,
+    changed: Notify,
+}
+
+impl SubmissionOrder {
+    /// Order requests for the given inputs, in the order they must be submitted
+    pub fn new(inputs: Vec<u64>) -> Arc<Self> {
+        Arc::new(Self {
+            finished: Mutex::new(vec![None; inputs.len()]),
+            inputs,
+            changed: Notify::new(),
+        })
+    }
+
+    /// The turn of the request at `position`
+    pub fn turn(self: &Arc<Self>, position: usize) -> Turn {
+        Turn { order: self.clone(), position, settled: false }
+    }
+
+    fn finish(&self, position: usize, settled: bool) {
+        self.finished.lock().unwrap()[position].get_or_insert(settled);
+        self.changed.notify_waiters();
+    }
+
+    /// `None` while a request before `position` is unfinished, otherwise the
+    /// position of the first one that failed, if any
+    fn predecessors(&self, position: usize) -> Option<Option<usize>> {
+        let finished = self.finished.lock().unwrap();
+        let mut failed = None;
+        for (earlier, outcome) in finished[..position].iter().enumerate() {
+            match outcome {
+                None => return None,
+                Some(false) => {
+                    failed.get_or_insert(earlier);
+                }
+                Some(true) => {}
+            }
+        }
+        Some(failed)
+    }
+}
+
+/// A request's place in the order. Dropping it without [`Turn::finish`],
+/// on any path, counts the request as failed, so later ones never wait on
+/// it forever.
+pub struct Turn {
+    order: Arc<SubmissionOrder>,
+    position: usize,
+    settled: bool,
+}
+
+impl Turn {
+    /// Wait until every earlier request has finished, failing if one of them failed
+    pub async fn wait(&self) -> Result<()> {
+        loop {
+            let changed = self.order.changed.notified();
+            tokio::pin!(changed);
+            // Registered before the check, so a finish in between is not missed
+            changed.as_mut().enable();
+
+            match self.order.predecessors(self.position) {
+                Some(None) => return Ok(()),
+                Some(Some(failed)) => {
+                    return Err(anyhow::anyhow!(
+                        "Holding input {} back: input {} before it was not submitted",
+                        self.order.inputs[self.position], self.order.inputs[failed]
+                    ));
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    /// Finish the turn with the request's result. Submitted, skipped and
+    /// rejected requests let later ones go ahead; any other failure holds
+    /// them back.
+    pub fn finish(mut self, result: &Result<()>) {
+        self.settled = result.as_ref().is_ok() || result.as_ref().is_err_and(is_rejection);
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.order.finish(self.position, self.settled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::error::VerifierError;
+
+    #[tokio::test]
+    async fn test_turns_go_in_order_and_stop_after_a_failure() {
+        let order = SubmissionOrder::new(vec![10, 11, 12, 13]);
+        let (first, second, third, fourth) = (order.turn(0), order.turn(1), order.turn(2), order.turn(3));
+
+        // The second waits for the first, which a rejection settles too
+        let waiting = tokio::spawn(async move {
+            second.wait().await.unwrap();
+            second.finish(&Ok(()));
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        first.wait().await.unwrap();
+        first.finish(&Err(VerifierError::ProofVerification("invalid seal".to_string()).into()));
+        waiting.await.unwrap();
+
+        // A turn dropped without finishing holds back every later one
+        drop(third);
+        let err = fourth.wait().await.unwrap_err();
+        assert!(err.to_string().contains("input 12 before it was not submitted"), "{}", err);
+    }
+}
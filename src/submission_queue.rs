@@ -0,0 +1,234 @@
+//! Queue of signed receipts waiting to be submitted in batches
+//!
+//! Request tasks enqueue receipts and wait for them to be submitted; one
+//! drain task takes them off in batches of up to `batch_size`, so a busy poll
+//! costs a few InputBox round-trips instead of one per receipt. A request only
+//! counts as processed once the drain task reports its receipt submitted, so
+//! the watermark never passes a receipt that is still queued. The queue is
+//! bounded, so request tasks wait when the drain task falls behind.
+
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot, watch};
+use crate::error::VerifierError;
+use crate::types::VerifiedReceipt;
+
+/// Batches the queue holds before enqueueing waits
+const QUEUED_BATCHES: usize = 8;
+
+/// A receipt in the queue, with the request task waiting on it
+pub struct QueuedReceipt {
+    pub receipt: VerifiedReceipt,
+    /// Batches the receipt has been part of
+    attempts: u32,
+    done: oneshot::Sender<Result<()>>,
+}
+
+impl QueuedReceipt {
+    /// Batches the receipt has been part of, including the current one
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+/// Resolves once the drain task has submitted or dropped a receipt
+pub struct Submitted(oneshot::Receiver<Result<()>>);
+
+impl Submitted {
+    /// Wait for the receipt's outcome
+    pub async fn wait(self) -> Result<()> {
+        self.0.await.unwrap_or_else(|_| {
+            Err(VerifierError::InputBox("Submission queue closed before the receipt was submitted".to_string()).into())
+        })
+    }
+}
+
+/// Sending side, held by the pipeline
+pub struct SubmissionQueue {
+    sender: mpsc::Sender<QueuedReceipt>,
+    /// Receipts enqueued but not yet submitted or dropped
+    outstanding: Arc<watch::Sender<usize>>,
+}
+
+/// Receiving side, owned by the drain task
+pub struct SubmissionBatcher {
+    receiver: mpsc::Receiver<QueuedReceipt>,
+    /// Receipts put back after a failed submission; sent before new ones
+    requeued: VecDeque<QueuedReceipt>,
+    batch_size: usize,
+    outstanding: Arc<watch::Sender<usize>>,
+}
+
+/// A queue whose batches hold up to `batch_size` receipts
+pub fn channel(batch_size: usize) -> (SubmissionQueue, SubmissionBatcher) {
+    let batch_size = batch_size.max(1);
+    let (sender, receiver) = mpsc::channel(batch_size * QUEUED_BATCHES);
+    let outstanding = Arc::new(watch::Sender::new(0));
+
+    let queue = SubmissionQueue { sender, outstanding: outstanding.clone() };
+    let batcher = SubmissionBatcher { receiver, requeued: VecDeque::new(), batch_size, outstanding };
+    (queue, batcher)
+}
+
+impl SubmissionQueue {
+    /// Add a receipt, waiting for room if the queue is full
+    pub async fn enqueue(&self, receipt: VerifiedReceipt) -> Result<Submitted> {
+        let (done, submitted) = oneshot::channel();
+        self.outstanding.send_modify(|count| *count += 1);
+        if self.sender.send(QueuedReceipt { receipt, attempts: 0, done }).await.is_err() {
+            self.outstanding.send_modify(|count| *count -= 1);
+            return Err(VerifierError::InputBox("Submission queue is closed".to_string()).into());
+        }
+        Ok(Submitted(submitted))
+    }
+
+    /// Wait until every enqueued receipt has been submitted or dropped
+    pub async fn flushed(&self) {
+        let mut outstanding = self.outstanding.subscribe();
+        let _ = outstanding.wait_for(|&count| count == 0).await;
+    }
+}
+
+impl SubmissionBatcher {
+    /// Wait for the next batch, ordered by epoch and input index. Returns
+    /// `None` once the queue is closed and empty.
+    pub async fn next_batch(&mut self) -> Option<Vec<QueuedReceipt>> {
+        let mut batch: Vec<QueuedReceipt> = self.requeued.drain(..self.requeued.len().min(self.batch_size)).collect();
+        if batch.is_empty() && self.receiver.recv_many(&mut batch, self.batch_size).await == 0 {
+            return None;
+        }
+        while batch.len() < self.batch_size {
+            match self.receiver.try_recv() {
+                Ok(receipt) => batch.push(receipt),
+                Err(_) => break,
+            }
+        }
+
+        for queued in &mut batch {
+            queued.attempts += 1;
+        }
+        batch.sort_by_key(|queued| (queued.receipt.epoch_index, queued.receipt.input_index));
+        Some(batch)
+    }
+
+    /// Report a receipt as submitted to the request waiting on it
+    pub fn complete(&mut self, queued: QueuedReceipt) {
+        self.finish(queued, Ok(()));
+    }
+
+    /// Drop a receipt from the queue, failing the request waiting on it
+    pub fn fail(&mut self, queued: QueuedReceipt, error: anyhow::Error) {
+        self.finish(queued, Err(error));
+    }
+
+    fn finish(&mut self, queued: QueuedReceipt, result: Result<()>) {
+        self.outstanding.send_modify(|outstanding| *outstanding -= 1);
+        // The request may have stopped waiting, e.g. at shutdown
+        let _ = queued.done.send(result);
+    }
+
+    /// Put receipts that were not submitted back at the front of the queue
+    pub fn requeue(&mut self, receipts: Vec<QueuedReceipt>) {
+        for queued in receipts.into_iter().rev() {
+            self.requeued.push_front(queued);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ProofKind;
+
+    fn receipt(epoch_index: u64, input_index: u64) -> VerifiedReceipt {
+        VerifiedReceipt {
+            device_id: "device123".to_string(),
+            proof_type: "iot_validation".to_string(),
+            receipt_hash: format!("{}-{}", epoch_index, input_index),
+            image_id: "0x5678".to_string(),
+            journal_hash: "0xabcd".to_string(),
+            journal_empty: false,
+            proof_kind: ProofKind::Composite,
+            journal_summary: None,
+            epoch_index,
+            input_index,
+            signature: String::new(),
+            timestamp: None,
+            verifier_address: None,
+            bls_signature: None,
+            bls_public_key: None,
+        }
+    }
+
+    fn hashes(batch: &[QueuedReceipt]) -> Vec<&str> {
+        batch.iter().map(|queued| queued.receipt.receipt_hash.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_bounded_and_ordered() {
+        let (queue, mut batcher) = channel(2);
+        for (epoch_index, input_index) in [(2, 7), (1, 4), (1, 3), (3, 1), (2, 5)] {
+            queue.enqueue(receipt(epoch_index, input_index)).await.unwrap();
+        }
+
+        let mut batches = Vec::new();
+        for _ in 0..3 {
+            let batch = batcher.next_batch().await.unwrap();
+            batches.push(hashes(&batch).join(","));
+            for queued in batch {
+                batcher.complete(queued);
+            }
+        }
+        // Ordering holds within each batch; batches follow arrival order
+        assert_eq!(batches, ["1-4,2-7", "1-3,3-1", "2-5"]);
+        tokio::time::timeout(std::time::Duration::from_secs(1), queue.flushed()).await.unwrap();
+
+        drop(queue);
+        assert!(batcher.next_batch().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_requeued_receipts_go_first() {
+        let (queue, mut batcher) = channel(3);
+        let mut submitted = Vec::new();
+        for input_index in 1..=4 {
+            submitted.push(queue.enqueue(receipt(1, input_index)).await.unwrap());
+        }
+
+        // Only the first receipt of the batch made it
+        let mut batch = batcher.next_batch().await.unwrap();
+        assert_eq!(hashes(&batch), ["1-1", "1-2", "1-3"]);
+        let rest = batch.split_off(1);
+        batcher.complete(batch.pop().unwrap());
+        batcher.requeue(rest);
+        let pending = tokio::time::timeout(std::time::Duration::from_millis(20), queue.flushed()).await;
+        assert!(pending.is_err(), "requeued receipts are still outstanding");
+
+        let batch = batcher.next_batch().await.unwrap();
+        assert_eq!(hashes(&batch), ["1-2", "1-3", "1-4"]);
+        assert_eq!(batch.iter().map(QueuedReceipt::attempts).collect::<Vec<_>>(), [2, 2, 1]);
+        for queued in batch {
+            batcher.complete(queued);
+        }
+
+        tokio::time::timeout(std::time::Duration::from_secs(1), queue.flushed()).await.unwrap();
+        for submitted in submitted {
+            submitted.wait().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dropped_receipt_fails_its_request() {
+        let (queue, mut batcher) = channel(2);
+        let submitted = queue.enqueue(receipt(1, 1)).await.unwrap();
+
+        let mut batch = batcher.next_batch().await.unwrap();
+        batcher.fail(batch.pop().unwrap(), anyhow::anyhow!("relay rejected the input"));
+
+        let err = submitted.wait().await.unwrap_err();
+        assert!(err.to_string().contains("relay rejected the input"), "{}", err);
+        // A dropped receipt no longer keeps the queue from flushing
+        tokio::time::timeout(std::time::Duration::from_secs(1), queue.flushed()).await.unwrap();
+    }
+}
@@ -0,0 +1,228 @@
+//! Tracing subscriber setup, with optional OpenTelemetry span export
+//!
+//! Spans are always emitted; with the `otel` feature and an OTLP endpoint
+//! configured they are additionally exported to a collector. Log lines go to
+//! stdout as text, or as JSON objects carrying the fields of their spans.
+//! The log level can be changed while running through [`LogLevel`].
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use tracing::level_filters::LevelFilter;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+use crate::config::LogFormat;
+use crate::error::VerifierError;
+
+/// Keeps the span exporter alive; flushes pending spans when dropped
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+    log_level: LogLevel,
+}
+
+impl TelemetryGuard {
+    /// Control over the installed subscriber's log level
+    pub fn log_level(&self) -> LogLevel {
+        self.log_level.clone()
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush OpenTelemetry spans: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global subscriber, exporting spans to `otlp_endpoint` if given
+pub fn init(filter: &str, format: LogFormat, otlp_endpoint: Option<&str>) -> Result<TelemetryGuard> {
+    let (filter, log_level) = LogLevel::new(filter);
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer(format, std::io::stdout));
+
+    #[cfg(feature = "otel")]
+    {
+        let provider = otlp_endpoint.map(otel::provider).transpose()?;
+        let layer = provider.as_ref().map(otel::layer);
+        registry.with(layer).init();
+
+        if let Some(endpoint) = otlp_endpoint {
+            tracing::info!("Exporting OpenTelemetry spans to {}", endpoint);
+        }
+
+        Ok(TelemetryGuard { provider, log_level })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        registry.init();
+
+        if otlp_endpoint.is_some() {
+            tracing::warn!("otlp_endpoint is set but this build lacks the `otel` feature; spans will not be exported");
+        }
+
+        Ok(TelemetryGuard { log_level })
+    }
+}
+
+/// Swaps the filter of a running subscriber
+#[derive(Clone)]
+pub struct LogLevel {
+    handle: reload::Handle<EnvFilter, Registry>,
+    current: Arc<Mutex<String>>,
+}
+
+impl LogLevel {
+    /// A reloadable filter layer starting at `filter`, and its control
+    pub fn new(filter: &str) -> (reload::Layer<EnvFilter, Registry>, Self) {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new(filter));
+        (layer, Self { handle, current: Arc::new(Mutex::new(filter.to_string())) })
+    }
+
+    pub fn current(&self) -> String {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Log at `level` (`off`, `error`, `warn`, `info`, `debug` or `trace`)
+    /// from now on, returning the previous level
+    pub fn set(&self, level: &str) -> Result<String> {
+        let level = level.trim().parse::<LevelFilter>()
+            .map_err(|_| VerifierError::InvalidQuery(format!("{:?} is not a log level", level)))?
+            .to_string()
+            .to_lowercase();
+
+        let mut current = self.current.lock().unwrap();
+        self.handle.reload(EnvFilter::new(&level))
+            .map_err(|e| anyhow::anyhow!("Failed to change the log level: {}", e))?;
+        Ok(std::mem::replace(&mut *current, level))
+    }
+}
+
+/// Layer writing log lines in `format` to `writer`
+fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use anyhow::Result;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_subscriber::registry::LookupSpan;
+
+    pub fn provider(endpoint: &str) -> Result<TracerProvider> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()?;
+
+        Ok(TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new("service.name", env!("CARGO_PKG_NAME"))]))
+            .build())
+    }
+
+    pub fn layer<S>(provider: &TracerProvider) -> impl tracing_subscriber::Layer<S>
+    where
+        S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Log output captured in memory
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn log_lines(format: LogFormat) -> Vec<String> {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::registry().with(fmt_layer(format, move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("process_request", device_id = "device-1", input_index = 17u64);
+            span.in_scope(|| tracing::info!("Verified receipt"));
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        output.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_json_lines_carry_span_fields() {
+        let lines = log_lines(LogFormat::Json);
+        assert_eq!(lines.len(), 1);
+
+        let line: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(line["fields"]["message"], "Verified receipt");
+        assert_eq!(line["span"]["name"], "process_request");
+        assert_eq!(line["span"]["device_id"], "device-1");
+        assert_eq!(line["span"]["input_index"], 17);
+    }
+
+    #[test]
+    fn test_log_level_changes_at_runtime() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let (filter, log_level) = LogLevel::new("info");
+        let subscriber = tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt_layer(LogFormat::Text, move || writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("Suppressed at info");
+            assert_eq!(log_level.set("DEBUG").unwrap(), "info");
+            tracing::debug!("Emitted at debug");
+
+            assert!(log_level.set("loud").is_err());
+            assert_eq!(log_level.current(), "debug");
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.contains("Suppressed at info"), "{}", output);
+        assert!(output.contains("Emitted at debug"), "{}", output);
+    }
+
+    #[test]
+    fn test_text_is_not_json() {
+        let lines = log_lines(LogFormat::Text);
+        assert_eq!(lines.len(), 1);
+        assert!(serde_json::from_str::<serde_json::Value>(&lines[0]).is_err());
+        assert!(lines[0].contains("device_id") && lines[0].contains("Verified receipt"), "{}", lines[0]);
+    }
+}
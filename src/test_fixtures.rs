@@ -0,0 +1,103 @@
+//! Shared fixtures for unit tests
+
+use risc0_zkvm::sha::Digest;
+use risc0_zkvm::sha::Digestible;
+use risc0_zkvm::{
+    Assumptions, CompactReceipt, CompositeReceipt, ExitCode, InnerReceipt, MaybePruned, Output,
+    Receipt, ReceiptClaim, SegmentReceipt, SuccinctReceipt, SystemState,
+};
+
+/// Fake receipts only pass `Receipt::verify` in RISC Zero dev mode
+fn enable_dev_mode() {
+    static DEV_MODE: std::sync::Once = std::sync::Once::new();
+    DEV_MODE.call_once(|| std::env::set_var("RISC0_DEV_MODE", "1"));
+}
+
+/// Claim of an execution of `image_id` committing `journal`
+fn claim(image_id: Digest, journal: &[u8], exit_code: ExitCode) -> ReceiptClaim {
+    ReceiptClaim {
+        pre: MaybePruned::Pruned(image_id),
+        post: MaybePruned::Value(SystemState {
+            pc: 0x0020_0000,
+            merkle_root: Digest::from([7u32; 8]),
+        }),
+        exit_code,
+        input: Digest::ZERO,
+        output: MaybePruned::Value(Some(Output {
+            journal: MaybePruned::Value(journal.to_vec()),
+            assumptions: MaybePruned::Value(Assumptions(vec![])),
+        })),
+    }
+}
+
+/// Build a fake (dev-mode) receipt for `image_id` committing `journal`
+pub fn fake_receipt(image_id: Digest, journal: &[u8], exit_code: ExitCode) -> Receipt {
+    enable_dev_mode();
+    let claim = claim(image_id, journal, exit_code);
+    Receipt::new(InnerReceipt::Fake { claim }, journal.to_vec())
+}
+
+/// A succinct receipt with a placeholder seal; it only passes a stubbed check
+pub fn succinct_receipt(image_id: Digest, journal: &[u8]) -> Receipt {
+    let succinct = SuccinctReceipt {
+        seal: vec![0; 64],
+        control_id: Digest::ZERO,
+        claim: claim(image_id, journal, ExitCode::Halted(0)),
+    };
+    Receipt::new(InnerReceipt::Succinct(succinct), journal.to_vec())
+}
+
+/// A Groth16 receipt with a placeholder seal; it only passes a stubbed check
+pub fn groth16_receipt(image_id: Digest, journal: &[u8]) -> Receipt {
+    let compact = CompactReceipt {
+        seal: vec![0; 256],
+        claim: claim(image_id, journal, ExitCode::Halted(0)),
+    };
+    Receipt::new(InnerReceipt::Compact(compact), journal.to_vec())
+}
+
+/// Seal size, in words, of each segment from `segmented_receipt`
+pub const SEAL_WORDS: usize = 64 * 1024;
+
+/// System state at `pc`, as chained between segments
+pub fn segment_state(pc: u32) -> SystemState {
+    SystemState { pc, merkle_root: Digest::from([pc; 8]) }
+}
+
+/// A chained composite receipt with `count` segments of sizeable fake seals
+pub fn segmented_receipt(count: u32, journal: &[u8]) -> Receipt {
+    let segments = (0..count).map(|i| {
+        let last = i + 1 == count;
+        SegmentReceipt {
+            seal: vec![i; SEAL_WORDS],
+            index: i,
+            hashfn: "poseidon2".to_string(),
+            claim: ReceiptClaim {
+                pre: if i == 0 {
+                    MaybePruned::Pruned(Digest::from([1u32; 8]))
+                } else {
+                    MaybePruned::Value(segment_state(0x1000 * i))
+                },
+                // Post-state PCs are stored plus 4
+                post: MaybePruned::Value(SystemState { pc: 0x1000 * (i + 1) + 4, ..segment_state(0x1000 * (i + 1)) }),
+                exit_code: if last { ExitCode::Halted(0) } else { ExitCode::SystemSplit },
+                input: Digest::ZERO,
+                output: if last {
+                    MaybePruned::Value(Some(Output {
+                        journal: MaybePruned::Value(journal.to_vec()),
+                        assumptions: MaybePruned::Value(Assumptions(vec![])),
+                    }))
+                } else {
+                    MaybePruned::Value(None)
+                },
+            },
+        }
+    }).collect();
+
+    let composite = CompositeReceipt {
+        segments,
+        assumptions: vec![],
+        journal_digest: Some(journal.to_vec().digest()),
+    };
+    Receipt::new(InnerReceipt::Composite(composite), journal.to_vec())
+}
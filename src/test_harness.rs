@@ -0,0 +1,281 @@
+//! A mock rollup node and InputBox for running the pipeline end to end
+//!
+//! [`MockRollup`] serves canned proof request notices from a GraphQL
+//! endpoint and accepts InputBox submissions, recording the verified
+//! receipts it is sent. [`MockRollup::config`] points a verifier at both.
+//! [`FakeRollup`] does the same in memory, standing in for the clients
+//! themselves.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use risc0_zkvm::sha::Digest;
+use risc0_zkvm::ExitCode;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use crate::config::Config;
+use crate::graphql::NoticeSource;
+use crate::inputbox_client::InputBoxSink;
+use crate::types::{EpochSummary, ProofRequest, VerifiedReceipt};
+
+/// Image id of the receipts built by [`proof_request`]
+pub fn image_id() -> String {
+    format!("0x{}", hex::encode(Digest::from([1u32; 8])))
+}
+
+/// A proof request from `device_id` carrying an inline fake receipt of [`image_id`]
+pub fn proof_request(device_id: &str, input_index: u64, journal: &[u8]) -> ProofRequest {
+    let receipt = crate::test_fixtures::fake_receipt(Digest::from([1u32; 8]), journal, ExitCode::Halted(0));
+    ProofRequest {
+        device_id: device_id.to_string(),
+        proof_type: "iot_validation".to_string(),
+        receipt_url: String::new(),
+        expected_image_id: image_id(),
+        epoch_index: 0,
+        input_index,
+        receipt_inline: Some(format!("0x{}", hex::encode(bincode::serialize(&receipt).unwrap()))),
+        receipt_format: None,
+        expected_receipt_hash: None,
+        input_timestamp: None,
+    }
+}
+
+/// GraphQL node and InputBox servers on localhost
+pub struct MockRollup {
+    node: mockito::ServerGuard,
+    inputbox: mockito::ServerGuard,
+    /// Proof requests the node currently reports
+    requests: Arc<Mutex<Vec<ProofRequest>>>,
+    /// Kept alive for as long as the servers should answer
+    _mocks: Vec<mockito::Mock>,
+    submissions: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl MockRollup {
+    /// Start both servers. The node reports no proof requests, epoch 0 as the
+    /// latest, and no receipt as already on chain; the InputBox accepts everything.
+    pub async fn start() -> Self {
+        let mut node = mockito::Server::new_async().await;
+        let mut inputbox = mockito::Server::new_async().await;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let submissions = Arc::new(Mutex::new(Vec::new()));
+
+        let served = requests.clone();
+        let proof_requests = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("GetProofRequests".to_string()))
+            .with_body_from_request(move |request| {
+                let query: serde_json::Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+                notices_response(&served.lock().unwrap(), &query).into_bytes()
+            })
+            .create_async()
+            .await;
+
+        let latest_epoch = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("LatestEpoch".to_string()))
+            .with_body(r#"{"data": {"epochs": {"edges": [{"node": {"index": 0}}]}}}"#)
+            .create_async()
+            .await;
+        let not_on_chain = node.mock("POST", "/graphql")
+            .match_body(mockito::Matcher::Regex("CheckReceipt".to_string()))
+            .with_body(r#"{"data": {"inputs": {"edges": []}}}"#)
+            .create_async()
+            .await;
+        let recorded = submissions.clone();
+        let accept = inputbox.mock("POST", "/input")
+            .with_body_from_request(move |request| {
+                let mut recorded = recorded.lock().unwrap();
+                if let Some(command) = decode_submission(request.body().unwrap()) {
+                    recorded.push(command);
+                }
+                format!(r#"{{"index": {}}}"#, recorded.len()).into_bytes()
+            })
+            .create_async()
+            .await;
+
+        Self {
+            node,
+            inputbox,
+            requests,
+            _mocks: vec![proof_requests, latest_epoch, not_on_chain, accept],
+            submissions,
+        }
+    }
+
+    /// Report `requests` as notices, each on the input its `input_index` names
+    pub fn serve_proof_requests(&self, requests: &[ProofRequest]) {
+        *self.requests.lock().unwrap() = requests.to_vec();
+    }
+
+    /// A config pointing at both servers with a test signing key, allowing [`image_id`]
+    pub fn config(&self) -> Config {
+        Config {
+            graphql_endpoints: vec![format!("{}/graphql", self.node.url())],
+            inputbox_endpoint: format!("{}/input", self.inputbox.url()),
+            verifier_private_key: hex::encode([0x11u8; 32]),
+            allowed_image_ids: vec![image_id()],
+            ..Config::default()
+        }
+    }
+
+    /// Verified receipts submitted so far, in order
+    pub fn submitted_receipts(&self) -> Vec<VerifiedReceipt> {
+        self.submissions.lock().unwrap().iter()
+            .filter(|command| command["command"] == "submit_verified_receipt")
+            .map(|command| serde_json::from_value(command["data"].clone()).unwrap())
+            .collect()
+    }
+}
+
+/// Answer a `GetProofRequests` query, or a `GetProofRequestsSince` query
+/// with only the inputs past its `since` index
+fn notices_response(requests: &[ProofRequest], query: &serde_json::Value) -> String {
+    let payload = |request: &ProofRequest| {
+        let notice = serde_json::json!({ "type": "risc0_proof_request", "data": request });
+        format!("0x{}", hex::encode(notice.to_string()))
+    };
+    let page_info = serde_json::json!({ "hasNextPage": false, "endCursor": null });
+
+    let Some(since) = query["variables"]["since"].as_u64() else {
+        let edges: Vec<_> = requests.iter().map(|request| serde_json::json!({ "node": {
+            "index": request.input_index,
+            "input": { "index": request.input_index },
+            "payload": payload(request),
+        } })).collect();
+        return serde_json::json!({ "data": { "notices": { "edges": edges, "pageInfo": page_info } } }).to_string();
+    };
+
+    let edges: Vec<_> = requests.iter().filter(|request| request.input_index > since).map(|request| serde_json::json!({ "node": {
+        "index": request.input_index,
+        "notices": { "edges": [{ "node": { "index": 0, "payload": payload(request) } }] },
+    } })).collect();
+    serde_json::json!({ "data": { "inputs": { "edges": edges, "pageInfo": page_info } } }).to_string()
+}
+
+/// The `{"command", "data"}` object inside an InputBox submission
+fn decode_submission(body: &[u8]) -> Option<serde_json::Value> {
+    let input: serde_json::Value = serde_json::from_slice(body).ok()?;
+    let payload = hex::decode(input["payload"].as_str()?.trim_start_matches("0x")).ok()?;
+    serde_json::from_slice(&payload).ok()
+}
+
+#[derive(Default)]
+struct FakeState {
+    requests: Vec<ProofRequest>,
+    on_chain: HashSet<String>,
+    watermark: u64,
+    submitted: Vec<VerifiedReceipt>,
+    summaries: Vec<EpochSummary>,
+    latest_epoch: u64,
+    failing_submissions: bool,
+}
+
+/// An in-memory [`NoticeSource`] and [`InputBoxSink`]; clones share state
+#[derive(Clone, Default)]
+pub struct FakeRollup {
+    state: Arc<Mutex<FakeState>>,
+    /// Submissions wait on a read lock; a held write lock pauses them
+    gate: Arc<tokio::sync::RwLock<()>>,
+}
+
+impl FakeRollup {
+    /// Report `requests`; a query returns those from the watermark on
+    pub fn serve_proof_requests(&self, requests: &[ProofRequest]) {
+        self.state.lock().unwrap().requests = requests.to_vec();
+    }
+
+    /// Report `epoch` as the chain's latest epoch (0 until set)
+    pub fn set_latest_epoch(&self, epoch: u64) {
+        self.state.lock().unwrap().latest_epoch = epoch;
+    }
+
+    /// Make submissions fail (or succeed again)
+    pub fn fail_submissions(&self, failing: bool) {
+        self.state.lock().unwrap().failing_submissions = failing;
+    }
+
+    /// Hold submissions until the returned guard is dropped
+    pub async fn pause_submissions(&self) -> tokio::sync::OwnedRwLockWriteGuard<()> {
+        self.gate.clone().write_owned().await
+    }
+
+    /// Verified receipts submitted so far, in order
+    pub fn submitted_receipts(&self) -> Vec<VerifiedReceipt> {
+        self.state.lock().unwrap().submitted.clone()
+    }
+
+    /// Epoch summaries submitted so far, in order
+    pub fn submitted_summaries(&self) -> Vec<EpochSummary> {
+        self.state.lock().unwrap().summaries.clone()
+    }
+}
+
+#[async_trait]
+impl NoticeSource for FakeRollup {
+    async fn query_proof_requests(&self) -> Result<Vec<ProofRequest>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.requests.iter().filter(|request| request.input_index >= state.watermark).cloned().collect())
+    }
+    
+    async fn query_proof_requests_in_epochs(&self, epochs: RangeInclusive<u64>) -> Result<Vec<ProofRequest>> {
+        let state = self.state.lock().unwrap();
+        Ok(state.requests.iter().filter(|request| epochs.contains(&request.epoch_index)).cloned().collect())
+    }
+
+    async fn check_receipt_processed(&self, receipt_hash: &str) -> Result<bool> {
+        Ok(self.state.lock().unwrap().on_chain.contains(receipt_hash))
+    }
+
+    async fn query_latest_epoch(&self) -> Result<Option<u64>> {
+        Ok(Some(self.state.lock().unwrap().latest_epoch))
+    }
+
+    fn last_seen_index(&self) -> u64 {
+        self.state.lock().unwrap().watermark
+    }
+
+    fn advance_watermark(&self, index: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.watermark = state.watermark.max(index);
+    }
+
+    async fn ping(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl InputBoxSink for FakeRollup {
+    async fn submit_verified_receipt(&self, receipt: &VerifiedReceipt) -> Result<()> {
+        self.submit_verified_receipts_batch(std::slice::from_ref(receipt)).await.map(|_| ())
+    }
+
+    async fn submit_verified_receipts_batch(&self, receipts: &[VerifiedReceipt]) -> Result<usize> {
+        let _open = self.gate.read().await;
+        let mut state = self.state.lock().unwrap();
+        if state.failing_submissions {
+            return Err(anyhow!("InputBox unavailable"));
+        }
+        for receipt in receipts {
+            state.on_chain.insert(receipt.receipt_hash.clone());
+            state.submitted.push(receipt.clone());
+        }
+        Ok(receipts.len())
+    }
+
+    async fn submit_epoch_summary(&self, summary: &EpochSummary) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.failing_submissions {
+            return Err(anyhow!("InputBox unavailable"));
+        }
+        state.summaries.push(summary.clone());
+        Ok(())
+    }
+
+    fn log_would_submit(&self, _receipt: &VerifiedReceipt) -> Result<()> {
+        Ok(())
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        Ok(true)
+    }
+}
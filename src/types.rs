@@ -7,10 +7,102 @@ use serde::{Deserialize, Serialize};
 pub struct ProofRequest {
     pub device_id: String,
     pub proof_type: String,
+    #[serde(default)]
     pub receipt_url: String,
     pub expected_image_id: String,
     pub epoch_index: u64,
     pub input_index: u64,
+    
+    /// Optional: receipt bytes embedded in the notice (0x-prefixed hex or
+    /// base64), used instead of fetching `receipt_url`
+    #[serde(default)]
+    pub receipt_inline: Option<String>,
+    
+    /// Optional: serialization format of the receipt, skipping format probing
+    #[serde(default)]
+    pub receipt_format: Option<ReceiptFormat>,
+    
+    /// Optional: Keccak256 of the raw receipt bytes, checked before decoding
+    /// to detect a receipt substituted in transit
+    #[serde(default)]
+    pub expected_receipt_hash: Option<String>,
+    
+    /// Unix timestamp of the input the request was posted in, taken from the
+    /// node rather than the payload
+    #[serde(skip_deserializing)]
+    pub input_timestamp: Option<u64>,
+}
+
+/// Serialization formats a RISC Zero receipt may be delivered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptFormat {
+    /// `bincode` (the RISC Zero CLI default)
+    Bincode,
+    
+    /// RISC Zero's word-oriented `risc0_zkvm::serde` encoding
+    Risc0Serde,
+    
+    /// `postcard`
+    Postcard,
+    
+    /// JSON, as `serde_json` writes it
+    Json,
+    
+    /// CBOR (RFC 8949)
+    Cbor,
+}
+
+impl ReceiptFormat {
+    /// Probing order when no (or a wrong) hint is given
+    pub const ALL: [ReceiptFormat; 5] = [
+        ReceiptFormat::Bincode,
+        ReceiptFormat::Risc0Serde,
+        ReceiptFormat::Postcard,
+        ReceiptFormat::Json,
+        ReceiptFormat::Cbor,
+    ];
+    
+    /// Guess a self-describing format from the leading byte: a JSON object
+    /// starts with `{`, a CBOR receipt with a map header or the CBOR
+    /// self-describe tag. The binary formats have no such marker.
+    pub fn sniff(bytes: &[u8]) -> Option<ReceiptFormat> {
+        match bytes.iter().find(|b| !b.is_ascii_whitespace())? {
+            b'{' => Some(ReceiptFormat::Json),
+            0xa0..=0xbf => Some(ReceiptFormat::Cbor),
+            0xd9 if bytes.starts_with(&[0xd9, 0xd9, 0xf7]) => Some(ReceiptFormat::Cbor),
+            _ => None,
+        }
+    }
+}
+
+/// How a verified receipt's seal proves its claim
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofKind {
+    /// STARK segment receipts, one per continuation segment
+    #[default]
+    Composite,
+    
+    /// A single recursion-compressed STARK
+    Succinct,
+    
+    /// A STARK wrapped in a Groth16 SNARK over BN254, cheap to verify on chain
+    Groth16,
+    
+    /// A dev-mode receipt with no seal; only accepted under `RISC0_DEV_MODE`
+    Fake,
+}
+
+impl ProofKind {
+    pub fn of(inner: &risc0_zkvm::InnerReceipt) -> Self {
+        match inner {
+            risc0_zkvm::InnerReceipt::Composite(_) => ProofKind::Composite,
+            risc0_zkvm::InnerReceipt::Succinct(_) => ProofKind::Succinct,
+            risc0_zkvm::InnerReceipt::Compact(_) => ProofKind::Groth16,
+            risc0_zkvm::InnerReceipt::Fake { .. } => ProofKind::Fake,
+        }
+    }
 }
 
 /// Verified receipt to be submitted to InputBox
@@ -28,9 +120,26 @@ pub struct VerifiedReceipt {
     /// Expected RISC Zero guest program image ID
     pub image_id: String,
     
-    /// Keccak256 hash of the journal data
+    /// Keccak256 hash of the journal data; an empty journal always uses the
+    /// sentinel `EMPTY_JOURNAL_HASH` (Keccak256 of zero bytes)
     pub journal_hash: String,
     
+    /// Whether the journal was empty. Implied by the signed `journal_hash`
+    /// equalling the sentinel, so it is not itself part of the signing hash.
+    #[serde(default)]
+    pub journal_empty: bool,
+    
+    /// Receipt kind that was verified. Determined by the receipt that
+    /// `receipt_hash` commits to, so it is not part of the signing hash.
+    #[serde(default)]
+    pub proof_kind: ProofKind,
+    
+    /// Journal fields decoded with the proof type's configured
+    /// `journal_schemas` layout. Derived from the journal that
+    /// `journal_hash` commits to, so it is not part of the signing hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal_summary: Option<serde_json::Value>,
+    
     /// Cartesi epoch index
     pub epoch_index: u64,
     
@@ -45,10 +154,39 @@ pub struct VerifiedReceipt {
     
     /// Optional: Address of the verifier who signed this receipt
     pub verifier_address: Option<String>,
+    
+    /// Optional: aggregatable BLS signature over the same signing hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bls_signature: Option<String>,
+    
+    /// Optional: BLS public key matching `bls_signature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bls_public_key: Option<String>,
+}
+
+/// Verifier attestation over all receipts it verified in one epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochSummary {
+    /// Cartesi epoch index
+    pub epoch_index: u64,
+    
+    /// Number of receipts the verifier submitted for the epoch
+    pub receipt_count: u64,
+    
+    /// Keccak256 Merkle root over the epoch's receipt hashes, with
+    /// domain-separated leaves and nodes (see `epoch::merkle_root`)
+    pub merkle_root: String,
+    
+    /// ECDSA signature over keccak256 of all above fields
+    pub signature: String,
+    
+    /// Address of the verifier who signed this summary
+    pub verifier_address: Option<String>,
 }
 
 /// GraphQL notice data
 #[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
 pub struct Notice {
     pub index: String,
     pub input_index: String,
@@ -57,6 +195,7 @@ pub struct Notice {
 
 /// GraphQL input data
 #[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
 pub struct Input {
     pub index: String,
     pub timestamp: String,
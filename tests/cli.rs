@@ -0,0 +1,95 @@
+//! End-to-end checks of the command-line interface
+
+use risc0_zkvm::sha::Digest;
+use risc0_zkvm::{Assumptions, ExitCode, InnerReceipt, MaybePruned, Output, Receipt, ReceiptClaim, SystemState};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A dev-mode receipt of `image_id` committing `journal`, written to a temp file
+fn receipt_fixture(name: &str, image_id: Digest, journal: &[u8]) -> PathBuf {
+    let claim = ReceiptClaim {
+        pre: MaybePruned::Pruned(image_id),
+        post: MaybePruned::Value(SystemState { pc: 0x0020_0000, merkle_root: Digest::from([7u32; 8]) }),
+        exit_code: ExitCode::Halted(0),
+        input: Digest::ZERO,
+        output: MaybePruned::Value(Some(Output {
+            journal: MaybePruned::Value(journal.to_vec()),
+            assumptions: MaybePruned::Value(Assumptions(vec![])),
+        })),
+    };
+    let receipt = Receipt::new(InnerReceipt::Fake { claim }, journal.to_vec());
+
+    let path = std::env::temp_dir().join(format!("lcore-cli-{}-{}.bin", name, std::process::id()));
+    std::fs::write(&path, bincode::serialize(&receipt).unwrap()).unwrap();
+    path
+}
+
+/// Run `verify` on `receipt`, returning the exit status and parsed stdout
+fn verify(receipt: &Path, image_id: Digest) -> (bool, serde_json::Value) {
+    let output = Command::new(env!("CARGO_BIN_EXE_lcore-verifier"))
+        .args(["--config", "/nonexistent/verifier.toml", "verify", "--receipt"])
+        .arg(receipt)
+        .args(["--image-id", &format!("0x{}", hex::encode(image_id))])
+        // Fake receipts only verify in RISC Zero dev mode
+        .env("RISC0_DEV_MODE", "1")
+        .output()
+        .unwrap();
+    (output.status.success(), serde_json::from_slice(&output.stdout).unwrap())
+}
+
+#[test]
+fn test_verify_subcommand() {
+    let image_id = Digest::from([1u32; 8]);
+    let receipt = receipt_fixture("valid", image_id, b"journal");
+
+    let (success, report) = verify(&receipt, image_id);
+    assert!(success, "{}", report);
+    assert_eq!(report["valid"], true);
+    assert_eq!(report["image_id"], format!("0x{}", hex::encode(image_id)));
+    assert_eq!(report["journal_hash"], format!("0x{}", hex::encode(keccak256(b"journal"))));
+    assert!(report["receipt_hash"].as_str().unwrap().starts_with("0x"));
+
+    // The same receipt does not verify against another image
+    let (success, report) = verify(&receipt, Digest::from([2u32; 8]));
+    assert!(!success);
+    assert_eq!(report["valid"], false);
+    assert!(report["error"].as_str().is_some());
+
+    std::fs::remove_file(&receipt).unwrap();
+}
+
+#[test]
+fn test_address_subcommand() {
+    // The well-known address of private key 1
+    let key = format!("{:064x}", 1);
+    let expected = "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf";
+
+    let key_file = std::env::temp_dir().join(format!("lcore-cli-key-{}", std::process::id()));
+    std::fs::write(&key_file, format!("{}\n", key)).unwrap();
+
+    for (reference, value) in [
+        (key.clone(), None),
+        (format!("file://{}", key_file.display()), None),
+        ("env://TEST_VERIFIER_KEY".to_string(), Some(key.clone())),
+    ] {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_lcore-verifier"));
+        command
+            .args(["--config", "/nonexistent/verifier.toml", "address"])
+            .env("VERIFIER_PRIVATE_KEY", &reference)
+            .env("ALLOW_ANY_IMAGE", "true");
+        if let Some(value) = value {
+            command.env("TEST_VERIFIER_KEY", value);
+        }
+
+        let output = command.output().unwrap();
+        assert!(output.status.success(), "{}: {}", reference, String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), expected, "{}", reference);
+    }
+
+    std::fs::remove_file(&key_file).unwrap();
+}
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    use sha3::{Digest as _, Keccak256};
+    Keccak256::digest(data).into()
+}